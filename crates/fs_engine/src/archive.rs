@@ -1,8 +1,15 @@
 use crate::error::{FsError, Result};
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::io;
+use std::io::{self, Read, Write};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Entries smaller than this are never ratio-checked; tiny INIs compress absurdly well.
+const RATIO_CHECK_MIN_SIZE: u64 = 1024 * 1024;
+/// Emit a progress event at least this often while copying a large entry.
+const PROGRESS_INTERVAL_BYTES: u64 = 4 * 1024 * 1024;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ExtractionReport {
@@ -11,94 +18,491 @@ pub struct ExtractionReport {
     pub has_modinfo_json: bool,
 }
 
+/// Upper bounds applied before and during extraction to stop zip bombs.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ExtractionLimits {
+    pub max_total_size: u64,
+    pub max_entries: usize,
+    /// Maximum uncompressed/compressed ratio for any single entry.
+    pub max_compression_ratio: u64,
+}
+
+impl Default for ExtractionLimits {
+    fn default() -> Self {
+        Self {
+            max_total_size: 16 * 1024 * 1024 * 1024,
+            max_entries: 100_000,
+            max_compression_ratio: 200,
+        }
+    }
+}
+
+impl ExtractionLimits {
+    fn check_count(&self, entries: usize) -> Result<()> {
+        if entries > self.max_entries {
+            return Err(FsError::ArchiveLimit(format!(
+                "{} entries (limit {})",
+                entries, self.max_entries
+            )));
+        }
+        Ok(())
+    }
+
+    fn check_total(&self, bytes: u64) -> Result<()> {
+        if bytes > self.max_total_size {
+            return Err(FsError::ArchiveLimit(format!(
+                "{} bytes uncompressed (limit {})",
+                bytes, self.max_total_size
+            )));
+        }
+        Ok(())
+    }
+
+    fn check_entry(&self, name: &str, size: u64, compressed_size: u64) -> Result<()> {
+        if size < RATIO_CHECK_MIN_SIZE {
+            return Ok(());
+        }
+        if compressed_size == 0 || size / compressed_size > self.max_compression_ratio {
+            return Err(FsError::ArchiveLimit(format!(
+                "{} expands from {} to {} bytes",
+                name, compressed_size, size
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtractionProgress {
+    pub entries_done: usize,
+    pub entries_total: usize,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    pub current_file: String,
+}
+
+/// Shared flag used to abort a running extraction from another thread.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+pub type ProgressCallback = Arc<dyn Fn(ExtractionProgress) + Send + Sync>;
+
+#[derive(Clone, Default)]
+pub struct ExtractOptions {
+    pub limits: ExtractionLimits,
+    pub cancel: CancelToken,
+    pub on_progress: Option<ProgressCallback>,
+    /// Password for encrypted zip/7z archives. Ignored for unencrypted ones.
+    pub password: Option<String>,
+}
+
+impl ExtractOptions {
+    fn report(&self, progress: &ExtractionProgress) {
+        if let Some(cb) = &self.on_progress {
+            cb(progress.clone());
+        }
+    }
+
+    fn check_cancelled(&self) -> Result<()> {
+        if self.cancel.is_cancelled() {
+            return Err(FsError::Cancelled);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ArchiveFormat {
+    Zip,
+    SevenZ,
+}
+
+/// Sniffs the magic bytes, falling back to the extension for empty or odd files.
+pub(crate) fn detect_format(path: &Path) -> Result<ArchiveFormat> {
+    let mut magic = [0u8; 6];
+    let read = fs::File::open(path)
+        .and_then(|mut f| f.read(&mut magic))
+        .map_err(FsError::Io)?;
+
+    let is_zip = magic[..4] == [0x50, 0x4B, 0x03, 0x04] || magic[..4] == [0x50, 0x4B, 0x05, 0x06];
+    if read >= 4 && is_zip {
+        return Ok(ArchiveFormat::Zip);
+    }
+    if read == 6 && magic == [0x37, 0x7A, 0xBC, 0xAF, 0x27, 0x1C] {
+        return Ok(ArchiveFormat::SevenZ);
+    }
+
+    let extension = path
+        .extension()
+        .and_then(|s| s.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    match extension.as_str() {
+        "zip" => Ok(ArchiveFormat::Zip),
+        "7z" => Ok(ArchiveFormat::SevenZ),
+        _ => Err(FsError::Io(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Unsupported archive format: .{}", extension),
+        ))),
+    }
+}
+
 pub fn extract_and_sanitize(archive_path: &Path, destination: &Path) -> Result<ExtractionReport> {
+    extract_and_sanitize_with(archive_path, destination, &ExtractOptions::default())
+}
+
+/// Same as [`extract_and_sanitize`], with progress reporting, cancellation and size limits.
+pub fn extract_and_sanitize_with(
+    archive_path: &Path,
+    destination: &Path,
+    options: &ExtractOptions,
+) -> Result<ExtractionReport> {
+    match detect_format(archive_path)? {
+        ArchiveFormat::Zip => extract_zip(archive_path, destination, options),
+        ArchiveFormat::SevenZ => extract_7z(archive_path, destination, options),
+    }
+}
+
+fn extract_zip(
+    archive_path: &Path,
+    destination: &Path,
+    options: &ExtractOptions,
+) -> Result<ExtractionReport> {
     let file = fs::File::open(archive_path).map_err(FsError::Io)?;
     let mut archive = zip::ZipArchive::new(file).map_err(|e| FsError::Io(io::Error::other(e)))?;
 
-    let mut report = ExtractionReport {
-        files_ignored: Vec::new(),
-        has_mod_json: false,
-        has_modinfo_json: false,
-    };
-
     // 1. Detect if there is a single common root directory to strip (common in GitHub zips)
-    let mut common_root: Option<String> = None;
-    let mut all_in_root = true;
+    let root_to_strip = detect_common_root(archive.file_names());
 
+    // 2. Check limits against the central directory before writing anything
+    let limits = &options.limits;
+    limits.check_count(archive.len())?;
+
+    let mut bytes_total: u64 = 0;
+    let mut encrypted = false;
     for i in 0..archive.len() {
         let file = archive
-            .by_index(i)
+            .by_index_raw(i)
             .map_err(|e| FsError::Io(io::Error::other(e)))?;
-        let name = file.name();
+        limits.check_entry(file.name(), file.size(), file.compressed_size())?;
+        bytes_total = bytes_total.saturating_add(file.size());
+        encrypted |= file.encrypted();
+    }
+    limits.check_total(bytes_total)?;
 
-        if name.ends_with('/') && name.chars().filter(|&c| c == '/').count() == 1 {
-            if let Some(root) = &common_root {
-                if root != name {
-                    all_in_root = false;
-                    break;
-                }
+    if encrypted && options.password.is_none() {
+        return Err(FsError::PasswordRequired(archive_path.to_path_buf()));
+    }
+
+    // 3. Extract
+    let mut writer = SanitizedWriter::new(
+        destination,
+        root_to_strip,
+        options,
+        archive.len(),
+        bytes_total,
+    );
+    for i in 0..archive.len() {
+        options.check_cancelled()?;
+
+        let file = match &options.password {
+            Some(password) => archive.by_index_decrypt(i, password.as_bytes()),
+            None => archive.by_index(i),
+        };
+        let mut file = file.map_err(|e| match e {
+            zip::result::ZipError::InvalidPassword => {
+                FsError::InvalidPassword(archive_path.to_path_buf())
+            }
+            zip::result::ZipError::UnsupportedArchive(zip::result::ZipError::PASSWORD_REQUIRED) => {
+                FsError::PasswordRequired(archive_path.to_path_buf())
+            }
+            other => FsError::Io(io::Error::other(other)),
+        })?;
+
+        let name = file.name().to_string();
+        let is_dir = file.is_dir();
+        writer.write_entry(i, &name, is_dir, &mut file)?;
+    }
+
+    Ok(writer.finish())
+}
+
+fn extract_7z(
+    archive_path: &Path,
+    destination: &Path,
+    options: &ExtractOptions,
+) -> Result<ExtractionReport> {
+    let password = options
+        .password
+        .as_deref()
+        .map(sevenz_rust::Password::from)
+        .unwrap_or_else(sevenz_rust::Password::empty);
+    let has_password = options.password.is_some();
+    let map_err = |e| map_7z_error(e, archive_path, has_password);
+
+    // Encrypted headers already fail here with PasswordRequired
+    let archive =
+        sevenz_rust::Archive::open_with_password(archive_path, &password).map_err(map_err)?;
+
+    let encrypted = archive.folders.iter().any(|f| {
+        f.coders
+            .iter()
+            .any(|c| c.decompression_method_id() == sevenz_rust::SevenZMethod::ID_AES256SHA256)
+    });
+    if encrypted && !has_password {
+        return Err(FsError::PasswordRequired(archive_path.to_path_buf()));
+    }
+
+    let limits = &options.limits;
+    limits.check_count(archive.files.len())?;
+
+    // Solid blocks have no per-file compressed size, so the ratio is checked archive-wide
+    let bytes_total = archive
+        .files
+        .iter()
+        .fold(0u64, |acc, f| acc.saturating_add(f.size()));
+    let packed_size = fs::metadata(archive_path).map_err(FsError::Io)?.len();
+    limits.check_entry(&archive_path.to_string_lossy(), bytes_total, packed_size)?;
+    limits.check_total(bytes_total)?;
+
+    // 7z stores directories without the trailing slash zip uses, and some writers
+    // add an unnamed entry for the source folder itself
+    let names: Vec<String> = archive
+        .files
+        .iter()
+        .filter(|f| !f.name().is_empty())
+        .map(|f| {
+            if f.is_directory() {
+                format!("{}/", f.name())
             } else {
-                common_root = Some(name.to_string());
+                f.name().to_string()
             }
-            continue;
-        }
+        })
+        .collect();
+    let root_to_strip = detect_common_root(names.iter().map(|n| n.as_str()));
+    let entries_total = archive.files.len();
 
-        if let Some(root) = &common_root {
-            if !name.starts_with(root) {
-                all_in_root = false;
-                break;
+    let source = fs::File::open(archive_path).map_err(FsError::Io)?;
+    let mut reader = sevenz_rust::SevenZReader::from_archive(archive, source, password);
+    let mut writer = SanitizedWriter::new(
+        destination,
+        root_to_strip,
+        options,
+        entries_total,
+        bytes_total,
+    );
+
+    let mut failure: Option<FsError> = None;
+    let mut index = 0;
+    let result = reader.for_each_entries(|entry, data| {
+        let outcome = options
+            .check_cancelled()
+            .and_then(|_| writer.write_entry(index, entry.name(), entry.is_directory(), data));
+        index += 1;
+
+        match outcome {
+            Ok(true) => Ok(true),
+            Ok(false) => {
+                // Entries share one decoder stream, so skipped data must be drained
+                io::copy(data, &mut io::sink())?;
+                Ok(true)
             }
-        } else {
-            let parts: Vec<&str> = name.split('/').collect();
-            if parts.len() > 1 {
-                common_root = Some(format!("{}/", parts[0]));
-            } else {
-                all_in_root = false;
-                break;
+            Err(e) => {
+                failure = Some(e);
+                Err(sevenz_rust::Error::other("extraction aborted"))
             }
         }
+    });
+
+    if let Some(e) = failure {
+        return Err(e);
     }
+    result.map_err(map_err)?;
 
-    let root_to_strip = if all_in_root { common_root } else { None };
+    Ok(writer.finish())
+}
 
-    // 2. Extract
-    for i in 0..archive.len() {
-        let mut file = archive
-            .by_index(i)
-            .map_err(|e| FsError::Io(io::Error::other(e)))?;
-        let original_name = file.name();
+/// Copies an already extracted mod folder with the same rules as an archive: only
+/// allowed file types, a single top-level folder stripped, limits, progress and
+/// cancellation. Symlinks are not followed.
+pub fn copy_and_sanitize_with(
+    source: &Path,
+    destination: &Path,
+    options: &ExtractOptions,
+) -> Result<ExtractionReport> {
+    if !source.is_dir() {
+        return Err(FsError::NotFound(source.to_path_buf()));
+    }
+
+    let mut entries: Vec<(String, PathBuf, bool)> = Vec::new();
+    for entry in walkdir::WalkDir::new(source)
+        .min_depth(1)
+        .sort_by_file_name()
+    {
+        let entry = entry.map_err(|e| FsError::Io(e.into()))?;
+        let name = entry
+            .path()
+            .strip_prefix(source)
+            .unwrap_or(entry.path())
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let file_type = entry.file_type();
+        if file_type.is_dir() {
+            entries.push((format!("{}/", name), entry.into_path(), true));
+        } else if file_type.is_file() {
+            entries.push((name, entry.into_path(), false));
+        }
+    }
 
-        let stripped_name = if let Some(root) = &root_to_strip {
+    let limits = &options.limits;
+    limits.check_count(entries.len())?;
+    let mut bytes_total: u64 = 0;
+    for (_, path, is_dir) in &entries {
+        if !is_dir {
+            let size = fs::metadata(path).map_err(FsError::Io)?.len();
+            bytes_total = bytes_total.saturating_add(size);
+        }
+    }
+    limits.check_total(bytes_total)?;
+
+    let root_to_strip = detect_common_root(entries.iter().map(|(n, _, _)| n.as_str()));
+    let mut writer = SanitizedWriter::new(
+        destination,
+        root_to_strip,
+        options,
+        entries.len(),
+        bytes_total,
+    );
+    for (index, (name, path, is_dir)) in entries.iter().enumerate() {
+        options.check_cancelled()?;
+        if *is_dir {
+            writer.write_entry(index, name, true, &mut io::empty())?;
+            continue;
+        }
+        let mut file = fs::File::open(path).map_err(FsError::Io)?;
+        writer.write_entry(index, name, false, &mut file)?;
+    }
+
+    Ok(writer.finish())
+}
+
+pub(crate) fn map_7z_error(
+    e: sevenz_rust::Error,
+    archive_path: &Path,
+    has_password: bool,
+) -> FsError {
+    match e {
+        sevenz_rust::Error::PasswordRequired => {
+            FsError::PasswordRequired(archive_path.to_path_buf())
+        }
+        sevenz_rust::Error::MaybeBadPassword(_) => {
+            FsError::InvalidPassword(archive_path.to_path_buf())
+        }
+        sevenz_rust::Error::ChecksumVerificationFailed if has_password => {
+            FsError::InvalidPassword(archive_path.to_path_buf())
+        }
+        other => FsError::Io(io::Error::other(other.to_string())),
+    }
+}
+
+/// Applies the mod sanitization rules to entries coming out of any archive format.
+struct SanitizedWriter<'a> {
+    destination: &'a Path,
+    root_to_strip: Option<String>,
+    is_loader_import: bool,
+    options: &'a ExtractOptions,
+    report: ExtractionReport,
+    progress: ExtractionProgress,
+}
+
+impl<'a> SanitizedWriter<'a> {
+    fn new(
+        destination: &'a Path,
+        root_to_strip: Option<String>,
+        options: &'a ExtractOptions,
+        entries_total: usize,
+        bytes_total: u64,
+    ) -> Self {
+        // Sanitization & Validation (Only for mods, loaders might have different rules)
+        // If it's a DLL/EXE and we are NOT in a 'loader' named path, ignore it.
+        let is_loader_import = destination.to_string_lossy().contains("loaders");
+
+        let progress = ExtractionProgress {
+            entries_done: 0,
+            entries_total,
+            bytes_done: 0,
+            bytes_total,
+            current_file: String::new(),
+        };
+        options.report(&progress);
+
+        Self {
+            destination,
+            root_to_strip,
+            is_loader_import,
+            options,
+            report: ExtractionReport {
+                files_ignored: Vec::new(),
+                has_mod_json: false,
+                has_modinfo_json: false,
+            },
+            progress,
+        }
+    }
+
+    /// Writes one entry, returning `false` if it was skipped without reading `data`.
+    fn write_entry(
+        &mut self,
+        index: usize,
+        original_name: &str,
+        is_dir: bool,
+        data: &mut dyn Read,
+    ) -> Result<bool> {
+        self.progress.entries_done = index;
+
+        let stripped_name = if let Some(root) = &self.root_to_strip {
             if original_name == root {
-                continue;
+                return Ok(false);
             }
-            original_name.strip_prefix(root).unwrap_or(original_name)
+            original_name
+                .strip_prefix(root.as_str())
+                .unwrap_or(original_name)
         } else {
             original_name
         };
 
-        if file.is_dir() {
-            continue;
+        if is_dir {
+            return Ok(false);
         }
 
-        // Sanitization & Validation (Only for mods, loaders might have different rules)
-        // Check if we are in a 'loader' mode vs 'mod' mode?
-        // Actually, let's keep sanitization flexible or add a flag.
-        // For now, if it's a DLL/EXE and we are NOT in a 'loader' named path, ignore it.
-        let is_loader_import = destination.to_string_lossy().contains("loaders");
-
-        if !is_loader_import && !is_allowed(stripped_name) {
-            report.files_ignored.push(stripped_name.to_string());
-            continue;
+        if !self.is_loader_import && !is_allowed(stripped_name) {
+            self.report.files_ignored.push(stripped_name.to_string());
+            return Ok(false);
         }
 
-        let safe_path = sanitize_path(destination, stripped_name)?;
+        let safe_path = sanitize_path(self.destination, stripped_name)?;
 
         if let Some(name) = safe_path.file_name().and_then(|n| n.to_str()) {
             if name == "mod.json" {
-                report.has_mod_json = true;
+                self.report.has_mod_json = true;
             } else if name == "modinfo.json" {
-                report.has_modinfo_json = true;
+                self.report.has_modinfo_json = true;
             }
         }
 
@@ -108,11 +512,88 @@ pub fn extract_and_sanitize(archive_path: &Path, destination: &Path) -> Result<E
             }
         }
 
+        self.progress.current_file = stripped_name.to_string();
         let mut outfile = fs::File::create(&safe_path).map_err(FsError::Io)?;
-        io::copy(&mut file, &mut outfile).map_err(FsError::Io)?;
+        copy_tracked(data, &mut outfile, self.options, &mut self.progress)?;
+        Ok(true)
+    }
+
+    fn finish(mut self) -> ExtractionReport {
+        self.progress.entries_done = self.progress.entries_total;
+        self.options.report(&self.progress);
+        self.report
+    }
+}
+
+/// Copies one entry while enforcing the size limit on the bytes actually produced
+/// (headers can lie) and emitting progress / honouring cancellation.
+fn copy_tracked(
+    reader: &mut dyn Read,
+    writer: &mut impl Write,
+    options: &ExtractOptions,
+    progress: &mut ExtractionProgress,
+) -> Result<()> {
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut since_report = 0;
+
+    options.report(progress);
+    loop {
+        let read = reader.read(&mut buffer).map_err(FsError::Io)?;
+        if read == 0 {
+            break;
+        }
+
+        progress.bytes_done += read as u64;
+        if progress.bytes_done > options.limits.max_total_size {
+            return Err(FsError::ArchiveLimit(format!(
+                "more than {} bytes written",
+                options.limits.max_total_size
+            )));
+        }
+        writer.write_all(&buffer[..read]).map_err(FsError::Io)?;
+
+        since_report += read as u64;
+        if since_report >= PROGRESS_INTERVAL_BYTES {
+            since_report = 0;
+            options.check_cancelled()?;
+            options.report(progress);
+        }
+    }
+    Ok(())
+}
+
+/// Returns the single top-level directory (with trailing `/`) that every entry lives under,
+/// if there is one. GitHub and most mod-site zips wrap their content this way.
+pub(crate) fn detect_common_root<'a>(names: impl IntoIterator<Item = &'a str>) -> Option<String> {
+    let mut common_root: Option<String> = None;
+
+    for name in names {
+        if name.ends_with('/') && name.chars().filter(|&c| c == '/').count() == 1 {
+            if let Some(root) = &common_root {
+                if root != name {
+                    return None;
+                }
+            } else {
+                common_root = Some(name.to_string());
+            }
+            continue;
+        }
+
+        if let Some(root) = &common_root {
+            if !name.starts_with(root) {
+                return None;
+            }
+        } else {
+            let parts: Vec<&str> = name.split('/').collect();
+            if parts.len() > 1 {
+                common_root = Some(format!("{}/", parts[0]));
+            } else {
+                return None;
+            }
+        }
     }
 
-    Ok(report)
+    common_root
 }
 
 pub fn extract_targz(archive_path: &Path, destination: &Path) -> Result<()> {
@@ -0,0 +1,335 @@
+use crate::archive::{detect_common_root, detect_format, is_allowed, map_7z_error, ArchiveFormat};
+use crate::error::{FsError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+/// Extensions that are never imported and are worth warning the user about.
+const EXECUTABLE_EXTENSIONS: &[&str] = &[
+    "exe", "dll", "bat", "cmd", "ps1", "vbs", "js", "scr", "msi", "com", "sh",
+];
+
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "webp"];
+
+/// Most [`ArchiveInspector::read_entry`] will hold in memory. Previews and metadata are
+/// far smaller; the declared size in the header is not trusted.
+pub const MAX_ENTRY_READ: u64 = 32 * 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveEntry {
+    /// Path inside the archive, always `/` separated.
+    pub path: String,
+    pub size: u64,
+    pub compressed_size: u64,
+    pub is_dir: bool,
+    /// Whether `extract_and_sanitize` would keep this entry.
+    pub allowed: bool,
+    pub encrypted: bool,
+}
+
+/// Summary of an archive's content, built from its directory listing alone.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivePreview {
+    pub format: ArchiveFormat,
+    pub entries: Vec<ArchiveEntry>,
+    pub total_size: u64,
+    pub file_count: usize,
+    /// Top-level folder that extraction would strip, if any.
+    pub common_root: Option<String>,
+    pub ini_files: Vec<String>,
+    pub preview_images: Vec<String>,
+    /// Top-most folders containing INIs. More than one usually means a mod pack.
+    pub mod_roots: Vec<String>,
+    pub blocked_executables: Vec<String>,
+    pub files_ignored: Vec<String>,
+    pub has_mod_json: bool,
+    pub has_modinfo_json: bool,
+    /// Extraction will need a password (see `ExtractOptions::password`).
+    pub encrypted: bool,
+}
+
+impl ArchivePreview {
+    pub fn is_multi_mod(&self) -> bool {
+        self.mod_roots.len() > 1
+    }
+}
+
+/// Reads archive listings and single entries without extracting to disk.
+pub struct ArchiveInspector {
+    path: PathBuf,
+    format: ArchiveFormat,
+    password: Option<String>,
+}
+
+impl ArchiveInspector {
+    pub fn open(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Err(FsError::NotFound(path.to_path_buf()));
+        }
+
+        let format = detect_format(path)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            format,
+            password: None,
+        })
+    }
+
+    /// Password used by [`ArchiveInspector::read_entry`] for encrypted archives.
+    pub fn with_password(mut self, password: Option<String>) -> Self {
+        self.password = password;
+        self
+    }
+
+    pub fn format(&self) -> ArchiveFormat {
+        self.format
+    }
+
+    /// Lists every entry in archive order.
+    pub fn entries(&self) -> Result<Vec<ArchiveEntry>> {
+        match self.format {
+            ArchiveFormat::Zip => {
+                let file = fs::File::open(&self.path).map_err(FsError::Io)?;
+                let mut archive =
+                    zip::ZipArchive::new(file).map_err(|e| FsError::Io(io::Error::other(e)))?;
+
+                let mut entries = Vec::with_capacity(archive.len());
+                for i in 0..archive.len() {
+                    let file = archive
+                        .by_index_raw(i)
+                        .map_err(|e| FsError::Io(io::Error::other(e)))?;
+                    entries.push(Self::make_entry(
+                        file.name(),
+                        file.size(),
+                        file.compressed_size(),
+                        file.is_dir(),
+                        file.encrypted(),
+                    ));
+                }
+                Ok(entries)
+            }
+            ArchiveFormat::SevenZ => {
+                let archive = sevenz_rust::Archive::open(&self.path).map_err(|e| match e {
+                    sevenz_rust::Error::PasswordRequired => {
+                        FsError::PasswordRequired(self.path.clone())
+                    }
+                    other => FsError::Io(io::Error::other(other)),
+                })?;
+
+                // 7z encrypts whole folders, so the flag applies to every stored file
+                let encrypted = archive.folders.iter().any(|f| {
+                    f.coders.iter().any(|c| {
+                        c.decompression_method_id() == sevenz_rust::SevenZMethod::ID_AES256SHA256
+                    })
+                });
+
+                Ok(archive
+                    .files
+                    .iter()
+                    .filter(|f| !f.name().is_empty())
+                    .map(|f| {
+                        Self::make_entry(
+                            f.name(),
+                            f.size(),
+                            f.compressed_size,
+                            f.is_directory(),
+                            encrypted && f.has_stream(),
+                        )
+                    })
+                    .collect())
+            }
+        }
+    }
+
+    fn make_entry(
+        name: &str,
+        size: u64,
+        compressed_size: u64,
+        is_dir: bool,
+        encrypted: bool,
+    ) -> ArchiveEntry {
+        let mut path = name.replace('\\', "/");
+        if is_dir && !path.ends_with('/') {
+            path.push('/');
+        }
+        let allowed = !is_dir && is_allowed(&path);
+        ArchiveEntry {
+            path,
+            size,
+            compressed_size,
+            is_dir,
+            allowed,
+            encrypted,
+        }
+    }
+
+    /// Builds a full preview: tree, sizes, INIs, images, mod roots and blocked files.
+    pub fn inspect(&self) -> Result<ArchivePreview> {
+        let entries = self.entries()?;
+        let common_root = detect_common_root(entries.iter().map(|e| e.path.as_str()));
+
+        let mut preview = ArchivePreview {
+            format: self.format,
+            entries: Vec::new(),
+            total_size: 0,
+            file_count: 0,
+            common_root: common_root.clone(),
+            ini_files: Vec::new(),
+            preview_images: Vec::new(),
+            mod_roots: Vec::new(),
+            blocked_executables: Vec::new(),
+            files_ignored: Vec::new(),
+            has_mod_json: false,
+            has_modinfo_json: false,
+            encrypted: entries.iter().any(|e| e.encrypted),
+        };
+
+        let mut ini_dirs = BTreeSet::new();
+
+        for entry in &entries {
+            if entry.is_dir {
+                continue;
+            }
+            preview.file_count += 1;
+            preview.total_size += entry.size;
+
+            let relative = common_root
+                .as_deref()
+                .and_then(|root| entry.path.strip_prefix(root))
+                .unwrap_or(&entry.path);
+            let path = Path::new(relative);
+            let file_name = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("")
+                .to_lowercase();
+            let extension = path
+                .extension()
+                .and_then(|s| s.to_str())
+                .unwrap_or("")
+                .to_lowercase();
+
+            if !entry.allowed {
+                preview.files_ignored.push(entry.path.clone());
+                if EXECUTABLE_EXTENSIONS.contains(&extension.as_str()) {
+                    preview.blocked_executables.push(entry.path.clone());
+                }
+                continue;
+            }
+
+            if file_name == "mod.json" {
+                preview.has_mod_json = true;
+            } else if file_name == "modinfo.json" {
+                preview.has_modinfo_json = true;
+            }
+
+            if extension == "ini" {
+                preview.ini_files.push(entry.path.clone());
+                let parent = relative.rsplit_once('/').map(|(p, _)| p).unwrap_or("");
+                ini_dirs.insert(parent.to_string());
+            } else if IMAGE_EXTENSIONS.contains(&extension.as_str()) {
+                preview.preview_images.push(entry.path.clone());
+            }
+        }
+
+        // Prefer images that look like deliberate previews
+        preview
+            .preview_images
+            .sort_by_key(|p| !p.to_lowercase().contains("preview"));
+
+        preview.mod_roots = Self::collapse_roots(ini_dirs);
+        preview.entries = entries;
+        Ok(preview)
+    }
+
+    /// Keeps only folders that have no INI-bearing ancestor.
+    fn collapse_roots(dirs: BTreeSet<String>) -> Vec<String> {
+        let mut roots: Vec<String> = Vec::new();
+        for dir in dirs {
+            let nested = roots
+                .iter()
+                .any(|r| r.is_empty() || dir.starts_with(&format!("{}/", r)));
+            if !nested {
+                roots.push(dir);
+            }
+        }
+        roots
+    }
+
+    /// Reads a single entry (e.g. `preview.png`, `mod.json`) into memory, up to
+    /// [`MAX_ENTRY_READ`] bytes.
+    pub fn read_entry(&self, name: &str) -> Result<Vec<u8>> {
+        let not_found = || FsError::NotFound(self.path.join(name));
+
+        match self.format {
+            ArchiveFormat::Zip => {
+                let file = fs::File::open(&self.path).map_err(FsError::Io)?;
+                let mut archive =
+                    zip::ZipArchive::new(file).map_err(|e| FsError::Io(io::Error::other(e)))?;
+
+                let index = archive.index_for_name(name).ok_or_else(not_found)?;
+                let file = match &self.password {
+                    Some(password) => archive.by_index_decrypt(index, password.as_bytes()),
+                    None => archive.by_index(index),
+                };
+                let file = file.map_err(|e| match e {
+                    zip::result::ZipError::InvalidPassword => {
+                        FsError::InvalidPassword(self.path.clone())
+                    }
+                    zip::result::ZipError::UnsupportedArchive(
+                        zip::result::ZipError::PASSWORD_REQUIRED,
+                    ) => FsError::PasswordRequired(self.path.clone()),
+                    other => FsError::Io(io::Error::other(other)),
+                })?;
+
+                Self::read_limited(name, file)
+            }
+            ArchiveFormat::SevenZ => {
+                let password = self
+                    .password
+                    .as_deref()
+                    .map(sevenz_rust::Password::from)
+                    .unwrap_or_else(sevenz_rust::Password::empty);
+                let map_err = |e| map_7z_error(e, &self.path, self.password.is_some());
+                let mut reader =
+                    sevenz_rust::SevenZReader::open(&self.path, password).map_err(map_err)?;
+
+                // Solid blocks have to be decoded in order, so stream until the entry shows up
+                let mut found = None;
+                reader
+                    .for_each_entries(|entry, data| {
+                        if found.is_some() {
+                            return Ok(false);
+                        }
+                        if entry.name().replace('\\', "/") != name {
+                            // Entries share one decoder stream, so skipped data must be drained
+                            io::copy(data, &mut io::sink())?;
+                            return Ok(true);
+                        }
+                        found = Some(Self::read_limited(name, data));
+                        Ok(false)
+                    })
+                    .map_err(map_err)?;
+
+                found.ok_or_else(not_found)?
+            }
+        }
+    }
+
+    fn read_limited(name: &str, reader: impl Read) -> Result<Vec<u8>> {
+        let mut buffer = Vec::new();
+        reader
+            .take(MAX_ENTRY_READ + 1)
+            .read_to_end(&mut buffer)
+            .map_err(FsError::Io)?;
+        if buffer.len() as u64 > MAX_ENTRY_READ {
+            return Err(FsError::ArchiveLimit(format!(
+                "{} is larger than {} bytes",
+                name, MAX_ENTRY_READ
+            )));
+        }
+        Ok(buffer)
+    }
+}
@@ -0,0 +1,176 @@
+//! Rotating, timestamped copies of small state files (library databases, settings).
+//!
+//! Backups are named `<file name>.<unix millis>.bak` so they sort by age without
+//! trusting file times, which copies and restores from other machines do not keep.
+
+use crate::error::{FsError, Result};
+use crate::safety::Safety;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How many backups of each file are kept unless configured otherwise.
+pub const DEFAULT_BACKUP_COUNT: usize = 5;
+
+/// A damaged file that was replaced by one of its backups.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RestoredBackup {
+    pub file: PathBuf,
+    pub backup: PathBuf,
+    /// Where the damaged contents were moved, for a bug report or manual rescue.
+    pub corrupt_copy: Option<PathBuf>,
+}
+
+pub struct BackupRotation {
+    dir: PathBuf,
+    keep: usize,
+    min_interval: Duration,
+}
+
+impl BackupRotation {
+    /// Keeps the newest `keep` backups of each file in `dir`.
+    pub fn new(dir: impl Into<PathBuf>, keep: usize) -> Self {
+        Self {
+            dir: dir.into(),
+            keep: keep.max(1),
+            min_interval: Duration::ZERO,
+        }
+    }
+
+    /// Skips a snapshot while the newest backup is younger than `interval`, so files
+    /// saved on every click do not churn through their backups within seconds.
+    pub fn with_min_interval(mut self, interval: Duration) -> Self {
+        self.min_interval = interval;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Copies `file` into the backup folder and prunes the oldest copies. Returns the
+    /// new backup, or `None` when the file does not exist or a recent backup exists.
+    pub fn snapshot(&self, file: &Path) -> Result<Option<PathBuf>> {
+        self.snapshot_with(file, |backup| {
+            let data = std::fs::read(file)?;
+            Safety::write_atomic(backup, &data)
+        })
+    }
+
+    /// Like [`BackupRotation::snapshot`], but `write` produces the backup at the path it
+    /// is given, for files that cannot simply be copied while in use, such as databases.
+    /// The backup only gets its final name once `write` succeeded.
+    pub fn snapshot_with<F>(&self, file: &Path, write: F) -> Result<Option<PathBuf>>
+    where
+        F: FnOnce(&Path) -> Result<()>,
+    {
+        if !file.is_file() {
+            return Ok(None);
+        }
+        let name = Self::file_name(file)?;
+        let now = Self::now_millis();
+        let existing = self.stamped_backups(&name)?;
+        if let Some((newest, _)) = existing.first() {
+            let age = Duration::from_millis(now.saturating_sub(*newest));
+            if age < self.min_interval {
+                return Ok(None);
+            }
+        }
+
+        std::fs::create_dir_all(&self.dir)?;
+        // Two snapshots within the same millisecond still get distinct names
+        let mut stamp = now;
+        while existing.iter().any(|(s, _)| *s == stamp) {
+            stamp += 1;
+        }
+        let backup = self.dir.join(format!("{}.{}.bak", name, stamp));
+        let partial = self.dir.join(format!("{}.{}.partial", name, stamp));
+        if let Err(e) = write(&partial).and_then(|_| Ok(std::fs::rename(&partial, &backup)?)) {
+            let _ = std::fs::remove_file(&partial);
+            return Err(e);
+        }
+
+        for (_, old) in self.stamped_backups(&name)?.into_iter().skip(self.keep) {
+            let _ = std::fs::remove_file(old);
+        }
+        Ok(Some(backup))
+    }
+
+    /// Backups of `file`, newest first.
+    pub fn backups(&self, file: &Path) -> Result<Vec<PathBuf>> {
+        let name = Self::file_name(file)?;
+        Ok(self
+            .stamped_backups(&name)?
+            .into_iter()
+            .map(|(_, path)| path)
+            .collect())
+    }
+
+    /// Replaces `file` with its newest backup that `is_valid` accepts. The damaged file
+    /// is kept next to the backups as `<name>.<millis>.corrupt`. Returns `None` when no
+    /// backup is usable, leaving `file` untouched.
+    pub fn restore<F>(&self, file: &Path, is_valid: F) -> Result<Option<RestoredBackup>>
+    where
+        F: Fn(&[u8]) -> bool,
+    {
+        let name = Self::file_name(file)?;
+        for backup in self.backups(file)? {
+            let Ok(data) = std::fs::read(&backup) else {
+                continue;
+            };
+            if !is_valid(&data) {
+                continue;
+            }
+
+            let corrupt_copy = if file.exists() {
+                let copy = self
+                    .dir
+                    .join(format!("{}.{}.corrupt", name, Self::now_millis()));
+                std::fs::copy(file, &copy)?;
+                Some(copy)
+            } else {
+                None
+            };
+            Safety::write_atomic(file, &data)?;
+            return Ok(Some(RestoredBackup {
+                file: file.to_path_buf(),
+                backup,
+                corrupt_copy,
+            }));
+        }
+        Ok(None)
+    }
+
+    fn stamped_backups(&self, name: &str) -> Result<Vec<(u64, PathBuf)>> {
+        if !self.dir.is_dir() {
+            return Ok(Vec::new());
+        }
+        let prefix = format!("{}.", name);
+        let mut backups = Vec::new();
+        for entry in std::fs::read_dir(&self.dir)?.filter_map(|e| e.ok()) {
+            let entry_name = entry.file_name().to_string_lossy().to_string();
+            let stamp = entry_name
+                .strip_prefix(&prefix)
+                .and_then(|rest| rest.strip_suffix(".bak"))
+                .and_then(|stamp| stamp.parse::<u64>().ok());
+            if let Some(stamp) = stamp {
+                backups.push((stamp, entry.path()));
+            }
+        }
+        backups.sort_by_key(|b| std::cmp::Reverse(b.0));
+        Ok(backups)
+    }
+
+    fn file_name(file: &Path) -> Result<String> {
+        file.file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .ok_or_else(|| FsError::InvalidPath(file.to_path_buf()))
+    }
+
+    fn now_millis() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0)
+    }
+}
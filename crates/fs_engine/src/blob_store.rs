@@ -0,0 +1,410 @@
+//! Content-addressed storage for mod files.
+//!
+//! Versions and variants of the same character mod tend to ship identical textures of
+//! several hundred MB each. `BlobStore` keeps one copy of each large file, keyed by its
+//! SHA-256, and turns the files inside mod folders into hardlinks to that copy. Every
+//! mod's linked files are listed in a manifest under `refs/`; garbage collection and
+//! the space statistics go by those manifests, never by scanning the mods themselves.
+//!
+//! Hardlinks share their contents, so a file edited in place changes every mod linked
+//! to it. Only binary assets above [`MIN_BLOB_SIZE`] are linked (INIs stay private to
+//! their mod), and code that rewrites a linked file either replaces it through a rename,
+//! as the texture fixes do, or calls [`BlobStore::detach`] first.
+
+use crate::error::{FsError, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Smaller files are left alone; linking them saves little and costs an inode lookup.
+pub const MIN_BLOB_SIZE: u64 = 64 * 1024;
+
+/// Files that users and tools edit by hand, and that must never be shared between mods.
+const PRIVATE_EXTENSIONS: &[&str] = &["ini", "json", "txt", "md", "toml", "cfg", "hlsl"];
+
+/// A file's entry in a mod's manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlobRef {
+    pub hash: String,
+    pub size: u64,
+}
+
+/// Files of one mod that are linked into the store, by `/`-separated relative path.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    files: BTreeMap<String, BlobRef>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IngestReport {
+    /// Files now backed by a blob.
+    pub linked: usize,
+    /// Of those, files whose contents were already in the store.
+    pub deduplicated: usize,
+    /// Files that could not be linked (the store is on another filesystem, say) and
+    /// remain independent copies.
+    pub skipped: usize,
+    /// Bytes that are no longer stored twice.
+    pub bytes_saved: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoreStats {
+    pub blobs: usize,
+    /// Size of all blobs, each counted once.
+    pub stored_bytes: u64,
+    /// Mods with a manifest.
+    pub mods: usize,
+    pub linked_files: usize,
+    /// What the linked files would take up as separate copies.
+    pub logical_bytes: u64,
+    /// `logical_bytes` minus the size of the blobs they share.
+    pub saved_bytes: u64,
+    /// Blobs no manifest refers to; garbage collection removes them.
+    pub unreferenced_blobs: usize,
+    pub unreferenced_bytes: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GcReport {
+    pub removed_manifests: usize,
+    pub removed_blobs: usize,
+    /// Disk space actually released. A removed blob still linked from a folder outside
+    /// the store (a mod in the trash, say) frees nothing until that link goes too.
+    pub freed_bytes: u64,
+}
+
+pub struct BlobStore {
+    root: PathBuf,
+}
+
+impl BlobStore {
+    /// The store keeps `objects/` and `refs/` under `root`. Hardlinks cannot cross
+    /// filesystems, so `root` should sit on the same volume as the mods.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Where the blob with the given hash lives, fanned out by its first two digits.
+    pub fn blob_path(&self, hash: &str) -> PathBuf {
+        let fanout = hash.get(..2).unwrap_or("00");
+        self.root.join("objects").join(fanout).join(hash)
+    }
+
+    /// Lowercase hex SHA-256 of a file's contents.
+    pub fn hash_file(path: &Path) -> Result<String> {
+        let mut file = std::fs::File::open(path)?;
+        let mut hasher = Sha256::new();
+        let mut buffer = vec![0u8; 1024 * 1024];
+        loop {
+            let read = file.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+        }
+        Ok(hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect())
+    }
+
+    /// Links every eligible file under `dir` into the store and records them as the
+    /// files of `owner` (a mod id), replacing whatever that owner had recorded before.
+    pub fn ingest(&self, owner: &str, dir: &Path) -> Result<IngestReport> {
+        if !dir.is_dir() {
+            return Err(FsError::NotFound(dir.to_path_buf()));
+        }
+        let manifest_path = self.manifest_path(owner)?;
+
+        let mut report = IngestReport::default();
+        let mut manifest = Manifest::default();
+        for entry in WalkDir::new(dir).into_iter().filter_map(|e| e.ok()) {
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let path = entry.path();
+            let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+            if !Self::is_shareable(path, size) {
+                continue;
+            }
+            let Ok(relative) = path.strip_prefix(dir) else {
+                continue;
+            };
+
+            let hash = Self::hash_file(path)?;
+            match self.link_file(path, &hash, size) {
+                Ok(deduplicated) => {
+                    report.linked += 1;
+                    if deduplicated {
+                        report.deduplicated += 1;
+                        report.bytes_saved += size;
+                    }
+                    let key = relative
+                        .components()
+                        .map(|c| c.as_os_str().to_string_lossy())
+                        .collect::<Vec<_>>()
+                        .join("/");
+                    manifest.files.insert(key, BlobRef { hash, size });
+                }
+                Err(e) => {
+                    eprintln!("BlobStore: kept {} as a copy: {}", path.display(), e);
+                    report.skipped += 1;
+                }
+            }
+        }
+
+        if manifest.files.is_empty() {
+            let _ = std::fs::remove_file(&manifest_path);
+        } else {
+            Self::write_manifest(&manifest_path, &manifest)?;
+        }
+        Ok(report)
+    }
+
+    /// Forgets `owner`'s manifest. The blobs stay until the next garbage collection.
+    pub fn release(&self, owner: &str) -> Result<()> {
+        match std::fs::remove_file(self.manifest_path(owner)?) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Gives `path` its own copy of its contents so it can be edited in place without
+    /// touching the store or other mods.
+    pub fn detach(path: &Path) -> Result<()> {
+        let temp = Self::sibling_temp(path, "detach");
+        if let Err(e) = std::fs::copy(path, &temp).and_then(|_| std::fs::rename(&temp, path)) {
+            let _ = std::fs::remove_file(&temp);
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    pub fn stats(&self) -> Result<StoreStats> {
+        let mut stats = StoreStats::default();
+        let mut referenced: BTreeMap<String, u64> = BTreeMap::new();
+        for (_, manifest) in self.manifests()? {
+            stats.mods += 1;
+            for blob in manifest.files.values() {
+                stats.linked_files += 1;
+                stats.logical_bytes += blob.size;
+                referenced.insert(blob.hash.clone(), blob.size);
+            }
+        }
+
+        let mut shared_bytes = 0;
+        for (hash, size) in self.blobs()? {
+            stats.blobs += 1;
+            stats.stored_bytes += size;
+            if referenced.contains_key(&hash) {
+                shared_bytes += size;
+            } else {
+                stats.unreferenced_blobs += 1;
+                stats.unreferenced_bytes += size;
+            }
+        }
+        stats.saved_bytes = stats.logical_bytes.saturating_sub(shared_bytes);
+        Ok(stats)
+    }
+
+    /// Drops the manifests of owners not in `live` and then every blob no remaining
+    /// manifest refers to. Removing a blob never touches a mod's files: they are links
+    /// of their own and keep the contents alive.
+    pub fn collect_garbage(&self, live: &HashSet<String>) -> Result<GcReport> {
+        let mut report = GcReport::default();
+        let mut referenced: HashSet<String> = HashSet::new();
+        for (owner, manifest) in self.manifests()? {
+            if live.contains(&owner) {
+                referenced.extend(manifest.files.into_values().map(|b| b.hash));
+            } else {
+                std::fs::remove_file(self.manifest_path(&owner)?)?;
+                report.removed_manifests += 1;
+            }
+        }
+
+        for (hash, size) in self.blobs()? {
+            if referenced.contains(&hash) {
+                continue;
+            }
+            let path = self.blob_path(&hash);
+            let last_link = std::fs::metadata(&path)
+                .ok()
+                .and_then(|m| Self::link_count(&m))
+                .is_none_or(|links| links <= 1);
+            std::fs::remove_file(&path)?;
+            report.removed_blobs += 1;
+            if last_link {
+                report.freed_bytes += size;
+            }
+            if let Some(fanout) = path.parent() {
+                // Only succeeds once the fan-out folder is empty
+                let _ = std::fs::remove_dir(fanout);
+            }
+        }
+        Ok(report)
+    }
+
+    /// Makes `path` a link to the blob for `hash`. Returns whether the blob already
+    /// existed, i.e. whether a duplicate copy was dropped.
+    fn link_file(&self, path: &Path, hash: &str, size: u64) -> std::io::Result<bool> {
+        let blob = self.blob_path(hash);
+        if let Some(parent) = blob.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        match std::fs::metadata(&blob) {
+            Ok(_) if Self::same_file(path, &blob) => Ok(false),
+            // A blob whose size no longer matches was edited through some mod's link;
+            // this file becomes the blob instead of inheriting the damage
+            Ok(meta) if meta.len() != size => {
+                std::fs::remove_file(&blob)?;
+                std::fs::hard_link(path, &blob)?;
+                Ok(false)
+            }
+            Ok(_) => {
+                let temp = Self::sibling_temp(path, "link");
+                let linked =
+                    std::fs::hard_link(&blob, &temp).and_then(|_| std::fs::rename(&temp, path));
+                if linked.is_err() {
+                    let _ = std::fs::remove_file(&temp);
+                }
+                linked.map(|_| true)
+            }
+            Err(_) => match std::fs::hard_link(path, &blob) {
+                // Another import stored the same contents in the meantime
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    self.link_file(path, hash, size)
+                }
+                other => other.map(|_| false),
+            },
+        }
+    }
+
+    fn is_shareable(path: &Path, size: u64) -> bool {
+        if size < MIN_BLOB_SIZE {
+            return false;
+        }
+        let extension = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        !PRIVATE_EXTENSIONS.contains(&extension.as_str())
+    }
+
+    fn manifest_path(&self, owner: &str) -> Result<PathBuf> {
+        let valid = !owner.is_empty()
+            && !owner.starts_with('.')
+            && owner
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            return Err(FsError::InvalidPath(PathBuf::from(owner)));
+        }
+        Ok(self.root.join("refs").join(format!("{}.json", owner)))
+    }
+
+    fn write_manifest(path: &Path, manifest: &Manifest) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string_pretty(manifest)
+            .map_err(|e| FsError::Io(std::io::Error::other(e)))?;
+        let temp = Self::sibling_temp(path, "tmp");
+        std::fs::write(&temp, content)?;
+        std::fs::rename(&temp, path)?;
+        Ok(())
+    }
+
+    /// All readable manifests by owner. A manifest that fails to parse is skipped; at
+    /// worst its blobs get collected and its mod keeps plain copies.
+    fn manifests(&self) -> Result<Vec<(String, Manifest)>> {
+        let refs = self.root.join("refs");
+        if !refs.is_dir() {
+            return Ok(Vec::new());
+        }
+        let mut manifests = Vec::new();
+        for entry in std::fs::read_dir(&refs)?.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let Some(owner) = path.file_stem().map(|s| s.to_string_lossy().to_string()) else {
+                continue;
+            };
+            let parsed = std::fs::read_to_string(&path)
+                .ok()
+                .and_then(|content| serde_json::from_str::<Manifest>(&content).ok());
+            match parsed {
+                Some(manifest) => manifests.push((owner, manifest)),
+                None => eprintln!("BlobStore: unreadable manifest {}", path.display()),
+            }
+        }
+        manifests.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(manifests)
+    }
+
+    /// Every blob in the store with its size.
+    fn blobs(&self) -> Result<Vec<(String, u64)>> {
+        let objects = self.root.join("objects");
+        if !objects.is_dir() {
+            return Ok(Vec::new());
+        }
+        let mut blobs = Vec::new();
+        for entry in WalkDir::new(&objects)
+            .min_depth(2)
+            .max_depth(2)
+            .into_iter()
+            .filter_map(|e| e.ok())
+        {
+            if entry.file_type().is_file() {
+                let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+                blobs.push((entry.file_name().to_string_lossy().to_string(), size));
+            }
+        }
+        blobs.sort();
+        Ok(blobs)
+    }
+
+    fn sibling_temp(path: &Path, purpose: &str) -> PathBuf {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        path.with_file_name(format!(".{}.yago-{}", name, purpose))
+    }
+
+    #[cfg(unix)]
+    fn same_file(a: &Path, b: &Path) -> bool {
+        use std::os::unix::fs::MetadataExt;
+        match (std::fs::metadata(a), std::fs::metadata(b)) {
+            (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+            _ => false,
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn same_file(_a: &Path, _b: &Path) -> bool {
+        // Relinking an already linked file is harmless, just slower
+        false
+    }
+
+    #[cfg(unix)]
+    fn link_count(meta: &std::fs::Metadata) -> Option<u64> {
+        use std::os::unix::fs::MetadataExt;
+        Some(meta.nlink())
+    }
+
+    #[cfg(not(unix))]
+    fn link_count(_meta: &std::fs::Metadata) -> Option<u64> {
+        None
+    }
+}
@@ -0,0 +1,251 @@
+//! BC1, BC3 and BC4/BC5 block codecs.
+//!
+//! Blocks are 4x4 pixels in row-major order. Encoders pick endpoints along the
+//! principal axis of the block and then assign the nearest palette entry.
+
+/// A decoded 4x4 block, row-major RGBA.
+pub(super) type Block = [[u8; 4]; 16];
+
+/// Endpoints spanning the block along its principal axis over the first `channels` channels.
+/// The remaining channels are left at the block mean.
+pub(super) fn principal_endpoints(pixels: &Block, channels: usize) -> ([f32; 4], [f32; 4]) {
+    let mut mean = [0.0f32; 4];
+    for pixel in pixels {
+        for channel in 0..4 {
+            mean[channel] += pixel[channel] as f32 / 16.0;
+        }
+    }
+
+    let mut covariance = [[0.0f32; 4]; 4];
+    for pixel in pixels {
+        for i in 0..channels {
+            for j in 0..channels {
+                covariance[i][j] += (pixel[i] as f32 - mean[i]) * (pixel[j] as f32 - mean[j]);
+            }
+        }
+    }
+
+    // Power iteration; a handful of steps is enough for 16 points
+    let mut axis = [1.0f32; 4];
+    for _ in 0..8 {
+        let mut next = [0.0f32; 4];
+        for i in 0..channels {
+            for j in 0..channels {
+                next[i] += covariance[i][j] * axis[j];
+            }
+        }
+        let length = next.iter().map(|v| v * v).sum::<f32>().sqrt();
+        if length < 1e-6 {
+            return (mean, mean);
+        }
+        axis = next.map(|v| v / length);
+    }
+
+    let mut min = f32::MAX;
+    let mut max = f32::MIN;
+    for pixel in pixels {
+        let projection: f32 = (0..channels)
+            .map(|c| (pixel[c] as f32 - mean[c]) * axis[c])
+            .sum();
+        min = min.min(projection);
+        max = max.max(projection);
+    }
+
+    let mut low = mean;
+    let mut high = mean;
+    for channel in 0..channels {
+        low[channel] = (mean[channel] + axis[channel] * min).clamp(0.0, 255.0);
+        high[channel] = (mean[channel] + axis[channel] * max).clamp(0.0, 255.0);
+    }
+    (low, high)
+}
+
+fn unpack_565(color: u16) -> [u8; 4] {
+    let r = ((color >> 11) & 0x1f) as u8;
+    let g = ((color >> 5) & 0x3f) as u8;
+    let b = (color & 0x1f) as u8;
+    [
+        (r << 3) | (r >> 2),
+        (g << 2) | (g >> 4),
+        (b << 3) | (b >> 2),
+        255,
+    ]
+}
+
+fn pack_565(color: [f32; 4]) -> u16 {
+    let r = (color[0] * 31.0 / 255.0).round().clamp(0.0, 31.0) as u16;
+    let g = (color[1] * 63.0 / 255.0).round().clamp(0.0, 63.0) as u16;
+    let b = (color[2] * 31.0 / 255.0).round().clamp(0.0, 31.0) as u16;
+    (r << 11) | (g << 5) | b
+}
+
+fn mix(a: [u8; 4], b: [u8; 4], wa: u16, wb: u16) -> [u8; 4] {
+    let total = wa + wb;
+    let mut out = [255u8; 4];
+    for channel in 0..3 {
+        out[channel] = ((a[channel] as u16 * wa + b[channel] as u16 * wb) / total) as u8;
+    }
+    out
+}
+
+fn bc1_palette(c0: u16, c1: u16, force_four_colors: bool) -> [[u8; 4]; 4] {
+    let p0 = unpack_565(c0);
+    let p1 = unpack_565(c1);
+    if c0 > c1 || force_four_colors {
+        [p0, p1, mix(p0, p1, 2, 1), mix(p0, p1, 1, 2)]
+    } else {
+        [p0, p1, mix(p0, p1, 1, 1), [0, 0, 0, 0]]
+    }
+}
+
+/// Decodes an 8-byte BC1 color block. BC2/BC3 color blocks always use four colors.
+pub(super) fn decode_bc1(block: &[u8], force_four_colors: bool) -> Block {
+    let c0 = u16::from_le_bytes([block[0], block[1]]);
+    let c1 = u16::from_le_bytes([block[2], block[3]]);
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+    let palette = bc1_palette(c0, c1, force_four_colors);
+
+    let mut pixels = [[0u8; 4]; 16];
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        *pixel = palette[((indices >> (i * 2)) & 0b11) as usize];
+    }
+    pixels
+}
+
+fn color_distance(a: &[u8; 4], b: &[u8; 4]) -> u32 {
+    (0..3)
+        .map(|c| {
+            let d = a[c] as i32 - b[c] as i32;
+            (d * d) as u32
+        })
+        .sum()
+}
+
+/// Encodes an 8-byte BC1 color block. With `allow_alpha`, pixels under 50% alpha
+/// switch the block to the three-color mode with a transparent entry.
+pub(super) fn encode_bc1(pixels: &Block, allow_alpha: bool) -> [u8; 8] {
+    let transparent = allow_alpha && pixels.iter().any(|p| p[3] < 128);
+    let (low, high) = principal_endpoints(pixels, 3);
+    let mut c0 = pack_565(high);
+    let mut c1 = pack_565(low);
+
+    // Endpoint order selects the mode: c0 > c1 is four colors, otherwise three plus transparent
+    if (transparent && c0 > c1) || (!transparent && c0 < c1) {
+        std::mem::swap(&mut c0, &mut c1);
+    }
+
+    let palette = bc1_palette(c0, c1, !allow_alpha);
+    let candidates = if transparent || (c0 == c1 && allow_alpha) {
+        3
+    } else {
+        4
+    };
+
+    let mut indices = 0u32;
+    for (i, pixel) in pixels.iter().enumerate() {
+        let index = if transparent && pixel[3] < 128 {
+            3
+        } else {
+            (0..candidates)
+                .min_by_key(|&k| color_distance(pixel, &palette[k]))
+                .unwrap_or(0)
+        };
+        indices |= (index as u32) << (i * 2);
+    }
+
+    let mut block = [0u8; 8];
+    block[0..2].copy_from_slice(&c0.to_le_bytes());
+    block[2..4].copy_from_slice(&c1.to_le_bytes());
+    block[4..8].copy_from_slice(&indices.to_le_bytes());
+    block
+}
+
+fn bc4_palette(a0: u8, a1: u8) -> [u8; 8] {
+    let (a0w, a1w) = (a0 as u32, a1 as u32);
+    let mut palette = [a0, a1, 0, 0, 0, 0, 0, 255];
+    if a0 > a1 {
+        for i in 1..7u32 {
+            palette[i as usize + 1] = (((7 - i) * a0w + i * a1w) / 7) as u8;
+        }
+    } else {
+        for i in 1..5u32 {
+            palette[i as usize + 1] = (((5 - i) * a0w + i * a1w) / 5) as u8;
+        }
+        palette[6] = 0;
+    }
+    palette
+}
+
+/// Decodes an 8-byte BC4 block (BC3 alpha, one BC5 channel) into 16 values.
+pub(super) fn decode_bc4(block: &[u8]) -> [u8; 16] {
+    let palette = bc4_palette(block[0], block[1]);
+    let mut index_bytes = [0u8; 8];
+    index_bytes[..6].copy_from_slice(&block[2..8]);
+    let indices = u64::from_le_bytes(index_bytes);
+
+    let mut values = [0u8; 16];
+    for (i, value) in values.iter_mut().enumerate() {
+        *value = palette[((indices >> (i * 3)) & 0b111) as usize];
+    }
+    values
+}
+
+/// Encodes 16 values as an 8-byte BC4 block using the eight-value mode.
+pub(super) fn encode_bc4(values: &[u8; 16]) -> [u8; 8] {
+    let max = values.iter().copied().max().unwrap_or(0);
+    let min = values.iter().copied().min().unwrap_or(0);
+
+    let mut block = [0u8; 8];
+    block[0] = max;
+    block[1] = min;
+    if max == min {
+        return block;
+    }
+
+    let palette = bc4_palette(max, min);
+    let mut indices = 0u64;
+    for (i, value) in values.iter().enumerate() {
+        let index = (0..8)
+            .min_by_key(|&k| (palette[k] as i32 - *value as i32).abs())
+            .unwrap_or(0);
+        indices |= (index as u64) << (i * 3);
+    }
+    block[2..8].copy_from_slice(&indices.to_le_bytes()[..6]);
+    block
+}
+
+pub(super) fn decode_bc3(block: &[u8]) -> Block {
+    let alpha = decode_bc4(&block[0..8]);
+    let mut pixels = decode_bc1(&block[8..16], true);
+    for (pixel, a) in pixels.iter_mut().zip(alpha) {
+        pixel[3] = a;
+    }
+    pixels
+}
+
+pub(super) fn encode_bc3(pixels: &Block) -> [u8; 16] {
+    let alpha = pixels.map(|p| p[3]);
+    let mut block = [0u8; 16];
+    block[0..8].copy_from_slice(&encode_bc4(&alpha));
+    block[8..16].copy_from_slice(&encode_bc1(pixels, false));
+    block
+}
+
+/// BC5 stores X in red and Y in green. Blue is left at zero like the hardware does;
+/// callers that need Z reconstruct it.
+pub(super) fn decode_bc5(block: &[u8]) -> Block {
+    let red = decode_bc4(&block[0..8]);
+    let green = decode_bc4(&block[8..16]);
+    let mut pixels = [[0u8; 4]; 16];
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        *pixel = [red[i], green[i], 0, 255];
+    }
+    pixels
+}
+
+pub(super) fn encode_bc5(pixels: &Block) -> [u8; 16] {
+    let mut block = [0u8; 16];
+    block[0..8].copy_from_slice(&encode_bc4(&pixels.map(|p| p[0])));
+    block[8..16].copy_from_slice(&encode_bc4(&pixels.map(|p| p[1])));
+    block
+}
@@ -0,0 +1,554 @@
+//! BC7 (BPTC) block codec.
+//!
+//! Decoding covers all eight modes. Encoding only emits mode 6 (one subset, 7-bit RGBA
+//! endpoints with P-bits, 4-bit indices), which is plenty for re-saving a normal map
+//! that was already lossy.
+
+use super::bc::{principal_endpoints, Block};
+
+struct Mode {
+    subsets: usize,
+    partition_bits: u32,
+    rotation_bits: u32,
+    index_selection_bits: u32,
+    color_bits: u32,
+    alpha_bits: u32,
+    endpoint_pbits: bool,
+    shared_pbits: bool,
+    index_bits: u32,
+    secondary_index_bits: u32,
+}
+
+const MODES: [Mode; 8] = [
+    Mode {
+        subsets: 3,
+        partition_bits: 4,
+        rotation_bits: 0,
+        index_selection_bits: 0,
+        color_bits: 4,
+        alpha_bits: 0,
+        endpoint_pbits: true,
+        shared_pbits: false,
+        index_bits: 3,
+        secondary_index_bits: 0,
+    },
+    Mode {
+        subsets: 2,
+        partition_bits: 6,
+        rotation_bits: 0,
+        index_selection_bits: 0,
+        color_bits: 6,
+        alpha_bits: 0,
+        endpoint_pbits: false,
+        shared_pbits: true,
+        index_bits: 3,
+        secondary_index_bits: 0,
+    },
+    Mode {
+        subsets: 3,
+        partition_bits: 6,
+        rotation_bits: 0,
+        index_selection_bits: 0,
+        color_bits: 5,
+        alpha_bits: 0,
+        endpoint_pbits: false,
+        shared_pbits: false,
+        index_bits: 2,
+        secondary_index_bits: 0,
+    },
+    Mode {
+        subsets: 2,
+        partition_bits: 6,
+        rotation_bits: 0,
+        index_selection_bits: 0,
+        color_bits: 7,
+        alpha_bits: 0,
+        endpoint_pbits: true,
+        shared_pbits: false,
+        index_bits: 2,
+        secondary_index_bits: 0,
+    },
+    Mode {
+        subsets: 1,
+        partition_bits: 0,
+        rotation_bits: 2,
+        index_selection_bits: 1,
+        color_bits: 5,
+        alpha_bits: 6,
+        endpoint_pbits: false,
+        shared_pbits: false,
+        index_bits: 2,
+        secondary_index_bits: 3,
+    },
+    Mode {
+        subsets: 1,
+        partition_bits: 0,
+        rotation_bits: 2,
+        index_selection_bits: 0,
+        color_bits: 7,
+        alpha_bits: 8,
+        endpoint_pbits: false,
+        shared_pbits: false,
+        index_bits: 2,
+        secondary_index_bits: 2,
+    },
+    Mode {
+        subsets: 1,
+        partition_bits: 0,
+        rotation_bits: 0,
+        index_selection_bits: 0,
+        color_bits: 7,
+        alpha_bits: 7,
+        endpoint_pbits: true,
+        shared_pbits: false,
+        index_bits: 4,
+        secondary_index_bits: 0,
+    },
+    Mode {
+        subsets: 2,
+        partition_bits: 6,
+        rotation_bits: 0,
+        index_selection_bits: 0,
+        color_bits: 5,
+        alpha_bits: 5,
+        endpoint_pbits: true,
+        shared_pbits: false,
+        index_bits: 2,
+        secondary_index_bits: 0,
+    },
+];
+
+const WEIGHTS_2: [u32; 4] = [0, 21, 43, 64];
+const WEIGHTS_3: [u32; 8] = [0, 9, 18, 27, 37, 46, 55, 64];
+const WEIGHTS_4: [u32; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+const PARTITIONS_2: [u16; 64] = [
+    0xcccc, 0x8888, 0xeeee, 0xecc8, 0xc880, 0xfeec, 0xfec8, 0xec80, 0xc800, 0xffec, 0xfe80, 0xe800,
+    0xffe8, 0xff00, 0xfff0, 0xf000, 0xf710, 0x008e, 0x7100, 0x08ce, 0x008c, 0x7310, 0x3100, 0x8cce,
+    0x088c, 0x3110, 0x6666, 0x366c, 0x17e8, 0x0ff0, 0x718e, 0x399c, 0xaaaa, 0xf0f0, 0x5a5a, 0x33cc,
+    0x3c3c, 0x55aa, 0x9696, 0xa55a, 0x73ce, 0x13c8, 0x324c, 0x3bdc, 0x6996, 0xc33c, 0x9966, 0x0660,
+    0x0272, 0x04e4, 0x4e40, 0x2720, 0xc936, 0x936c, 0x39c6, 0x639c, 0x9336, 0x9cc6, 0x817e, 0xe718,
+    0xccf0, 0x0fcc, 0x7744, 0xee22,
+];
+
+const ANCHOR_2: [u8; 64] = [
+    15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 2, 8, 2, 2, 8, 8, 15, 2, 8,
+    2, 2, 8, 8, 2, 2, 15, 15, 6, 8, 2, 8, 15, 15, 2, 8, 2, 2, 2, 15, 15, 6, 6, 2, 6, 8, 15, 15, 2,
+    2, 15, 15, 15, 15, 15, 2, 2, 15,
+];
+
+const ANCHOR_3A: [u8; 64] = [
+    3, 3, 15, 15, 8, 3, 15, 15, 8, 8, 6, 6, 6, 5, 3, 3, 3, 3, 8, 15, 3, 3, 6, 10, 5, 8, 8, 6, 8, 5,
+    15, 15, 8, 15, 3, 5, 6, 10, 8, 15, 15, 3, 15, 5, 15, 15, 15, 15, 3, 15, 5, 5, 5, 8, 5, 10, 5,
+    10, 8, 13, 15, 12, 3, 3,
+];
+
+const ANCHOR_3B: [u8; 64] = [
+    15, 8, 8, 3, 15, 15, 3, 8, 15, 15, 15, 15, 15, 15, 15, 8, 15, 8, 15, 3, 15, 8, 15, 8, 3, 15, 6,
+    10, 15, 15, 10, 8, 15, 3, 15, 10, 10, 8, 9, 10, 6, 15, 8, 15, 3, 6, 6, 8, 15, 3, 15, 15, 15,
+    15, 15, 15, 15, 15, 15, 15, 3, 15, 15, 8,
+];
+
+const PARTITIONS_3: [[u8; 16]; 64] = [
+    [0, 0, 1, 1, 0, 0, 1, 1, 0, 2, 2, 1, 2, 2, 2, 2],
+    [0, 0, 0, 1, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2, 2, 1],
+    [0, 0, 0, 0, 2, 0, 0, 1, 2, 2, 1, 1, 2, 2, 1, 1],
+    [0, 2, 2, 2, 0, 0, 2, 2, 0, 0, 1, 1, 0, 1, 1, 1],
+    [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2],
+    [0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 2, 2, 0, 0, 2, 2],
+    [0, 0, 2, 2, 0, 0, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1],
+    [0, 0, 1, 1, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2, 1, 1],
+    [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2],
+    [0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2],
+    [0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2],
+    [0, 0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2],
+    [0, 1, 1, 2, 0, 1, 1, 2, 0, 1, 1, 2, 0, 1, 1, 2],
+    [0, 1, 2, 2, 0, 1, 2, 2, 0, 1, 2, 2, 0, 1, 2, 2],
+    [0, 0, 1, 1, 0, 1, 1, 2, 1, 1, 2, 2, 1, 2, 2, 2],
+    [0, 0, 1, 1, 2, 0, 0, 1, 2, 2, 0, 0, 2, 2, 2, 0],
+    [0, 0, 0, 1, 0, 0, 1, 1, 0, 1, 1, 2, 1, 1, 2, 2],
+    [0, 1, 1, 1, 0, 0, 1, 1, 2, 0, 0, 1, 2, 2, 0, 0],
+    [0, 0, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2, 1, 1, 2, 2],
+    [0, 0, 2, 2, 0, 0, 2, 2, 0, 0, 2, 2, 1, 1, 1, 1],
+    [0, 1, 1, 1, 0, 1, 1, 1, 0, 2, 2, 2, 0, 2, 2, 2],
+    [0, 0, 0, 1, 0, 0, 0, 1, 2, 2, 2, 1, 2, 2, 2, 1],
+    [0, 0, 0, 0, 0, 0, 1, 1, 0, 1, 2, 2, 0, 1, 2, 2],
+    [0, 0, 0, 0, 1, 1, 0, 0, 2, 2, 1, 0, 2, 2, 1, 0],
+    [0, 1, 2, 2, 0, 1, 2, 2, 0, 0, 1, 1, 0, 0, 0, 0],
+    [0, 0, 1, 2, 0, 0, 1, 2, 1, 1, 2, 2, 2, 2, 2, 2],
+    [0, 1, 1, 0, 1, 2, 2, 1, 1, 2, 2, 1, 0, 1, 1, 0],
+    [0, 0, 0, 0, 0, 1, 1, 0, 1, 2, 2, 1, 1, 2, 2, 1],
+    [0, 0, 2, 2, 1, 1, 0, 2, 1, 1, 0, 2, 0, 0, 2, 2],
+    [0, 1, 1, 0, 0, 1, 1, 0, 2, 0, 0, 2, 2, 2, 2, 2],
+    [0, 0, 1, 1, 0, 1, 2, 2, 0, 1, 2, 2, 0, 0, 1, 1],
+    [0, 0, 0, 0, 2, 0, 0, 0, 2, 2, 1, 1, 2, 2, 2, 1],
+    [0, 0, 0, 0, 0, 0, 0, 2, 1, 1, 2, 2, 1, 2, 2, 2],
+    [0, 2, 2, 2, 0, 0, 2, 2, 0, 0, 1, 2, 0, 0, 1, 1],
+    [0, 0, 1, 1, 0, 0, 1, 2, 0, 0, 2, 2, 0, 2, 2, 2],
+    [0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2, 0],
+    [0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 0, 0, 0, 0],
+    [0, 1, 2, 0, 1, 2, 0, 1, 2, 0, 1, 2, 0, 1, 2, 0],
+    [0, 1, 2, 0, 2, 0, 1, 2, 1, 2, 0, 1, 0, 1, 2, 0],
+    [0, 0, 1, 1, 2, 2, 0, 0, 1, 1, 2, 2, 0, 0, 1, 1],
+    [0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 0, 0, 0, 0, 1, 1],
+    [0, 1, 0, 1, 0, 1, 0, 1, 2, 2, 2, 2, 2, 2, 2, 2],
+    [0, 0, 0, 0, 0, 0, 0, 0, 2, 1, 2, 1, 2, 1, 2, 1],
+    [0, 0, 2, 2, 1, 1, 2, 2, 0, 0, 2, 2, 1, 1, 2, 2],
+    [0, 0, 2, 2, 0, 0, 1, 1, 0, 0, 2, 2, 0, 0, 1, 1],
+    [0, 2, 2, 0, 1, 2, 2, 1, 0, 2, 2, 0, 1, 2, 2, 1],
+    [0, 1, 0, 1, 2, 2, 2, 2, 2, 2, 2, 2, 0, 1, 0, 1],
+    [0, 0, 0, 0, 2, 1, 2, 1, 2, 1, 2, 1, 2, 1, 2, 1],
+    [0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 2, 2, 2, 2],
+    [0, 2, 2, 2, 0, 1, 1, 1, 0, 2, 2, 2, 0, 1, 1, 1],
+    [0, 0, 0, 2, 1, 1, 1, 2, 0, 0, 0, 2, 1, 1, 1, 2],
+    [0, 0, 0, 0, 2, 1, 1, 2, 2, 1, 1, 2, 2, 1, 1, 2],
+    [0, 2, 2, 2, 0, 1, 1, 1, 0, 1, 1, 1, 0, 2, 2, 2],
+    [0, 0, 0, 2, 1, 1, 1, 2, 1, 1, 1, 2, 0, 0, 0, 2],
+    [0, 1, 1, 0, 0, 1, 1, 0, 0, 1, 1, 0, 2, 2, 2, 2],
+    [0, 0, 0, 0, 0, 0, 0, 0, 2, 1, 1, 2, 2, 1, 1, 2],
+    [0, 1, 1, 0, 0, 1, 1, 0, 2, 2, 2, 2, 2, 2, 2, 2],
+    [0, 0, 2, 2, 0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 2, 2],
+    [0, 0, 2, 2, 1, 1, 2, 2, 1, 1, 2, 2, 0, 0, 2, 2],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 1, 1, 2],
+    [0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 1],
+    [0, 2, 2, 2, 1, 2, 2, 2, 0, 2, 2, 2, 1, 2, 2, 2],
+    [0, 1, 0, 1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2],
+    [0, 1, 1, 1, 2, 0, 1, 1, 2, 2, 0, 1, 2, 2, 2, 0],
+];
+
+struct BitReader {
+    bits: u128,
+    pos: u32,
+}
+
+impl BitReader {
+    fn read(&mut self, count: u32) -> u32 {
+        if count == 0 {
+            return 0;
+        }
+        let value = (self.bits >> self.pos) as u32 & ((1u32 << count) - 1);
+        self.pos += count;
+        value
+    }
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bits: u128,
+    pos: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, count: u32) {
+        self.bits |= (value as u128 & ((1u128 << count) - 1)) << self.pos;
+        self.pos += count;
+    }
+}
+
+fn weights(bits: u32) -> &'static [u32] {
+    match bits {
+        2 => &WEIGHTS_2,
+        3 => &WEIGHTS_3,
+        _ => &WEIGHTS_4,
+    }
+}
+
+fn interpolate(e0: u32, e1: u32, weight: u32) -> u8 {
+    (((64 - weight) * e0 + weight * e1 + 32) >> 6) as u8
+}
+
+/// Expands an n-bit endpoint to 8 bits by replicating its high bits.
+fn unquantize(value: u32, bits: u32) -> u32 {
+    if bits >= 8 {
+        return value;
+    }
+    let value = value << (8 - bits);
+    value | (value >> bits)
+}
+
+fn subset_of(subsets: usize, partition: usize, pixel: usize) -> usize {
+    match subsets {
+        2 => ((PARTITIONS_2[partition] >> pixel) & 1) as usize,
+        3 => PARTITIONS_3[partition][pixel] as usize,
+        _ => 0,
+    }
+}
+
+fn is_anchor(subsets: usize, partition: usize, pixel: usize) -> bool {
+    pixel == 0
+        || match subsets {
+            2 => pixel == ANCHOR_2[partition] as usize,
+            3 => pixel == ANCHOR_3A[partition] as usize || pixel == ANCHOR_3B[partition] as usize,
+            _ => false,
+        }
+}
+
+pub(super) fn decode_block(block: &[u8]) -> Block {
+    let mut bits = [0u8; 16];
+    bits.copy_from_slice(&block[..16]);
+    let mut reader = BitReader {
+        bits: u128::from_le_bytes(bits),
+        pos: 0,
+    };
+
+    if block[0] == 0 {
+        // Reserved mode: the spec mandates transparent black
+        return [[0; 4]; 16];
+    }
+    let mode_index = block[0].trailing_zeros() as usize;
+    let mode = &MODES[mode_index];
+    reader.pos = mode_index as u32 + 1;
+
+    let partition = reader.read(mode.partition_bits) as usize;
+    let rotation = reader.read(mode.rotation_bits);
+    let index_selection = reader.read(mode.index_selection_bits);
+
+    let endpoint_count = mode.subsets * 2;
+    let mut endpoints = [[0u32; 4]; 6];
+    for channel in 0..3 {
+        for endpoint in endpoints.iter_mut().take(endpoint_count) {
+            endpoint[channel] = reader.read(mode.color_bits);
+        }
+    }
+    if mode.alpha_bits > 0 {
+        for endpoint in endpoints.iter_mut().take(endpoint_count) {
+            endpoint[3] = reader.read(mode.alpha_bits);
+        }
+    }
+
+    let mut color_bits = mode.color_bits;
+    let mut alpha_bits = mode.alpha_bits;
+    if mode.endpoint_pbits || mode.shared_pbits {
+        let mut pbits = [0u32; 6];
+        if mode.endpoint_pbits {
+            for pbit in pbits.iter_mut().take(endpoint_count) {
+                *pbit = reader.read(1);
+            }
+        } else {
+            for subset in 0..mode.subsets {
+                let pbit = reader.read(1);
+                pbits[subset * 2] = pbit;
+                pbits[subset * 2 + 1] = pbit;
+            }
+        }
+        for (endpoint, pbit) in endpoints.iter_mut().zip(pbits).take(endpoint_count) {
+            for channel in endpoint.iter_mut() {
+                *channel = (*channel << 1) | pbit;
+            }
+        }
+        color_bits += 1;
+        if alpha_bits > 0 {
+            alpha_bits += 1;
+        }
+    }
+
+    for endpoint in endpoints.iter_mut().take(endpoint_count) {
+        for channel in endpoint.iter_mut().take(3) {
+            *channel = unquantize(*channel, color_bits);
+        }
+        endpoint[3] = if alpha_bits > 0 {
+            unquantize(endpoint[3], alpha_bits)
+        } else {
+            255
+        };
+    }
+
+    let mut indices = [0u32; 16];
+    for (pixel, index) in indices.iter_mut().enumerate() {
+        let anchor = is_anchor(mode.subsets, partition, pixel) as u32;
+        *index = reader.read(mode.index_bits - anchor);
+    }
+    let mut secondary = [0u32; 16];
+    if mode.secondary_index_bits > 0 {
+        for (pixel, index) in secondary.iter_mut().enumerate() {
+            let anchor = (pixel == 0) as u32;
+            *index = reader.read(mode.secondary_index_bits - anchor);
+        }
+    }
+
+    let mut pixels = [[0u8; 4]; 16];
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        let subset = subset_of(mode.subsets, partition, i);
+        let e0 = endpoints[subset * 2];
+        let e1 = endpoints[subset * 2 + 1];
+
+        let (color_index, color_weights, alpha_index, alpha_weights) =
+            if mode.secondary_index_bits == 0 {
+                let w = weights(mode.index_bits);
+                (indices[i], w, indices[i], w)
+            } else if index_selection == 0 {
+                (
+                    indices[i],
+                    weights(mode.index_bits),
+                    secondary[i],
+                    weights(mode.secondary_index_bits),
+                )
+            } else {
+                (
+                    secondary[i],
+                    weights(mode.secondary_index_bits),
+                    indices[i],
+                    weights(mode.index_bits),
+                )
+            };
+
+        for channel in 0..3 {
+            pixel[channel] = interpolate(
+                e0[channel],
+                e1[channel],
+                color_weights[color_index as usize],
+            );
+        }
+        pixel[3] = interpolate(e0[3], e1[3], alpha_weights[alpha_index as usize]);
+
+        match rotation {
+            1 => pixel.swap(0, 3),
+            2 => pixel.swap(1, 3),
+            3 => pixel.swap(2, 3),
+            _ => {}
+        }
+    }
+    pixels
+}
+
+/// Quantized mode 6 endpoints: 7 bits per channel plus one P-bit each.
+#[derive(Clone, Copy)]
+struct Mode6Endpoints {
+    values: [[u32; 4]; 2],
+    pbits: [u32; 2],
+}
+
+impl Mode6Endpoints {
+    fn quantize(e0: [f32; 4], e1: [f32; 4], pbits: [u32; 2]) -> Self {
+        let mut values = [[0u32; 4]; 2];
+        for (target, (source, pbit)) in values.iter_mut().zip([(e0, pbits[0]), (e1, pbits[1])]) {
+            for channel in 0..4 {
+                let q = ((source[channel] - pbit as f32) / 2.0).round();
+                target[channel] = q.clamp(0.0, 127.0) as u32;
+            }
+        }
+        Self { values, pbits }
+    }
+
+    fn expanded(&self, endpoint: usize) -> [u32; 4] {
+        self.values[endpoint].map(|v| (v << 1) | self.pbits[endpoint])
+    }
+
+    fn palette(&self) -> [[u8; 4]; 16] {
+        let e0 = self.expanded(0);
+        let e1 = self.expanded(1);
+        let mut palette = [[0u8; 4]; 16];
+        for (entry, weight) in palette.iter_mut().zip(WEIGHTS_4) {
+            for channel in 0..4 {
+                entry[channel] = interpolate(e0[channel], e1[channel], weight);
+            }
+        }
+        palette
+    }
+}
+
+fn distance(a: &[u8; 4], b: &[u8; 4]) -> u32 {
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| {
+            let d = *x as i32 - *y as i32;
+            (d * d) as u32
+        })
+        .sum()
+}
+
+fn assign_indices(pixels: &Block, palette: &[[u8; 4]; 16]) -> ([u32; 16], u64) {
+    let mut indices = [0u32; 16];
+    let mut error = 0u64;
+    for (pixel, index) in pixels.iter().zip(indices.iter_mut()) {
+        let (best, best_error) = palette
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| (i, distance(pixel, entry)))
+            .min_by_key(|(_, e)| *e)
+            .unwrap_or((0, 0));
+        *index = best as u32;
+        error += best_error as u64;
+    }
+    (indices, error)
+}
+
+fn best_quantization(
+    pixels: &Block,
+    e0: [f32; 4],
+    e1: [f32; 4],
+) -> (Mode6Endpoints, [u32; 16], u64) {
+    let mut best: Option<(Mode6Endpoints, [u32; 16], u64)> = None;
+    for pbits in [[0, 0], [0, 1], [1, 0], [1, 1]] {
+        let endpoints = Mode6Endpoints::quantize(e0, e1, pbits);
+        let (indices, error) = assign_indices(pixels, &endpoints.palette());
+        if best.as_ref().is_none_or(|(_, _, e)| error < *e) {
+            best = Some((endpoints, indices, error));
+        }
+    }
+    best.expect("at least one P-bit combination")
+}
+
+/// Least-squares endpoints for fixed interpolation weights.
+fn refine_endpoints(pixels: &Block, indices: &[u32; 16]) -> Option<([f32; 4], [f32; 4])> {
+    let (mut aa, mut ab, mut bb) = (0.0f32, 0.0f32, 0.0f32);
+    let mut ax = [0.0f32; 4];
+    let mut bx = [0.0f32; 4];
+    for (pixel, index) in pixels.iter().zip(indices) {
+        let w = WEIGHTS_4[*index as usize] as f32 / 64.0;
+        let a = 1.0 - w;
+        aa += a * a;
+        ab += a * w;
+        bb += w * w;
+        for channel in 0..4 {
+            ax[channel] += a * pixel[channel] as f32;
+            bx[channel] += w * pixel[channel] as f32;
+        }
+    }
+    let det = aa * bb - ab * ab;
+    if det.abs() < 1e-6 {
+        return None;
+    }
+    let mut e0 = [0.0f32; 4];
+    let mut e1 = [0.0f32; 4];
+    for channel in 0..4 {
+        e0[channel] = ((bb * ax[channel] - ab * bx[channel]) / det).clamp(0.0, 255.0);
+        e1[channel] = ((aa * bx[channel] - ab * ax[channel]) / det).clamp(0.0, 255.0);
+    }
+    Some((e0, e1))
+}
+
+pub(super) fn encode_block(pixels: &Block) -> [u8; 16] {
+    let (e0, e1) = principal_endpoints(pixels, 4);
+    let (mut endpoints, mut indices, error) = best_quantization(pixels, e0, e1);
+
+    if let Some((r0, r1)) = refine_endpoints(pixels, &indices) {
+        let (refined, refined_indices, refined_error) = best_quantization(pixels, r0, r1);
+        if refined_error < error {
+            endpoints = refined;
+            indices = refined_indices;
+        }
+    }
+
+    // The anchor index is stored without its high bit, so it has to be < 8
+    if indices[0] >= 8 {
+        endpoints.values.swap(0, 1);
+        endpoints.pbits.swap(0, 1);
+        for index in indices.iter_mut() {
+            *index = 15 - *index;
+        }
+    }
+
+    let mut writer = BitWriter::default();
+    writer.write(1 << 6, 7);
+    for channel in 0..4 {
+        writer.write(endpoints.values[0][channel], 7);
+        writer.write(endpoints.values[1][channel], 7);
+    }
+    writer.write(endpoints.pbits[0], 1);
+    writer.write(endpoints.pbits[1], 1);
+    for (pixel, index) in indices.iter().enumerate() {
+        writer.write(*index, if pixel == 0 { 3 } else { 4 });
+    }
+    writer.bits.to_le_bytes()
+}
@@ -0,0 +1,512 @@
+//! DirectDraw Surface reading and writing with software BC1/BC3/BC5/BC7 codecs.
+//!
+//! Mods ship their textures as DDS, which the `image` crate cannot decode when they are
+//! block compressed. Everything here is CPU-only so it runs during import.
+
+mod bc;
+mod bc7;
+
+use crate::error::{FsError, Result};
+use bc::Block;
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+const MAGIC: &[u8; 4] = b"DDS ";
+const HEADER_SIZE: usize = 124;
+const DX10_HEADER_SIZE: usize = 20;
+
+/// Direct3D 11 limits for 2D textures. Anything larger is not a texture the game loads,
+/// and the header is untrusted input.
+const MAX_DIMENSION: u32 = 16384;
+const MAX_ARRAY_SIZE: u32 = 2048;
+
+const DDSD_CAPS: u32 = 0x1;
+const DDSD_HEIGHT: u32 = 0x2;
+const DDSD_WIDTH: u32 = 0x4;
+const DDSD_PITCH: u32 = 0x8;
+const DDSD_PIXELFORMAT: u32 = 0x1000;
+const DDSD_MIPMAPCOUNT: u32 = 0x20000;
+const DDSD_LINEARSIZE: u32 = 0x80000;
+const DDSD_DEPTH: u32 = 0x800000;
+
+const DDPF_ALPHAPIXELS: u32 = 0x1;
+const DDPF_FOURCC: u32 = 0x4;
+const DDPF_RGB: u32 = 0x40;
+
+const DDSCAPS_COMPLEX: u32 = 0x8;
+const DDSCAPS_TEXTURE: u32 = 0x1000;
+const DDSCAPS_MIPMAP: u32 = 0x400000;
+const DDSCAPS2_CUBEMAP_ALL_FACES: u32 = 0xfe00;
+
+const DX10_RESOURCE_TEXTURE2D: u32 = 3;
+const DX10_MISC_TEXTURECUBE: u32 = 0x4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DdsFormat {
+    Bc1,
+    Bc3,
+    Bc5,
+    Bc7,
+    Rgba8,
+    Bgra8,
+}
+
+impl DdsFormat {
+    fn from_dxgi(code: u32) -> Option<(Self, bool)> {
+        Some(match code {
+            28 => (Self::Rgba8, false),
+            29 => (Self::Rgba8, true),
+            71 => (Self::Bc1, false),
+            72 => (Self::Bc1, true),
+            77 => (Self::Bc3, false),
+            78 => (Self::Bc3, true),
+            83 => (Self::Bc5, false),
+            87 => (Self::Bgra8, false),
+            91 => (Self::Bgra8, true),
+            98 => (Self::Bc7, false),
+            99 => (Self::Bc7, true),
+            _ => return None,
+        })
+    }
+
+    fn dxgi(self, srgb: bool) -> u32 {
+        match (self, srgb) {
+            (Self::Rgba8, false) => 28,
+            (Self::Rgba8, true) => 29,
+            (Self::Bc1, false) => 71,
+            (Self::Bc1, true) => 72,
+            (Self::Bc3, false) => 77,
+            (Self::Bc3, true) => 78,
+            (Self::Bc5, _) => 83,
+            (Self::Bgra8, false) => 87,
+            (Self::Bgra8, true) => 91,
+            (Self::Bc7, false) => 98,
+            (Self::Bc7, true) => 99,
+        }
+    }
+
+    /// Bytes per 4x4 block, or `None` for uncompressed formats.
+    pub fn block_size(self) -> Option<usize> {
+        match self {
+            Self::Bc1 => Some(8),
+            Self::Bc3 | Self::Bc5 | Self::Bc7 => Some(16),
+            Self::Rgba8 | Self::Bgra8 => None,
+        }
+    }
+
+    pub fn is_compressed(self) -> bool {
+        self.block_size().is_some()
+    }
+
+    /// Size in bytes of one surface of the given dimensions.
+    pub fn surface_size(self, width: u32, height: u32) -> usize {
+        let (width, height) = (width.max(1) as usize, height.max(1) as usize);
+        match self.block_size() {
+            Some(block) => width.div_ceil(4) * height.div_ceil(4) * block,
+            None => width * height * 4,
+        }
+    }
+}
+
+/// The parts of a DDS header needed to decode a file and write it back equivalently.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DdsHeader {
+    pub width: u32,
+    pub height: u32,
+    pub mip_count: u32,
+    pub format: DdsFormat,
+    pub srgb: bool,
+    /// Number of array layers; cubemaps count six per layer.
+    pub layers: u32,
+    pub cubemap: bool,
+    /// File carried the DX10 extension header.
+    pub dx10: bool,
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+fn invalid(message: impl Into<String>) -> FsError {
+    FsError::Texture(message.into())
+}
+
+impl DdsHeader {
+    /// Parses the header and returns it with the offset where surface data starts.
+    pub fn parse(data: &[u8]) -> Result<(Self, usize)> {
+        if data.len() < 4 + HEADER_SIZE || &data[0..4] != MAGIC {
+            return Err(invalid("not a DDS file"));
+        }
+        if read_u32(data, 4) as usize != HEADER_SIZE {
+            return Err(invalid("bad DDS header size"));
+        }
+
+        let flags = read_u32(data, 8);
+        let height = read_u32(data, 12);
+        let width = read_u32(data, 16);
+        let depth = read_u32(data, 24);
+        let mip_count = read_u32(data, 28).max(1);
+        let pf_flags = read_u32(data, 80);
+        let four_cc = &data[84..88];
+        let bit_count = read_u32(data, 88);
+        let red_mask = read_u32(data, 92);
+        let caps2 = read_u32(data, 112);
+
+        if flags & DDSD_DEPTH != 0 && depth > 1 {
+            return Err(invalid("volume textures are not supported"));
+        }
+        if width == 0 || height == 0 {
+            return Err(invalid("DDS has zero dimensions"));
+        }
+        if width > MAX_DIMENSION || height > MAX_DIMENSION {
+            return Err(invalid(format!("DDS is too large ({}x{})", width, height)));
+        }
+        // A full chain ends at 1x1
+        let max_mips = 32 - width.max(height).leading_zeros();
+        if mip_count > max_mips {
+            return Err(invalid(format!(
+                "DDS claims {} mips for a {}x{} texture",
+                mip_count, width, height
+            )));
+        }
+
+        let mut header = DdsHeader {
+            width,
+            height,
+            mip_count,
+            format: DdsFormat::Rgba8,
+            srgb: false,
+            layers: 1,
+            cubemap: caps2 & DDSCAPS2_CUBEMAP_ALL_FACES != 0,
+            dx10: false,
+        };
+        let mut offset = 4 + HEADER_SIZE;
+
+        if pf_flags & DDPF_FOURCC != 0 {
+            header.format = match four_cc {
+                b"DXT1" => DdsFormat::Bc1,
+                b"DXT5" => DdsFormat::Bc3,
+                b"ATI2" | b"BC5U" => DdsFormat::Bc5,
+                b"DX10" => {
+                    if data.len() < offset + DX10_HEADER_SIZE {
+                        return Err(invalid("truncated DX10 header"));
+                    }
+                    let dxgi = read_u32(data, offset);
+                    let dimension = read_u32(data, offset + 4);
+                    let misc = read_u32(data, offset + 8);
+                    let array_size = read_u32(data, offset + 12).max(1);
+                    if dimension != DX10_RESOURCE_TEXTURE2D {
+                        return Err(invalid("only 2D textures are supported"));
+                    }
+                    if array_size > MAX_ARRAY_SIZE {
+                        return Err(invalid(format!("DDS has {} array layers", array_size)));
+                    }
+                    let (format, srgb) = DdsFormat::from_dxgi(dxgi)
+                        .ok_or_else(|| invalid(format!("unsupported DXGI format {}", dxgi)))?;
+                    header.srgb = srgb;
+                    header.layers = array_size;
+                    header.cubemap = misc & DX10_MISC_TEXTURECUBE != 0;
+                    header.dx10 = true;
+                    offset += DX10_HEADER_SIZE;
+                    format
+                }
+                other => {
+                    return Err(invalid(format!(
+                        "unsupported FourCC {}",
+                        String::from_utf8_lossy(other)
+                    )))
+                }
+            };
+        } else if pf_flags & DDPF_RGB != 0 && bit_count == 32 {
+            header.format = match red_mask {
+                0x0000_00ff => DdsFormat::Rgba8,
+                0x00ff_0000 => DdsFormat::Bgra8,
+                _ => return Err(invalid("unsupported RGB channel layout")),
+            };
+        } else {
+            return Err(invalid("unsupported pixel format"));
+        }
+
+        Ok((header, offset))
+    }
+
+    /// Mip dimensions for the given level.
+    pub fn mip_size(&self, level: u32) -> (u32, u32) {
+        let shift = |size: u32| size.checked_shr(level).unwrap_or(0).max(1);
+        (shift(self.width), shift(self.height))
+    }
+
+    /// Total number of surfaces (faces x layers x mips) in file order.
+    pub fn surface_count(&self) -> usize {
+        let faces = if self.cubemap { 6 } else { 1 };
+        self.layers as usize * faces * self.mip_count as usize
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        // BC7 has no legacy FourCC, so it always needs the DX10 header
+        let dx10 = self.dx10 || self.format == DdsFormat::Bc7 || self.layers > 1;
+        let compressed = self.format.is_compressed();
+
+        let mut flags = DDSD_CAPS | DDSD_HEIGHT | DDSD_WIDTH | DDSD_PIXELFORMAT;
+        flags |= if compressed {
+            DDSD_LINEARSIZE
+        } else {
+            DDSD_PITCH
+        };
+        if self.mip_count > 1 {
+            flags |= DDSD_MIPMAPCOUNT;
+        }
+        let pitch = if compressed {
+            self.format.surface_size(self.width, self.height) as u32
+        } else {
+            self.width * 4
+        };
+
+        let mut caps = DDSCAPS_TEXTURE;
+        if self.mip_count > 1 {
+            caps |= DDSCAPS_MIPMAP | DDSCAPS_COMPLEX;
+        }
+        if self.cubemap {
+            caps |= DDSCAPS_COMPLEX;
+        }
+        let caps2 = if self.cubemap {
+            DDSCAPS2_CUBEMAP_ALL_FACES
+        } else {
+            0
+        };
+
+        let (pf_flags, four_cc, bit_count, masks) = match (dx10, self.format) {
+            (true, _) => (DDPF_FOURCC, *b"DX10", 0, [0; 4]),
+            (false, DdsFormat::Rgba8) => (
+                DDPF_RGB | DDPF_ALPHAPIXELS,
+                [0; 4],
+                32,
+                [0x0000_00ff, 0x0000_ff00, 0x00ff_0000, 0xff00_0000],
+            ),
+            (false, DdsFormat::Bgra8) => (
+                DDPF_RGB | DDPF_ALPHAPIXELS,
+                [0; 4],
+                32,
+                [0x00ff_0000, 0x0000_ff00, 0x0000_00ff, 0xff00_0000],
+            ),
+            (false, DdsFormat::Bc1) => (DDPF_FOURCC, *b"DXT1", 0, [0; 4]),
+            (false, DdsFormat::Bc3) => (DDPF_FOURCC, *b"DXT5", 0, [0; 4]),
+            (false, DdsFormat::Bc5) => (DDPF_FOURCC, *b"ATI2", 0, [0; 4]),
+            (false, DdsFormat::Bc7) => unreachable!("BC7 always uses the DX10 header"),
+        };
+
+        let mut out = Vec::with_capacity(4 + HEADER_SIZE + DX10_HEADER_SIZE);
+        out.extend_from_slice(MAGIC);
+        let mut push = |value: u32| out.extend_from_slice(&value.to_le_bytes());
+        push(HEADER_SIZE as u32);
+        push(flags);
+        push(self.height);
+        push(self.width);
+        push(pitch);
+        push(0); // depth
+        push(self.mip_count);
+        for _ in 0..11 {
+            push(0);
+        }
+        push(32); // pixel format size
+        push(pf_flags);
+        push(u32::from_le_bytes(four_cc));
+        push(bit_count);
+        for mask in masks {
+            push(mask);
+        }
+        push(caps);
+        push(caps2);
+        push(0);
+        push(0);
+        push(0);
+
+        if dx10 {
+            push(self.format.dxgi(self.srgb));
+            push(DX10_RESOURCE_TEXTURE2D);
+            push(if self.cubemap {
+                DX10_MISC_TEXTURECUBE
+            } else {
+                0
+            });
+            push(self.layers);
+            push(0);
+        }
+        out
+    }
+}
+
+/// A decoded DDS file: every surface as RGBA8, in file order.
+#[derive(Debug, Clone)]
+pub struct DdsTexture {
+    pub header: DdsHeader,
+    pub surfaces: Vec<RgbaImage>,
+}
+
+impl DdsTexture {
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let (header, mut offset) = DdsHeader::parse(data)?;
+
+        let surface = |index: usize| {
+            let level = index as u32 % header.mip_count;
+            let (width, height) = header.mip_size(level);
+            (width, height, header.format.surface_size(width, height))
+        };
+        // Check the file holds every surface before allocating for any of them
+        let total = (0..header.surface_count())
+            .try_fold(offset, |total, index| total.checked_add(surface(index).2));
+        if total.is_none_or(|total| total > data.len()) {
+            return Err(invalid("DDS surface data is truncated"));
+        }
+
+        let mut surfaces = Vec::with_capacity(header.surface_count());
+        for index in 0..header.surface_count() {
+            let (width, height, size) = surface(index);
+            let bytes = &data[offset..offset + size];
+            surfaces.push(decode_surface(header.format, width, height, bytes));
+            offset += size;
+        }
+
+        Ok(Self { header, surfaces })
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    /// Re-encodes every surface in the header's format.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        if self.surfaces.len() != self.header.surface_count() {
+            return Err(invalid("surface count does not match header"));
+        }
+        let mut out = self.header.to_bytes();
+        for (index, surface) in self.surfaces.iter().enumerate() {
+            let level = index as u32 % self.header.mip_count;
+            if surface.dimensions() != self.header.mip_size(level) {
+                return Err(invalid("surface size does not match header"));
+            }
+            out.extend(encode_surface(self.header.format, surface));
+        }
+        Ok(out)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, self.to_bytes()?)?;
+        Ok(())
+    }
+
+    /// The top mip of the first layer.
+    pub fn image(&self) -> &RgbaImage {
+        &self.surfaces[0]
+    }
+
+    /// Top mips of every layer/face, the surfaces worth analysing.
+    pub fn top_levels_mut(&mut self) -> impl Iterator<Item = &mut RgbaImage> {
+        let mips = self.header.mip_count as usize;
+        self.surfaces.iter_mut().step_by(mips)
+    }
+}
+
+pub fn is_dds(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("dds"))
+}
+
+/// Loads any texture the importer cares about: DDS through this module, everything
+/// else through the `image` crate.
+pub fn load_rgba(path: &Path) -> Result<RgbaImage> {
+    if is_dds(path) {
+        let mut texture = DdsTexture::load(path)?;
+        Ok(texture.surfaces.swap_remove(0))
+    } else {
+        Ok(image::ImageReader::open(path)?
+            .with_guessed_format()?
+            .decode()?
+            .to_rgba8())
+    }
+}
+
+fn decode_block(format: DdsFormat, block: &[u8]) -> Block {
+    match format {
+        DdsFormat::Bc1 => bc::decode_bc1(block, false),
+        DdsFormat::Bc3 => bc::decode_bc3(block),
+        DdsFormat::Bc5 => bc::decode_bc5(block),
+        DdsFormat::Bc7 => bc7::decode_block(block),
+        DdsFormat::Rgba8 | DdsFormat::Bgra8 => unreachable!("uncompressed formats have no blocks"),
+    }
+}
+
+fn encode_block(format: DdsFormat, pixels: &Block) -> Vec<u8> {
+    match format {
+        DdsFormat::Bc1 => bc::encode_bc1(pixels, true).to_vec(),
+        DdsFormat::Bc3 => bc::encode_bc3(pixels).to_vec(),
+        DdsFormat::Bc5 => bc::encode_bc5(pixels).to_vec(),
+        DdsFormat::Bc7 => bc7::encode_block(pixels).to_vec(),
+        DdsFormat::Rgba8 | DdsFormat::Bgra8 => unreachable!("uncompressed formats have no blocks"),
+    }
+}
+
+fn decode_surface(format: DdsFormat, width: u32, height: u32, data: &[u8]) -> RgbaImage {
+    let mut image = RgbaImage::new(width, height);
+
+    let Some(block_size) = format.block_size() else {
+        for (pixel, chunk) in image.pixels_mut().zip(data.chunks_exact(4)) {
+            pixel.0 = match format {
+                DdsFormat::Bgra8 => [chunk[2], chunk[1], chunk[0], chunk[3]],
+                _ => [chunk[0], chunk[1], chunk[2], chunk[3]],
+            };
+        }
+        return image;
+    };
+
+    let blocks_x = width.div_ceil(4);
+    for (index, block) in data.chunks_exact(block_size).enumerate() {
+        let bx = index as u32 % blocks_x * 4;
+        let by = index as u32 / blocks_x * 4;
+        let pixels = decode_block(format, block);
+        for (i, pixel) in pixels.iter().enumerate() {
+            let (x, y) = (bx + i as u32 % 4, by + i as u32 / 4);
+            if x < width && y < height {
+                image.put_pixel(x, y, image::Rgba(*pixel));
+            }
+        }
+    }
+    image
+}
+
+fn encode_surface(format: DdsFormat, image: &RgbaImage) -> Vec<u8> {
+    let (width, height) = image.dimensions();
+    let mut out = Vec::with_capacity(format.surface_size(width, height));
+
+    if !format.is_compressed() {
+        for pixel in image.pixels() {
+            let [r, g, b, a] = pixel.0;
+            match format {
+                DdsFormat::Bgra8 => out.extend_from_slice(&[b, g, r, a]),
+                _ => out.extend_from_slice(&[r, g, b, a]),
+            }
+        }
+        return out;
+    }
+
+    for by in (0..height).step_by(4) {
+        for bx in (0..width).step_by(4) {
+            // Partial edge blocks repeat the last row/column
+            let mut pixels = [[0u8; 4]; 16];
+            for (i, pixel) in pixels.iter_mut().enumerate() {
+                let x = (bx + i as u32 % 4).min(width - 1);
+                let y = (by + i as u32 / 4).min(height - 1);
+                *pixel = image.get_pixel(x, y).0;
+            }
+            out.extend(encode_block(format, &pixels));
+        }
+    }
+    out
+}
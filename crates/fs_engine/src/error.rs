@@ -23,6 +23,30 @@ pub enum FsError {
 
     #[error("Directory not empty: {0}")]
     DirectoryNotEmpty(PathBuf),
+
+    #[error("Archive exceeds safety limits: {0}")]
+    ArchiveLimit(String),
+
+    #[error("Operation cancelled")]
+    Cancelled,
+
+    #[error("Archive is password protected: {0}")]
+    PasswordRequired(PathBuf),
+
+    #[error("Incorrect password for archive: {0}")]
+    InvalidPassword(PathBuf),
+
+    #[error("Texture error: {0}")]
+    Texture(String),
+
+    #[error("Invalid executable: {0}")]
+    InvalidExecutable(String),
+
+    #[error("Watch error: {0}")]
+    Watch(String),
+
+    #[error("Copy does not match the original: {0}")]
+    VerificationFailed(PathBuf),
 }
 
 pub type Result<T> = std::result::Result<T, FsError>;
@@ -1,6 +1,5 @@
 use crate::error::{FsError, Result};
-use pelite::pe64::{Pe, PeFile};
-use pelite::FileMap;
+use crate::pe::PeInfo;
 use std::path::Path;
 
 pub struct ExeInspector;
@@ -104,27 +103,32 @@ impl ExeInspector {
         }
 
         // 2. Fallback to PE resources
-        let file_map_res = FileMap::open(path);
-        if let Ok(file_map) = file_map_res {
-            if let Ok(pe_file) = PeFile::from_bytes(file_map.as_ref()) {
-                if let Ok(resources) = pe_file.resources() {
-                    if let Ok(version_info) = resources.version_info() {
-                        if let Some(fixed) = version_info.fixed() {
-                            let v = fixed.dwFileVersion;
-                            let version_str =
-                                format!("{}.{}.{}.{}", v.Major, v.Minor, v.Patch, v.Build);
-
-                            if v.Major < 2000 {
-                                return Ok(version_str);
-                            } else {
-                                println!("Sophon: Detected Unity version {} in PE, ignoring as game version.", version_str);
-                            }
-                        }
-                    }
+        if let Ok(info) = Self::inspect(path) {
+            if let Some(version) = info.version.and_then(|v| v.file_version) {
+                let major: u32 = version
+                    .split('.')
+                    .next()
+                    .and_then(|m| m.parse().ok())
+                    .unwrap_or(0);
+                if major < 2000 {
+                    return Ok(version);
+                } else {
+                    println!(
+                        "Sophon: Detected Unity version {} in PE, ignoring as game version.",
+                        version
+                    );
                 }
             }
         }
 
         Ok("Unknown".to_string())
     }
+
+    /// Parses the PE headers, import table and version resource of a Windows binary.
+    pub fn inspect(path: &Path) -> Result<PeInfo> {
+        if !path.is_file() {
+            return Err(FsError::NotFound(path.to_path_buf()));
+        }
+        PeInfo::load(path)
+    }
 }
@@ -1,16 +1,45 @@
 pub mod archive;
+pub mod archive_inspector;
+pub mod backups;
+pub mod blob_store;
+pub mod dds;
 pub mod error;
 pub mod inspector;
+pub mod mirror;
+pub mod normalize;
+pub mod pe;
 pub mod safety;
+#[cfg(feature = "test-util")]
+pub mod test_support;
+pub mod thumbnails;
 pub mod transcoder;
 pub mod vfs;
+pub mod watcher;
 
-pub use archive::{extract_and_sanitize, extract_targz, ExtractionReport};
+pub use archive::{
+    copy_and_sanitize_with, extract_and_sanitize, extract_and_sanitize_with, extract_targz,
+    ArchiveFormat, CancelToken, ExtractOptions, ExtractionLimits, ExtractionProgress,
+    ExtractionReport,
+};
+pub use archive_inspector::{ArchiveEntry, ArchiveInspector, ArchivePreview, MAX_ENTRY_READ};
+pub use backups::{BackupRotation, RestoredBackup, DEFAULT_BACKUP_COUNT};
+pub use blob_store::{BlobRef, BlobStore, GcReport, IngestReport, StoreStats, MIN_BLOB_SIZE};
+pub use dds::{DdsFormat, DdsHeader, DdsTexture};
 pub use error::{FsError, Result};
 pub use inspector::ExeInspector;
+pub use mirror::{Mirror, MirrorOptions, MirrorProgress, MirrorProgressCallback, MirrorReport};
+pub use normalize::{
+    CaseCollision, CaseNormalizer, NormalizationPlan, NormalizationReport, PlannedRename,
+    ReferenceIssue, ReferenceIssueKind, ReferenceRewrite,
+};
+pub use pe::{PeImport, PeInfo, PeMachine, PeSubsystem, PeVersionInfo};
 pub use safety::Safety;
-pub use transcoder::Transcoder;
+pub use thumbnails::{
+    TextureCandidate, Thumbnail, ThumbnailFormat, ThumbnailOptions, ThumbnailSet, Thumbnailer,
+};
+pub use transcoder::{NormalConvention, NormalMapAnalysis, NormalMapIssue, Transcoder};
 pub use vfs::Vfs;
+pub use watcher::{DirWatcher, WatchChange};
 
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
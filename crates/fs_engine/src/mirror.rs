@@ -0,0 +1,207 @@
+//! Copies a folder tree to another place, possibly another disk, so it can be moved.
+//!
+//! Every file is written under a temporary name and renamed once complete, so a copy
+//! that was cancelled or cut short picks up where it stopped: files already in place
+//! with the right size are skipped. [`Mirror::verify`] then checks the copy before the
+//! caller deletes the original.
+
+use crate::archive::CancelToken;
+use crate::error::{FsError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use walkdir::WalkDir;
+
+/// Suffix of files still being copied.
+const PART_SUFFIX: &str = ".yago_part";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MirrorProgress {
+    pub files_done: u64,
+    pub files_total: u64,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+}
+
+pub type MirrorProgressCallback = Arc<dyn Fn(&MirrorProgress) + Send + Sync>;
+
+#[derive(Clone, Default)]
+pub struct MirrorOptions {
+    pub cancel: CancelToken,
+    /// Called after every file.
+    pub on_progress: Option<MirrorProgressCallback>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MirrorReport {
+    pub files_copied: u64,
+    /// Files left from an earlier, interrupted copy.
+    pub files_resumed: u64,
+    /// Files recreated as hard links to another copied file.
+    pub files_linked: u64,
+    pub symlinks: u64,
+    pub bytes_copied: u64,
+}
+
+pub struct Mirror;
+
+impl Mirror {
+    /// Copies everything under `source` into `destination`, which is created if needed.
+    /// Symlinks are recreated rather than followed, and on Unix files hard-linked to each
+    /// other (the blob store's, say) stay linked in the copy.
+    pub fn copy_tree(
+        source: &Path,
+        destination: &Path,
+        options: &MirrorOptions,
+    ) -> Result<MirrorReport> {
+        if !source.is_dir() {
+            return Err(FsError::NotFound(source.to_path_buf()));
+        }
+        let entries: Vec<walkdir::DirEntry> = WalkDir::new(source)
+            .follow_links(false)
+            .into_iter()
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|e| FsError::Io(e.into()))?;
+
+        let mut progress = MirrorProgress::default();
+        for entry in entries.iter().filter(|e| e.file_type().is_file()) {
+            progress.files_total += 1;
+            progress.bytes_total += entry.metadata().map(|m| m.len()).unwrap_or(0);
+        }
+
+        let mut report = MirrorReport::default();
+        // First copy of each file with several hard links, by device and inode
+        let mut linked: HashMap<(u64, u64), PathBuf> = HashMap::new();
+        for entry in &entries {
+            let relative = entry
+                .path()
+                .strip_prefix(source)
+                .map_err(|_| FsError::InvalidPath(entry.path().to_path_buf()))?;
+            let target = destination.join(relative);
+            let file_type = entry.file_type();
+
+            if file_type.is_dir() {
+                std::fs::create_dir_all(&target)?;
+                continue;
+            }
+            if file_type.is_symlink() {
+                Self::copy_symlink(entry.path(), &target)?;
+                report.symlinks += 1;
+                continue;
+            }
+            if options.cancel.is_cancelled() {
+                return Err(FsError::Cancelled);
+            }
+
+            let metadata = entry.metadata().map_err(|e| FsError::Io(e.into()))?;
+            let len = metadata.len();
+            let link_key = Self::link_key(&metadata);
+            let first_copy = link_key.and_then(|key| linked.get(&key));
+
+            let in_place =
+                std::fs::symlink_metadata(&target).is_ok_and(|m| m.is_file() && m.len() == len);
+            if in_place {
+                report.files_resumed += 1;
+            } else if let Some(first_copy) = first_copy {
+                let _ = std::fs::remove_file(&target);
+                std::fs::hard_link(first_copy, &target)?;
+                report.files_linked += 1;
+            } else {
+                Self::copy_file(entry.path(), &target, &metadata)?;
+                report.files_copied += 1;
+                report.bytes_copied += len;
+            }
+            if let Some(key) = link_key {
+                linked.entry(key).or_insert(target);
+            }
+
+            progress.files_done += 1;
+            progress.bytes_done += len;
+            if let Some(cb) = &options.on_progress {
+                cb(&progress);
+            }
+        }
+        Ok(report)
+    }
+
+    /// Checks that every folder, file and symlink under `source` is in `destination`,
+    /// files with the same size and links with the same target. Fails with the first
+    /// path that differs.
+    pub fn verify(source: &Path, destination: &Path) -> Result<()> {
+        for entry in WalkDir::new(source).follow_links(false) {
+            let entry = entry.map_err(|e| FsError::Io(e.into()))?;
+            let relative = entry
+                .path()
+                .strip_prefix(source)
+                .map_err(|_| FsError::InvalidPath(entry.path().to_path_buf()))?;
+            let target = destination.join(relative);
+            let Ok(copied) = std::fs::symlink_metadata(&target) else {
+                return Err(FsError::VerificationFailed(relative.to_path_buf()));
+            };
+
+            let file_type = entry.file_type();
+            let matches = if file_type.is_dir() {
+                copied.is_dir()
+            } else if file_type.is_symlink() {
+                copied.file_type().is_symlink()
+                    && std::fs::read_link(entry.path()).ok() == std::fs::read_link(&target).ok()
+            } else {
+                let len = entry.metadata().map(|m| m.len()).ok();
+                copied.is_file() && Some(copied.len()) == len
+            };
+            if !matches {
+                return Err(FsError::VerificationFailed(relative.to_path_buf()));
+            }
+        }
+        Ok(())
+    }
+
+    fn copy_file(source: &Path, target: &Path, metadata: &std::fs::Metadata) -> Result<()> {
+        let mut part_name = target.file_name().unwrap_or_default().to_os_string();
+        part_name.push(PART_SUFFIX);
+        let part = target.with_file_name(part_name);
+
+        std::fs::copy(source, &part)?;
+        // Keep the modification time, which mod sync and update checks look at
+        if let Ok(modified) = metadata.modified() {
+            File::options()
+                .write(true)
+                .open(&part)?
+                .set_modified(modified)?;
+        }
+        std::fs::rename(&part, target)?;
+        Ok(())
+    }
+
+    fn copy_symlink(source: &Path, target: &Path) -> Result<()> {
+        let link = std::fs::read_link(source)?;
+        if std::fs::read_link(target).ok().as_ref() == Some(&link) {
+            return Ok(());
+        }
+        if std::fs::symlink_metadata(target).is_ok() {
+            std::fs::remove_file(target)?;
+        }
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&link, target)?;
+        #[cfg(windows)]
+        if source.is_dir() {
+            std::os::windows::fs::symlink_dir(&link, target)?;
+        } else {
+            std::os::windows::fs::symlink_file(&link, target)?;
+        }
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    fn link_key(metadata: &std::fs::Metadata) -> Option<(u64, u64)> {
+        use std::os::unix::fs::MetadataExt;
+        (metadata.nlink() > 1).then(|| (metadata.dev(), metadata.ino()))
+    }
+
+    #[cfg(not(unix))]
+    fn link_key(_metadata: &std::fs::Metadata) -> Option<(u64, u64)> {
+        None
+    }
+}
@@ -0,0 +1,431 @@
+//! Lowercases every file and folder in a mod so it loads the same on case-sensitive
+//! filesystems, and rewrites the INI `filename =` lines that point at renamed files.
+//!
+//! Planning and applying are separate, so callers can show what will happen (and which
+//! names collide) before anything on disk changes.
+
+use crate::error::{FsError, Result};
+use ini_forge::IniRewriter;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlannedRename {
+    /// Relative to the root, as it is now.
+    pub from: PathBuf,
+    /// Relative to the root, after every rename in the plan has been applied.
+    pub to: PathBuf,
+    pub is_dir: bool,
+}
+
+/// Several entries in one folder that only differ by case. The already-lowercase one
+/// (or the first by name) gets the lowercase name; the others keep theirs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaseCollision {
+    /// The lowercase path everything wanted to become.
+    pub target: PathBuf,
+    pub kept: PathBuf,
+    pub conflicting: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReferenceRewrite {
+    /// The INI, relative to the root, as it is now.
+    pub ini: PathBuf,
+    /// Zero-based line index.
+    pub line: usize,
+    pub section: String,
+    pub old_value: String,
+    pub new_value: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReferenceIssueKind {
+    /// Nothing matches, not even case-insensitively; the mod was already broken.
+    Missing,
+    /// Only case-insensitive matches, and more than one because of a collision.
+    Ambiguous,
+}
+
+/// A `filename =` reference that cannot be made to work by renaming.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReferenceIssue {
+    pub ini: PathBuf,
+    pub line: usize,
+    pub section: String,
+    pub value: String,
+    pub kind: ReferenceIssueKind,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NormalizationPlan {
+    pub root: PathBuf,
+    /// Ordered so that applying them front to back never invalidates a later `from`.
+    pub renames: Vec<PlannedRename>,
+    pub collisions: Vec<CaseCollision>,
+    pub rewrites: Vec<ReferenceRewrite>,
+    pub issues: Vec<ReferenceIssue>,
+}
+
+impl NormalizationPlan {
+    /// True if applying the plan would not touch the disk.
+    pub fn is_noop(&self) -> bool {
+        self.renames.is_empty() && self.rewrites.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NormalizationReport {
+    pub renamed: usize,
+    pub ini_files_rewritten: usize,
+    pub references_rewritten: usize,
+    pub collisions: Vec<CaseCollision>,
+    pub issues: Vec<ReferenceIssue>,
+}
+
+struct Node {
+    name: String,
+    new_name: String,
+    is_dir: bool,
+    parent: usize,
+    children: Vec<usize>,
+}
+
+/// In-memory copy of the tree with each entry's planned name.
+struct Tree {
+    nodes: Vec<Node>,
+}
+
+impl Tree {
+    fn old_path(&self, index: usize) -> PathBuf {
+        self.path(index, |n| &n.name)
+    }
+
+    fn new_path(&self, index: usize) -> PathBuf {
+        self.path(index, |n| &n.new_name)
+    }
+
+    fn path(&self, mut index: usize, name: impl Fn(&Node) -> &String) -> PathBuf {
+        let mut parts = Vec::new();
+        while index != 0 {
+            parts.push(name(&self.nodes[index]).as_str());
+            index = self.nodes[index].parent;
+        }
+        parts.iter().rev().collect()
+    }
+
+    fn depth(&self, mut index: usize) -> usize {
+        let mut depth = 0;
+        while index != 0 {
+            depth += 1;
+            index = self.nodes[index].parent;
+        }
+        depth
+    }
+
+    /// Resolves an INI reference from `dir` the way Windows would (case-insensitively,
+    /// preferring an exact match) and returns the value rewritten to the new names.
+    /// `Ok(None)` means the path leaves the tree and is none of our business.
+    fn resolve(
+        &self,
+        dir: usize,
+        value: &str,
+    ) -> std::result::Result<Option<String>, ReferenceIssueKind> {
+        let mut current = dir;
+        let mut rewritten = String::with_capacity(value.len());
+        for (component, separator) in split_keeping_separators(value) {
+            match component {
+                "" | "." => rewritten.push_str(component),
+                ".." => {
+                    if current == 0 {
+                        return Ok(None);
+                    }
+                    current = self.nodes[current].parent;
+                    rewritten.push_str(component);
+                }
+                _ => {
+                    let children = &self.nodes[current].children;
+                    let exact = children.iter().find(|c| self.nodes[**c].name == component);
+                    let next = match exact {
+                        Some(child) => *child,
+                        None => {
+                            let lower = component.to_lowercase();
+                            let mut matches = children
+                                .iter()
+                                .filter(|c| self.nodes[**c].name.to_lowercase() == lower);
+                            match (matches.next(), matches.next()) {
+                                (Some(child), None) => *child,
+                                (None, _) => return Err(ReferenceIssueKind::Missing),
+                                (Some(_), Some(_)) => return Err(ReferenceIssueKind::Ambiguous),
+                            }
+                        }
+                    };
+                    rewritten.push_str(&self.nodes[next].new_name);
+                    current = next;
+                }
+            }
+            if let Some(separator) = separator {
+                rewritten.push(separator);
+            }
+        }
+        Ok(Some(rewritten))
+    }
+}
+
+fn split_keeping_separators(value: &str) -> Vec<(&str, Option<char>)> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    for (index, c) in value.char_indices() {
+        if c == '/' || c == '\\' {
+            parts.push((&value[start..index], Some(c)));
+            start = index + 1;
+        }
+    }
+    parts.push((&value[start..], None));
+    parts
+}
+
+fn is_ini(name: &str) -> bool {
+    Path::new(name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("ini"))
+}
+
+pub struct CaseNormalizer;
+
+impl CaseNormalizer {
+    /// Plans and applies in one go.
+    pub fn normalize(root: &Path) -> Result<NormalizationReport> {
+        let plan = Self::plan(root)?;
+        Self::apply(&plan)
+    }
+
+    /// Works out the renames, collisions and INI edits without touching the disk.
+    /// Dot-prefixed entries (and everything below them) are left alone.
+    pub fn plan(root: &Path) -> Result<NormalizationPlan> {
+        if !root.exists() {
+            return Err(FsError::NotFound(root.to_path_buf()));
+        }
+
+        let mut tree = Tree {
+            nodes: vec![Node {
+                name: String::new(),
+                new_name: String::new(),
+                is_dir: true,
+                parent: 0,
+                children: Vec::new(),
+            }],
+        };
+        let mut collisions = Vec::new();
+        Self::scan(root, 0, &mut tree, &mut collisions)?;
+
+        let mut renames: Vec<(usize, PlannedRename)> = (1..tree.nodes.len())
+            .filter(|i| tree.nodes[*i].name != tree.nodes[*i].new_name)
+            .map(|i| {
+                (
+                    tree.depth(i),
+                    PlannedRename {
+                        from: tree.old_path(i),
+                        to: tree.new_path(i),
+                        is_dir: tree.nodes[i].is_dir,
+                    },
+                )
+            })
+            .collect();
+        // Deepest first, so parents still carry their old names when children move
+        renames.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.from.cmp(&b.1.from)));
+
+        let mut rewrites = Vec::new();
+        let mut issues = Vec::new();
+        for index in 1..tree.nodes.len() {
+            let node = &tree.nodes[index];
+            if node.is_dir || !is_ini(&node.name) {
+                continue;
+            }
+            let ini = tree.old_path(index);
+            let Ok(content) = std::fs::read_to_string(root.join(&ini)) else {
+                continue;
+            };
+            for reference in IniRewriter::find_values(&content, "filename") {
+                match tree.resolve(node.parent, &reference.value) {
+                    Ok(Some(new_value)) if new_value != reference.value => {
+                        rewrites.push(ReferenceRewrite {
+                            ini: ini.clone(),
+                            line: reference.line,
+                            section: reference.section,
+                            old_value: reference.value,
+                            new_value,
+                        })
+                    }
+                    Ok(_) => {}
+                    Err(kind) => issues.push(ReferenceIssue {
+                        ini: ini.clone(),
+                        line: reference.line,
+                        section: reference.section,
+                        value: reference.value,
+                        kind,
+                    }),
+                }
+            }
+        }
+
+        Ok(NormalizationPlan {
+            root: root.to_path_buf(),
+            renames: renames.into_iter().map(|(_, r)| r).collect(),
+            collisions,
+            rewrites,
+            issues,
+        })
+    }
+
+    fn scan(
+        dir: &Path,
+        parent: usize,
+        tree: &mut Tree,
+        collisions: &mut Vec<CaseCollision>,
+    ) -> Result<()> {
+        let mut entries = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            // Non-UTF-8 names cannot be referenced from an INI anyway
+            let Some(name) = entry.file_name().to_str().map(|s| s.to_string()) else {
+                continue;
+            };
+            if name.starts_with('.') {
+                continue;
+            }
+            entries.push((name, entry.file_type()?.is_dir()));
+        }
+        // The entry that already has the lowercase name wins its slot
+        entries.sort_by(|a, b| {
+            (a.0 != a.0.to_lowercase(), &a.0).cmp(&(b.0 != b.0.to_lowercase(), &b.0))
+        });
+
+        let mut claimed: HashMap<String, usize> = HashMap::new();
+        let mut collided: HashMap<String, usize> = HashMap::new();
+        for (name, is_dir) in entries {
+            let lower = name.to_lowercase();
+            let index = tree.nodes.len();
+            let new_name = match claimed.get(&lower) {
+                None => {
+                    claimed.insert(lower.clone(), index);
+                    lower.clone()
+                }
+                Some(&winner) => {
+                    let slot = *collided.entry(lower.clone()).or_insert_with(|| {
+                        collisions.push(CaseCollision {
+                            target: tree.new_path(parent).join(&lower),
+                            kept: tree.old_path(winner),
+                            conflicting: Vec::new(),
+                        });
+                        collisions.len() - 1
+                    });
+                    collisions[slot]
+                        .conflicting
+                        .push(tree.old_path(parent).join(&name));
+                    name.clone()
+                }
+            };
+            tree.nodes.push(Node {
+                name: name.clone(),
+                new_name,
+                is_dir,
+                parent,
+                children: Vec::new(),
+            });
+            tree.nodes[parent].children.push(index);
+            if is_dir {
+                Self::scan(&dir.join(&name), index, tree, collisions)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies the plan as one transaction: if any rename or INI write fails, everything
+    /// done so far is undone before the error is returned.
+    pub fn apply(plan: &NormalizationPlan) -> Result<NormalizationReport> {
+        let root = &plan.root;
+
+        // Prepare every INI edit up front so a bad file fails before anything moves
+        let mut edits: HashMap<&Path, Vec<(usize, String)>> = HashMap::new();
+        for rewrite in &plan.rewrites {
+            edits
+                .entry(rewrite.ini.as_path())
+                .or_default()
+                .push((rewrite.line, rewrite.new_value.clone()));
+        }
+        let mut ini_writes = Vec::new();
+        for (ini, lines) in &edits {
+            let original = std::fs::read_to_string(root.join(ini))?;
+            let updated = IniRewriter::replace_values(&original, lines);
+            ini_writes.push((root.join(Self::renamed_path(plan, ini)), original, updated));
+        }
+
+        let mut done: Vec<(PathBuf, PathBuf)> = Vec::new();
+        for rename in &plan.renames {
+            let source = root.join(&rename.from);
+            let target = source.with_file_name(rename.to.file_name().unwrap_or_default());
+            if let Err(e) = std::fs::rename(&source, &target) {
+                Self::rollback(&done, &[]);
+                return Err(FsError::Io(e));
+            }
+            done.push((source, target));
+        }
+
+        let mut written: Vec<(PathBuf, String)> = Vec::new();
+        for (path, original, updated) in &ini_writes {
+            if let Err(e) = Self::write_atomic(path, updated) {
+                Self::rollback(&done, &written);
+                return Err(e);
+            }
+            written.push((path.clone(), original.clone()));
+        }
+
+        Ok(NormalizationReport {
+            renamed: plan.renames.len(),
+            ini_files_rewritten: ini_writes.len(),
+            references_rewritten: plan.rewrites.len(),
+            collisions: plan.collisions.clone(),
+            issues: plan.issues.clone(),
+        })
+    }
+
+    /// Where `old` (relative) ends up once the plan's renames are applied.
+    fn renamed_path(plan: &NormalizationPlan, old: &Path) -> PathBuf {
+        let mut from = PathBuf::new();
+        let mut to = PathBuf::new();
+        for component in old.components() {
+            from.push(component);
+            match plan.renames.iter().find(|r| r.from == from) {
+                Some(rename) => to = rename.to.clone(),
+                None => to.push(component),
+            }
+        }
+        to
+    }
+
+    fn write_atomic(path: &Path, content: &str) -> Result<()> {
+        let temp = path.with_extension("ini.yago_tmp");
+        std::fs::write(&temp, content)?;
+        std::fs::rename(&temp, path).inspect_err(|_| {
+            let _ = std::fs::remove_file(&temp);
+        })?;
+        Ok(())
+    }
+
+    fn rollback(renamed: &[(PathBuf, PathBuf)], written: &[(PathBuf, String)]) {
+        for (path, original) in written.iter().rev() {
+            let _ = std::fs::write(path, original);
+        }
+        for (source, target) in renamed.iter().rev() {
+            if let Err(e) = std::fs::rename(target, source) {
+                eprintln!(
+                    "CaseNormalizer: Failed to roll back {:?} -> {:?}: {}",
+                    target, source, e
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,504 @@
+//! Minimal Portable Executable reader: headers, import table and VS_VERSIONINFO.
+//!
+//! Everything is bounds-checked against the file, so truncated or hostile binaries
+//! fail with `FsError::InvalidExecutable` instead of panicking. It parses PE32 and
+//! PE32+ from one byte slice, which is why it replaced `pelite`: the `pe64` view we used
+//! rejected the 32-bit launchers and proxy DLLs discovery has to recognise.
+
+use crate::error::{FsError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+const PE32_MAGIC: u16 = 0x10b;
+const PE32_PLUS_MAGIC: u16 = 0x20b;
+const IMAGE_FILE_DLL: u16 = 0x2000;
+const DIRECTORY_IMPORT: usize = 1;
+const DIRECTORY_RESOURCE: usize = 2;
+const RT_VERSION: u32 = 16;
+const VS_FIXEDFILEINFO_SIGNATURE: u32 = 0xfeef_04bd;
+
+/// Caps that keep malformed tables from turning into endless loops.
+const MAX_IMPORT_DESCRIPTORS: usize = 4096;
+const MAX_IMPORT_THUNKS: usize = 65_536;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PeMachine {
+    X86,
+    X64,
+    Arm64,
+    Other(u16),
+}
+
+impl PeMachine {
+    fn from_raw(value: u16) -> Self {
+        match value {
+            0x014c => Self::X86,
+            0x8664 => Self::X64,
+            0xaa64 => Self::Arm64,
+            other => Self::Other(other),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PeSubsystem {
+    Native,
+    WindowsGui,
+    WindowsConsole,
+    Other(u16),
+}
+
+impl PeSubsystem {
+    fn from_raw(value: u16) -> Self {
+        match value {
+            1 => Self::Native,
+            2 => Self::WindowsGui,
+            3 => Self::WindowsConsole,
+            other => Self::Other(other),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeImport {
+    pub dll: String,
+    /// Imported names; ordinal-only imports are listed as `#<ordinal>`.
+    pub functions: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeVersionInfo {
+    /// From VS_FIXEDFILEINFO, `major.minor.patch.build`.
+    pub file_version: Option<String>,
+    pub product_version: Option<String>,
+    /// The first StringFileInfo table (`CompanyName`, `OriginalFilename`, ...).
+    pub strings: BTreeMap<String, String>,
+}
+
+impl PeVersionInfo {
+    pub fn string(&self, key: &str) -> Option<&str> {
+        self.strings
+            .get(key)
+            .map(|s| s.as_str())
+            .filter(|s| !s.is_empty())
+    }
+
+    pub fn company_name(&self) -> Option<&str> {
+        self.string("CompanyName")
+    }
+
+    pub fn product_name(&self) -> Option<&str> {
+        self.string("ProductName")
+    }
+
+    pub fn file_description(&self) -> Option<&str> {
+        self.string("FileDescription")
+    }
+
+    pub fn original_filename(&self) -> Option<&str> {
+        self.string("OriginalFilename")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeInfo {
+    pub machine: PeMachine,
+    pub subsystem: PeSubsystem,
+    pub is_dll: bool,
+    pub imports: Vec<PeImport>,
+    pub version: Option<PeVersionInfo>,
+    /// Wine marks its builtin PE modules in the DOS stub.
+    pub wine_builtin: bool,
+}
+
+impl PeInfo {
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::parse(&std::fs::read(path)?)
+    }
+
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        Parser::new(data)?.parse()
+    }
+
+    /// Whether the import table references `dll` (case-insensitive).
+    pub fn imports_dll(&self, dll: &str) -> bool {
+        self.imports.iter().any(|i| i.dll.eq_ignore_ascii_case(dll))
+    }
+
+    /// All version strings joined, lowercased; handy for vendor sniffing.
+    pub fn version_text(&self) -> String {
+        self.version
+            .as_ref()
+            .map(|v| {
+                v.strings
+                    .values()
+                    .map(|s| s.to_lowercase())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .unwrap_or_default()
+    }
+}
+
+fn invalid(message: &str) -> FsError {
+    FsError::InvalidExecutable(message.to_string())
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16> {
+    data.get(offset..offset + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or_else(|| invalid("unexpected end of file"))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| invalid("unexpected end of file"))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Result<u64> {
+    Ok(read_u32(data, offset)? as u64 | (read_u32(data, offset + 4)? as u64) << 32)
+}
+
+fn read_cstr(data: &[u8], offset: usize) -> Result<String> {
+    let bytes = data
+        .get(offset..)
+        .ok_or_else(|| invalid("string out of bounds"))?;
+    let end = bytes
+        .iter()
+        .position(|b| *b == 0)
+        .ok_or_else(|| invalid("unterminated string"))?;
+    Ok(String::from_utf8_lossy(&bytes[..end]).into_owned())
+}
+
+struct Section {
+    virtual_address: u32,
+    virtual_size: u32,
+    raw_offset: u32,
+}
+
+struct Parser<'a> {
+    data: &'a [u8],
+    pe64: bool,
+    machine: u16,
+    characteristics: u16,
+    subsystem: u16,
+    size_of_headers: u32,
+    directories: Vec<(u32, u32)>,
+    sections: Vec<Section>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(data: &'a [u8]) -> Result<Self> {
+        if data.get(0..2) != Some(b"MZ") {
+            return Err(invalid("missing MZ header"));
+        }
+        let pe_offset = read_u32(data, 0x3c)? as usize;
+        if data.get(pe_offset..pe_offset + 4) != Some(b"PE\0\0") {
+            return Err(invalid("missing PE signature"));
+        }
+
+        let coff = pe_offset + 4;
+        let machine = read_u16(data, coff)?;
+        let section_count = read_u16(data, coff + 2)? as usize;
+        let optional_size = read_u16(data, coff + 16)? as usize;
+        let characteristics = read_u16(data, coff + 18)?;
+
+        let optional = coff + 20;
+        let pe64 = match read_u16(data, optional)? {
+            PE32_MAGIC => false,
+            PE32_PLUS_MAGIC => true,
+            _ => return Err(invalid("unknown optional header magic")),
+        };
+        let size_of_headers = read_u32(data, optional + 60)?;
+        let subsystem = read_u16(data, optional + 68)?;
+        let (count_offset, directories_offset) = if pe64 { (108, 112) } else { (92, 96) };
+        let directory_count = (read_u32(data, optional + count_offset)? as usize).min(16);
+
+        let mut directories = Vec::with_capacity(directory_count);
+        for i in 0..directory_count {
+            let entry = optional + directories_offset + i * 8;
+            directories.push((read_u32(data, entry)?, read_u32(data, entry + 4)?));
+        }
+
+        let mut sections = Vec::with_capacity(section_count);
+        let section_table = optional + optional_size;
+        for i in 0..section_count {
+            let header = section_table + i * 40;
+            let virtual_size = read_u32(data, header + 8)?;
+            let raw_size = read_u32(data, header + 16)?;
+            sections.push(Section {
+                virtual_address: read_u32(data, header + 12)?,
+                virtual_size: virtual_size.max(raw_size),
+                raw_offset: read_u32(data, header + 20)?,
+            });
+        }
+
+        Ok(Self {
+            data,
+            pe64,
+            machine,
+            characteristics,
+            subsystem,
+            size_of_headers,
+            directories,
+            sections,
+        })
+    }
+
+    fn parse(&self) -> Result<PeInfo> {
+        // Optional tables are best-effort: a broken resource section should not hide
+        // the machine type or imports
+        Ok(PeInfo {
+            machine: PeMachine::from_raw(self.machine),
+            subsystem: PeSubsystem::from_raw(self.subsystem),
+            is_dll: self.characteristics & IMAGE_FILE_DLL != 0,
+            imports: self.imports().unwrap_or_default(),
+            version: self.version_info().ok().flatten(),
+            wine_builtin: self
+                .data
+                .get(0x40..0x60)
+                .is_some_and(|stub| stub.starts_with(b"Wine builtin DLL")),
+        })
+    }
+
+    fn rva_to_offset(&self, rva: u32) -> Result<usize> {
+        if rva < self.size_of_headers {
+            return Ok(rva as usize);
+        }
+        self.sections
+            .iter()
+            .find(|s| rva >= s.virtual_address && rva - s.virtual_address < s.virtual_size)
+            .map(|s| (rva - s.virtual_address) as usize + s.raw_offset as usize)
+            .ok_or_else(|| invalid("RVA outside of any section"))
+    }
+
+    fn directory(&self, index: usize) -> Option<(u32, u32)> {
+        self.directories
+            .get(index)
+            .copied()
+            .filter(|(rva, size)| *rva != 0 && *size != 0)
+    }
+
+    fn imports(&self) -> Result<Vec<PeImport>> {
+        let Some((rva, _)) = self.directory(DIRECTORY_IMPORT) else {
+            return Ok(Vec::new());
+        };
+        let table = self.rva_to_offset(rva)?;
+
+        let mut imports = Vec::new();
+        for index in 0..MAX_IMPORT_DESCRIPTORS {
+            let descriptor = table + index * 20;
+            let original_thunk = read_u32(self.data, descriptor)?;
+            let name_rva = read_u32(self.data, descriptor + 12)?;
+            let first_thunk = read_u32(self.data, descriptor + 16)?;
+            if original_thunk == 0 && name_rva == 0 && first_thunk == 0 {
+                break;
+            }
+
+            let dll = read_cstr(self.data, self.rva_to_offset(name_rva)?)?;
+            let thunk_rva = if original_thunk != 0 {
+                original_thunk
+            } else {
+                first_thunk
+            };
+            let functions = self.import_names(thunk_rva).unwrap_or_default();
+            imports.push(PeImport { dll, functions });
+        }
+        Ok(imports)
+    }
+
+    fn import_names(&self, thunk_rva: u32) -> Result<Vec<String>> {
+        let start = self.rva_to_offset(thunk_rva)?;
+        let width = if self.pe64 { 8 } else { 4 };
+
+        let mut names = Vec::new();
+        for index in 0..MAX_IMPORT_THUNKS {
+            let offset = start + index * width;
+            let (value, by_ordinal) = if self.pe64 {
+                let v = read_u64(self.data, offset)?;
+                (v, v & (1 << 63) != 0)
+            } else {
+                let v = read_u32(self.data, offset)? as u64;
+                (v, v & (1 << 31) != 0)
+            };
+            if value == 0 {
+                break;
+            }
+            if by_ordinal {
+                names.push(format!("#{}", value & 0xffff));
+            } else {
+                // Hint/name entry: u16 hint, then the name
+                let entry = self.rva_to_offset(value as u32)?;
+                names.push(read_cstr(self.data, entry + 2)?);
+            }
+        }
+        Ok(names)
+    }
+
+    /// Walks type -> name -> language in the resource tree, taking the first
+    /// name and language under RT_VERSION.
+    fn version_info(&self) -> Result<Option<PeVersionInfo>> {
+        let Some((rva, _)) = self.directory(DIRECTORY_RESOURCE) else {
+            return Ok(None);
+        };
+        let root = self.rva_to_offset(rva)?;
+
+        let Some(mut entry) = self.find_resource_entry(root, Some(RT_VERSION))? else {
+            return Ok(None);
+        };
+        for _ in 0..2 {
+            if entry & 0x8000_0000 == 0 {
+                break;
+            }
+            let directory = root + (entry & 0x7fff_ffff) as usize;
+            match self.find_resource_entry(directory, None)? {
+                Some(next) => entry = next,
+                None => return Ok(None),
+            }
+        }
+        if entry & 0x8000_0000 != 0 {
+            return Err(invalid("resource tree too deep"));
+        }
+
+        let data_entry = root + entry as usize;
+        let data_rva = read_u32(self.data, data_entry)?;
+        let size = read_u32(self.data, data_entry + 4)? as usize;
+        let offset = self.rva_to_offset(data_rva)?;
+        let block = self
+            .data
+            .get(offset..offset + size)
+            .ok_or_else(|| invalid("version resource out of bounds"))?;
+        parse_version_block(block).map(Some)
+    }
+
+    /// Returns the raw `OffsetToData` of the entry with `id`, or of the first entry.
+    fn find_resource_entry(&self, directory: usize, id: Option<u32>) -> Result<Option<u32>> {
+        let named = read_u16(self.data, directory + 12)? as usize;
+        let ids = read_u16(self.data, directory + 14)? as usize;
+        for index in 0..named + ids {
+            let entry = directory + 16 + index * 8;
+            let name = read_u32(self.data, entry)?;
+            let target = read_u32(self.data, entry + 4)?;
+            match id {
+                Some(id) if name & 0x8000_0000 != 0 || name != id => continue,
+                _ => return Ok(Some(target)),
+            }
+        }
+        Ok(None)
+    }
+}
+
+fn align4(value: usize) -> usize {
+    (value + 3) & !3
+}
+
+/// One node of the VS_VERSIONINFO tree.
+struct VersionBlock<'a> {
+    key: String,
+    value_type: u16,
+    value: &'a [u8],
+    children: &'a [u8],
+}
+
+/// Splits a block at the start of `data`, returning it and its padded length.
+fn read_version_block(data: &[u8]) -> Result<(VersionBlock<'_>, usize)> {
+    let length = read_u16(data, 0)? as usize;
+    let value_length = read_u16(data, 2)? as usize;
+    let value_type = read_u16(data, 4)?;
+    if length < 6 || length > data.len() {
+        return Err(invalid("bad version block length"));
+    }
+    let block = &data[..length];
+
+    let mut key = Vec::new();
+    let mut cursor = 6;
+    loop {
+        let unit = read_u16(block, cursor)?;
+        cursor += 2;
+        if unit == 0 {
+            break;
+        }
+        key.push(unit);
+    }
+    let value_start = align4(cursor).min(length);
+
+    // Text values are counted in UTF-16 units, binary ones in bytes
+    let value_bytes = if value_type == 1 {
+        value_length * 2
+    } else {
+        value_length
+    };
+    let value_end = (value_start + value_bytes).min(length);
+    let children_start = align4(value_end).min(length);
+
+    Ok((
+        VersionBlock {
+            key: String::from_utf16_lossy(&key),
+            value_type,
+            value: &block[value_start..value_end],
+            children: &block[children_start..],
+        },
+        align4(length),
+    ))
+}
+
+fn version_children(data: &[u8]) -> Vec<VersionBlock<'_>> {
+    let mut blocks = Vec::new();
+    let mut rest = data;
+    while rest.len() >= 6 {
+        let Ok((block, consumed)) = read_version_block(rest) else {
+            break;
+        };
+        blocks.push(block);
+        rest = rest.get(consumed..).unwrap_or(&[]);
+    }
+    blocks
+}
+
+fn utf16_text(bytes: &[u8]) -> String {
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .take_while(|u| *u != 0)
+        .collect();
+    String::from_utf16_lossy(&units).trim().to_string()
+}
+
+fn parse_version_block(data: &[u8]) -> Result<PeVersionInfo> {
+    let (root, _) = read_version_block(data)?;
+    if root.key != "VS_VERSION_INFO" {
+        return Err(invalid("missing VS_VERSION_INFO"));
+    }
+
+    let mut info = PeVersionInfo::default();
+    if root.value.len() >= 24 && read_u32(root.value, 0)? == VS_FIXEDFILEINFO_SIGNATURE {
+        let version =
+            |ms: u32, ls: u32| format!("{}.{}.{}.{}", ms >> 16, ms & 0xffff, ls >> 16, ls & 0xffff);
+        info.file_version = Some(version(read_u32(root.value, 8)?, read_u32(root.value, 12)?));
+        info.product_version = Some(version(
+            read_u32(root.value, 16)?,
+            read_u32(root.value, 20)?,
+        ));
+    }
+
+    let string_table = version_children(root.children)
+        .into_iter()
+        .find(|b| b.key == "StringFileInfo")
+        .and_then(|b| version_children(b.children).into_iter().next());
+    if let Some(table) = string_table {
+        for entry in version_children(table.children) {
+            let value = if entry.value_type == 1 {
+                utf16_text(entry.value)
+            } else {
+                String::from_utf8_lossy(entry.value)
+                    .trim_end_matches('\0')
+                    .to_string()
+            };
+            info.strings.insert(entry.key, value);
+        }
+    }
+
+    Ok(info)
+}
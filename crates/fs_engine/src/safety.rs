@@ -1,11 +1,16 @@
 use crate::error::{FsError, Result};
+use crate::normalize::CaseNormalizer;
 use std::fs::File;
-use std::io;
+use std::io::{self, Write};
 use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
 use tokio::fs;
 use trash;
 use walkdir::WalkDir;
 
+/// Keeps temp names unique when one process writes the same file from two threads.
+static WRITE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
 pub struct Safety;
 
 impl Safety {
@@ -70,56 +75,44 @@ impl Safety {
         trash::delete(path).map_err(|e| FsError::Trash(e.to_string()))
     }
 
-    /// Recursively renames files to lowercase.
-    pub fn sanitize_filenames(root: &Path) -> Result<u64> {
-        if !root.exists() {
-            return Err(FsError::NotFound(root.to_path_buf()));
+    /// Replaces `path` with `data` so that a crash or full disk leaves either the old or
+    /// the new contents, never a mix: the data goes to a temp file in the same folder,
+    /// is flushed to disk, and is then renamed over the target.
+    pub fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| FsError::InvalidPath(path.to_path_buf()))?
+            .to_string_lossy();
+        let temp = path.with_file_name(format!(
+            ".{}.{}-{}.tmp",
+            file_name,
+            std::process::id(),
+            WRITE_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let written = File::create(&temp).and_then(|mut file| {
+            file.write_all(data)?;
+            file.sync_all()
+        });
+        if let Err(e) = written.and_then(|_| std::fs::rename(&temp, path)) {
+            let _ = std::fs::remove_file(&temp);
+            return Err(e.into());
         }
 
-        let mut count = 0;
-        let walker = WalkDir::new(root).contents_first(true);
-
-        for entry in walker {
-            let entry = entry.map_err(|e| FsError::Io(e.into()))?;
-            let path = entry.path();
-            if path == root {
-                continue;
-            }
-
-            if let Some(file_name) = path.file_name().and_then(|n| n.to_str()) {
-                // Skip if any part of the path (relative to root) is hidden
-                let is_hidden = path
-                    .strip_prefix(root)
-                    .ok()
-                    .map(|rel| {
-                        rel.components().any(|c| {
-                            c.as_os_str()
-                                .to_str()
-                                .map(|s| s.starts_with('.'))
-                                .unwrap_or(false)
-                        })
-                    })
-                    .unwrap_or(false);
-
-                if is_hidden {
-                    continue;
-                }
-
-                let lower_name = file_name.to_lowercase();
-                if file_name != lower_name {
-                    let mut new_path = path.to_path_buf();
-                    new_path.set_file_name(lower_name);
-
-                    if new_path.exists() {
-                        continue;
-                    }
-
-                    std::fs::rename(path, new_path).map_err(FsError::Io)?;
-                    count += 1;
-                }
+        // The rename itself only survives a power cut once the folder is flushed too
+        #[cfg(unix)]
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            if let Ok(dir) = File::open(parent) {
+                let _ = dir.sync_all();
             }
         }
-        Ok(count)
+        Ok(())
+    }
+
+    /// Recursively renames files to lowercase, fixing up INI references.
+    /// See [`CaseNormalizer`] for the full report.
+    pub fn sanitize_filenames(root: &Path) -> Result<u64> {
+        Ok(CaseNormalizer::normalize(root)?.renamed as u64)
     }
 
     /// Atomic Import: Moves content from staging to library.
@@ -0,0 +1,215 @@
+//! Builders for test fixtures, shared by the tests of the crates that depend on this
+//! one. Only compiled with the `test-util` feature.
+
+/// Address the only section is mapped at.
+pub const SECTION_RVA: u32 = 0x1000;
+/// File offset of the only section; everything before it is headers.
+pub const SECTION_RAW: usize = 0x200;
+
+/// What [`build_pe`] puts in the image. The default is a 64-bit GUI executable with no
+/// imports and no version resource.
+pub struct PeSpec<'a> {
+    pub pe64: bool,
+    pub dll: bool,
+    pub subsystem: u16,
+    /// DLL name and imported functions; `#n` imports by ordinal.
+    pub imports: &'a [(&'a str, &'a [&'a str])],
+    pub file_version: Option<[u16; 4]>,
+    /// `StringFileInfo` entries, e.g. `("CompanyName", "crosire")`.
+    pub strings: &'a [(&'a str, &'a str)],
+    /// Puts the "Wine builtin DLL" marker in the DOS stub.
+    pub wine_stub: bool,
+}
+
+impl Default for PeSpec<'_> {
+    fn default() -> Self {
+        Self {
+            pe64: true,
+            dll: false,
+            subsystem: 2,
+            imports: &[],
+            file_version: None,
+            strings: &[],
+            wine_stub: false,
+        }
+    }
+}
+
+fn put_u16(buf: &mut [u8], offset: usize, value: u16) {
+    buf[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(buf: &mut [u8], offset: usize, value: u32) {
+    buf[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+fn pad4(buf: &mut Vec<u8>) {
+    buf.resize(buf.len().div_ceil(4) * 4, 0);
+}
+
+fn utf16z(text: &str) -> Vec<u8> {
+    text.encode_utf16()
+        .chain(std::iter::once(0))
+        .flat_map(|u| u.to_le_bytes())
+        .collect()
+}
+
+fn version_block(key: &str, text: bool, value: &[u8], children: &[Vec<u8>]) -> Vec<u8> {
+    let mut block = vec![0u8; 6];
+    block.extend(utf16z(key));
+    pad4(&mut block);
+    block.extend_from_slice(value);
+    for child in children {
+        pad4(&mut block);
+        block.extend_from_slice(child);
+    }
+    let value_length = if text { value.len() / 2 } else { value.len() };
+    let length = block.len() as u16;
+    put_u16(&mut block, 0, length);
+    put_u16(&mut block, 2, value_length as u16);
+    put_u16(&mut block, 4, text as u16);
+    block
+}
+
+fn version_resource(file_version: Option<[u16; 4]>, strings: &[(&str, &str)]) -> Vec<u8> {
+    let mut fixed = Vec::new();
+    if let Some([a, b, c, d]) = file_version {
+        fixed = vec![0u8; 52];
+        put_u32(&mut fixed, 0, 0xfeef04bd);
+        put_u32(&mut fixed, 4, 0x0001_0000);
+        for base in [8, 16] {
+            put_u32(&mut fixed, base, (a as u32) << 16 | b as u32);
+            put_u32(&mut fixed, base + 4, (c as u32) << 16 | d as u32);
+        }
+    }
+    let entries: Vec<Vec<u8>> = strings
+        .iter()
+        .map(|(k, v)| version_block(k, true, &utf16z(v), &[]))
+        .collect();
+    let table = version_block("040904b0", true, &[], &entries);
+    let string_info = version_block("StringFileInfo", true, &[], &[table]);
+    version_block("VS_VERSION_INFO", false, &fixed, &[string_info])
+}
+
+/// One-section PE whose `.rdata` holds the import table and version resource.
+pub fn build_pe(spec: &PeSpec) -> Vec<u8> {
+    let mut section = Vec::new();
+    let mut import_dir = (0u32, 0u32);
+    let mut resource_dir = (0u32, 0u32);
+    let rva = |offset: usize| SECTION_RVA + offset as u32;
+    let thunk_size = if spec.pe64 { 8 } else { 4 };
+
+    if !spec.imports.is_empty() {
+        let descriptors = (spec.imports.len() + 1) * 20;
+        section.resize(descriptors, 0);
+        for (index, (dll, functions)) in spec.imports.iter().enumerate() {
+            let name_offset = section.len();
+            section.extend(dll.as_bytes());
+            section.push(0);
+            pad4(&mut section);
+
+            let thunks_offset = section.len();
+            section.resize(thunks_offset + (functions.len() + 1) * thunk_size, 0);
+            for (slot, function) in functions.iter().enumerate() {
+                let entry = thunk_offset_value(&mut section, function, spec.pe64);
+                let at = thunks_offset + slot * thunk_size;
+                section[at..at + thunk_size].copy_from_slice(&entry.to_le_bytes()[..thunk_size]);
+            }
+
+            let descriptor = index * 20;
+            put_u32(&mut section, descriptor, rva(thunks_offset));
+            put_u32(&mut section, descriptor + 12, rva(name_offset));
+            put_u32(&mut section, descriptor + 16, rva(thunks_offset));
+        }
+        import_dir = (rva(0), descriptors as u32);
+        pad4(&mut section);
+    }
+
+    if spec.file_version.is_some() || !spec.strings.is_empty() {
+        let root = section.len();
+        let data = version_resource(spec.file_version, spec.strings);
+        // root -> RT_VERSION -> id 1 -> language 0x409 -> data entry
+        section.resize(root + 3 * 24 + 16, 0);
+        for (level, id) in [(0usize, 16u32), (1, 1), (2, 0x409)] {
+            let directory = root + level * 24;
+            put_u16(&mut section, directory + 14, 1);
+            put_u32(&mut section, directory + 16, id);
+            let target = if level < 2 {
+                0x8000_0000 | ((level + 1) * 24) as u32
+            } else {
+                (3 * 24) as u32
+            };
+            put_u32(&mut section, directory + 20, target);
+        }
+        let data_offset = section.len();
+        section.extend(&data);
+        put_u32(&mut section, root + 72, rva(data_offset));
+        put_u32(&mut section, root + 76, data.len() as u32);
+        resource_dir = (rva(root), (section.len() - root) as u32);
+    }
+    pad4(&mut section);
+
+    let mut image = vec![0u8; SECTION_RAW];
+    image[0..2].copy_from_slice(b"MZ");
+    put_u32(&mut image, 0x3c, 0x80);
+    if spec.wine_stub {
+        image[0x40..0x50].copy_from_slice(b"Wine builtin DLL");
+    }
+    image[0x80..0x84].copy_from_slice(b"PE\0\0");
+
+    let coff = 0x84;
+    let optional_size = if spec.pe64 { 240 } else { 224 };
+    put_u16(&mut image, coff, if spec.pe64 { 0x8664 } else { 0x014c });
+    put_u16(&mut image, coff + 2, 1);
+    put_u16(&mut image, coff + 16, optional_size as u16);
+    put_u16(
+        &mut image,
+        coff + 18,
+        if spec.dll { 0x2022 } else { 0x0022 },
+    );
+
+    let optional = coff + 20;
+    put_u16(&mut image, optional, if spec.pe64 { 0x20b } else { 0x10b });
+    put_u32(&mut image, optional + 60, SECTION_RAW as u32);
+    put_u16(&mut image, optional + 68, spec.subsystem);
+    let (count, directories) = if spec.pe64 { (108, 112) } else { (92, 96) };
+    put_u32(&mut image, optional + count, 16);
+    put_u32(&mut image, optional + directories + 8, import_dir.0);
+    put_u32(&mut image, optional + directories + 12, import_dir.1);
+    put_u32(&mut image, optional + directories + 16, resource_dir.0);
+    put_u32(&mut image, optional + directories + 20, resource_dir.1);
+
+    let header = optional + optional_size;
+    image[header..header + 6].copy_from_slice(b".rdata");
+    put_u32(&mut image, header + 8, section.len() as u32);
+    put_u32(&mut image, header + 12, SECTION_RVA);
+    put_u32(&mut image, header + 16, section.len() as u32);
+    put_u32(&mut image, header + 20, SECTION_RAW as u32);
+
+    image.extend(section);
+    image
+}
+
+/// Appends a hint/name entry (or nothing, for `#ordinal`) and returns the thunk value.
+fn thunk_offset_value(section: &mut Vec<u8>, function: &str, pe64: bool) -> u64 {
+    if let Some(ordinal) = function.strip_prefix('#') {
+        let flag = if pe64 { 1u64 << 63 } else { 1u64 << 31 };
+        return flag | ordinal.parse::<u64>().unwrap();
+    }
+    pad4(section);
+    let offset = section.len();
+    section.extend([0, 0]);
+    section.extend(function.as_bytes());
+    section.push(0);
+    (SECTION_RVA as usize + offset) as u64
+}
+
+/// DLL carrying only a version resource with `strings`, like the proxy DLLs loaders
+/// ship.
+pub fn fake_dll(strings: &[(&str, &str)]) -> Vec<u8> {
+    build_pe(&PeSpec {
+        dll: true,
+        strings,
+        ..Default::default()
+    })
+}
@@ -0,0 +1,386 @@
+use crate::dds;
+use crate::error::{FsError, Result};
+use image::imageops::FilterType;
+use image::{ImageFormat, RgbaImage};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+const MANIFEST_NAME: &str = "thumbnails.json";
+const TEXTURE_EXTENSIONS: &[&str] = &["dds", "png", "jpg", "jpeg", "tga"];
+
+/// Name fragments of textures that are never a good preview on their own.
+const NON_DIFFUSE_HINTS: &[&str] = &[
+    "normal",
+    "lightmap",
+    "light_map",
+    "shadow",
+    "ramp",
+    "mask",
+    "material",
+    "specular",
+    "metal",
+    "rough",
+    "_n.",
+    "_nm.",
+    "_lm.",
+    "_m.",
+];
+const DIFFUSE_HINTS: &[&str] = &[
+    "diffuse",
+    "albedo",
+    "basecolor",
+    "base_color",
+    "color",
+    "_d.",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ThumbnailFormat {
+    WebP,
+    Png,
+}
+
+impl ThumbnailFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            Self::WebP => "webp",
+            Self::Png => "png",
+        }
+    }
+
+    fn image_format(self) -> ImageFormat {
+        match self {
+            Self::WebP => ImageFormat::WebP,
+            Self::Png => ImageFormat::Png,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ThumbnailOptions {
+    /// Longest edge of each generated thumbnail. Sources are never upscaled.
+    pub sizes: Vec<u32>,
+    pub formats: Vec<ThumbnailFormat>,
+}
+
+impl Default for ThumbnailOptions {
+    fn default() -> Self {
+        Self {
+            sizes: vec![128, 256, 512],
+            formats: vec![ThumbnailFormat::WebP, ThumbnailFormat::Png],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Thumbnail {
+    pub size: u32,
+    pub format: ThumbnailFormat,
+    pub width: u32,
+    pub height: u32,
+    pub path: PathBuf,
+}
+
+/// What was generated for a mod, persisted next to the images as `thumbnails.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThumbnailSet {
+    /// Texture the thumbnails were made from, relative to the mod folder.
+    pub source: PathBuf,
+    /// Fingerprint of the mod folder at generation time; a mismatch means stale.
+    pub fingerprint: String,
+    pub thumbnails: Vec<Thumbnail>,
+}
+
+impl ThumbnailSet {
+    /// Smallest thumbnail at least `size` wide in the given format, or the largest one.
+    pub fn best(&self, size: u32, format: ThumbnailFormat) -> Option<&Thumbnail> {
+        let mut matching: Vec<&Thumbnail> = self
+            .thumbnails
+            .iter()
+            .filter(|t| t.format == format)
+            .collect();
+        matching.sort_by_key(|t| t.size);
+        matching
+            .iter()
+            .find(|t| t.size >= size)
+            .or(matching.last())
+            .copied()
+    }
+}
+
+/// A texture referenced by a mod, scored for how well it represents the mod.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TextureCandidate {
+    /// Relative to the mod folder.
+    pub path: PathBuf,
+    pub file_size: u64,
+    /// How often the `[Resource]` sections pointing at this file are used in INIs.
+    pub references: usize,
+    pub diffuse_hint: bool,
+    pub non_diffuse_hint: bool,
+}
+
+pub struct Thumbnailer;
+
+impl Thumbnailer {
+    /// Returns the cached thumbnails for a mod, regenerating them if the mod changed
+    /// since they were made. `Ok(None)` means the mod has no usable texture.
+    pub fn ensure(
+        mod_dir: &Path,
+        cache_dir: &Path,
+        options: &ThumbnailOptions,
+    ) -> Result<Option<ThumbnailSet>> {
+        let fingerprint = Self::fingerprint(mod_dir)?;
+        if let Some(cached) = Self::load_cached(cache_dir) {
+            let complete = cached.thumbnails.iter().all(|t| t.path.exists());
+            if cached.fingerprint == fingerprint && complete {
+                return Ok(Some(cached));
+            }
+        }
+        Self::generate(mod_dir, cache_dir, options, fingerprint)
+    }
+
+    /// Reads the manifest without checking whether it is stale.
+    pub fn load_cached(cache_dir: &Path) -> Option<ThumbnailSet> {
+        let content = std::fs::read_to_string(cache_dir.join(MANIFEST_NAME)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    fn generate(
+        mod_dir: &Path,
+        cache_dir: &Path,
+        options: &ThumbnailOptions,
+        fingerprint: String,
+    ) -> Result<Option<ThumbnailSet>> {
+        if cache_dir.exists() {
+            std::fs::remove_dir_all(cache_dir)?;
+        }
+
+        // A texture that fails to decode (unsupported DDS format, corrupt file) is skipped
+        let mut decoded = None;
+        for candidate in Self::rank_textures(mod_dir) {
+            if let Ok(image) = dds::load_rgba(&mod_dir.join(&candidate.path)) {
+                decoded = Some((candidate.path, image));
+                break;
+            }
+        }
+        let Some((source, mut image)) = decoded else {
+            return Ok(None);
+        };
+
+        // Diffuse alpha usually carries masks, not transparency
+        image.pixels_mut().for_each(|p| p[3] = 255);
+
+        std::fs::create_dir_all(cache_dir)?;
+        let mut thumbnails = Vec::new();
+        for &size in &options.sizes {
+            let resized = Self::fit(&image, size);
+            for &format in &options.formats {
+                let path = cache_dir.join(format!("thumb_{}.{}", size, format.extension()));
+                resized.save_with_format(&path, format.image_format())?;
+                thumbnails.push(Thumbnail {
+                    size,
+                    format,
+                    width: resized.width(),
+                    height: resized.height(),
+                    path,
+                });
+            }
+        }
+
+        let set = ThumbnailSet {
+            source,
+            fingerprint,
+            thumbnails,
+        };
+        let manifest = serde_json::to_string_pretty(&set)
+            .map_err(|e| FsError::Io(std::io::Error::other(e)))?;
+        std::fs::write(cache_dir.join(MANIFEST_NAME), manifest)?;
+        Ok(Some(set))
+    }
+
+    /// Scales so the longest edge is at most `size`, keeping the aspect ratio.
+    fn fit(image: &RgbaImage, size: u32) -> RgbaImage {
+        let (width, height) = image.dimensions();
+        let longest = width.max(height);
+        if longest <= size {
+            return image.clone();
+        }
+        let scale = size as f64 / longest as f64;
+        let new_width = ((width as f64 * scale).round() as u32).max(1);
+        let new_height = ((height as f64 * scale).round() as u32).max(1);
+        image::imageops::resize(image, new_width, new_height, FilterType::Triangle)
+    }
+
+    /// Changes whenever a file in the mod is added, removed, resized or touched.
+    pub fn fingerprint(mod_dir: &Path) -> Result<String> {
+        if !mod_dir.exists() {
+            return Err(FsError::NotFound(mod_dir.to_path_buf()));
+        }
+
+        let mut entries = Vec::new();
+        for entry in WalkDir::new(mod_dir).into_iter().filter_map(|e| e.ok()) {
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            let modified = metadata
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| d.as_nanos())
+                .unwrap_or(0);
+            let relative = entry.path().strip_prefix(mod_dir).unwrap_or(entry.path());
+            entries.push((relative.to_path_buf(), metadata.len(), modified));
+        }
+        entries.sort();
+
+        // Stored with the thumbnail, so the hash has to stay the same across Rust releases
+        let mut hasher = Sha256::new();
+        for (relative, len, modified) in entries {
+            hasher.update(relative.to_string_lossy().as_bytes());
+            hasher.update([0]);
+            hasher.update(len.to_le_bytes());
+            hasher.update(modified.to_le_bytes());
+        }
+        Ok(hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect())
+    }
+
+    /// All textures in the mod, best preview candidate first: a diffuse-looking name,
+    /// then how heavily its `[Resource]` is used, then file size.
+    pub fn rank_textures(mod_dir: &Path) -> Vec<TextureCandidate> {
+        let references = Self::resource_references(mod_dir);
+
+        let mut candidates: Vec<TextureCandidate> = WalkDir::new(mod_dir)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .filter(|e| {
+                e.path()
+                    .extension()
+                    .and_then(|s| s.to_str())
+                    .is_some_and(|ext| TEXTURE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+            })
+            .map(|e| {
+                let relative = e.path().strip_prefix(mod_dir).unwrap_or(e.path());
+                let key = normalize(&relative.to_string_lossy());
+                let name = relative
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_lowercase())
+                    .unwrap_or_default();
+                TextureCandidate {
+                    path: relative.to_path_buf(),
+                    file_size: e.metadata().map(|m| m.len()).unwrap_or(0),
+                    references: references.get(&key).copied().unwrap_or(0),
+                    diffuse_hint: DIFFUSE_HINTS.iter().any(|h| name.contains(h)),
+                    non_diffuse_hint: NON_DIFFUSE_HINTS.iter().any(|h| name.contains(h)),
+                }
+            })
+            .collect();
+
+        candidates.sort_by(|a, b| {
+            (
+                !a.non_diffuse_hint,
+                a.diffuse_hint,
+                a.references,
+                a.file_size,
+            )
+                .cmp(&(
+                    !b.non_diffuse_hint,
+                    b.diffuse_hint,
+                    b.references,
+                    b.file_size,
+                ))
+                .reverse()
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        candidates
+    }
+
+    /// Maps texture paths (normalized, relative to the mod) to how often the
+    /// `[Resource...]` sections loading them are referenced elsewhere in the INIs.
+    fn resource_references(mod_dir: &Path) -> HashMap<String, usize> {
+        let mut resource_files: Vec<(String, String)> = Vec::new(); // (section, file)
+        let mut values: Vec<String> = Vec::new();
+
+        for entry in WalkDir::new(mod_dir).into_iter().filter_map(|e| e.ok()) {
+            let is_ini = entry
+                .path()
+                .extension()
+                .and_then(|s| s.to_str())
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("ini"));
+            if !is_ini {
+                continue;
+            }
+            let Ok(content) = std::fs::read_to_string(entry.path()) else {
+                continue;
+            };
+            let ini_dir = entry
+                .path()
+                .parent()
+                .and_then(|p| p.strip_prefix(mod_dir).ok())
+                .unwrap_or(Path::new(""))
+                .to_path_buf();
+
+            let mut section = String::new();
+            for line in content.lines() {
+                let line = line.trim();
+                if line.starts_with(';') || line.is_empty() {
+                    continue;
+                }
+                if line.starts_with('[') && line.ends_with(']') {
+                    section = line[1..line.len() - 1].trim().to_lowercase();
+                    continue;
+                }
+                let Some((key, value)) = line.split_once('=') else {
+                    continue;
+                };
+                let key = key.trim().to_lowercase();
+                let value = value.trim().trim_matches('"');
+                if section.starts_with("resource") && key == "filename" {
+                    let file = normalize(&ini_dir.join(value).to_string_lossy());
+                    resource_files.push((section.clone(), file));
+                } else {
+                    values.push(value.to_lowercase());
+                }
+            }
+        }
+
+        let mut references = HashMap::new();
+        for (section, file) in resource_files {
+            let uses = values
+                .iter()
+                .filter(|v| {
+                    v.split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.'))
+                        .any(|token| token == section)
+                })
+                .count();
+            *references.entry(file).or_insert(0) += uses.max(1);
+        }
+        references
+    }
+}
+
+/// Lowercase, `/`-separated, with `.` and `..` resolved lexically.
+fn normalize(path: &str) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.split(['/', '\\']) {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            other => parts.push(other),
+        }
+    }
+    parts.join("/").to_lowercase()
+}
@@ -1,41 +1,317 @@
+use crate::dds::{self, DdsFormat, DdsTexture};
 use crate::error::Result;
-use image::{DynamicImage, ImageReader};
+use image::{ImageFormat, RgbaImage};
+use serde::{Deserialize, Serialize};
 use std::path::Path;
 
+/// Pixels sampled when scoring channel layouts; larger maps are strided.
+const MAX_SAMPLES: u32 = 65_536;
+/// Mean deviation from unit length a layout may have and still count as a normal map.
+const MAX_UNIT_ERROR: f32 = 0.12;
+/// Normal maps point out of the surface, so Z should be clearly positive on average.
+const MIN_MEAN_Z: f32 = 0.3;
+/// Edge length the curl test downsamples to.
+const CURL_SIZE: u32 = 512;
+/// One green convention has to be this much more integrable than the other to count.
+const CURL_MARGIN: f32 = 0.8;
+
+/// Which way the green channel points. Unity (and so the HoYo games) expects `OpenGl`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NormalConvention {
+    /// Y+, green up.
+    OpenGl,
+    /// Y-, green down.
+    DirectX,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NormalMapIssue {
+    /// X lives in alpha; what ZZZ 1.3+ broke for older mods.
+    SwappedRedAlpha,
+    SwappedRedBlue,
+    InvertedBlue,
+    /// Green follows the other convention than the one expected.
+    InvertedGreen,
+}
+
+impl NormalMapIssue {
+    fn repair(self, image: &mut RgbaImage) {
+        for pixel in image.pixels_mut() {
+            match self {
+                Self::SwappedRedAlpha => pixel.0.swap(0, 3),
+                Self::SwappedRedBlue => pixel.0.swap(0, 2),
+                Self::InvertedBlue => pixel[2] = 255 - pixel[2],
+                Self::InvertedGreen => pixel[1] = 255 - pixel[1],
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NormalMapAnalysis {
+    pub is_normal_map: bool,
+    /// Problems found, in the order they should be repaired.
+    pub issues: Vec<NormalMapIssue>,
+    /// Green convention the content follows, when the map has enough detail to tell.
+    pub convention: Option<NormalConvention>,
+    /// Mean deviation from unit length once the layout is corrected (0 is perfect).
+    pub unit_error: f32,
+}
+
+/// Channel layout candidate: where X/Y/Z are read from and whether Z is negated.
+struct Layout {
+    issue: Option<NormalMapIssue>,
+    channels: [usize; 3],
+    invert_z: bool,
+}
+
+const LAYOUTS: [Layout; 4] = [
+    Layout {
+        issue: None,
+        channels: [0, 1, 2],
+        invert_z: false,
+    },
+    Layout {
+        issue: Some(NormalMapIssue::SwappedRedAlpha),
+        channels: [3, 1, 2],
+        invert_z: false,
+    },
+    Layout {
+        issue: Some(NormalMapIssue::SwappedRedBlue),
+        channels: [2, 1, 0],
+        invert_z: false,
+    },
+    Layout {
+        issue: Some(NormalMapIssue::InvertedBlue),
+        channels: [0, 1, 2],
+        invert_z: true,
+    },
+];
+
+fn unorm(value: u8) -> f32 {
+    value as f32 / 255.0 * 2.0 - 1.0
+}
+
+/// Decoded normal for a pixel. Two-channel maps (BC5) get Z reconstructed.
+fn normal_at(pixel: &[u8; 4], two_channel: bool) -> [f32; 3] {
+    let x = unorm(pixel[0]);
+    let y = unorm(pixel[1]);
+    let z = if two_channel {
+        (1.0 - x * x - y * y).max(0.0).sqrt()
+    } else {
+        unorm(pixel[2])
+    };
+    [x, y, z]
+}
+
 pub struct Transcoder;
 
 impl Transcoder {
-    /// Checks if an image is likely a normal map and re-encodes it if necessary.
-    /// This logic specifically targets fixing ZZZ v1.3+ maps where channels might be swapped.
-    ///
-    /// For now, this is a stub that loads and saves the image to verify integrity/format.
-    /// Actual channel swapping logic would go here.
+    /// Whether a file name suggests a normal map (`*NormalMap.dds`, `body_n.png`, ...).
+    pub fn is_normal_map_name(path: &Path) -> bool {
+        let stem = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+        stem.contains("normal") || stem.ends_with("_n") || stem.ends_with("_nm")
+    }
+
+    /// Scores the image against the known broken layouts. `two_channel` is for BC5
+    /// maps, where only the green convention can be wrong.
+    pub fn analyze_normal_map(
+        image: &RgbaImage,
+        two_channel: bool,
+        expected: Option<NormalConvention>,
+    ) -> NormalMapAnalysis {
+        let mut analysis = NormalMapAnalysis {
+            is_normal_map: false,
+            issues: Vec::new(),
+            convention: None,
+            unit_error: 1.0,
+        };
+
+        let layouts: &[Layout] = if two_channel { &LAYOUTS[..1] } else { &LAYOUTS };
+        let mut best: Option<(&Layout, f32)> = None;
+        for layout in layouts {
+            let (error, mean_z) = Self::score_layout(image, layout, two_channel);
+            if error > MAX_UNIT_ERROR || mean_z < MIN_MEAN_Z {
+                continue;
+            }
+            // A valid standard layout always wins; alternatives only replace a broken one
+            if layout.issue.is_none() {
+                best = Some((layout, error));
+                break;
+            }
+            if best.is_none_or(|(_, e)| error < e) {
+                best = Some((layout, error));
+            }
+        }
+
+        let Some((layout, error)) = best else {
+            return analysis;
+        };
+        analysis.is_normal_map = true;
+        analysis.unit_error = error;
+        analysis.issues.extend(layout.issue);
+
+        let mut corrected = image.clone();
+        for issue in &analysis.issues {
+            issue.repair(&mut corrected);
+        }
+        analysis.convention = Self::detect_convention(&corrected, two_channel);
+        if let (Some(found), Some(expected)) = (analysis.convention, expected) {
+            if found != expected {
+                analysis.issues.push(NormalMapIssue::InvertedGreen);
+            }
+        }
+        analysis
+    }
+
+    /// Mean unit-length error and mean Z over a strided sample.
+    fn score_layout(image: &RgbaImage, layout: &Layout, two_channel: bool) -> (f32, f32) {
+        let (width, height) = image.dimensions();
+        let stride = ((width as u64 * height as u64 / MAX_SAMPLES as u64) as f64)
+            .sqrt()
+            .ceil()
+            .max(1.0) as usize;
+
+        let (mut error, mut z_sum, mut count) = (0.0f32, 0.0f32, 0u32);
+        for y in (0..height).step_by(stride) {
+            for x in (0..width).step_by(stride) {
+                let pixel = image.get_pixel(x, y).0;
+                let reordered = layout.channels.map(|c| pixel[c]);
+                let mut normal = normal_at(
+                    &[reordered[0], reordered[1], reordered[2], 255],
+                    two_channel,
+                );
+                if layout.invert_z {
+                    normal[2] = -normal[2];
+                }
+                let length = normal.iter().map(|v| v * v).sum::<f32>().sqrt();
+                error += (length - 1.0).abs();
+                z_sum += normal[2];
+                count += 1;
+            }
+        }
+        if count == 0 {
+            return (f32::MAX, 0.0);
+        }
+        (error / count as f32, z_sum / count as f32)
+    }
+
+    /// A normal map derived from a height field has a curl-free gradient field. Reading
+    /// green with the wrong sign breaks that, so the convention with less curl wins.
+    fn detect_convention(image: &RgbaImage, two_channel: bool) -> Option<NormalConvention> {
+        let image = if image.width() > CURL_SIZE || image.height() > CURL_SIZE {
+            image::imageops::thumbnail(
+                image,
+                CURL_SIZE.min(image.width()),
+                CURL_SIZE.min(image.height()),
+            )
+        } else {
+            image.clone()
+        };
+        let (width, height) = image.dimensions();
+        if width < 3 || height < 3 {
+            return None;
+        }
+
+        // Height gradients assuming DirectX (green down): dh/du = -x/z, dh/dv = -y/z
+        let gradients: Vec<(f32, f32)> = image
+            .pixels()
+            .map(|p| {
+                let [x, y, z] = normal_at(&p.0, two_channel);
+                let z = z.max(0.1);
+                (-x / z, -y / z)
+            })
+            .collect();
+        let at = |x: u32, y: u32| gradients[(y * width + x) as usize];
+
+        let (mut curl_dx, mut curl_gl, mut signal) = (0.0f32, 0.0f32, 0.0f32);
+        for y in 0..height - 1 {
+            for x in 0..width - 1 {
+                let (gu, gv) = at(x, y);
+                let du_dv = at(x, y + 1).0 - gu;
+                let dv_du = at(x + 1, y).1 - gv;
+                curl_dx += (du_dv - dv_du).abs();
+                curl_gl += (du_dv + dv_du).abs();
+                signal += du_dv.abs() + dv_du.abs();
+            }
+        }
+
+        let cells = ((width - 1) * (height - 1)) as f32;
+        if signal / cells < 1e-3 {
+            return None;
+        }
+        if curl_dx < curl_gl * CURL_MARGIN {
+            Some(NormalConvention::DirectX)
+        } else if curl_gl < curl_dx * CURL_MARGIN {
+            Some(NormalConvention::OpenGl)
+        } else {
+            None
+        }
+    }
+
+    /// Checks if an image is likely a normal map and rewrites it if its channels are
+    /// swapped or inverted. This targets ZZZ v1.3+, which broke older mods' maps.
+    /// Handles block-compressed DDS as well as anything the `image` crate reads.
     pub fn fix_normal_map(path: &Path) -> Result<bool> {
-        let img = ImageReader::open(path)?.decode()?;
+        Ok(!Self::fix_normal_map_with(path, None)?.is_empty())
+    }
+
+    /// Like [`Transcoder::fix_normal_map`], additionally flipping green when the map
+    /// clearly follows the other convention than `expected`. Returns what was repaired.
+    pub fn fix_normal_map_with(
+        path: &Path,
+        expected: Option<NormalConvention>,
+    ) -> Result<Vec<NormalMapIssue>> {
+        if dds::is_dds(path) {
+            let mut texture = DdsTexture::load(path)?;
+            let two_channel = texture.header.format == DdsFormat::Bc5;
+            let analysis = Self::analyze_normal_map(texture.image(), two_channel, expected);
+            if analysis.issues.is_empty() {
+                return Ok(analysis.issues);
+            }
 
-        // Mock Detection Logic:
-        // In real world, we might check if Alpha channel is fully opaque or check color distribution.
-        let is_suspicious = false; // Placeholder
+            for surface in texture.surfaces.iter_mut() {
+                for issue in &analysis.issues {
+                    issue.repair(surface);
+                }
+            }
+            Self::replace_file(path, &texture.to_bytes()?)?;
+            return Ok(analysis.issues);
+        }
 
-        if is_suspicious {
-            let fixed_img = Self::swap_channels(img);
-            fixed_img.save(path)?;
-            return Ok(true);
+        let format = ImageFormat::from_path(path)?;
+        let mut image = image::ImageReader::open(path)?.decode()?.to_rgba8();
+        let analysis = Self::analyze_normal_map(&image, false, expected);
+        if analysis.issues.is_empty() {
+            return Ok(analysis.issues);
         }
 
-        Ok(false)
+        for issue in &analysis.issues {
+            issue.repair(&mut image);
+        }
+        let mut encoded = std::io::Cursor::new(Vec::new());
+        image.write_to(&mut encoded, format)?;
+        Self::replace_file(path, encoded.get_ref())?;
+        Ok(analysis.issues)
     }
 
-    fn swap_channels(img: DynamicImage) -> DynamicImage {
-        // Placeholder for channel swapping logic (e.g., R <-> A)
-        // let mut buffer = img.to_rgba8();
-        // for pixel in buffer.pixels_mut() {
-        //     let r = pixel[0];
-        //     let a = pixel[3];
-        //     pixel[0] = a;
-        //     pixel[3] = r;
-        // }
-        // DynamicImage::ImageRgba8(buffer)
-        img
+    /// Writes next to the target and renames over it, so a failed encode never
+    /// leaves a half-written texture behind.
+    fn replace_file(path: &Path, data: &[u8]) -> Result<()> {
+        let file_name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("texture");
+        let temp = path.with_file_name(format!(".{}.tmp", file_name));
+        std::fs::write(&temp, data)?;
+        if let Err(e) = std::fs::rename(&temp, path) {
+            let _ = std::fs::remove_file(&temp);
+            return Err(e.into());
+        }
+        Ok(())
     }
 }
@@ -0,0 +1,152 @@
+//! Debounced recursive directory watching, grouped by the entry that changed.
+//!
+//! Mod folders are edited by many small writes (an editor saving, an archive tool
+//! unpacking), so raw events are collected until the tree has been quiet for the
+//! debounce interval and then handed over as one batch.
+
+use crate::error::{FsError, Result};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A batch never waits longer than this many debounce intervals, so a long copy still
+/// produces intermediate updates.
+const MAX_DELAY_FACTOR: u32 = 10;
+
+/// Something below `entry` changed, was created or was removed.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct WatchChange {
+    /// The watched root the change happened under.
+    pub root: PathBuf,
+    /// The entry `depth` levels below the root that contains the change (a mod folder,
+    /// say). Changes closer to the root than `depth` report the changed path itself.
+    pub entry: PathBuf,
+}
+
+pub struct DirWatcher {
+    watcher: RecommendedWatcher,
+    roots: Arc<Mutex<Vec<PathBuf>>>,
+}
+
+impl DirWatcher {
+    /// Starts the debounce thread, reporting changes per direct child of each root.
+    /// `on_change` runs on that thread once per batch; the thread exits when the
+    /// watcher is dropped.
+    pub fn new<F>(debounce: Duration, on_change: F) -> Result<Self>
+    where
+        F: Fn(Vec<WatchChange>) + Send + 'static,
+    {
+        Self::with_depth(debounce, 1, on_change)
+    }
+
+    /// Like [`DirWatcher::new`], but groups changes `depth` levels below the root,
+    /// e.g. `2` for `<mods>/<game>/<mod>`.
+    pub fn with_depth<F>(debounce: Duration, depth: usize, on_change: F) -> Result<Self>
+    where
+        F: Fn(Vec<WatchChange>) + Send + 'static,
+    {
+        let roots: Arc<Mutex<Vec<PathBuf>>> = Arc::new(Mutex::new(Vec::new()));
+        let (tx, rx) = mpsc::channel::<PathBuf>();
+
+        let watcher =
+            notify::recommended_watcher(move |res: notify::Result<notify::Event>| match res {
+                Ok(event) if !matches!(event.kind, EventKind::Access(_)) => {
+                    for path in event.paths {
+                        let _ = tx.send(path);
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("DirWatcher: {}", e),
+            })
+            .map_err(|e| FsError::Watch(e.to_string()))?;
+
+        let thread_roots = roots.clone();
+        std::thread::spawn(move || {
+            let max_delay = debounce * MAX_DELAY_FACTOR;
+            let mut pending: BTreeSet<WatchChange> = BTreeSet::new();
+            let mut first_seen: Option<Instant> = None;
+            loop {
+                match rx.recv_timeout(debounce) {
+                    Ok(path) => {
+                        let roots = thread_roots.lock().unwrap_or_else(|e| e.into_inner());
+                        if let Some(change) = Self::classify(&roots, &path, depth) {
+                            pending.insert(change);
+                            first_seen.get_or_insert_with(Instant::now);
+                        }
+                        let overdue = first_seen.is_some_and(|t| t.elapsed() >= max_delay);
+                        if !overdue {
+                            continue;
+                        }
+                    }
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+                if !pending.is_empty() {
+                    on_change(std::mem::take(&mut pending).into_iter().collect());
+                }
+                first_seen = None;
+            }
+        });
+
+        Ok(Self { watcher, roots })
+    }
+
+    /// Watches `root` recursively. Watching a root twice is a no-op.
+    pub fn watch(&mut self, root: &Path) -> Result<()> {
+        if !root.is_dir() {
+            return Err(FsError::NotFound(root.to_path_buf()));
+        }
+        let mut roots = self.roots.lock().unwrap_or_else(|e| e.into_inner());
+        if roots.iter().any(|r| r == root) {
+            return Ok(());
+        }
+        self.watcher
+            .watch(root, RecursiveMode::Recursive)
+            .map_err(|e| FsError::Watch(e.to_string()))?;
+        roots.push(root.to_path_buf());
+        Ok(())
+    }
+
+    pub fn unwatch(&mut self, root: &Path) -> Result<()> {
+        let mut roots = self.roots.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(index) = roots.iter().position(|r| r == root) {
+            roots.remove(index);
+            self.watcher
+                .unwatch(root)
+                .map_err(|e| FsError::Watch(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    pub fn roots(&self) -> Vec<PathBuf> {
+        self.roots.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Maps a changed path to the entry `depth` levels below its root. Changes to the
+    /// root itself and under dot-prefixed entries (staging folders, `.git`) are dropped.
+    fn classify(roots: &[PathBuf], path: &Path, depth: usize) -> Option<WatchChange> {
+        let root = roots
+            .iter()
+            .filter(|r| path.starts_with(r))
+            .max_by_key(|r| r.components().count())?;
+        let relative = path.strip_prefix(root).ok()?;
+        let mut entry = root.clone();
+        for component in relative.components().take(depth.max(1)) {
+            let name = component.as_os_str();
+            if name.to_string_lossy().starts_with('.') {
+                return None;
+            }
+            entry.push(name);
+        }
+        if entry == *root {
+            return None;
+        }
+        Some(WatchChange {
+            root: root.clone(),
+            entry,
+        })
+    }
+}
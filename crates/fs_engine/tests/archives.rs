@@ -163,3 +163,362 @@ fn test_extract_targz() {
     let content = fs::read_to_string(dest_path.join("hello.txt")).unwrap();
     assert_eq!(content, "tar content");
 }
+
+fn build_zip(path: &Path, files: &[(&str, &[u8])]) {
+    let file = fs::File::create(path).unwrap();
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::SimpleFileOptions::default();
+    for (name, content) in files {
+        if name.ends_with('/') {
+            zip.add_directory(*name, options).unwrap();
+        } else {
+            zip.start_file(*name, options).unwrap();
+            zip.write_all(content).unwrap();
+        }
+    }
+    zip.finish().unwrap();
+}
+
+#[test]
+fn test_inspect_archive_without_extraction() {
+    let dir = tempdir().unwrap();
+    let zip_path = dir.path().join("pack.zip");
+    build_zip(
+        &zip_path,
+        &[
+            ("Pack/", b""),
+            ("Pack/mod.json", b"{}"),
+            ("Pack/Raiden/raiden.ini", b"[TextureOverrideBody]"),
+            ("Pack/Raiden/Textures/body.dds", b"DDS "),
+            ("Pack/Raiden/Extra/toggle.ini", b"[Key]"),
+            ("Pack/Nahida/nahida.ini", b"[TextureOverrideBody]"),
+            ("Pack/preview.png", b"png"),
+            ("Pack/setup.exe", b"MZ"),
+        ],
+    );
+
+    let inspector = ArchiveInspector::open(&zip_path).unwrap();
+    assert_eq!(inspector.format(), ArchiveFormat::Zip);
+
+    let preview = inspector.inspect().unwrap();
+    assert_eq!(preview.common_root.as_deref(), Some("Pack/"));
+    assert_eq!(preview.file_count, 7);
+    assert!(preview.has_mod_json);
+    assert_eq!(preview.ini_files.len(), 3);
+    assert_eq!(preview.mod_roots, vec!["Nahida", "Raiden"]);
+    assert!(preview.is_multi_mod());
+    assert_eq!(preview.preview_images, vec!["Pack/preview.png"]);
+    assert_eq!(preview.blocked_executables, vec!["Pack/setup.exe"]);
+
+    let exe = preview
+        .entries
+        .iter()
+        .find(|e| e.path == "Pack/setup.exe")
+        .unwrap();
+    assert!(!exe.allowed);
+
+    // Nothing was written next to the archive
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn test_inspect_flags_match_extraction() {
+    let dir = tempdir().unwrap();
+    let zip_path = dir.path().join("mod.zip");
+    build_zip(
+        &zip_path,
+        &[
+            ("char.ini", b"[TextureOverride]"),
+            ("d3dx.ini", b"[Loader]"),
+            ("notes.log", b"log"),
+        ],
+    );
+
+    let preview = ArchiveInspector::open(&zip_path)
+        .unwrap()
+        .inspect()
+        .unwrap();
+    let report = extract_and_sanitize(&zip_path, &dir.path().join("out")).unwrap();
+
+    assert_eq!(preview.files_ignored, report.files_ignored);
+    assert_eq!(preview.mod_roots, vec![""]);
+    assert!(!preview.is_multi_mod());
+}
+
+#[test]
+fn test_read_single_entry() {
+    let dir = tempdir().unwrap();
+    let zip_path = dir.path().join("mod.zip");
+    build_zip(
+        &zip_path,
+        &[
+            ("mod.json", b"{\"name\":\"Test\"}"),
+            ("preview.png", b"\x89PNG"),
+        ],
+    );
+
+    let inspector = ArchiveInspector::open(&zip_path).unwrap();
+    assert_eq!(
+        inspector.read_entry("mod.json").unwrap(),
+        b"{\"name\":\"Test\"}"
+    );
+    assert_eq!(inspector.read_entry("preview.png").unwrap(), b"\x89PNG");
+    assert!(matches!(
+        inspector.read_entry("missing.txt"),
+        Err(FsError::NotFound(_))
+    ));
+}
+
+#[test]
+fn test_read_entry_is_bounded() {
+    let dir = tempdir().unwrap();
+    let zip_path = dir.path().join("bomb.zip");
+    let big = vec![0u8; MAX_ENTRY_READ as usize + 1];
+    build_zip(&zip_path, &[("preview.png", &big), ("mod.json", b"{}")]);
+
+    let inspector = ArchiveInspector::open(&zip_path).unwrap();
+    assert!(matches!(
+        inspector.read_entry("preview.png"),
+        Err(FsError::ArchiveLimit(_))
+    ));
+    assert_eq!(inspector.read_entry("mod.json").unwrap(), b"{}");
+}
+
+#[test]
+fn test_read_entry_of_encrypted_archive() {
+    let dir = tempdir().unwrap();
+    let zip_path = dir.path().join("locked.zip");
+    build_encrypted_zip(&zip_path, "hunter2");
+
+    let inspector = ArchiveInspector::open(&zip_path).unwrap();
+    assert!(matches!(
+        inspector.read_entry("mod.ini"),
+        Err(FsError::PasswordRequired(_))
+    ));
+    let inspector = inspector.with_password(Some("nope".to_string()));
+    assert!(matches!(
+        inspector.read_entry("mod.ini"),
+        Err(FsError::InvalidPassword(_))
+    ));
+    let inspector = inspector.with_password(Some("hunter2".to_string()));
+    assert_eq!(
+        inspector.read_entry("mod.ini").unwrap(),
+        b"[TextureOverride]"
+    );
+
+    let src = dir.path().join("src");
+    fs::create_dir_all(&src).unwrap();
+    fs::write(src.join("mod.ini"), "[TextureOverride]").unwrap();
+    let archive = dir.path().join("locked.7z");
+    sevenz_rust::compress_to_path_encrypted(&src, &archive, "hunter2".into()).unwrap();
+    let inspector = ArchiveInspector::open(&archive)
+        .unwrap()
+        .with_password(Some("hunter2".to_string()));
+    assert_eq!(
+        inspector.read_entry("mod.ini").unwrap(),
+        b"[TextureOverride]"
+    );
+}
+
+#[test]
+fn test_extraction_reports_progress() {
+    let dir = tempdir().unwrap();
+    let zip_path = dir.path().join("mod.zip");
+    build_zip(
+        &zip_path,
+        &[("a.ini", b"[A]"), ("b.ini", b"[B]"), ("tool.exe", b"MZ")],
+    );
+
+    let events = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let sink = events.clone();
+    let options = ExtractOptions {
+        on_progress: Some(std::sync::Arc::new(move |p: ExtractionProgress| {
+            sink.lock().unwrap().push(p)
+        })),
+        ..Default::default()
+    };
+
+    extract_and_sanitize_with(&zip_path, &dir.path().join("out"), &options).unwrap();
+
+    let events = events.lock().unwrap();
+    let last = events.last().unwrap();
+    assert_eq!(last.entries_total, 3);
+    assert_eq!(last.entries_done, 3);
+    assert_eq!(last.bytes_done, 6); // the skipped exe is never read
+    assert!(events.iter().any(|p| p.current_file == "b.ini"));
+}
+
+#[test]
+fn test_extraction_cancelled() {
+    let dir = tempdir().unwrap();
+    let zip_path = dir.path().join("mod.zip");
+    build_zip(&zip_path, &[("a.ini", b"[A]")]);
+
+    let options = ExtractOptions::default();
+    options.cancel.cancel();
+
+    let result = extract_and_sanitize_with(&zip_path, &dir.path().join("out"), &options);
+    assert!(matches!(result, Err(FsError::Cancelled)));
+    assert!(!dir.path().join("out/a.ini").exists());
+}
+
+#[test]
+fn test_extraction_rejects_compression_bomb() {
+    let dir = tempdir().unwrap();
+    let zip_path = dir.path().join("bomb.zip");
+    {
+        let file = fs::File::create(&zip_path).unwrap();
+        let mut zip = zip::ZipWriter::new(file);
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated);
+        zip.start_file("huge.dds", options).unwrap();
+        zip.write_all(&vec![0u8; 8 * 1024 * 1024]).unwrap();
+        zip.finish().unwrap();
+    }
+
+    let result = extract_and_sanitize(&zip_path, &dir.path().join("out"));
+    assert!(matches!(result, Err(FsError::ArchiveLimit(_))));
+    assert!(!dir.path().join("out").exists());
+}
+
+#[test]
+fn test_extraction_entry_and_size_limits() {
+    let dir = tempdir().unwrap();
+    let zip_path = dir.path().join("mod.zip");
+    build_zip(
+        &zip_path,
+        &[("a.ini", b"[A]"), ("b.ini", b"[B]"), ("c.ini", b"[C]")],
+    );
+
+    let too_many = ExtractOptions {
+        limits: ExtractionLimits {
+            max_entries: 2,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let result = extract_and_sanitize_with(&zip_path, &dir.path().join("out"), &too_many);
+    assert!(matches!(result, Err(FsError::ArchiveLimit(_))));
+
+    let too_big = ExtractOptions {
+        limits: ExtractionLimits {
+            max_total_size: 5,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let result = extract_and_sanitize_with(&zip_path, &dir.path().join("out"), &too_big);
+    assert!(matches!(result, Err(FsError::ArchiveLimit(_))));
+}
+
+fn build_encrypted_zip(path: &Path, password: &str) {
+    let file = fs::File::create(path).unwrap();
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::SimpleFileOptions::default()
+        .with_aes_encryption(zip::AesMode::Aes256, password);
+    zip.start_file("mod.ini", options).unwrap();
+    zip.write_all(b"[TextureOverride]").unwrap();
+    zip.finish().unwrap();
+}
+
+#[test]
+fn test_encrypted_zip_requires_password() {
+    let dir = tempdir().unwrap();
+    let zip_path = dir.path().join("locked.zip");
+    build_encrypted_zip(&zip_path, "hunter2");
+    let out = dir.path().join("out");
+
+    let preview = ArchiveInspector::open(&zip_path)
+        .unwrap()
+        .inspect()
+        .unwrap();
+    assert!(preview.encrypted);
+
+    let result = extract_and_sanitize(&zip_path, &out);
+    assert!(matches!(result, Err(FsError::PasswordRequired(_))));
+
+    let wrong = ExtractOptions {
+        password: Some("nope".to_string()),
+        ..Default::default()
+    };
+    let result = extract_and_sanitize_with(&zip_path, &out, &wrong);
+    assert!(matches!(result, Err(FsError::InvalidPassword(_))));
+
+    let right = ExtractOptions {
+        password: Some("hunter2".to_string()),
+        ..Default::default()
+    };
+    extract_and_sanitize_with(&zip_path, &out, &right).unwrap();
+    assert_eq!(
+        fs::read_to_string(out.join("mod.ini")).unwrap(),
+        "[TextureOverride]"
+    );
+}
+
+#[test]
+fn test_extract_and_sanitize_7z() {
+    let dir = tempdir().unwrap();
+    let src = dir.path().join("src/CoolMod");
+    fs::create_dir_all(&src).unwrap();
+    fs::write(src.join("mod.ini"), "[TextureOverride]").unwrap();
+    fs::write(src.join("setup.exe"), "MZ").unwrap();
+
+    let archive = dir.path().join("mod.7z");
+    sevenz_rust::compress_to_path(dir.path().join("src"), &archive).unwrap();
+
+    let out = dir.path().join("out");
+    let report = extract_and_sanitize(&archive, &out).unwrap();
+    assert_eq!(
+        fs::read_to_string(out.join("mod.ini")).unwrap(),
+        "[TextureOverride]"
+    );
+    assert!(report.files_ignored.contains(&"setup.exe".to_string()));
+}
+
+#[test]
+fn test_encrypted_7z_requires_password() {
+    let dir = tempdir().unwrap();
+    let src = dir.path().join("src");
+    fs::create_dir_all(&src).unwrap();
+    fs::write(src.join("mod.ini"), "[TextureOverride]").unwrap();
+
+    let archive = dir.path().join("locked.7z");
+    sevenz_rust::compress_to_path_encrypted(&src, &archive, "hunter2".into()).unwrap();
+    let out = dir.path().join("out");
+
+    let result = extract_and_sanitize(&archive, &out);
+    assert!(matches!(result, Err(FsError::PasswordRequired(_))));
+
+    let right = ExtractOptions {
+        password: Some("hunter2".to_string()),
+        ..Default::default()
+    };
+    extract_and_sanitize_with(&archive, &out, &right).unwrap();
+    assert!(out.join("mod.ini").exists());
+}
+
+#[test]
+fn test_copy_and_sanitize_folder() {
+    let dir = tempdir().unwrap();
+    let source = dir.path().join("Downloads/Kimono");
+    fs::create_dir_all(source.join("Kimono/Textures")).unwrap();
+    fs::write(source.join("Kimono/mod.ini"), "[TextureOverrideBody]").unwrap();
+    fs::write(source.join("Kimono/Textures/body.dds"), "dds").unwrap();
+    fs::write(source.join("Kimono/setup.exe"), "MZ").unwrap();
+    #[cfg(unix)]
+    std::os::unix::fs::symlink("/etc/passwd", source.join("Kimono/passwd.txt")).unwrap();
+
+    let dest = dir.path().join("out");
+    let report = copy_and_sanitize_with(&source, &dest, &ExtractOptions::default()).unwrap();
+    assert!(dest.join("mod.ini").exists());
+    assert!(dest.join("Textures/body.dds").exists());
+    assert!(!dest.join("setup.exe").exists());
+    assert!(!dest.join("passwd.txt").exists());
+    assert_eq!(report.files_ignored, vec!["setup.exe"]);
+    assert!(source.join("Kimono/setup.exe").exists());
+
+    let cancelled = ExtractOptions::default();
+    cancelled.cancel.cancel();
+    let result = copy_and_sanitize_with(&source, &dir.path().join("out2"), &cancelled);
+    assert!(matches!(result, Err(FsError::Cancelled)));
+}
@@ -0,0 +1,67 @@
+use fs_engine::{BackupRotation, Safety};
+use std::fs;
+use std::time::Duration;
+use tempfile::tempdir;
+
+#[test]
+fn test_write_atomic_replaces_without_leftovers() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("game.json");
+    fs::write(&path, "old").unwrap();
+
+    Safety::write_atomic(&path, b"new").unwrap();
+
+    assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+    let names: Vec<_> = fs::read_dir(dir.path())
+        .unwrap()
+        .map(|e| e.unwrap().file_name())
+        .collect();
+    assert_eq!(names, vec!["game.json"]);
+}
+
+#[test]
+fn test_snapshot_rotates_and_throttles() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("game.json");
+    let rotation = BackupRotation::new(dir.path().join("backups"), 3);
+
+    for version in 0..5 {
+        fs::write(&path, format!("{{\"v\":{}}}", version)).unwrap();
+        assert!(rotation.snapshot(&path).unwrap().is_some());
+    }
+    let backups = rotation.backups(&path).unwrap();
+    assert_eq!(backups.len(), 3);
+    assert_eq!(fs::read_to_string(&backups[0]).unwrap(), "{\"v\":4}");
+    assert_eq!(fs::read_to_string(&backups[2]).unwrap(), "{\"v\":2}");
+
+    let throttled = BackupRotation::new(dir.path().join("backups"), 3)
+        .with_min_interval(Duration::from_secs(3600));
+    assert!(throttled.snapshot(&path).unwrap().is_none());
+}
+
+#[test]
+fn test_restore_picks_newest_valid_backup() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("settings.json");
+    let rotation = BackupRotation::new(dir.path().join("backups"), 5);
+
+    fs::write(&path, "good").unwrap();
+    rotation.snapshot(&path).unwrap();
+    fs::write(&path, "bad newer").unwrap();
+    rotation.snapshot(&path).unwrap();
+    fs::write(&path, "garbage").unwrap();
+
+    let is_valid = |data: &[u8]| data.starts_with(b"good");
+    let restored = rotation.restore(&path, is_valid).unwrap().unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "good");
+    let corrupt = restored.corrupt_copy.unwrap();
+    assert_eq!(fs::read_to_string(corrupt).unwrap(), "garbage");
+
+    // Nothing usable leaves the file alone
+    fs::write(&path, "garbage").unwrap();
+    let none = rotation
+        .restore(&path, |data| data.starts_with(b"never"))
+        .unwrap();
+    assert!(none.is_none());
+    assert_eq!(fs::read_to_string(&path).unwrap(), "garbage");
+}
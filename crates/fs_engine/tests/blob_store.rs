@@ -0,0 +1,128 @@
+use fs_engine::{BlobStore, MIN_BLOB_SIZE};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+fn write(root: &Path, relative: &str, content: &[u8]) {
+    let path = root.join(relative);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
+
+fn texture(seed: u8) -> Vec<u8> {
+    (0..MIN_BLOB_SIZE as usize + 100)
+        .map(|i| (i as u8).wrapping_mul(seed))
+        .collect()
+}
+
+#[cfg(unix)]
+fn same_inode(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    fs::metadata(a).unwrap().ino() == fs::metadata(b).unwrap().ino()
+}
+
+#[test]
+fn test_ingest_links_identical_files() {
+    let dir = tempdir().unwrap();
+    let store = BlobStore::new(dir.path().join("store"));
+    let mod_a = dir.path().join("a");
+    let mod_b = dir.path().join("b");
+    let body = texture(3);
+    write(&mod_a, "Body.dds", &body);
+    write(&mod_a, "Face.dds", &texture(5));
+    write(&mod_a, "mod.ini", &vec![b';'; MIN_BLOB_SIZE as usize + 1]);
+    write(&mod_b, "tex/Body.dds", &body);
+    write(&mod_b, "small.buf", b"tiny");
+
+    let first = store.ingest("mod-a", &mod_a).unwrap();
+    assert_eq!(first.linked, 2);
+    assert_eq!(first.deduplicated, 0);
+
+    let second = store.ingest("mod-b", &mod_b).unwrap();
+    assert_eq!(second.linked, 1);
+    assert_eq!(second.deduplicated, 1);
+    assert_eq!(second.bytes_saved, body.len() as u64);
+    assert_eq!(fs::read(mod_b.join("tex/Body.dds")).unwrap(), body);
+    #[cfg(unix)]
+    assert!(same_inode(
+        &mod_a.join("Body.dds"),
+        &mod_b.join("tex/Body.dds")
+    ));
+
+    // Running again changes nothing
+    let again = store.ingest("mod-b", &mod_b).unwrap();
+    assert_eq!(again.linked, 1);
+    assert_eq!(again.bytes_saved, 0);
+
+    let stats = store.stats().unwrap();
+    assert_eq!(stats.blobs, 2);
+    assert_eq!(stats.mods, 2);
+    assert_eq!(stats.linked_files, 3);
+    assert_eq!(stats.saved_bytes, body.len() as u64);
+    assert_eq!(stats.unreferenced_blobs, 0);
+}
+
+#[test]
+fn test_gc_removes_blobs_of_released_mods_only() {
+    let dir = tempdir().unwrap();
+    let store = BlobStore::new(dir.path().join("store"));
+    let shared = texture(7);
+    let only_b = texture(11);
+    let mod_a = dir.path().join("a");
+    let mod_b = dir.path().join("b");
+    write(&mod_a, "Body.dds", &shared);
+    write(&mod_b, "Body.dds", &shared);
+    write(&mod_b, "Hair.dds", &only_b);
+    store.ingest("mod-a", &mod_a).unwrap();
+    store.ingest("mod-b", &mod_b).unwrap();
+
+    // mod-b's record is gone; its folder is still around, e.g. in the trash
+    let live: HashSet<String> = ["mod-a".to_string()].into_iter().collect();
+    let report = store.collect_garbage(&live).unwrap();
+    assert_eq!(report.removed_manifests, 1);
+    assert_eq!(report.removed_blobs, 1);
+    #[cfg(unix)]
+    assert_eq!(report.freed_bytes, 0);
+
+    let hair = BlobStore::hash_file(&mod_b.join("Hair.dds")).unwrap();
+    let body = BlobStore::hash_file(&mod_a.join("Body.dds")).unwrap();
+    assert!(!store.blob_path(&hair).exists());
+    assert!(store.blob_path(&body).exists());
+    assert_eq!(fs::read(mod_b.join("Hair.dds")).unwrap(), only_b);
+
+    store.release("mod-a").unwrap();
+    let report = store.collect_garbage(&HashSet::new()).unwrap();
+    assert_eq!(report.removed_blobs, 1);
+    assert_eq!(store.stats().unwrap().blobs, 0);
+    assert_eq!(fs::read(mod_a.join("Body.dds")).unwrap(), shared);
+}
+
+#[test]
+fn test_detach_breaks_the_link() {
+    let dir = tempdir().unwrap();
+    let store = BlobStore::new(dir.path().join("store"));
+    let body = texture(13);
+    let mod_a = dir.path().join("a");
+    let mod_b = dir.path().join("b");
+    write(&mod_a, "Body.dds", &body);
+    write(&mod_b, "Body.dds", &body);
+    store.ingest("mod-a", &mod_a).unwrap();
+    store.ingest("mod-b", &mod_b).unwrap();
+
+    BlobStore::detach(&mod_b.join("Body.dds")).unwrap();
+    fs::write(mod_b.join("Body.dds"), b"edited").unwrap();
+
+    assert_eq!(fs::read(mod_a.join("Body.dds")).unwrap(), body);
+    let hash = BlobStore::hash_file(&mod_a.join("Body.dds")).unwrap();
+    assert_eq!(fs::read(store.blob_path(&hash)).unwrap(), body);
+}
+
+#[test]
+fn test_rejects_owner_that_escapes_refs() {
+    let dir = tempdir().unwrap();
+    let store = BlobStore::new(dir.path().join("store"));
+    write(dir.path(), "a/Body.dds", &texture(17));
+    assert!(store.ingest("../escape", &dir.path().join("a")).is_err());
+    assert!(store.release("..").is_err());
+}
@@ -0,0 +1,285 @@
+use fs_engine::*;
+use image::{Rgba, RgbaImage};
+
+fn header(width: u32, height: u32, mip_count: u32, format: DdsFormat, dx10: bool) -> DdsHeader {
+    DdsHeader {
+        width,
+        height,
+        mip_count,
+        format,
+        srgb: false,
+        layers: 1,
+        cubemap: false,
+        dx10,
+    }
+}
+
+/// A single-block DDS file around hand-built block data.
+fn single_block_dds(format: DdsFormat, block: &[u8]) -> DdsTexture {
+    let mut data = header(4, 4, 1, format, false).to_bytes();
+    data.extend_from_slice(block);
+    DdsTexture::from_bytes(&data).unwrap()
+}
+
+/// Packs (value, bit count) pairs LSB-first into a 128-bit BC7 block.
+fn pack_bits(fields: &[(u32, u32)]) -> [u8; 16] {
+    let mut bits = 0u128;
+    let mut pos = 0;
+    for (value, count) in fields {
+        bits |= (*value as u128) << pos;
+        pos += count;
+    }
+    assert_eq!(pos, 128);
+    bits.to_le_bytes()
+}
+
+/// Smooth ramps with slopes typical of real texture content.
+fn gradient(width: u32, height: u32) -> RgbaImage {
+    RgbaImage::from_fn(width, height, |x, y| {
+        Rgba([
+            (x * 4) as u8,
+            (y * 4) as u8,
+            ((x + y) * 2) as u8,
+            255 - (x * 3) as u8,
+        ])
+    })
+}
+
+fn max_channel_error(a: &RgbaImage, b: &RgbaImage, channels: usize) -> u8 {
+    a.pixels()
+        .zip(b.pixels())
+        .flat_map(|(p, q)| (0..channels).map(move |c| p[c].abs_diff(q[c])))
+        .max()
+        .unwrap()
+}
+
+fn round_trip(format: DdsFormat, source: &RgbaImage) -> RgbaImage {
+    let (width, height) = source.dimensions();
+    let texture = DdsTexture {
+        header: header(width, height, 1, format, false),
+        surfaces: vec![source.clone()],
+    };
+    let bytes = texture.to_bytes().unwrap();
+    assert_eq!(DdsHeader::parse(&bytes).unwrap().0.format, format);
+    DdsTexture::from_bytes(&bytes).unwrap().surfaces.remove(0)
+}
+
+#[test]
+fn test_bc1_golden_block() {
+    // Pure red and pure blue endpoints, pixel i uses index i % 4
+    let mut block = Vec::new();
+    block.extend_from_slice(&0xf800u16.to_le_bytes());
+    block.extend_from_slice(&0x001fu16.to_le_bytes());
+    block.extend_from_slice(&0xe4e4_e4e4u32.to_le_bytes());
+
+    let texture = single_block_dds(DdsFormat::Bc1, &block);
+    let image = texture.image();
+    assert_eq!(image.get_pixel(0, 0).0, [255, 0, 0, 255]);
+    assert_eq!(image.get_pixel(1, 0).0, [0, 0, 255, 255]);
+    assert_eq!(image.get_pixel(2, 0).0, [170, 0, 85, 255]);
+    assert_eq!(image.get_pixel(3, 3).0, [85, 0, 170, 255]);
+}
+
+#[test]
+fn test_bc1_punch_through_alpha() {
+    // color0 <= color1 selects three colors plus transparent black
+    let mut block = Vec::new();
+    block.extend_from_slice(&0x001fu16.to_le_bytes());
+    block.extend_from_slice(&0xf800u16.to_le_bytes());
+    block.extend_from_slice(&0xffff_fffeu32.to_le_bytes());
+
+    let texture = single_block_dds(DdsFormat::Bc1, &block);
+    assert_eq!(texture.image().get_pixel(0, 0).0, [127, 0, 127, 255]);
+    assert_eq!(texture.image().get_pixel(1, 0).0, [0, 0, 0, 0]);
+}
+
+#[test]
+fn test_bc3_golden_alpha() {
+    // Alpha 255..0 in eight-value mode; pixel 0 index 0, pixel 1 index 1, pixel 2 index 2
+    let mut block = vec![255, 0];
+    let indices: u64 = 0b010_001_000;
+    block.extend_from_slice(&indices.to_le_bytes()[..6]);
+    block.extend_from_slice(&0xffffu16.to_le_bytes());
+    block.extend_from_slice(&0xffffu16.to_le_bytes());
+    block.extend_from_slice(&0u32.to_le_bytes());
+
+    let texture = single_block_dds(DdsFormat::Bc3, &block);
+    let image = texture.image();
+    assert_eq!(image.get_pixel(0, 0).0, [255, 255, 255, 255]);
+    assert_eq!(image.get_pixel(1, 0)[3], 0);
+    assert_eq!(image.get_pixel(2, 0)[3], 218);
+}
+
+#[test]
+fn test_bc5_golden_block() {
+    let mut block = vec![200, 100, 0, 0, 0, 0, 0, 0];
+    block.extend_from_slice(&[10, 20, 0, 0, 0, 0, 0, 0]);
+
+    let texture = single_block_dds(DdsFormat::Bc5, &block);
+    assert_eq!(texture.image().get_pixel(2, 2).0, [200, 10, 0, 255]);
+}
+
+#[test]
+fn test_bc7_mode6_golden_block() {
+    // Endpoint 0 = (127, 64, 0, 127) p=1, endpoint 1 = (0, 0, 127, 127) p=1
+    let mut fields = vec![(1 << 6, 7)];
+    for (e0, e1) in [(127, 0), (64, 0), (0, 127), (127, 127)] {
+        fields.push((e0, 7));
+        fields.push((e1, 7));
+    }
+    fields.push((1, 1));
+    fields.push((1, 1));
+    fields.push((0, 3)); // anchor
+    fields.push((15, 4));
+    fields.push((8, 4));
+    for _ in 3..16 {
+        fields.push((0, 4));
+    }
+
+    let mut data = header(4, 4, 1, DdsFormat::Bc7, true).to_bytes();
+    data.extend_from_slice(&pack_bits(&fields));
+    let texture = DdsTexture::from_bytes(&data).unwrap();
+    let image = texture.image();
+    assert_eq!(image.get_pixel(0, 0).0, [255, 129, 1, 255]);
+    assert_eq!(image.get_pixel(1, 0).0, [1, 1, 255, 255]);
+    assert_eq!(image.get_pixel(2, 0).0, [120, 61, 136, 255]);
+    assert_eq!(image.get_pixel(3, 3).0, [255, 129, 1, 255]);
+}
+
+#[test]
+fn test_bc7_mode5_rotation() {
+    // Mode 5, rotation 1 swaps red and alpha after interpolation
+    let mut fields = vec![(1 << 5, 6), (1, 2)];
+    for (e0, e1) in [(127, 127), (0, 0), (0, 0)] {
+        fields.push((e0, 7));
+        fields.push((e1, 7));
+    }
+    fields.push((64, 8));
+    fields.push((64, 8));
+    fields.push((0, 31));
+    fields.push((0, 31));
+
+    let mut data = header(4, 4, 1, DdsFormat::Bc7, true).to_bytes();
+    data.extend_from_slice(&pack_bits(&fields));
+    let texture = DdsTexture::from_bytes(&data).unwrap();
+    assert_eq!(texture.image().get_pixel(1, 1).0, [64, 0, 0, 255]);
+}
+
+#[test]
+fn test_bc7_reserved_mode_is_transparent_black() {
+    let mut data = header(4, 4, 1, DdsFormat::Bc7, true).to_bytes();
+    data.extend_from_slice(&[0u8; 16]);
+    let texture = DdsTexture::from_bytes(&data).unwrap();
+    assert!(texture.image().pixels().all(|p| p.0 == [0, 0, 0, 0]));
+}
+
+#[test]
+fn test_round_trip_error_bounds() {
+    let source = gradient(64, 64);
+    let mut opaque = source.clone();
+    opaque.pixels_mut().for_each(|p| p[3] = 255);
+
+    assert_eq!(round_trip(DdsFormat::Rgba8, &source), source);
+    assert_eq!(round_trip(DdsFormat::Bgra8, &source), source);
+    assert!(max_channel_error(&opaque, &round_trip(DdsFormat::Bc1, &opaque), 4) <= 12);
+    assert!(max_channel_error(&source, &round_trip(DdsFormat::Bc3, &source), 4) <= 12);
+    assert!(max_channel_error(&source, &round_trip(DdsFormat::Bc5, &source), 2) <= 2);
+    assert!(max_channel_error(&source, &round_trip(DdsFormat::Bc7, &source), 4) <= 8);
+}
+
+#[test]
+fn test_round_trip_odd_sizes() {
+    // Partial edge blocks must decode to the right dimensions
+    let source = gradient(7, 5);
+    let decoded = round_trip(DdsFormat::Bc7, &source);
+    assert_eq!(decoded.dimensions(), (7, 5));
+    assert!(max_channel_error(&source, &decoded, 4) <= 8);
+}
+
+#[test]
+fn test_header_round_trip_and_mips() {
+    let mut dx10 = header(16, 8, 5, DdsFormat::Bc7, true);
+    dx10.srgb = true;
+    let surfaces = (0..5)
+        .map(|level| {
+            let (w, h) = dx10.mip_size(level);
+            RgbaImage::from_pixel(w, h, Rgba([level as u8 * 40, 90, 200, 255]))
+        })
+        .collect();
+
+    let texture = DdsTexture {
+        header: dx10.clone(),
+        surfaces,
+    };
+    let bytes = texture.to_bytes().unwrap();
+    let (parsed, offset) = DdsHeader::parse(&bytes).unwrap();
+    assert_eq!(parsed, dx10);
+    assert_eq!(offset, 148);
+    // Levels 16x8, 8x4, 4x2, 2x1, 1x1 take 8, 2, 1, 1 and 1 blocks
+    assert_eq!(bytes.len(), 148 + (8 + 2 + 1 + 1 + 1) * 16);
+
+    let decoded = DdsTexture::from_bytes(&bytes).unwrap();
+    assert_eq!(decoded.surfaces.len(), 5);
+    assert_eq!(decoded.surfaces[3].dimensions(), (2, 1));
+
+    let legacy = header(8, 8, 1, DdsFormat::Bc1, false);
+    let (parsed, offset) = DdsHeader::parse(&legacy.to_bytes()).unwrap();
+    assert_eq!(parsed, legacy);
+    assert_eq!(offset, 128);
+}
+
+#[test]
+fn test_rejects_bad_files() {
+    assert!(matches!(
+        DdsTexture::from_bytes(b"PNG not a dds"),
+        Err(FsError::Texture(_))
+    ));
+
+    // Header promises a 4x4 BC1 surface but the data is missing
+    let truncated = header(4, 4, 1, DdsFormat::Bc1, false).to_bytes();
+    assert!(matches!(
+        DdsTexture::from_bytes(&truncated),
+        Err(FsError::Texture(_))
+    ));
+
+    let mut dxt3 = header(4, 4, 1, DdsFormat::Bc1, false).to_bytes();
+    dxt3[84..88].copy_from_slice(b"DXT3");
+    dxt3.extend_from_slice(&[0u8; 16]);
+    assert!(matches!(
+        DdsTexture::from_bytes(&dxt3),
+        Err(FsError::Texture(_))
+    ));
+}
+
+#[test]
+fn test_rejects_hostile_headers() {
+    let rejects = |data: &[u8]| matches!(DdsTexture::from_bytes(data), Err(FsError::Texture(_)));
+    let with = |header: &DdsHeader, offset: usize, value: u32| {
+        let mut data = header.to_bytes();
+        data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+        data.resize(192, 0);
+        data
+    };
+    let bc1 = header(4, 4, 1, DdsFormat::Bc1, false);
+
+    // More mips than a 4x4 texture can have, up to the whole u32 range
+    assert!(rejects(&with(&bc1, 28, u32::MAX)));
+    assert!(rejects(&with(&bc1, 28, 4)));
+    assert!(DdsTexture::from_bytes(&with(&bc1, 28, 3)).is_ok());
+
+    assert!(rejects(&with(&bc1, 16, 1 << 20)));
+    assert!(rejects(&with(&bc1, 12, u32::MAX)));
+    // Allowed size, but the surface data is nowhere near there
+    let mut large = with(&bc1, 16, 16384);
+    large[12..16].copy_from_slice(&16384u32.to_le_bytes());
+    large[28..32].copy_from_slice(&15u32.to_le_bytes());
+    assert!(rejects(&large));
+
+    let array = header(4, 4, 1, DdsFormat::Bc7, true);
+    assert!(rejects(&with(&array, 140, u32::MAX)));
+    assert!(rejects(&with(&array, 140, 64)));
+
+    let levels = header(8, 2, 4, DdsFormat::Rgba8, false);
+    assert_eq!(levels.mip_size(3), (1, 1));
+    assert_eq!(levels.mip_size(40), (1, 1));
+}
@@ -0,0 +1,90 @@
+use fs_engine::{FsError, Mirror, MirrorOptions};
+use std::fs;
+use std::sync::{Arc, Mutex};
+use tempfile::tempdir;
+
+#[test]
+fn test_copy_tree_resumes_and_verifies() {
+    let dir = tempdir().unwrap();
+    let source = dir.path().join("mods");
+    fs::create_dir_all(source.join("gi/raiden/Textures")).unwrap();
+    fs::create_dir_all(source.join("gi/empty")).unwrap();
+    fs::write(source.join("gi/raiden/mod.ini"), "[TextureOverrideBody]").unwrap();
+    fs::write(source.join("gi/raiden/Textures/body.dds"), vec![7u8; 4096]).unwrap();
+    #[cfg(unix)]
+    {
+        fs::hard_link(
+            source.join("gi/raiden/Textures/body.dds"),
+            source.join("gi/raiden/Textures/copy.dds"),
+        )
+        .unwrap();
+        std::os::unix::fs::symlink("raiden", source.join("gi/latest")).unwrap();
+    }
+
+    let destination = dir.path().join("other disk/mods");
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let progress = seen.clone();
+    let options = MirrorOptions {
+        on_progress: Some(Arc::new(move |p| progress.lock().unwrap().push(p.clone()))),
+        ..Default::default()
+    };
+    let report = Mirror::copy_tree(&source, &destination, &options).unwrap();
+    Mirror::verify(&source, &destination).unwrap();
+    assert!(destination.join("gi/empty").is_dir());
+    assert_eq!(
+        fs::read(destination.join("gi/raiden/Textures/body.dds")).unwrap(),
+        vec![7u8; 4096]
+    );
+    let last = seen.lock().unwrap().last().unwrap().clone();
+    assert_eq!(last.files_done, last.files_total);
+    assert_eq!(last.bytes_done, last.bytes_total);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        assert_eq!(report.files_copied, 2);
+        assert_eq!(report.files_linked, 1);
+        assert_eq!(report.symlinks, 1);
+        let body = fs::metadata(destination.join("gi/raiden/Textures/body.dds")).unwrap();
+        let copy = fs::metadata(destination.join("gi/raiden/Textures/copy.dds")).unwrap();
+        assert_eq!(body.ino(), copy.ino());
+        assert_eq!(
+            fs::read_link(destination.join("gi/latest")).unwrap(),
+            std::path::Path::new("raiden")
+        );
+    }
+
+    // An interrupted copy leaves a part file; running again only redoes that file
+    fs::remove_file(destination.join("gi/raiden/mod.ini")).unwrap();
+    fs::write(destination.join("gi/raiden/mod.ini.yago_part"), "[Tex").unwrap();
+    assert!(Mirror::verify(&source, &destination).is_err());
+    let report = Mirror::copy_tree(&source, &destination, &MirrorOptions::default()).unwrap();
+    assert_eq!(report.files_copied, 1);
+    assert!(report.files_resumed >= 1);
+    assert!(!destination.join("gi/raiden/mod.ini.yago_part").exists());
+    Mirror::verify(&source, &destination).unwrap();
+
+    fs::write(destination.join("gi/raiden/mod.ini"), "changed").unwrap();
+    let err = Mirror::verify(&source, &destination).unwrap_err();
+    assert!(matches!(err, FsError::VerificationFailed(p) if p.ends_with("mod.ini")));
+}
+
+#[test]
+fn test_copy_tree_cancel() {
+    let dir = tempdir().unwrap();
+    let source = dir.path().join("runners");
+    fs::create_dir_all(&source).unwrap();
+    fs::write(source.join("proton"), "#!/bin/sh").unwrap();
+
+    let options = MirrorOptions::default();
+    options.cancel.cancel();
+    let result = Mirror::copy_tree(&source, &dir.path().join("moved"), &options);
+    assert!(matches!(result, Err(FsError::Cancelled)));
+
+    let missing = Mirror::copy_tree(
+        &dir.path().join("nope"),
+        &dir.path().join("moved"),
+        &options,
+    );
+    assert!(matches!(missing, Err(FsError::NotFound(_))));
+}
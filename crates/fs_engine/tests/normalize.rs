@@ -0,0 +1,140 @@
+use fs_engine::{CaseNormalizer, ReferenceIssueKind, Safety};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::tempdir;
+
+fn write(root: &Path, relative: &str, content: &str) {
+    let path = root.join(relative);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
+
+#[test]
+fn test_plan_detects_collisions_and_references() {
+    let dir = tempdir().unwrap();
+    let root = dir.path();
+    write(root, "Textures/Body.dds", "upper");
+    write(root, "Textures/body.dds", "lower");
+    write(root, "Textures/Face.DDS", "face");
+    write(
+        root,
+        "Mod.ini",
+        "[ResourceBody]\nfilename = textures\\body.dds\n[ResourceFace]\nfilename = Textures\\FACE.dds\n[ResourceBodyOld]\nfilename = Textures/BODY.DDS\n[ResourceGone]\nfilename = Textures\\Hair.dds\n",
+    );
+
+    let plan = CaseNormalizer::plan(root).unwrap();
+
+    assert_eq!(plan.collisions.len(), 1);
+    let collision = &plan.collisions[0];
+    assert_eq!(collision.target, PathBuf::from("textures/body.dds"));
+    assert_eq!(collision.kept, PathBuf::from("Textures/body.dds"));
+    assert_eq!(
+        collision.conflicting,
+        vec![PathBuf::from("Textures/Body.dds")]
+    );
+
+    // Children move before their folder
+    let froms: Vec<_> = plan.renames.iter().map(|r| r.from.clone()).collect();
+    assert_eq!(
+        froms,
+        vec![
+            PathBuf::from("Textures/Face.DDS"),
+            PathBuf::from("Mod.ini"),
+            PathBuf::from("Textures"),
+        ]
+    );
+    assert_eq!(plan.renames[0].to, PathBuf::from("textures/face.dds"));
+
+    let rewrites: Vec<_> = plan
+        .rewrites
+        .iter()
+        .map(|r| (r.line, r.new_value.as_str()))
+        .collect();
+    // Line 1 already matches the normalized names
+    assert_eq!(rewrites, vec![(3, "textures\\face.dds")]);
+    assert_eq!(plan.rewrites[0].old_value, "Textures\\FACE.dds");
+
+    let issues: Vec<_> = plan.issues.iter().map(|i| (i.line, i.kind)).collect();
+    assert_eq!(
+        issues,
+        vec![
+            (5, ReferenceIssueKind::Ambiguous),
+            (7, ReferenceIssueKind::Missing)
+        ]
+    );
+
+    // Planning never touches the disk
+    assert!(root.join("Textures/Face.DDS").exists());
+}
+
+#[test]
+fn test_normalize_renames_and_rewrites_inis() {
+    let dir = tempdir().unwrap();
+    let root = dir.path();
+    write(root, "Body/Diffuse.DDS", "diffuse");
+    write(root, "Body/LightMap.dds", "lightmap");
+    write(
+        root,
+        "Body/Body.ini",
+        "; keep me\r\n[ResourceDiffuse]\r\nfilename = Diffuse.DDS\r\n[ResourceLightMap]\r\nfilename = \"..\\Body\\LIGHTMAP.dds\"\r\nif $x == 1\r\nendif\r\n",
+    );
+    write(root, ".git/HEAD", "ref");
+
+    let report = CaseNormalizer::normalize(root).unwrap();
+    assert_eq!(report.renamed, 4);
+    assert_eq!(report.ini_files_rewritten, 1);
+    assert_eq!(report.references_rewritten, 2);
+    assert!(report.collisions.is_empty());
+    assert!(report.issues.is_empty());
+
+    assert!(root.join("body/diffuse.dds").is_file());
+    assert!(root.join("body/lightmap.dds").is_file());
+    assert!(root.join(".git/HEAD").is_file());
+    assert_eq!(
+        fs::read_to_string(root.join("body/body.ini")).unwrap(),
+        "; keep me\r\n[ResourceDiffuse]\r\nfilename = diffuse.dds\r\n[ResourceLightMap]\r\nfilename = \"..\\body\\lightmap.dds\"\r\nif $x == 1\r\nendif\r\n"
+    );
+
+    // A second pass has nothing left to do
+    assert!(CaseNormalizer::plan(root).unwrap().is_noop());
+}
+
+#[test]
+fn test_sanitize_keeps_colliding_file() {
+    let dir = tempdir().unwrap();
+    let root = dir.path();
+    write(root, "Body.dds", "upper");
+    write(root, "body.dds", "lower");
+    write(root, "Hair.dds", "hair");
+
+    assert_eq!(Safety::sanitize_filenames(root).unwrap(), 1);
+    assert_eq!(fs::read_to_string(root.join("body.dds")).unwrap(), "lower");
+    assert_eq!(fs::read_to_string(root.join("Body.dds")).unwrap(), "upper");
+    assert!(root.join("hair.dds").exists());
+}
+
+#[test]
+fn test_apply_rolls_back_on_failure() {
+    let dir = tempdir().unwrap();
+    let root = dir.path();
+    write(root, "Textures/Face.DDS", "face");
+    write(
+        root,
+        "Mod.ini",
+        "[ResourceFace]\nfilename = Textures\\Face.DDS\n",
+    );
+
+    let plan = CaseNormalizer::plan(root).unwrap();
+    assert_eq!(plan.renames.len(), 3);
+
+    // Something appears between planning and applying, so the last rename fails
+    write(root, "textures/other.dds", "other");
+    assert!(CaseNormalizer::apply(&plan).is_err());
+
+    assert!(root.join("Textures/Face.DDS").is_file());
+    assert_eq!(
+        fs::read_to_string(root.join("Mod.ini")).unwrap(),
+        "[ResourceFace]\nfilename = Textures\\Face.DDS\n"
+    );
+    assert!(!root.join("mod.ini").exists());
+}
@@ -0,0 +1,120 @@
+use fs_engine::test_support::{build_pe, PeSpec, SECTION_RAW};
+use fs_engine::{ExeInspector, FsError, PeInfo, PeMachine, PeSubsystem};
+use std::fs;
+use tempfile::tempdir;
+
+#[test]
+fn test_parse_pe64_version_and_imports() {
+    let data = build_pe(&PeSpec {
+        imports: &[
+            ("KERNEL32.dll", &["CreateFileW", "ExitProcess"]),
+            ("d3d11.dll", &["D3D11CreateDevice"]),
+        ],
+        file_version: Some([2, 5, 0, 1234]),
+        strings: &[
+            ("CompanyName", "COGNOSPHERE PTE. LTD."),
+            ("OriginalFilename", "ZenlessZoneZero.exe"),
+            ("ProductName", "ZenlessZoneZero"),
+        ],
+        ..Default::default()
+    });
+
+    let info = PeInfo::parse(&data).unwrap();
+    assert_eq!(info.machine, PeMachine::X64);
+    assert_eq!(info.subsystem, PeSubsystem::WindowsGui);
+    assert!(!info.is_dll);
+    assert!(!info.wine_builtin);
+
+    assert_eq!(info.imports.len(), 2);
+    assert_eq!(info.imports[0].dll, "KERNEL32.dll");
+    assert_eq!(
+        info.imports[0].functions,
+        vec!["CreateFileW", "ExitProcess"]
+    );
+    assert!(info.imports_dll("kernel32.DLL"));
+    assert!(!info.imports_dll("vulkan-1.dll"));
+
+    let version = info.version.unwrap();
+    assert_eq!(version.file_version.as_deref(), Some("2.5.0.1234"));
+    assert_eq!(version.product_version.as_deref(), Some("2.5.0.1234"));
+    assert_eq!(version.company_name(), Some("COGNOSPHERE PTE. LTD."));
+    assert_eq!(version.original_filename(), Some("ZenlessZoneZero.exe"));
+    assert_eq!(version.product_name(), Some("ZenlessZoneZero"));
+    assert_eq!(version.file_description(), None);
+}
+
+#[test]
+fn test_parse_pe32_dll_with_ordinals() {
+    let data = build_pe(&PeSpec {
+        pe64: false,
+        dll: true,
+        subsystem: 3,
+        imports: &[("WS2_32.dll", &["#23", "send"])],
+        wine_stub: true,
+        ..Default::default()
+    });
+
+    let info = PeInfo::parse(&data).unwrap();
+    assert_eq!(info.machine, PeMachine::X86);
+    assert_eq!(info.subsystem, PeSubsystem::WindowsConsole);
+    assert!(info.is_dll);
+    assert!(info.wine_builtin);
+    assert_eq!(info.imports[0].functions, vec!["#23", "send"]);
+    assert!(info.version.is_none());
+}
+
+#[test]
+fn test_parse_rejects_non_pe() {
+    assert!(matches!(
+        PeInfo::parse(b"MZ fake content"),
+        Err(FsError::InvalidExecutable(_))
+    ));
+    assert!(matches!(
+        PeInfo::parse(b"\x7fELF"),
+        Err(FsError::InvalidExecutable(_))
+    ));
+
+    // A PE whose header claims more than the file holds must not panic
+    let mut data = build_pe(&PeSpec {
+        imports: &[("KERNEL32.dll", &["ExitProcess"])],
+        strings: &[("CompanyName", "Test")],
+        ..Default::default()
+    });
+    for len in (0..data.len()).step_by(7) {
+        let _ = PeInfo::parse(&data[..len]);
+    }
+    data.truncate(SECTION_RAW + 8);
+    let info = PeInfo::parse(&data).unwrap();
+    assert!(info.imports.is_empty());
+    assert!(info.version.is_none());
+}
+
+#[test]
+fn test_get_version_falls_back_to_pe_resource() {
+    let dir = tempdir().unwrap();
+    let exe = dir.path().join("Game.exe");
+    fs::write(
+        &exe,
+        build_pe(&PeSpec {
+            file_version: Some([1, 4, 2, 0]),
+            ..Default::default()
+        }),
+    )
+    .unwrap();
+    assert_eq!(ExeInspector::get_version(&exe).unwrap(), "1.4.2.0");
+
+    // Unity stamps its engine version (e.g. 2019.4.x) on player executables
+    fs::write(
+        &exe,
+        build_pe(&PeSpec {
+            file_version: Some([2019, 4, 40, 0]),
+            ..Default::default()
+        }),
+    )
+    .unwrap();
+    assert_eq!(ExeInspector::get_version(&exe).unwrap(), "Unknown");
+
+    let info = ExeInspector::inspect(&exe).unwrap();
+    assert_eq!(info.machine, PeMachine::X64);
+    assert!(ExeInspector::inspect(&dir.path().join("missing.exe")).is_err());
+}
@@ -0,0 +1,176 @@
+use fs_engine::*;
+use image::{Rgba, RgbaImage};
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+fn write_dds(path: &Path, width: u32, height: u32, color: [u8; 4]) {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).unwrap();
+    }
+    DdsTexture {
+        header: DdsHeader {
+            width,
+            height,
+            mip_count: 1,
+            format: DdsFormat::Bc1,
+            srgb: false,
+            layers: 1,
+            cubemap: false,
+            dx10: false,
+        },
+        surfaces: vec![RgbaImage::from_pixel(width, height, Rgba(color))],
+    }
+    .save(path)
+    .unwrap();
+}
+
+const MOD_INI: &str = r#"
+[TextureOverrideBody]
+hash = 11111111
+ps-t0 = ResourceBodyNormalMap
+ps-t1 = ResourceBodyDiffuse
+
+[TextureOverrideBodyAlt]
+hash = 22222222
+ps-t1 = ResourceBodyDiffuse
+
+[TextureOverrideFace]
+hash = 33333333
+ps-t1 = ResourceFaceDiffuse
+
+[ResourceBodyDiffuse]
+filename = BodyDiffuse.dds
+
+[ResourceBodyNormalMap]
+filename = BodyNormalMap.dds
+
+[ResourceFaceDiffuse]
+filename = .\Face\FaceDiffuse.dds
+"#;
+
+fn build_mod(dir: &Path) {
+    fs::create_dir_all(dir).unwrap();
+    fs::write(dir.join("mod.ini"), MOD_INI).unwrap();
+    write_dds(&dir.join("BodyDiffuse.dds"), 600, 300, [200, 40, 40, 0]);
+    write_dds(
+        &dir.join("BodyNormalMap.dds"),
+        1024,
+        1024,
+        [128, 128, 255, 255],
+    );
+    write_dds(
+        &dir.join("Face/FaceDiffuse.dds"),
+        800,
+        800,
+        [40, 200, 40, 255],
+    );
+}
+
+#[test]
+fn test_rank_textures_by_resource_usage() {
+    let dir = tempdir().unwrap();
+    build_mod(dir.path());
+
+    let ranked = Thumbnailer::rank_textures(dir.path());
+    let paths: Vec<_> = ranked.iter().map(|c| c.path.clone()).collect();
+    assert_eq!(
+        paths,
+        vec![
+            Path::new("BodyDiffuse.dds").to_path_buf(),
+            Path::new("Face").join("FaceDiffuse.dds"),
+            Path::new("BodyNormalMap.dds").to_path_buf(),
+        ]
+    );
+    assert_eq!(ranked[0].references, 2);
+    assert_eq!(ranked[1].references, 1);
+    assert!(ranked[2].non_diffuse_hint);
+}
+
+#[test]
+fn test_generate_thumbnails_in_all_sizes() {
+    let dir = tempdir().unwrap();
+    let mod_dir = dir.path().join("mod");
+    let cache = dir.path().join("cache");
+    build_mod(&mod_dir);
+
+    let set = Thumbnailer::ensure(&mod_dir, &cache, &ThumbnailOptions::default())
+        .unwrap()
+        .unwrap();
+    assert_eq!(set.source, Path::new("BodyDiffuse.dds"));
+    assert_eq!(set.thumbnails.len(), 6);
+
+    let webp = set.best(200, ThumbnailFormat::WebP).unwrap();
+    assert_eq!((webp.size, webp.width, webp.height), (256, 256, 128));
+    let decoded = image::open(&webp.path).unwrap().to_rgba8();
+    assert_eq!(decoded.dimensions(), (256, 128));
+    // Diffuse alpha is dropped so the preview is never see-through
+    assert_eq!(decoded.get_pixel(10, 10)[3], 255);
+
+    let png = set.best(512, ThumbnailFormat::Png).unwrap();
+    assert_eq!(
+        image::open(&png.path).unwrap().to_rgba8().dimensions(),
+        (512, 256)
+    );
+    assert_eq!(set.best(4096, ThumbnailFormat::Png).unwrap().size, 512);
+}
+
+#[test]
+fn test_thumbnails_regenerate_when_mod_changes() {
+    let dir = tempdir().unwrap();
+    let mod_dir = dir.path().join("mod");
+    let cache = dir.path().join("cache");
+    build_mod(&mod_dir);
+    let options = ThumbnailOptions {
+        sizes: vec![64],
+        formats: vec![ThumbnailFormat::Png],
+    };
+
+    let first = Thumbnailer::ensure(&mod_dir, &cache, &options)
+        .unwrap()
+        .unwrap();
+    let second = Thumbnailer::ensure(&mod_dir, &cache, &options)
+        .unwrap()
+        .unwrap();
+    assert_eq!(first.fingerprint, second.fingerprint);
+
+    // Dropping the body texture makes the face the best candidate
+    fs::remove_file(mod_dir.join("BodyDiffuse.dds")).unwrap();
+    let third = Thumbnailer::ensure(&mod_dir, &cache, &options)
+        .unwrap()
+        .unwrap();
+    assert_ne!(third.fingerprint, first.fingerprint);
+    assert_eq!(third.source, Path::new("Face").join("FaceDiffuse.dds"));
+    let pixel = image::open(&third.thumbnails[0].path)
+        .unwrap()
+        .to_rgba8()
+        .get_pixel(0, 0)
+        .0;
+    assert!(pixel[1] > pixel[0]);
+}
+
+#[test]
+fn test_mod_without_textures_has_no_thumbnails() {
+    let dir = tempdir().unwrap();
+    let mod_dir = dir.path().join("mod");
+    fs::create_dir_all(&mod_dir).unwrap();
+    fs::write(mod_dir.join("mod.ini"), "[TextureOverrideX]\nhash = 1").unwrap();
+
+    let result = Thumbnailer::ensure(
+        &mod_dir,
+        &dir.path().join("cache"),
+        &ThumbnailOptions::default(),
+    )
+    .unwrap();
+    assert!(result.is_none());
+}
+
+#[test]
+fn test_fingerprint_is_stable() {
+    let dir = tempdir().unwrap();
+    // A fixed hash, not one that changes with the toolchain
+    assert_eq!(
+        Thumbnailer::fingerprint(dir.path()).unwrap(),
+        "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+    );
+}
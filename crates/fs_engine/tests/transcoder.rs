@@ -1,5 +1,6 @@
 use fs_engine::*;
-use image::{ImageBuffer, Rgba};
+use image::{ImageBuffer, Rgba, RgbaImage};
+use std::path::Path;
 use tempfile::tempdir;
 
 #[test]
@@ -21,6 +22,171 @@ fn test_transcoder_stub_integrity() {
     img.save(&img_path).unwrap();
 
     let result = Transcoder::fix_normal_map(&img_path).unwrap();
-    assert!(!result); // Stub returns false
+    assert!(!result); // Blank image is not a normal map
     assert!(img_path.exists());
 }
+
+/// Normal map of a bumpy height field, encoded in the given green convention.
+fn normal_map(convention: NormalConvention) -> RgbaImage {
+    let height =
+        |u: f32, v: f32| 2.0 * (u * 0.3).sin() * (v * 0.2).cos() + 1.5 * ((u + v) * 0.15).sin();
+    RgbaImage::from_fn(64, 64, |x, y| {
+        let (u, v) = (x as f32, y as f32);
+        let du = (height(u + 0.5, v) - height(u - 0.5, v)) * 0.5;
+        let dv = (height(u, v + 0.5) - height(u, v - 0.5)) * 0.5;
+        let ny = match convention {
+            NormalConvention::DirectX => -dv,
+            NormalConvention::OpenGl => dv,
+        };
+        let length = (du * du + ny * ny + 1.0).sqrt();
+        let encode = |c: f32| ((c / length + 1.0) * 0.5 * 255.0).round() as u8;
+        Rgba([encode(-du), encode(ny), encode(1.0), 255])
+    })
+}
+
+fn write_dds(path: &Path, format: DdsFormat, image: &RgbaImage) {
+    let (width, height) = image.dimensions();
+    DdsTexture {
+        header: DdsHeader {
+            width,
+            height,
+            mip_count: 1,
+            format,
+            srgb: false,
+            layers: 1,
+            cubemap: false,
+            dx10: format == DdsFormat::Bc7,
+        },
+        surfaces: vec![image.clone()],
+    }
+    .save(path)
+    .unwrap();
+}
+
+fn max_error(a: &RgbaImage, b: &RgbaImage) -> u8 {
+    a.pixels()
+        .zip(b.pixels())
+        .flat_map(|(p, q)| (0..4).map(move |c| p[c].abs_diff(q[c])))
+        .max()
+        .unwrap()
+}
+
+#[test]
+fn test_valid_normal_map_untouched() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("BodyNormalMap.png");
+    let image = normal_map(NormalConvention::OpenGl);
+    image.save(&path).unwrap();
+    let before = std::fs::read(&path).unwrap();
+
+    let analysis = Transcoder::analyze_normal_map(&image, false, None);
+    assert!(analysis.is_normal_map);
+    assert!(analysis.issues.is_empty());
+    assert_eq!(analysis.convention, Some(NormalConvention::OpenGl));
+
+    assert!(!Transcoder::fix_normal_map(&path).unwrap());
+    assert_eq!(std::fs::read(&path).unwrap(), before);
+}
+
+#[test]
+fn test_diffuse_texture_is_not_a_normal_map() {
+    let image = RgbaImage::from_fn(32, 32, |x, y| {
+        Rgba([200, (x * 8) as u8, (y * 4) as u8, 255])
+    });
+    let analysis = Transcoder::analyze_normal_map(&image, false, Some(NormalConvention::OpenGl));
+    assert!(!analysis.is_normal_map);
+    assert!(analysis.issues.is_empty());
+}
+
+#[test]
+fn test_fix_swapped_red_alpha_png() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("HairNormalMap.png");
+    let expected = normal_map(NormalConvention::OpenGl);
+    let mut broken = expected.clone();
+    broken.pixels_mut().for_each(|p| p.0.swap(0, 3));
+    broken.save(&path).unwrap();
+
+    let analysis = Transcoder::analyze_normal_map(&broken, false, None);
+    assert_eq!(analysis.issues, vec![NormalMapIssue::SwappedRedAlpha]);
+
+    assert!(Transcoder::fix_normal_map(&path).unwrap());
+    let fixed = image::open(&path).unwrap().to_rgba8();
+    assert_eq!(fixed, expected);
+}
+
+#[test]
+fn test_fix_swapped_red_alpha_bc7_dds() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("FaceNormalMap.dds");
+    let expected = normal_map(NormalConvention::OpenGl);
+    let mut broken = expected.clone();
+    broken.pixels_mut().for_each(|p| p.0.swap(0, 3));
+    write_dds(&path, DdsFormat::Bc7, &broken);
+
+    assert!(Transcoder::fix_normal_map(&path).unwrap());
+    let fixed = DdsTexture::load(&path).unwrap();
+    assert_eq!(fixed.header.format, DdsFormat::Bc7);
+    // Two lossy encodes of fairly bumpy content
+    assert!(max_error(fixed.image(), &expected) <= 20);
+    assert!(!Transcoder::fix_normal_map(&path).unwrap());
+}
+
+#[test]
+fn test_fix_inverted_blue_and_swapped_red_blue() {
+    let dir = tempdir().unwrap();
+    let expected = normal_map(NormalConvention::OpenGl);
+
+    let inverted = dir.path().join("inverted_n.dds");
+    let mut broken = expected.clone();
+    broken.pixels_mut().for_each(|p| p[2] = 255 - p[2]);
+    write_dds(&inverted, DdsFormat::Bc3, &broken);
+    assert_eq!(
+        Transcoder::fix_normal_map_with(&inverted, None).unwrap(),
+        vec![NormalMapIssue::InvertedBlue]
+    );
+    assert!(max_error(DdsTexture::load(&inverted).unwrap().image(), &expected) <= 24);
+
+    let swapped = dir.path().join("swapped_n.png");
+    let mut broken = expected.clone();
+    broken.pixels_mut().for_each(|p| p.0.swap(0, 2));
+    broken.save(&swapped).unwrap();
+    assert_eq!(
+        Transcoder::fix_normal_map_with(&swapped, None).unwrap(),
+        vec![NormalMapIssue::SwappedRedBlue]
+    );
+    assert_eq!(image::open(&swapped).unwrap().to_rgba8(), expected);
+}
+
+#[test]
+fn test_green_convention_detection_and_fix() {
+    let dir = tempdir().unwrap();
+    let directx = normal_map(NormalConvention::DirectX);
+    let analysis = Transcoder::analyze_normal_map(&directx, false, None);
+    assert_eq!(analysis.convention, Some(NormalConvention::DirectX));
+    assert!(analysis.issues.is_empty());
+
+    // BC5 only stores X/Y, so green is the only thing that can be wrong
+    let path = dir.path().join("BodyNormalMap.dds");
+    write_dds(&path, DdsFormat::Bc5, &directx);
+    assert!(!Transcoder::fix_normal_map(&path).unwrap());
+    assert_eq!(
+        Transcoder::fix_normal_map_with(&path, Some(NormalConvention::OpenGl)).unwrap(),
+        vec![NormalMapIssue::InvertedGreen]
+    );
+
+    let fixed = DdsTexture::load(&path).unwrap();
+    let analysis = Transcoder::analyze_normal_map(fixed.image(), true, None);
+    assert_eq!(analysis.convention, Some(NormalConvention::OpenGl));
+}
+
+#[test]
+fn test_normal_map_names() {
+    assert!(Transcoder::is_normal_map_name(Path::new(
+        "BodyNormalMap.dds"
+    )));
+    assert!(Transcoder::is_normal_map_name(Path::new("hair_n.png")));
+    assert!(!Transcoder::is_normal_map_name(Path::new(
+        "BodyDiffuse.dds"
+    )));
+}
@@ -0,0 +1,84 @@
+use fs_engine::{DirWatcher, WatchChange};
+use std::fs;
+use std::sync::mpsc;
+use std::time::Duration;
+use tempfile::tempdir;
+
+#[test]
+fn test_watcher_debounces_per_entry() {
+    let dir = tempdir().unwrap();
+    let root = dir.path().canonicalize().unwrap();
+    fs::create_dir(root.join("ModA")).unwrap();
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = DirWatcher::new(Duration::from_millis(200), move |batch| {
+        let _ = tx.send(batch);
+    })
+    .unwrap();
+    watcher.watch(&root).unwrap();
+    assert_eq!(watcher.roots(), vec![root.clone()]);
+
+    // A burst of writes across two mods, plus noise in a hidden staging folder
+    fs::create_dir(root.join(".yago_staging")).unwrap();
+    fs::write(root.join(".yago_staging/tmp.ini"), "x").unwrap();
+    for i in 0..5 {
+        fs::write(root.join("ModA").join(format!("{}.ini", i)), "x").unwrap();
+    }
+    fs::create_dir_all(root.join("ModB/Textures")).unwrap();
+    fs::write(root.join("ModB/Textures/a.dds"), "x").unwrap();
+
+    let mut seen = Vec::new();
+    while let Ok(batch) = rx.recv_timeout(Duration::from_secs(5)) {
+        seen.extend(batch);
+        if seen.len() >= 2 {
+            break;
+        }
+    }
+    seen.sort();
+    seen.dedup();
+    assert_eq!(
+        seen,
+        vec![
+            WatchChange {
+                root: root.clone(),
+                entry: root.join("ModA"),
+            },
+            WatchChange {
+                root: root.clone(),
+                entry: root.join("ModB"),
+            },
+        ]
+    );
+
+    // Unwatched roots stay quiet
+    watcher.unwatch(&root).unwrap();
+    while rx.recv_timeout(Duration::from_millis(400)).is_ok() {}
+    fs::write(root.join("ModA/late.ini"), "x").unwrap();
+    assert!(rx.recv_timeout(Duration::from_millis(600)).is_err());
+}
+
+#[test]
+fn test_watch_missing_root_fails() {
+    let mut watcher = DirWatcher::new(Duration::from_millis(50), |_| {}).unwrap();
+    assert!(watcher
+        .watch(std::path::Path::new("/nonexistent/yago/mods"))
+        .is_err());
+}
+
+#[test]
+fn test_watcher_groups_at_depth() {
+    let dir = tempdir().unwrap();
+    let root = dir.path().canonicalize().unwrap();
+    fs::create_dir_all(root.join("gi/ModA")).unwrap();
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = DirWatcher::with_depth(Duration::from_millis(200), 2, move |batch| {
+        let _ = tx.send(batch);
+    })
+    .unwrap();
+    watcher.watch(&root).unwrap();
+
+    fs::write(root.join("gi/ModA/deep.ini"), "x").unwrap();
+    let batch = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert!(batch.iter().all(|c| c.entry == root.join("gi/ModA")));
+}
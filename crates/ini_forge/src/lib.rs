@@ -3,8 +3,10 @@ pub mod compiler;
 pub mod error;
 pub mod parser;
 pub mod patcher;
+pub mod rewriter;
 
 pub use ast::{IniDocument, IniItem, Section};
 pub use compiler::IniCompiler;
 pub use error::{IniError, Result};
 pub use patcher::IniPatcher;
+pub use rewriter::{IniRewriter, IniValue};
@@ -0,0 +1,84 @@
+//! Line-level INI editing that leaves everything it does not touch byte-for-byte intact.
+//!
+//! `IniCompiler::serialize` rebuilds files from the AST, which drops formatting and
+//! `if`/`endif` blocks. Mod INIs that only need a value swapped go through here instead.
+
+/// A `key = value` line found while scanning an INI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IniValue {
+    /// Zero-based line index.
+    pub line: usize,
+    /// Name of the enclosing `[Section]`, empty before the first header.
+    pub section: String,
+    pub key: String,
+    /// Value with surrounding whitespace and quotes removed.
+    pub value: String,
+}
+
+pub struct IniRewriter;
+
+impl IniRewriter {
+    /// All assignments to `key` (case-insensitive), in file order.
+    pub fn find_values(content: &str, key: &str) -> Vec<IniValue> {
+        let mut section = String::new();
+        let mut values = Vec::new();
+        for (index, line) in content.lines().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.starts_with('[') && trimmed.ends_with(']') {
+                section = trimmed[1..trimmed.len() - 1].trim().to_string();
+                continue;
+            }
+            if trimmed.starts_with(';') || trimmed.starts_with("//") {
+                continue;
+            }
+            let Some((k, v)) = trimmed.split_once('=') else {
+                continue;
+            };
+            if k.trim().eq_ignore_ascii_case(key) {
+                values.push(IniValue {
+                    line: index,
+                    section: section.clone(),
+                    key: k.trim().to_string(),
+                    value: v.trim().trim_matches('"').to_string(),
+                });
+            }
+        }
+        values
+    }
+
+    /// Replaces the value on each `(line, new_value)`, keeping the key, spacing, quotes
+    /// and line endings as they were. Lines without an `=` are left alone.
+    pub fn replace_values(content: &str, edits: &[(usize, String)]) -> String {
+        let mut output = String::with_capacity(content.len());
+        for (index, line) in content.split_inclusive('\n').enumerate() {
+            let Some((_, new_value)) = edits.iter().find(|(l, _)| *l == index) else {
+                output.push_str(line);
+                continue;
+            };
+            let Some(eq) = line.find('=') else {
+                output.push_str(line);
+                continue;
+            };
+
+            let body_end = line.trim_end_matches(['\r', '\n']).len();
+            let (before, value) = line[..body_end].split_at(eq + 1);
+            let leading = value.len() - value.trim_start().len();
+            let trailing = value.len() - value.trim_end().len();
+            let old = value.trim();
+            let quoted = old.len() >= 2 && old.starts_with('"') && old.ends_with('"');
+
+            output.push_str(before);
+            output.push_str(&value[..leading]);
+            if quoted {
+                output.push('"');
+                output.push_str(new_value);
+                output.push('"');
+            } else {
+                output.push_str(new_value);
+            }
+            output.push_str(&value[value.len() - trailing..]);
+            output.push_str(&line[body_end..]);
+        }
+        output
+    }
+}
@@ -68,3 +68,30 @@ fn test_ini_patcher_patch_config() {
     assert!(content.contains("[NewSection]"));
     assert!(content.contains("key2 = new2"));
 }
+
+#[test]
+fn test_rewriter_preserves_untouched_lines() {
+    let content = "; Body textures\r\n[TextureOverrideBody]\r\nhash = 1a2b3c4d\r\nif $active == 1\r\n  ps-t0 = ResourceBody\r\nendif\r\n\r\n[ResourceBody]\r\nfilename = Textures\\Body.dds\r\n[ResourceFace]\r\nFileName=\"Face Diffuse.DDS\"  \r\n";
+
+    let values = IniRewriter::find_values(content, "filename");
+    assert_eq!(values.len(), 2);
+    assert_eq!(values[0].line, 8);
+    assert_eq!(values[0].section, "ResourceBody");
+    assert_eq!(values[0].value, "Textures\\Body.dds");
+    assert_eq!(values[1].key, "FileName");
+    assert_eq!(values[1].value, "Face Diffuse.DDS");
+
+    let edited = IniRewriter::replace_values(
+        content,
+        &[
+            (8, "textures\\body.dds".to_string()),
+            (10, "face diffuse.dds".to_string()),
+        ],
+    );
+    assert_eq!(
+        edited,
+        content
+            .replace("Textures\\Body.dds", "textures\\body.dds")
+            .replace("Face Diffuse.DDS", "face diffuse.dds")
+    );
+}
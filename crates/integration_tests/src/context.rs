@@ -26,6 +26,7 @@ impl SimulationContext {
             prefixes_path: None,
             cache_path: None,
             games_install_path: None,
+            portable: None,
         };
         let librarian = Librarian::new(config);
         librarian.ensure_core_dirs().unwrap();
@@ -39,6 +39,7 @@ async fn test_full_mod_import_flow() {
         prefixes_path: None,
         cache_path: None,
         games_install_path: None,
+        portable: None,
     };
     let librarian = Librarian::new(config);
     librarian.ensure_core_dirs().unwrap();
@@ -65,6 +66,7 @@ async fn test_full_mod_import_flow() {
         owner_game_id: game_id.clone(),
         path: target_path.clone(),
         size: "1.2 MB".into(),
+        size_bytes: 1_258_291,
         meta: ModMetadata {
             name: "Test Mod".to_string(),
             version: "1.0".to_string(),
@@ -72,19 +74,27 @@ async fn test_full_mod_import_flow() {
             url: None,
             preview_image: None,
             description: Some("Test".to_string()),
+            updated_at: None,
         },
         compatibility: ModCompatibility {
             game: "Genshin".to_string(),
             character: "Unknown".to_string(),
             hashes: vec![],
             fingerprint: "123".to_string(),
+            texture_hashes: Vec::new(),
+            characters: Vec::new(),
+            confidence: 0.0,
         },
         config: ModConfig {
             tags: vec![],
             keybinds: HashMap::new(),
+            auto_tags: Vec::new(),
         },
         enabled: true,
         added_at: Utc::now(),
+        version_id: Uuid::nil(),
+        versions: Vec::new(),
+        source: None,
     };
 
     let p_id = Uuid::new_v4();
@@ -125,6 +135,9 @@ async fn test_full_mod_import_flow() {
             supported_injection_methods: vec![],
             modloader_enabled: true,
             remote_info: None,
+            template_id: None,
+            region: None,
+            mod_pool: None,
         },
     );
 
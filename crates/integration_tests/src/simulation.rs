@@ -31,6 +31,7 @@ impl SimulationContext {
             prefixes_path: None,
             cache_path: None,
             games_install_path: None,
+            portable: None,
         };
         let librarian = Librarian::new(config);
         librarian.ensure_core_dirs().unwrap();
@@ -252,6 +253,7 @@ async fn test_sim_chaos_monkey() {
             owner_game_id: game_id.clone(),
             path: PathBuf::from("/tmp/gone"),
             size: "0".into(),
+            size_bytes: 0,
             meta: librarian::models::ModMetadata {
                 name: "Ghost".into(),
                 version: "1".into(),
@@ -259,19 +261,27 @@ async fn test_sim_chaos_monkey() {
                 url: None,
                 preview_image: None,
                 description: None,
+                updated_at: None,
             },
             compatibility: librarian::models::ModCompatibility {
                 game: "".into(),
                 character: "".into(),
                 hashes: vec![],
                 fingerprint: "".into(),
+                texture_hashes: Vec::new(),
+                characters: Vec::new(),
+                confidence: 0.0,
             },
             config: librarian::models::ModConfig {
                 tags: vec![],
                 keybinds: std::collections::HashMap::new(),
+                auto_tags: Vec::new(),
             },
             enabled: true,
             added_at: chrono::Utc::now(),
+            version_id: Uuid::nil(),
+            versions: Vec::new(),
+            source: None,
         },
     );
 
@@ -0,0 +1,428 @@
+//! Brings mods from a manual 3DMigoto setup into the library.
+//!
+//! Players coming from a hand-managed install already have mod folders in the game's own
+//! `Mods` folder, which deploying would hide or delete. A preview lists what is there,
+//! identified and checked against the library, and adopting moves or copies the chosen
+//! folders in as regular mods. 3DMigoto skips anything whose name starts with
+//! `DISABLED`, so those folders come in disabled. Nothing is deleted from the game:
+//! originals go to a backup folder next to `Mods`, see [`ModAdoption::backup_dir`].
+
+use crate::duplicates::{DuplicateFinder, DuplicateMatch};
+use crate::error::{LibrarianError, Result};
+use crate::import::{ImportOptions, Importer};
+use crate::models::{LibraryDatabase, ModCompatibility, ModRecord};
+use crate::queries::GLOBAL_CHARACTERS;
+use crate::Librarian;
+use fs_engine::Safety;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+use uuid::Uuid;
+
+/// Prefix 3DMigoto skips folders and INIs by, in any case.
+pub const DISABLED_PREFIX: &str = "DISABLED";
+
+/// Appended to the Mods folder's name for the folder adopted originals are moved to.
+pub const ADOPTION_BACKUP_SUFFIX: &str = ".yago-adopted";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AdoptionCandidate {
+    /// The mod's folder under the game's Mods folder.
+    pub path: PathBuf,
+    /// The folder name without its `DISABLED` prefix.
+    pub name: String,
+    /// False when the folder or one of its parents is `DISABLED`.
+    pub enabled: bool,
+    pub character: String,
+    pub characters: Vec<String>,
+    pub confidence: f32,
+    pub size: String,
+    /// A library mod with (nearly) the same files.
+    pub duplicate_of: Option<DuplicateMatch>,
+    /// Other enabled candidates and mods of the active profile overriding one of its
+    /// hashes, by name. Empty for disabled candidates.
+    pub conflicts_with: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AdoptionPreview {
+    pub candidates: Vec<AdoptionCandidate>,
+    /// Entries of the Mods folder that hold no mod: links, loose files, folders without INIs.
+    pub skipped: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AdoptMode {
+    /// Move the folder out of the game into the backup folder once the mod is in the
+    /// library. Files the import filters out, such as executables, stay in the backup.
+    #[default]
+    Move,
+    /// Leave the game's folder as it is.
+    Copy,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AdoptionReport {
+    pub adopted: Vec<ModRecord>,
+    pub failed: Vec<AdoptionFailure>,
+    /// Where the adopted originals went, when any were moved.
+    pub backup: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AdoptionFailure {
+    pub path: PathBuf,
+    pub error: String,
+}
+
+pub struct ModAdoption;
+
+impl ModAdoption {
+    /// Whether `mods_dir` is a real folder holding mods, rather than the link YAGO
+    /// deploys, nothing at all, or only entries the preview skips.
+    pub fn is_unmanaged(mods_dir: &Path) -> bool {
+        std::fs::symlink_metadata(mods_dir).is_ok_and(|m| m.is_dir())
+            && !Self::scan(mods_dir).0.is_empty()
+    }
+
+    /// Where originals from `mods_dir` are moved to: a sibling folder 3DMigoto does not load.
+    pub fn backup_dir(mods_dir: &Path) -> PathBuf {
+        let mut name = mods_dir.file_name().unwrap_or_default().to_os_string();
+        name.push(ADOPTION_BACKUP_SUFFIX);
+        mods_dir.with_file_name(name)
+    }
+
+    /// Moves whatever is left in a real `mods_dir` holding no mods into the backup
+    /// folder, so the deployed link can take its place. Returns the moved entries'
+    /// new paths.
+    pub fn set_aside_leftovers(mods_dir: &Path) -> Result<Vec<PathBuf>> {
+        if !std::fs::symlink_metadata(mods_dir).is_ok_and(|m| m.is_dir())
+            || Self::is_unmanaged(mods_dir)
+        {
+            return Ok(Vec::new());
+        }
+        let mut moved = Vec::new();
+        for entry in std::fs::read_dir(mods_dir)? {
+            moved.push(Self::move_aside(mods_dir, &entry?.path())?);
+        }
+        if !moved.is_empty() {
+            println!(
+                "Librarian: moved {} leftover entries of {} to {}",
+                moved.len(),
+                mods_dir.display(),
+                Self::backup_dir(mods_dir).display()
+            );
+        }
+        Ok(moved)
+    }
+
+    /// Lists the mods in `mods_dir` without touching them. A folder holding several mods,
+    /// such as a category folder or an unpacked pack, gives one candidate per mod.
+    pub async fn preview(
+        librarian: &Librarian,
+        game_id: &str,
+        mods_dir: &Path,
+    ) -> Result<AdoptionPreview> {
+        let db = librarian.load_game_db(game_id).await?;
+        let game_version = db.games.get(game_id).map(|g| g.version.clone());
+        let (folders, skipped) = Self::scan(mods_dir);
+
+        let mut candidates = Vec::new();
+        let mut hashes = Vec::new();
+        for path in folders {
+            let identity = Importer::identify_character_and_type(
+                &path,
+                &librarian.assets_root,
+                game_id,
+                game_version.as_deref(),
+            );
+            let (fingerprint, texture_hashes) = Importer::fingerprint(&path);
+            let mut compatibility = ModCompatibility {
+                game: "Unknown".to_string(),
+                character: String::new(),
+                hashes: Vec::new(),
+                fingerprint,
+                texture_hashes,
+                characters: Vec::new(),
+                confidence: 0.0,
+            };
+            identity.apply(&mut compatibility);
+
+            let (name, enabled) = Self::describe(mods_dir, &path);
+            candidates.push(AdoptionCandidate {
+                size: Importer::format_size(Safety::get_dir_size(&path).unwrap_or(0)),
+                duplicate_of: DuplicateFinder::best_match(&db, &compatibility),
+                character: compatibility.character,
+                characters: compatibility.characters,
+                confidence: compatibility.confidence,
+                conflicts_with: Vec::new(),
+                path,
+                name,
+                enabled,
+            });
+            hashes.push(compatibility.hashes);
+        }
+        Self::fill_conflicts(&db, game_id, &mut candidates, &hashes);
+
+        Ok(AdoptionPreview {
+            candidates,
+            skipped,
+        })
+    }
+
+    /// Imports the chosen candidate folders as new mods, appended to the active profile's
+    /// load order and enabled unless they were `DISABLED`. A folder that fails does not
+    /// stop the others.
+    pub async fn adopt(
+        librarian: &Librarian,
+        game_id: &str,
+        mods_dir: &Path,
+        folders: &[PathBuf],
+        mode: AdoptMode,
+        options: ImportOptions,
+    ) -> Result<AdoptionReport> {
+        let mut report = AdoptionReport::default();
+        for folder in folders {
+            match Self::adopt_one(librarian, game_id, mods_dir, folder, mode, &options).await {
+                Ok(record) => {
+                    if mode == AdoptMode::Move {
+                        report.backup = Some(Self::backup_dir(mods_dir));
+                    }
+                    report.adopted.push(record);
+                }
+                Err(e) => {
+                    eprintln!("Librarian: could not adopt {}: {}", folder.display(), e);
+                    report.failed.push(AdoptionFailure {
+                        path: folder.clone(),
+                        error: e.to_string(),
+                    });
+                }
+            }
+        }
+        println!(
+            "Librarian: adopted {} of {} mods for {}",
+            report.adopted.len(),
+            folders.len(),
+            game_id
+        );
+        Ok(report)
+    }
+
+    /// `name` without a leading `DISABLED` and the separator after it.
+    pub fn strip_disabled(name: &str) -> &str {
+        if !Self::is_disabled(name) {
+            return name;
+        }
+        let rest = name[DISABLED_PREFIX.len()..].trim_start_matches(['_', ' ', '-', '.']);
+        if rest.is_empty() {
+            name
+        } else {
+            rest
+        }
+    }
+
+    fn is_disabled(name: &str) -> bool {
+        name.get(..DISABLED_PREFIX.len())
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(DISABLED_PREFIX))
+    }
+
+    async fn adopt_one(
+        librarian: &Librarian,
+        game_id: &str,
+        mods_dir: &Path,
+        folder: &Path,
+        mode: AdoptMode,
+        options: &ImportOptions,
+    ) -> Result<ModRecord> {
+        let inside = folder.starts_with(mods_dir)
+            && folder != mods_dir
+            && !folder.components().any(|c| c == Component::ParentDir);
+        if !inside || !folder.is_dir() {
+            return Err(LibrarianError::Validation(format!(
+                "Not a mod folder in {}: {}",
+                mods_dir.display(),
+                folder.display()
+            )));
+        }
+
+        let (name, enabled) = Self::describe(mods_dir, folder);
+        let options = ImportOptions {
+            skip_active_profile: true,
+            name: Some(name),
+            ..options.clone()
+        };
+        let record = Importer::import_folder(
+            librarian,
+            folder.to_path_buf(),
+            game_id.to_string(),
+            options,
+        )
+        .await?;
+
+        {
+            let _guard = librarian.lock_imports().await;
+            let mut db = librarian.load_game_db(game_id).await?;
+            let profile_id = db
+                .games
+                .get(game_id)
+                .and_then(|g| Uuid::parse_str(&g.active_profile_id).ok());
+            if let Some(profile) = profile_id.and_then(|id| db.profiles.get_mut(&id)) {
+                profile.load_order.push(record.id);
+                if enabled {
+                    profile.enabled_mod_ids.push(record.id);
+                }
+            }
+            librarian.save_game_db(game_id, &db).await?;
+        }
+
+        if mode == AdoptMode::Move {
+            match Self::move_aside(mods_dir, folder) {
+                Ok(_) => Self::remove_empty_parents(mods_dir, folder),
+                Err(e) => eprintln!(
+                    "Librarian: adopted {} but could not move it aside: {}",
+                    folder.display(),
+                    e
+                ),
+            }
+        }
+        Ok(record)
+    }
+
+    /// Mod folders under `mods_dir`, and the top-level entries that are not mods.
+    fn scan(mods_dir: &Path) -> (Vec<PathBuf>, Vec<PathBuf>) {
+        let mut entries: Vec<PathBuf> = std::fs::read_dir(mods_dir)
+            .into_iter()
+            .flatten()
+            .filter_map(|e| e.ok())
+            .filter(|e| !e.file_name().to_string_lossy().starts_with('.'))
+            .map(|e| e.path())
+            .collect();
+        entries.sort();
+
+        let mut folders = Vec::new();
+        let mut skipped = Vec::new();
+        for path in entries {
+            // A link may point anywhere, YAGO's own deployment included
+            let is_dir = std::fs::symlink_metadata(&path).is_ok_and(|m| m.is_dir());
+            if is_dir && Importer::has_ini(&path, usize::MAX) {
+                folders.extend(Importer::mod_roots(&path));
+            } else {
+                skipped.push(path);
+            }
+        }
+        (folders, skipped)
+    }
+
+    /// The name to import `folder` under and whether 3DMigoto loads it.
+    fn describe(mods_dir: &Path, folder: &Path) -> (String, bool) {
+        let relative = folder.strip_prefix(mods_dir).unwrap_or(folder);
+        let enabled = !relative
+            .components()
+            .any(|c| Self::is_disabled(&c.as_os_str().to_string_lossy()));
+        let folder_name = folder
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        (Self::strip_disabled(&folder_name).to_string(), enabled)
+    }
+
+    /// Same rule as [`crate::queries::Queries::conflicts`]: skins of one character are
+    /// cycled, not in conflict.
+    fn fill_conflicts(
+        db: &LibraryDatabase,
+        game_id: &str,
+        candidates: &mut [AdoptionCandidate],
+        hashes: &[Vec<String>],
+    ) {
+        let profile = db
+            .games
+            .get(game_id)
+            .and_then(|g| Uuid::parse_str(&g.active_profile_id).ok())
+            .and_then(|id| db.profiles.get(&id));
+
+        // Name, character and hashes of everything that would be deployed
+        let mut entries: Vec<(String, String, &[String])> = profile
+            .into_iter()
+            .flat_map(|p| &p.enabled_mod_ids)
+            .filter_map(|id| db.mods.get(id))
+            .map(|r| {
+                (
+                    r.meta.name.clone(),
+                    r.compatibility.character.clone(),
+                    r.compatibility.hashes.as_slice(),
+                )
+            })
+            .collect();
+        let mut own_entry = vec![None; candidates.len()];
+        for (i, candidate) in candidates.iter().enumerate() {
+            if candidate.enabled {
+                own_entry[i] = Some(entries.len());
+                entries.push((
+                    candidate.name.clone(),
+                    candidate.character.clone(),
+                    hashes[i].as_slice(),
+                ));
+            }
+        }
+
+        let mut providers: HashMap<String, HashSet<usize>> = HashMap::new();
+        for (index, (_, _, entry_hashes)) in entries.iter().enumerate() {
+            for hash in entry_hashes.iter() {
+                providers
+                    .entry(hash.to_lowercase())
+                    .or_default()
+                    .insert(index);
+            }
+        }
+
+        for (i, candidate) in candidates.iter_mut().enumerate() {
+            let Some(own) = own_entry[i] else {
+                continue;
+            };
+            let character = &entries[own].1;
+            let cycles = !GLOBAL_CHARACTERS.contains(&character.as_str());
+            let mut names: Vec<String> = hashes[i]
+                .iter()
+                .filter_map(|hash| providers.get(&hash.to_lowercase()))
+                .flatten()
+                .filter(|&&other| other != own && !(cycles && entries[other].1 == *character))
+                .map(|&other| entries[other].0.clone())
+                .collect();
+            names.sort();
+            names.dedup();
+            candidate.conflicts_with = names;
+        }
+    }
+
+    /// Moves `path` to the same place under the backup folder, next to anything an
+    /// earlier adoption left there under that name.
+    fn move_aside(mods_dir: &Path, path: &Path) -> Result<PathBuf> {
+        let relative = path.strip_prefix(mods_dir).unwrap_or(path);
+        let mut target = Self::backup_dir(mods_dir).join(relative);
+        let file_name = target.file_name().unwrap_or_default().to_os_string();
+        let mut copy = 1;
+        while std::fs::symlink_metadata(&target).is_ok() {
+            copy += 1;
+            let mut name = file_name.clone();
+            name.push(format!(" ({})", copy));
+            target.set_file_name(name);
+        }
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::rename(path, &target)?;
+        Ok(target)
+    }
+
+    /// Deletes the parents an adopted folder leaves empty, up to `mods_dir`.
+    fn remove_empty_parents(mods_dir: &Path, folder: &Path) {
+        let mut parent = folder.parent();
+        while let Some(dir) = parent.filter(|d| *d != mods_dir && d.starts_with(mods_dir)) {
+            if std::fs::remove_dir(dir).is_err() {
+                break;
+            }
+            parent = dir.parent();
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use super::{BackendKind, LibraryBackend, BACKUP_INTERVAL};
+use crate::error::{LibrarianError, Result};
+use crate::models::{LibraryDatabase, Profile};
+use fs_engine::{BackupRotation, RestoredBackup, Safety, DEFAULT_BACKUP_COUNT};
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use uuid::Uuid;
+
+/// `games/<id>/game.json` for games and mods, `profiles.json` next to it for profiles.
+/// Both are written atomically and backed up to `games/<id>/backups`.
+pub struct JsonBackend {
+    games_root: PathBuf,
+    recoveries: Mutex<Vec<RestoredBackup>>,
+}
+
+impl JsonBackend {
+    pub fn new(games_root: PathBuf) -> Self {
+        Self {
+            games_root,
+            recoveries: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn backups(&self, game_id: &str) -> BackupRotation {
+        BackupRotation::new(
+            self.games_root.join(game_id).join("backups"),
+            DEFAULT_BACKUP_COUNT,
+        )
+        .with_min_interval(BACKUP_INTERVAL)
+    }
+
+    /// Parses `path`, replacing it with its newest backup that parses if it is damaged.
+    /// The inner error is the original parse error when no backup could stand in.
+    fn read_recovering<T: DeserializeOwned>(
+        &self,
+        game_id: &str,
+        path: &Path,
+    ) -> Result<std::result::Result<T, serde_json::Error>> {
+        let content = std::fs::read(path)?;
+        let error = match serde_json::from_slice::<T>(&content) {
+            Ok(value) => return Ok(Ok(value)),
+            Err(e) => e,
+        };
+
+        let restored = self
+            .backups(game_id)
+            .restore(path, |data| serde_json::from_slice::<T>(data).is_ok())?;
+        let Some(restored) = restored else {
+            return Ok(Err(error));
+        };
+        eprintln!(
+            "Librarian WARNING: {} was damaged ({}), restored {}",
+            path.display(),
+            error,
+            restored.backup.display()
+        );
+        let value = serde_json::from_slice::<T>(&std::fs::read(path)?);
+        self.recoveries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(restored);
+        Ok(value)
+    }
+
+    fn write(&self, game_id: &str, path: &Path, content: &str) -> Result<()> {
+        Safety::write_atomic(path, content.as_bytes())?;
+        // The write itself succeeded; a missing backup is only worth a warning
+        if let Err(e) = self.backups(game_id).snapshot(path) {
+            eprintln!(
+                "Librarian WARNING: Failed to back up {}: {}",
+                path.display(),
+                e
+            );
+        }
+        Ok(())
+    }
+
+    fn db_path(&self, game_id: &str) -> PathBuf {
+        self.games_root.join(game_id).join("game.json")
+    }
+
+    fn profiles_path(&self, game_id: &str) -> PathBuf {
+        self.games_root.join(game_id).join("profiles.json")
+    }
+}
+
+impl LibraryBackend for JsonBackend {
+    fn kind(&self) -> BackendKind {
+        BackendKind::Json
+    }
+
+    fn game_ids(&self) -> Result<Vec<String>> {
+        if !self.games_root.exists() {
+            return Ok(vec![]);
+        }
+        let mut ids = vec![];
+        for entry in std::fs::read_dir(&self.games_root)?.filter_map(|e| e.ok()) {
+            let Some(id) = entry.file_name().to_str().map(String::from) else {
+                continue;
+            };
+            if !id.starts_with('.') && self.db_path(&id).is_file() {
+                ids.push(id);
+            }
+        }
+        ids.sort();
+        Ok(ids)
+    }
+
+    fn has_game(&self, game_id: &str) -> Result<bool> {
+        Ok(self.db_path(game_id).exists())
+    }
+
+    fn load(&self, game_id: &str) -> Result<Option<LibraryDatabase>> {
+        let db_path = self.db_path(game_id);
+        if !db_path.exists() {
+            return Ok(None);
+        }
+
+        let mut db = self
+            .read_recovering::<LibraryDatabase>(game_id, &db_path)?
+            .map_err(|e| {
+                eprintln!(
+                    "Librarian ERROR: Failed to parse game.json for {}: {}",
+                    game_id, e
+                );
+                LibrarianError::Io(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    e.to_string(),
+                ))
+            })?;
+
+        let profiles_path = self.profiles_path(game_id);
+        if profiles_path.exists() {
+            match self.read_recovering::<HashMap<Uuid, Profile>>(game_id, &profiles_path)? {
+                Ok(profiles) => db.profiles = profiles,
+                Err(_) => eprintln!(
+                    "Librarian WARNING: Failed to parse profiles.json for {}, resetting to default.",
+                    game_id
+                ),
+            }
+        }
+        Ok(Some(db))
+    }
+
+    fn save(&self, game_id: &str, db: &LibraryDatabase) -> Result<()> {
+        std::fs::create_dir_all(self.games_root.join(game_id))?;
+
+        // 1. Save game.json (Games + Mods)
+        let mut db_clone = db.clone();
+        db_clone.profiles = HashMap::new(); // Don't save profiles in game.json
+
+        let content = serde_json::to_string_pretty(&db_clone)?;
+        self.write(game_id, &self.db_path(game_id), &content)?;
+
+        // 2. Save profiles.json
+        if !db.profiles.is_empty() {
+            let p_content = serde_json::to_string_pretty(&db.profiles)?;
+            self.write(game_id, &self.profiles_path(game_id), &p_content)?;
+        }
+
+        Ok(())
+    }
+
+    fn take_recoveries(&self) -> Vec<RestoredBackup> {
+        std::mem::take(&mut *self.recoveries.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    /// Only removes the database files; the game folder also holds profile data.
+    fn delete(&self, game_id: &str) -> Result<()> {
+        for path in [self.db_path(game_id), self.profiles_path(game_id)] {
+            match std::fs::remove_file(path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,128 @@
+//! Where per-game library databases are persisted.
+//!
+//! `JsonBackend` is the original one-file-per-game layout and the default for new
+//! libraries. `SqliteBackend` keeps every game in one indexed database and only
+//! rewrites the rows that changed on save. Which one a library uses is a setting; once
+//! games were moved into SQLite, their `game.json` files are stale, so a library never
+//! falls back to them when the database cannot be opened.
+
+pub mod json;
+pub mod sqlite;
+
+pub use json::JsonBackend;
+pub use sqlite::SqliteBackend;
+
+use crate::error::{LibrarianError, Result};
+use crate::models::{LibraryDatabase, ModRecord};
+use fs_engine::RestoredBackup;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Databases are saved on every toggle; one backup per this interval is plenty.
+const BACKUP_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackendKind {
+    #[default]
+    Json,
+    Sqlite,
+}
+
+pub trait LibraryBackend: Send + Sync {
+    fn kind(&self) -> BackendKind;
+
+    /// Ids of all games with a stored database.
+    fn game_ids(&self) -> Result<Vec<String>>;
+
+    fn has_game(&self, game_id: &str) -> Result<bool> {
+        Ok(self.game_ids()?.iter().any(|id| id == game_id))
+    }
+
+    /// The stored database, or `None` if nothing was ever saved for the game.
+    fn load(&self, game_id: &str) -> Result<Option<LibraryDatabase>>;
+
+    fn save(&self, game_id: &str, db: &LibraryDatabase) -> Result<()>;
+
+    fn delete(&self, game_id: &str) -> Result<()>;
+
+    /// Damaged files replaced from a backup since the last call.
+    fn take_recoveries(&self) -> Vec<RestoredBackup> {
+        Vec::new()
+    }
+
+    /// Mods identified as `character` (case-insensitive).
+    fn mods_by_character(&self, game_id: &str, character: &str) -> Result<Vec<ModRecord>> {
+        scan_mods(self.load(game_id)?, |m| {
+            m.compatibility.character.eq_ignore_ascii_case(character)
+        })
+    }
+
+    /// Mods carrying `tag` (case-insensitive).
+    fn mods_by_tag(&self, game_id: &str, tag: &str) -> Result<Vec<ModRecord>> {
+        scan_mods(self.load(game_id)?, |m| {
+            m.config.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+        })
+    }
+
+    /// Mods whose INIs override `hash`.
+    fn mods_by_hash(&self, game_id: &str, hash: &str) -> Result<Vec<ModRecord>> {
+        scan_mods(self.load(game_id)?, |m| {
+            m.compatibility
+                .hashes
+                .iter()
+                .any(|h| h.eq_ignore_ascii_case(hash))
+        })
+    }
+}
+
+/// Stands in for a backend that could not be opened, failing every call with the
+/// reason instead of serving another backend's older copy of the library.
+pub(crate) struct UnavailableBackend {
+    kind: BackendKind,
+    reason: String,
+}
+
+impl UnavailableBackend {
+    pub(crate) fn new(kind: BackendKind, reason: String) -> Self {
+        Self { kind, reason }
+    }
+
+    fn error(&self) -> LibrarianError {
+        LibrarianError::BackendUnavailable(self.reason.clone())
+    }
+}
+
+impl LibraryBackend for UnavailableBackend {
+    fn kind(&self) -> BackendKind {
+        self.kind
+    }
+
+    fn game_ids(&self) -> Result<Vec<String>> {
+        Err(self.error())
+    }
+
+    fn load(&self, _game_id: &str) -> Result<Option<LibraryDatabase>> {
+        Err(self.error())
+    }
+
+    fn save(&self, _game_id: &str, _db: &LibraryDatabase) -> Result<()> {
+        Err(self.error())
+    }
+
+    fn delete(&self, _game_id: &str) -> Result<()> {
+        Err(self.error())
+    }
+}
+
+/// Full scan of a stored database, used by the default query implementations.
+fn scan_mods(
+    db: Option<LibraryDatabase>,
+    predicate: impl Fn(&ModRecord) -> bool,
+) -> Result<Vec<ModRecord>> {
+    let mut mods: Vec<ModRecord> = db
+        .map(|db| db.mods.into_values().filter(|m| predicate(m)).collect())
+        .unwrap_or_default();
+    mods.sort_by_key(|m| m.added_at);
+    Ok(mods)
+}
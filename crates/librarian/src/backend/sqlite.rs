@@ -0,0 +1,492 @@
+use super::{BackendKind, JsonBackend, LibraryBackend, BACKUP_INTERVAL};
+use crate::error::{LibrarianError, Result};
+use crate::models::{GameConfig, LibraryDatabase, ModRecord, Profile};
+use chrono::{DateTime, Utc};
+use fs_engine::{BackupRotation, FsError, RestoredBackup, Safety, DEFAULT_BACKUP_COUNT};
+use rusqlite::{params, Connection, ErrorCode, OptionalExtension, Params, Transaction};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Schema steps, applied in order. `PRAGMA user_version` records how many have run, so
+/// a step must never change once released; add a new one instead.
+const MIGRATIONS: &[&str] = &[
+    // 1: rows keep the full record as JSON, with the queried fields pulled out
+    "CREATE TABLE libraries (
+        id TEXT PRIMARY KEY,
+        version TEXT NOT NULL,
+        last_sync TEXT
+    );
+    CREATE TABLE games (
+        library_id TEXT NOT NULL REFERENCES libraries(id) ON DELETE CASCADE,
+        id TEXT NOT NULL,
+        data TEXT NOT NULL,
+        PRIMARY KEY (library_id, id)
+    );
+    CREATE TABLE profiles (
+        id TEXT PRIMARY KEY,
+        library_id TEXT NOT NULL REFERENCES libraries(id) ON DELETE CASCADE,
+        data TEXT NOT NULL
+    );
+    CREATE INDEX idx_profiles_library ON profiles(library_id);
+    CREATE TABLE mods (
+        id TEXT PRIMARY KEY,
+        library_id TEXT NOT NULL REFERENCES libraries(id) ON DELETE CASCADE,
+        character TEXT NOT NULL,
+        added_at TEXT NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE INDEX idx_mods_character ON mods(library_id, character COLLATE NOCASE);
+    CREATE TABLE mod_tags (
+        mod_id TEXT NOT NULL REFERENCES mods(id) ON DELETE CASCADE,
+        tag TEXT NOT NULL COLLATE NOCASE,
+        PRIMARY KEY (mod_id, tag)
+    );
+    CREATE INDEX idx_mod_tags_tag ON mod_tags(tag);
+    CREATE TABLE mod_hashes (
+        mod_id TEXT NOT NULL REFERENCES mods(id) ON DELETE CASCADE,
+        hash TEXT NOT NULL COLLATE NOCASE,
+        PRIMARY KEY (mod_id, hash)
+    );
+    CREATE INDEX idx_mod_hashes_hash ON mod_hashes(hash);
+    CREATE TABLE json_imports (
+        library_id TEXT PRIMARY KEY,
+        imported_at TEXT NOT NULL
+    );",
+];
+
+/// One database file for the whole library. Connections are opened per call, so the
+/// backend is cheap to share between threads. Saves are backed up to `backups` next to
+/// the file with `VACUUM INTO`, and a damaged file is replaced by its newest intact
+/// backup when opened.
+pub struct SqliteBackend {
+    path: PathBuf,
+    recoveries: Mutex<Vec<RestoredBackup>>,
+}
+
+impl SqliteBackend {
+    /// Opens (creating if needed) the database at `path` and brings its schema up to date.
+    /// A database failing its integrity check is restored from a backup; without a
+    /// usable one, opening fails.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let backend = Self {
+            path: path.into(),
+            recoveries: Mutex::new(Vec::new()),
+        };
+        if let Some(parent) = backend.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        if let Some(damage) = Self::damage(&backend.path)? {
+            backend.restore(&damage)?;
+        }
+        let mut conn = backend.connect()?;
+        // Persistent; lets the UI read while a save is in progress
+        conn.pragma_update_and_check(None, "journal_mode", "WAL", |_| Ok(()))?;
+        Self::migrate(&mut conn)?;
+        Ok(backend)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn backups(&self) -> BackupRotation {
+        let dir = self
+            .path
+            .parent()
+            .map(|p| p.join("backups"))
+            .unwrap_or_else(|| PathBuf::from("backups"));
+        BackupRotation::new(dir, DEFAULT_BACKUP_COUNT).with_min_interval(BACKUP_INTERVAL)
+    }
+
+    /// What `PRAGMA integrity_check` found wrong with the database at `path`, or `None`
+    /// when it is intact or does not exist yet.
+    fn damage(path: &Path) -> Result<Option<String>> {
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let checked = Connection::open(path).and_then(|conn| {
+            conn.query_row("PRAGMA integrity_check", [], |row| row.get::<_, String>(0))
+        });
+        match checked {
+            Ok(result) if result == "ok" => Ok(None),
+            Ok(result) => Ok(Some(result)),
+            Err(rusqlite::Error::SqliteFailure(e, message))
+                if matches!(e.code, ErrorCode::DatabaseCorrupt | ErrorCode::NotADatabase) =>
+            {
+                Ok(Some(message.unwrap_or_else(|| e.to_string())))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Replaces the damaged database with its newest backup that passes the integrity
+    /// check. The damaged file and its journal are moved next to the backups.
+    fn restore(&self, damage: &str) -> Result<()> {
+        let backups = self.backups();
+        let name = self
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        for backup in backups.backups(&self.path)? {
+            if !matches!(Self::damage(&backup), Ok(None)) {
+                continue;
+            }
+            let data = std::fs::read(&backup)?;
+
+            let corrupt_copy = backups.dir().join(format!(
+                "{}.{}.corrupt",
+                name,
+                Utc::now().timestamp_millis()
+            ));
+            std::fs::rename(&self.path, &corrupt_copy)?;
+            // A journal left by the damaged file must not be replayed onto the backup
+            for suffix in ["-wal", "-shm"] {
+                let mut journal = self.path.clone().into_os_string();
+                journal.push(suffix);
+                let mut moved = corrupt_copy.clone().into_os_string();
+                moved.push(suffix);
+                let _ = std::fs::rename(journal, moved);
+            }
+            Safety::write_atomic(&self.path, &data)?;
+
+            eprintln!(
+                "Librarian WARNING: {} was damaged ({}), restored {}",
+                self.path.display(),
+                damage,
+                backup.display()
+            );
+            self.recoveries
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .push(RestoredBackup {
+                    file: self.path.clone(),
+                    backup,
+                    corrupt_copy: Some(corrupt_copy),
+                });
+            return Ok(());
+        }
+        Err(LibrarianError::BackendUnavailable(format!(
+            "{} is damaged ({}) and has no usable backup",
+            self.path.display(),
+            damage
+        )))
+    }
+
+    /// Number of migrations applied to the database.
+    pub fn schema_version(&self) -> Result<usize> {
+        let conn = self.connect()?;
+        let version: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        Ok(version as usize)
+    }
+
+    /// Copies every game from `json` that has not been imported before. Runs once per
+    /// game: later edits to the JSON files are not picked up, and the files are left
+    /// in place for older builds. Returns the ids that were imported.
+    pub fn import_json(&self, json: &JsonBackend) -> Result<Vec<String>> {
+        let mut imported = Vec::new();
+        for game_id in json.game_ids()? {
+            let conn = self.connect()?;
+            let done: bool = conn
+                .query_row(
+                    "SELECT 1 FROM json_imports WHERE library_id = ?1",
+                    [&game_id],
+                    |_| Ok(true),
+                )
+                .optional()?
+                .unwrap_or(false);
+            if done {
+                continue;
+            }
+
+            let db = match json.load(&game_id) {
+                Ok(Some(db)) => db,
+                Ok(None) => continue,
+                // A corrupt game.json must not block the rest of the library
+                Err(e) => {
+                    eprintln!("Librarian: skipped importing {}: {}", game_id, e);
+                    continue;
+                }
+            };
+            // Something saved directly to SQLite is newer than the JSON
+            if !self.has_game(&game_id)? {
+                self.save(&game_id, &db)?;
+            }
+            conn.execute(
+                "INSERT INTO json_imports (library_id, imported_at) VALUES (?1, ?2)",
+                params![game_id, Utc::now().to_rfc3339()],
+            )?;
+            imported.push(game_id);
+        }
+        Ok(imported)
+    }
+
+    fn connect(&self) -> Result<Connection> {
+        let conn = Connection::open(&self.path)?;
+        conn.busy_timeout(std::time::Duration::from_secs(5))?;
+        conn.pragma_update(None, "foreign_keys", true)?;
+        Ok(conn)
+    }
+
+    fn migrate(conn: &mut Connection) -> Result<()> {
+        let current: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        let current = current as usize;
+        if current > MIGRATIONS.len() {
+            return Err(LibrarianError::Validation(format!(
+                "Library database schema {} is newer than this build supports ({})",
+                current,
+                MIGRATIONS.len()
+            )));
+        }
+        for (index, sql) in MIGRATIONS.iter().enumerate().skip(current) {
+            let tx = conn.transaction()?;
+            tx.execute_batch(sql)?;
+            tx.pragma_update(None, "user_version", (index + 1) as i64)?;
+            tx.commit()?;
+            println!(
+                "Librarian: library database migrated to schema {}",
+                index + 1
+            );
+        }
+        Ok(())
+    }
+
+    fn query_mods(&self, sql: &str, game_id: &str, value: &str) -> Result<Vec<ModRecord>> {
+        Self::decode_all(&self.connect()?, sql, params![game_id, value])
+    }
+
+    /// Stored rows of a table for one library, by id.
+    fn existing(tx: &Transaction, table: &str, game_id: &str) -> Result<HashMap<String, String>> {
+        let mut stmt = tx.prepare(&format!(
+            "SELECT id, data FROM {} WHERE library_id = ?1",
+            table
+        ))?;
+        let rows = stmt.query_map([game_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Whether `stored` already holds `value`. Compared as JSON values because
+    /// `HashMap` fields serialize in no particular order.
+    fn unchanged<T: Serialize>(stored: Option<&String>, value: &T) -> Result<bool> {
+        let Some(stored) = stored else {
+            return Ok(false);
+        };
+        let stored: serde_json::Value = serde_json::from_str(stored)?;
+        Ok(stored == serde_json::to_value(value)?)
+    }
+
+    /// Deserializes the JSON in the first column of every row.
+    fn decode_all<T: DeserializeOwned>(
+        conn: &Connection,
+        sql: &str,
+        params: impl Params,
+    ) -> Result<Vec<T>> {
+        let mut stmt = conn.prepare(sql)?;
+        let rows = stmt.query_map(params, |row| row.get::<_, String>(0))?;
+        let mut values = Vec::new();
+        for data in rows {
+            values.push(serde_json::from_str(&data?)?);
+        }
+        Ok(values)
+    }
+}
+
+impl LibraryBackend for SqliteBackend {
+    fn kind(&self) -> BackendKind {
+        BackendKind::Sqlite
+    }
+
+    fn game_ids(&self) -> Result<Vec<String>> {
+        let conn = self.connect()?;
+        let mut stmt = conn.prepare("SELECT id FROM libraries ORDER BY id")?;
+        let ids = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(ids)
+    }
+
+    fn has_game(&self, game_id: &str) -> Result<bool> {
+        let conn = self.connect()?;
+        Ok(conn
+            .query_row("SELECT 1 FROM libraries WHERE id = ?1", [game_id], |_| {
+                Ok(())
+            })
+            .optional()?
+            .is_some())
+    }
+
+    fn load(&self, game_id: &str) -> Result<Option<LibraryDatabase>> {
+        let conn = self.connect()?;
+        let header: Option<(String, Option<String>)> = conn
+            .query_row(
+                "SELECT version, last_sync FROM libraries WHERE id = ?1",
+                [game_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        let Some((version, last_sync)) = header else {
+            return Ok(None);
+        };
+
+        let mut games = HashMap::new();
+        let mut stmt = conn.prepare("SELECT id, data FROM games WHERE library_id = ?1")?;
+        let rows = stmt.query_map([game_id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        for row in rows {
+            let (id, data) = row?;
+            games.insert(id, serde_json::from_str::<GameConfig>(&data)?);
+        }
+        let mods: Vec<ModRecord> = Self::decode_all(
+            &conn,
+            "SELECT data FROM mods WHERE library_id = ?1",
+            [game_id],
+        )?;
+        let profiles: Vec<Profile> = Self::decode_all(
+            &conn,
+            "SELECT data FROM profiles WHERE library_id = ?1",
+            [game_id],
+        )?;
+
+        Ok(Some(LibraryDatabase {
+            version,
+            games,
+            mods: mods.into_iter().map(|m| (m.id, m)).collect(),
+            profiles: profiles.into_iter().map(|p| (p.id, p)).collect(),
+            last_sync: last_sync
+                .and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
+                .map(|d| d.with_timezone(&Utc)),
+        }))
+    }
+
+    /// Writes only the rows that differ from what is stored, in one transaction.
+    fn save(&self, game_id: &str, db: &LibraryDatabase) -> Result<()> {
+        let mut conn = self.connect()?;
+        let tx = conn.transaction()?;
+
+        tx.execute(
+            "INSERT INTO libraries (id, version, last_sync) VALUES (?1, ?2, ?3)
+             ON CONFLICT(id) DO UPDATE SET version = excluded.version, last_sync = excluded.last_sync",
+            params![game_id, db.version, db.last_sync.map(|d| d.to_rfc3339())],
+        )?;
+
+        // A library has one or two games; not worth diffing
+        tx.execute("DELETE FROM games WHERE library_id = ?1", [game_id])?;
+        for (id, game) in &db.games {
+            tx.execute(
+                "INSERT INTO games (library_id, id, data) VALUES (?1, ?2, ?3)",
+                params![game_id, id, serde_json::to_string(game)?],
+            )?;
+        }
+
+        let mut stored = Self::existing(&tx, "profiles", game_id)?;
+        for (id, profile) in &db.profiles {
+            let id = id.to_string();
+            if Self::unchanged(stored.remove(&id).as_ref(), profile)? {
+                continue;
+            }
+            tx.execute(
+                "INSERT INTO profiles (id, library_id, data) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(id) DO UPDATE SET library_id = excluded.library_id, data = excluded.data",
+                params![id, game_id, serde_json::to_string(profile)?],
+            )?;
+        }
+        for id in stored.keys() {
+            tx.execute("DELETE FROM profiles WHERE id = ?1", [id])?;
+        }
+
+        let mut stored = Self::existing(&tx, "mods", game_id)?;
+        for (id, record) in &db.mods {
+            let id = id.to_string();
+            if Self::unchanged(stored.remove(&id).as_ref(), record)? {
+                continue;
+            }
+            tx.execute(
+                "INSERT INTO mods (id, library_id, character, added_at, data) VALUES (?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT(id) DO UPDATE SET library_id = excluded.library_id,
+                    character = excluded.character, added_at = excluded.added_at, data = excluded.data",
+                params![
+                    id,
+                    game_id,
+                    record.compatibility.character,
+                    record.added_at.to_rfc3339(),
+                    serde_json::to_string(record)?
+                ],
+            )?;
+            tx.execute("DELETE FROM mod_tags WHERE mod_id = ?1", [&id])?;
+            for tag in &record.config.tags {
+                tx.execute(
+                    "INSERT OR IGNORE INTO mod_tags (mod_id, tag) VALUES (?1, ?2)",
+                    params![id, tag],
+                )?;
+            }
+            tx.execute("DELETE FROM mod_hashes WHERE mod_id = ?1", [&id])?;
+            for hash in &record.compatibility.hashes {
+                tx.execute(
+                    "INSERT OR IGNORE INTO mod_hashes (mod_id, hash) VALUES (?1, ?2)",
+                    params![id, hash],
+                )?;
+            }
+        }
+        for id in stored.keys() {
+            tx.execute("DELETE FROM mods WHERE id = ?1", [id])?;
+        }
+
+        tx.commit()?;
+
+        // The save itself succeeded; a missing backup is only worth a warning
+        let snapshot = self.backups().snapshot_with(&self.path, |backup| {
+            conn.execute("VACUUM INTO ?1", [backup.to_string_lossy()])
+                .map(|_| ())
+                .map_err(|e| FsError::Io(std::io::Error::other(e.to_string())))
+        });
+        if let Err(e) = snapshot {
+            eprintln!(
+                "Librarian WARNING: Failed to back up {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+        Ok(())
+    }
+
+    fn take_recoveries(&self) -> Vec<RestoredBackup> {
+        std::mem::take(&mut *self.recoveries.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    fn delete(&self, game_id: &str) -> Result<()> {
+        let conn = self.connect()?;
+        conn.execute("DELETE FROM libraries WHERE id = ?1", [game_id])?;
+        Ok(())
+    }
+
+    fn mods_by_character(&self, game_id: &str, character: &str) -> Result<Vec<ModRecord>> {
+        self.query_mods(
+            "SELECT data FROM mods WHERE library_id = ?1 AND character = ?2 COLLATE NOCASE
+             ORDER BY added_at",
+            game_id,
+            character,
+        )
+    }
+
+    fn mods_by_tag(&self, game_id: &str, tag: &str) -> Result<Vec<ModRecord>> {
+        self.query_mods(
+            "SELECT m.data FROM mod_tags t JOIN mods m ON m.id = t.mod_id
+             WHERE t.tag = ?2 AND m.library_id = ?1 ORDER BY m.added_at",
+            game_id,
+            tag,
+        )
+    }
+
+    fn mods_by_hash(&self, game_id: &str, hash: &str) -> Result<Vec<ModRecord>> {
+        self.query_mods(
+            "SELECT m.data FROM mod_hashes h JOIN mods m ON m.id = h.mod_id
+             WHERE h.hash = ?2 AND m.library_id = ?1 ORDER BY m.added_at",
+            game_id,
+            hash,
+        )
+    }
+}
@@ -0,0 +1,435 @@
+//! Shareable profile bundles.
+//!
+//! A bundle is a zip holding `bundle.json` (the profile's settings and its mods in load
+//! order, each with metadata, source URL and file hashes) and, optionally, a zip of
+//! every mod under `mods/`. Importing matches mods already in the library by
+//! fingerprint, imports embedded ones and reports the rest as missing.
+
+use crate::duplicates::DuplicateFinder;
+use crate::error::{LibrarianError, Result};
+use crate::fingerprint::{FileHash, ModFingerprint};
+use crate::import::{ImportOptions, Importer};
+use crate::models::{LibraryDatabase, ModMetadata, Profile};
+use crate::Librarian;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// Bumped when the manifest changes incompatibly.
+pub const BUNDLE_FORMAT: u32 = 1;
+
+const MANIFEST_NAME: &str = "bundle.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileBundle {
+    pub format: u32,
+    pub exported_at: DateTime<Utc>,
+    pub game_id: String,
+    pub profile: BundleProfile,
+    /// Mods in load order.
+    pub mods: Vec<BundleMod>,
+}
+
+/// Launch settings of the exported profile. Paths such as the save data folder are
+/// machine-specific and stay behind.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleProfile {
+    pub name: String,
+    pub description: String,
+    pub use_gamescope: bool,
+    pub use_gamemode: bool,
+    pub use_mangohud: bool,
+    pub use_reshade: bool,
+    pub resolution: Option<(u32, u32)>,
+    pub launch_args: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleMod {
+    /// The mod's id on the exporting machine.
+    pub id: Uuid,
+    pub meta: ModMetadata,
+    pub character: String,
+    pub tags: Vec<String>,
+    pub enabled: bool,
+    pub fingerprint: String,
+    pub files: Vec<FileHash>,
+    /// Entry of the embedded mod zip inside the bundle, if archives were embedded.
+    pub archive: Option<String>,
+}
+
+/// A bundled mod that is neither in the library nor embedded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MissingMod {
+    pub name: String,
+    pub version: String,
+    pub url: Option<String>,
+    pub fingerprint: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleImportReport {
+    pub profile: Profile,
+    /// Local ids of mods that were already in the library.
+    pub matched: Vec<Uuid>,
+    /// Local ids of mods imported from the bundle.
+    pub imported: Vec<Uuid>,
+    pub missing: Vec<MissingMod>,
+}
+
+pub struct ProfileBundles;
+
+impl ProfileBundles {
+    /// Writes `profile_id` of `game_id` to `destination`. Mods pinned by the profile
+    /// are exported at their pinned release.
+    pub async fn export(
+        librarian: &Librarian,
+        game_id: &str,
+        profile_id: Uuid,
+        destination: &Path,
+        embed_archives: bool,
+    ) -> Result<ProfileBundle> {
+        let db = librarian.load_game_db(game_id).await?;
+        let profile = db.profiles.get(&profile_id).ok_or_else(|| {
+            LibrarianError::ProfileError(format!("Profile {} not found", profile_id))
+        })?;
+
+        // Enabled mods missing from the load order go last, as in deployment
+        let mut order = profile.load_order.clone();
+        for id in &profile.enabled_mod_ids {
+            if !order.contains(id) {
+                order.push(*id);
+            }
+        }
+
+        let partial = temp_path(destination);
+        let bundle =
+            match Self::write_bundle(game_id, &db, profile, order, &partial, embed_archives) {
+                Ok(bundle) => bundle,
+                Err(e) => {
+                    let _ = std::fs::remove_file(&partial);
+                    return Err(e);
+                }
+            };
+        std::fs::rename(&partial, destination)?;
+
+        println!(
+            "Librarian: Exported profile {} with {} mods to {}",
+            bundle.profile.name,
+            bundle.mods.len(),
+            destination.display()
+        );
+        Ok(bundle)
+    }
+
+    fn write_bundle(
+        game_id: &str,
+        db: &LibraryDatabase,
+        profile: &Profile,
+        order: Vec<Uuid>,
+        partial: &Path,
+        embed_archives: bool,
+    ) -> Result<ProfileBundle> {
+        let file = std::fs::File::create(partial)?;
+        let mut zip = zip::ZipWriter::new(file);
+        let stored = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Stored)
+            .large_file(true);
+
+        let mut mods = Vec::new();
+        for id in order {
+            let Some(record) = db.mods.get(&id) else {
+                continue;
+            };
+            let mod_root = record.path_for_profile(profile);
+            let files = ModFingerprint::files(mod_root)?;
+            let archive = if embed_archives {
+                let name = format!("mods/{}.zip", record.id);
+                let mod_zip = partial.with_extension(format!("{}.zip", record.id));
+                let written = write_mod_zip(mod_root, &mod_zip).and_then(|_| {
+                    zip.start_file(name.as_str(), stored).map_err(zip_error)?;
+                    std::io::copy(&mut std::fs::File::open(&mod_zip)?, &mut zip)?;
+                    Ok(())
+                });
+                let _ = std::fs::remove_file(&mod_zip);
+                written?;
+                Some(name)
+            } else {
+                None
+            };
+            mods.push(BundleMod {
+                id: record.id,
+                meta: record.meta.clone(),
+                character: record.compatibility.character.clone(),
+                tags: record.config.tags.clone(),
+                enabled: profile.enabled_mod_ids.contains(&record.id),
+                fingerprint: ModFingerprint::of_files(&files),
+                files,
+                archive,
+            });
+        }
+
+        let bundle = ProfileBundle {
+            format: BUNDLE_FORMAT,
+            exported_at: Utc::now(),
+            game_id: game_id.to_string(),
+            profile: BundleProfile {
+                name: profile.name.clone(),
+                description: profile.description.clone(),
+                use_gamescope: profile.use_gamescope,
+                use_gamemode: profile.use_gamemode,
+                use_mangohud: profile.use_mangohud,
+                use_reshade: profile.use_reshade,
+                resolution: profile.resolution,
+                launch_args: profile.launch_args.clone(),
+            },
+            mods,
+        };
+        zip.start_file(MANIFEST_NAME, zip::write::SimpleFileOptions::default())
+            .map_err(zip_error)?;
+        zip.write_all(serde_json::to_string_pretty(&bundle)?.as_bytes())?;
+        zip.finish().map_err(zip_error)?.sync_all()?;
+        Ok(bundle)
+    }
+
+    /// The manifest of a bundle, for previewing it before an import.
+    pub fn read_manifest(bundle_path: &Path) -> Result<ProfileBundle> {
+        let mut archive = open_bundle(bundle_path)?;
+        let mut entry = archive.by_name(MANIFEST_NAME).map_err(|_| {
+            LibrarianError::Validation(format!("{} is not a profile bundle", bundle_path.display()))
+        })?;
+        let mut content = String::new();
+        entry.read_to_string(&mut content)?;
+        let bundle: ProfileBundle = serde_json::from_str(&content)?;
+        if bundle.format > BUNDLE_FORMAT {
+            return Err(LibrarianError::Validation(format!(
+                "Bundle format {} is newer than this version of YAGO supports",
+                bundle.format
+            )));
+        }
+        Ok(bundle)
+    }
+
+    /// Recreates the bundled profile in `game_id`. `options` apply to embedded mods
+    /// that have to be imported.
+    pub async fn import(
+        librarian: &Librarian,
+        game_id: &str,
+        bundle_path: &Path,
+        options: ImportOptions,
+    ) -> Result<BundleImportReport> {
+        let bundle = Self::read_manifest(bundle_path)?;
+        if bundle.game_id != game_id {
+            return Err(LibrarianError::Validation(format!(
+                "Bundle is for {}, not {}",
+                bundle.game_id, game_id
+            )));
+        }
+
+        let by_fingerprint = Self::local_fingerprints(librarian, game_id).await?;
+        let staging_root = librarian.games_root.join(".yago_staging");
+        std::fs::create_dir_all(&staging_root)?;
+
+        // Local id and enabled state of each bundled mod that could be placed
+        let mut resolved: Vec<(Uuid, bool)> = Vec::new();
+        let mut restored: Vec<(&BundleMod, Uuid)> = Vec::new();
+        let mut matched = Vec::new();
+        let mut imported = Vec::new();
+        let mut missing = Vec::new();
+        for bundled in &bundle.mods {
+            if let Some(local) = by_fingerprint.get(&bundled.fingerprint) {
+                matched.push(*local);
+                resolved.push((*local, bundled.enabled));
+                continue;
+            }
+            let Some(entry) = &bundled.archive else {
+                missing.push(Self::missing(bundled));
+                continue;
+            };
+
+            let staged = staging_root.join(format!("{}.bundle.zip", Uuid::new_v4()));
+            let result = match Self::extract_entry(bundle_path, entry, &staged) {
+                Ok(()) => {
+                    let options = ImportOptions {
+                        replace: None,
+                        skip_active_profile: true,
+                        ..options.clone()
+                    };
+                    Importer::import_mod_with_options(
+                        librarian,
+                        staged.clone(),
+                        game_id.to_string(),
+                        options,
+                    )
+                    .await
+                }
+                Err(e) => Err(e),
+            };
+            let _ = std::fs::remove_file(&staged);
+            match result {
+                Ok(record) => {
+                    imported.push(record.id);
+                    resolved.push((record.id, bundled.enabled));
+                    restored.push((bundled, record.id));
+                }
+                Err(e) => {
+                    eprintln!(
+                        "Librarian WARNING: Failed to import {} from bundle: {}",
+                        bundled.meta.name, e
+                    );
+                    missing.push(Self::missing(bundled));
+                }
+            }
+        }
+
+        // Embedded mods were imported under their archive name; restore what the
+        // exporting library knew about them
+        let mut db = librarian.load_game_db(game_id).await?;
+        for (bundled, id) in &restored {
+            let Some(record) = db.mods.get_mut(id) else {
+                continue;
+            };
+            record.meta = ModMetadata {
+                updated_at: record.meta.updated_at,
+                ..bundled.meta.clone()
+            };
+            if !bundled.tags.is_empty() {
+                record.config.tags = bundled.tags.clone();
+            }
+            if record.compatibility.character == "Unknown" {
+                record.compatibility.character = bundled.character.clone();
+            }
+        }
+
+        let taken: Vec<&str> = db.profiles.values().map(|p| p.name.as_str()).collect();
+        let name = if taken.contains(&bundle.profile.name.as_str()) {
+            format!("{} (imported)", bundle.profile.name)
+        } else {
+            bundle.profile.name.clone()
+        };
+        let settings = &bundle.profile;
+        let profile = Profile {
+            name,
+            description: settings.description.clone(),
+            load_order: resolved.iter().map(|(id, _)| *id).collect(),
+            enabled_mod_ids: resolved
+                .iter()
+                .filter(|(_, enabled)| *enabled)
+                .map(|(id, _)| *id)
+                .collect(),
+            use_gamescope: settings.use_gamescope,
+            use_gamemode: settings.use_gamemode,
+            use_mangohud: settings.use_mangohud,
+            use_reshade: settings.use_reshade,
+            resolution: settings.resolution,
+            launch_args: settings.launch_args.clone(),
+            ..Default::default()
+        };
+        db.profiles.insert(profile.id, profile.clone());
+        librarian.save_game_db(game_id, &db).await?;
+
+        Ok(BundleImportReport {
+            profile,
+            matched,
+            imported,
+            missing,
+        })
+    }
+
+    /// Fingerprints of the game's mods, computing (and saving) any that are missing.
+    async fn local_fingerprints(
+        librarian: &Librarian,
+        game_id: &str,
+    ) -> Result<HashMap<String, Uuid>> {
+        let mut db = librarian.load_game_db(game_id).await?;
+        if DuplicateFinder::fill_fingerprints(&mut db) {
+            librarian.save_game_db(game_id, &db).await?;
+        }
+
+        let mut mods: Vec<_> = db.mods.values().collect();
+        mods.sort_by_key(|m| m.added_at);
+        let mut by_fingerprint = HashMap::new();
+        for record in mods {
+            if !record.compatibility.fingerprint.is_empty() {
+                by_fingerprint
+                    .entry(record.compatibility.fingerprint.clone())
+                    .or_insert(record.id);
+            }
+        }
+        Ok(by_fingerprint)
+    }
+
+    fn missing(bundled: &BundleMod) -> MissingMod {
+        MissingMod {
+            name: bundled.meta.name.clone(),
+            version: bundled.meta.version.clone(),
+            url: bundled.meta.url.clone(),
+            fingerprint: bundled.fingerprint.clone(),
+        }
+    }
+
+    /// Copies an embedded mod zip out of the bundle. Only entries named by the
+    /// exporter's scheme are read, whatever the manifest claims.
+    fn extract_entry(bundle_path: &Path, entry: &str, destination: &Path) -> Result<()> {
+        let valid = entry
+            .strip_prefix("mods/")
+            .and_then(|name| name.strip_suffix(".zip"))
+            .is_some_and(|id| Uuid::parse_str(id).is_ok());
+        if !valid {
+            return Err(LibrarianError::Validation(format!(
+                "Invalid bundle entry {}",
+                entry
+            )));
+        }
+        let mut archive = open_bundle(bundle_path)?;
+        let mut source = archive.by_name(entry).map_err(zip_error)?;
+        let mut file = std::fs::File::create(destination)?;
+        std::io::copy(&mut source, &mut file)?;
+        Ok(())
+    }
+}
+
+fn open_bundle(path: &Path) -> Result<zip::ZipArchive<std::fs::File>> {
+    zip::ZipArchive::new(std::fs::File::open(path)?).map_err(zip_error)
+}
+
+/// Zips the files under `mod_root` into `destination`.
+fn write_mod_zip(mod_root: &Path, destination: &Path) -> Result<()> {
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(destination)?);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated)
+        .large_file(true);
+    for entry in walkdir::WalkDir::new(mod_root)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+    {
+        let Ok(relative) = entry.path().strip_prefix(mod_root) else {
+            continue;
+        };
+        let name = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect::<Vec<_>>()
+            .join("/");
+        zip.start_file(name, options).map_err(zip_error)?;
+        std::io::copy(&mut std::fs::File::open(entry.path())?, &mut zip)?;
+    }
+    zip.finish().map_err(zip_error)?;
+    Ok(())
+}
+
+fn temp_path(destination: &Path) -> PathBuf {
+    let name = destination
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "bundle".to_string());
+    destination.with_file_name(format!(".{}.partial", name))
+}
+
+fn zip_error(e: zip::result::ZipError) -> LibrarianError {
+    LibrarianError::Io(std::io::Error::other(e))
+}
@@ -1,4 +1,5 @@
 use crate::error::{LibrarianError, Result};
+use crate::instances::GameInstances;
 use crate::models::{GameConfig, LibraryDatabase};
 use crate::storage::Librarian;
 use crate::template::GameTemplate;
@@ -11,11 +12,23 @@ pub struct Discovery;
 
 impl Discovery {
     /// Adds a game to the library by its executable path.
-    /// Returns the GameID (normalized exe name).
+    /// Returns the GameID (normalized exe name, see [`Discovery::add_instance`]).
     pub async fn add_game_by_path(
         librarian: &Librarian,
         path: PathBuf,
         templates: &HashMap<String, GameTemplate>,
+    ) -> Result<String> {
+        Self::add_instance(librarian, path, templates, None).await
+    }
+
+    /// Adds the install at `path` as a game instance of `region`, or of the region its
+    /// executable belongs to. An install already in the library keeps its id; another
+    /// install of the same game gets an id of its own.
+    pub async fn add_instance(
+        librarian: &Librarian,
+        path: PathBuf,
+        templates: &HashMap<String, GameTemplate>,
+        region: Option<String>,
     ) -> Result<String> {
         if !path.exists() {
             return Err(LibrarianError::Io(std::io::Error::new(
@@ -41,13 +54,44 @@ impl Discovery {
             .ok_or(LibrarianError::Validation("Invalid filename".to_string()))?
             .to_string();
 
-        // Normalize ID: lowercase filename
-        let game_id = exe_name.to_lowercase();
+        if let Some(game_id) = GameInstances::find_by_exe(librarian, &path).await? {
+            return Ok(game_id);
+        }
+
+        // Template Lookup
+        // Try exact match (genshinimpact.exe) then stem match (genshinimpact)
+        // Renamed executables still carry their OriginalFilename in the version resource
+        let exe_id = exe_name.to_lowercase();
+        let pe_info = ExeInspector::inspect(&path).ok();
+        let original_name = pe_info
+            .as_ref()
+            .and_then(|info| info.version.as_ref())
+            .and_then(|v| v.original_filename())
+            .map(|n| n.to_lowercase());
+        let template = Self::find_template(templates, &exe_id).or_else(|| {
+            original_name
+                .as_deref()
+                .and_then(|name| Self::find_template(templates, name))
+        });
+        if let (Some(info), Some(t)) = (&pe_info, template) {
+            println!(
+                "Discovery: Identified {} as '{}' ({:?}, {:?})",
+                exe_name, t.name, info.machine, info.subsystem
+            );
+        }
+        let region = region.or_else(|| {
+            template
+                .and_then(|t| t.region_for_exe(&exe_name))
+                .map(|r| r.id.clone())
+        });
+
+        // Normalize ID: lowercase filename, made unique among the installs
+        let wanted = GameInstances::instance_id(&exe_id, region.as_deref());
+        let game_id = GameInstances::unique_id(librarian, &wanted).await?;
 
         // 3. Initialization
         let game_dir = librarian.games_root.join(&game_id);
         let mods_dir = game_dir.join("mods");
-        let db_path = game_dir.join("game.json");
 
         if !game_dir.exists() {
             std::fs::create_dir_all(&game_dir)?;
@@ -56,19 +100,13 @@ impl Discovery {
             std::fs::create_dir_all(&mods_dir)?;
         }
 
-        if !db_path.exists() {
+        if !librarian.has_game_db(&game_id)? {
             let default_profile = crate::models::Profile::default();
             let p_id = default_profile.id;
 
-            // Template Lookup
-            // Try exact match (genshinimpact.exe) then stem match (genshinimpact)
-            let template = templates.get(&game_id).or_else(|| {
-                if game_id.ends_with(".exe") {
-                    templates.get(game_id.trim_end_matches(".exe"))
-                } else {
-                    None
-                }
-            });
+            // config.ini first, then the executable's own version resource
+            let version =
+                ExeInspector::get_version(&path).unwrap_or_else(|_| "Unknown".to_string());
 
             // Calculate Size
             let install_dir = path.parent().unwrap_or(&path).to_path_buf();
@@ -93,7 +131,7 @@ impl Discovery {
                 install_path: install_dir,
                 exe_path: path.clone(),
                 exe_name: exe_name.clone(),
-                version: "Unknown".to_string(),
+                version,
                 remote_version: None,
                 installed_components: vec![],
                 size: size_str,
@@ -140,6 +178,9 @@ impl Discovery {
                     .and_then(|t| t.supported_injection_methods.clone())
                     .unwrap_or_default(),
                 remote_info: None,
+                template_id: template.map(|t| t.id.clone()),
+                region,
+                mod_pool: None,
             };
 
             let mut db = LibraryDatabase::default();
@@ -152,4 +193,15 @@ impl Discovery {
 
         Ok(game_id)
     }
+
+    /// Exact match (genshinimpact.exe), then stem match (genshinimpact).
+    fn find_template<'a>(
+        templates: &'a HashMap<String, GameTemplate>,
+        name: &str,
+    ) -> Option<&'a GameTemplate> {
+        templates.get(name).or_else(|| {
+            name.strip_suffix(".exe")
+                .and_then(|stem| templates.get(stem))
+        })
+    }
 }
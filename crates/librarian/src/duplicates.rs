@@ -0,0 +1,184 @@
+//! Duplicate detection.
+//!
+//! Two mods are identical when their fingerprints match. Near-duplicates (a re-upload
+//! with one texture fixed, the same mod repacked under another name) are scored by
+//! how many texture contents and INI `hash =` overrides they share.
+
+use crate::error::Result;
+use crate::fingerprint::ModFingerprint;
+use crate::models::{LibraryDatabase, ModCompatibility, ModRecord};
+use crate::Librarian;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use uuid::Uuid;
+
+/// Similarity from which an import is reported as a likely duplicate.
+pub const DUPLICATE_THRESHOLD: f32 = 0.9;
+
+/// What an import does when the archive looks like a mod already in the library.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicatePolicy {
+    /// Import it as a separate mod.
+    #[default]
+    KeepBoth,
+    /// Stop with [`crate::LibrarianError::PossibleDuplicate`] so the user can choose
+    /// between replacing the existing mod and keeping both.
+    Ask,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DuplicateMatch {
+    pub mod_id: Uuid,
+    pub name: String,
+    /// 0.0 to 1.0; 1.0 for identical files.
+    pub similarity: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DuplicatePair {
+    pub first: DuplicateMatch,
+    pub second: DuplicateMatch,
+    pub similarity: f32,
+}
+
+pub struct DuplicateFinder;
+
+impl DuplicateFinder {
+    /// How alike two mods are, from 0.0 to 1.0. Identical fingerprints score 1.0;
+    /// otherwise the overlap of texture contents and of INI hashes are averaged,
+    /// leaving out whichever neither mod has.
+    pub fn similarity(a: &ModCompatibility, b: &ModCompatibility) -> f32 {
+        if !a.fingerprint.is_empty() && a.fingerprint == b.fingerprint {
+            return 1.0;
+        }
+        let scores: Vec<f32> = [
+            jaccard(&a.texture_hashes, &b.texture_hashes),
+            jaccard(&a.hashes, &b.hashes),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        if scores.is_empty() {
+            return 0.0;
+        }
+        scores.iter().sum::<f32>() / scores.len() as f32
+    }
+
+    /// The mod in `db` most like `candidate`, if any reaches [`DUPLICATE_THRESHOLD`].
+    pub fn best_match(
+        db: &LibraryDatabase,
+        candidate: &ModCompatibility,
+    ) -> Option<DuplicateMatch> {
+        db.mods
+            .values()
+            .map(|record| (record, Self::similarity(candidate, &record.compatibility)))
+            .filter(|(_, similarity)| *similarity >= DUPLICATE_THRESHOLD)
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(record, similarity)| Self::describe(record, similarity))
+    }
+
+    /// Every pair of the game's mods at or above `threshold`, most similar first.
+    /// Mods imported before fingerprinting are fingerprinted (and saved) first.
+    pub async fn report(
+        librarian: &Librarian,
+        game_id: &str,
+        threshold: f32,
+    ) -> Result<Vec<DuplicatePair>> {
+        let mut db = librarian.load_game_db(game_id).await?;
+        if Self::fill_fingerprints(&mut db) {
+            librarian.save_game_db(game_id, &db).await?;
+        }
+
+        let mut mods: Vec<&ModRecord> = db.mods.values().collect();
+        mods.sort_by_key(|m| m.added_at);
+
+        // Only mods sharing a fingerprint, texture or INI hash can score above zero
+        let mut index: HashMap<String, Vec<usize>> = HashMap::new();
+        for (i, record) in mods.iter().enumerate() {
+            let compatibility = &record.compatibility;
+            let keys = std::iter::once(format!("f:{}", compatibility.fingerprint))
+                .filter(|_| !compatibility.fingerprint.is_empty())
+                .chain(
+                    compatibility
+                        .texture_hashes
+                        .iter()
+                        .map(|h| format!("t:{}", h)),
+                )
+                .chain(
+                    compatibility
+                        .hashes
+                        .iter()
+                        .map(|h| format!("h:{}", h.to_lowercase())),
+                );
+            for key in keys.collect::<BTreeSet<_>>() {
+                index.entry(key).or_default().push(i);
+            }
+        }
+        let mut candidates = BTreeSet::new();
+        for members in index.values() {
+            for (n, &a) in members.iter().enumerate() {
+                for &b in &members[n + 1..] {
+                    candidates.insert((a, b));
+                }
+            }
+        }
+
+        let mut pairs: Vec<DuplicatePair> = candidates
+            .into_iter()
+            .filter_map(|(a, b)| {
+                let similarity = Self::similarity(&mods[a].compatibility, &mods[b].compatibility);
+                (similarity >= threshold).then(|| DuplicatePair {
+                    first: Self::describe(mods[a], similarity),
+                    second: Self::describe(mods[b], similarity),
+                    similarity,
+                })
+            })
+            .collect();
+        pairs.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+        Ok(pairs)
+    }
+
+    /// Computes fingerprints and texture hashes for records that predate them.
+    /// Returns whether any record changed.
+    pub fn fill_fingerprints(db: &mut LibraryDatabase) -> bool {
+        let mut changed = false;
+        for record in db.mods.values_mut() {
+            if !record.compatibility.fingerprint.is_empty() || !record.path.is_dir() {
+                continue;
+            }
+            match ModFingerprint::files(&record.path) {
+                Ok(files) => {
+                    record.compatibility.fingerprint = ModFingerprint::of_files(&files);
+                    record.compatibility.texture_hashes = ModFingerprint::texture_hashes(&files);
+                    changed = true;
+                }
+                Err(e) => eprintln!(
+                    "Librarian WARNING: Failed to fingerprint {}: {}",
+                    record.meta.name, e
+                ),
+            }
+        }
+        changed
+    }
+
+    fn describe(record: &ModRecord, similarity: f32) -> DuplicateMatch {
+        DuplicateMatch {
+            mod_id: record.id,
+            name: record.meta.name.clone(),
+            similarity,
+        }
+    }
+}
+
+/// Share of distinct values the lists have in common (case-insensitive), or `None`
+/// when both are empty.
+fn jaccard(a: &[String], b: &[String]) -> Option<f32> {
+    let a: HashSet<String> = a.iter().map(|h| h.to_lowercase()).collect();
+    let b: HashSet<String> = b.iter().map(|h| h.to_lowercase()).collect();
+    let union = a.union(&b).count();
+    if union == 0 {
+        return None;
+    }
+    Some(a.intersection(&b).count() as f32 / union as f32)
+}
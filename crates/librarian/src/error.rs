@@ -11,6 +11,12 @@ pub enum LibrarianError {
     #[error("Network error: {0}")]
     Network(#[from] reqwest::Error),
 
+    #[error("Database error: {0}")]
+    Database(#[from] rusqlite::Error),
+
+    #[error("Library database unavailable: {0}")]
+    BackendUnavailable(String),
+
     #[error("Internal Engine error: {0}")]
     Fs(#[from] fs_engine::FsError),
 
@@ -23,8 +29,18 @@ pub enum LibrarianError {
     #[error("Validation error: {0}")]
     Validation(String),
 
+    #[error("Download failed: {0}")]
+    Download(String),
+
     #[error("File imported as ReShade Preset: {0}")]
     ImportedPreset(String),
+
+    #[error(
+        "This looks like an existing mod: {} ({:.0}% identical)",
+        .0.name,
+        .0.similarity * 100.0
+    )]
+    PossibleDuplicate(crate::duplicates::DuplicateMatch),
 }
 
 pub type Result<T> = std::result::Result<T, LibrarianError>;
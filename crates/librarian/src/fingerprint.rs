@@ -0,0 +1,106 @@
+//! Content fingerprints of mod folders.
+//!
+//! A fingerprint hashes every meaningful file's relative path and contents, so the
+//! same mod extracted on two machines (or imported twice) gets the same value
+//! regardless of where it lives, what metadata YAGO stored next to it or which
+//! preview image the author shipped. Texture hashes feed the fuzzier similarity
+//! checks in [`crate::duplicates`].
+
+use crate::error::Result;
+use fs_engine::BlobStore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+/// Metadata files YAGO writes or rewrites itself; they do not make a mod different.
+const IGNORED_FILES: &[&str] = &["mod.json", "modinfo.json"];
+
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp"];
+
+const TEXTURE_EXTENSIONS: &[&str] = &["dds", "png", "jpg", "jpeg"];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileHash {
+    /// Relative to the mod root, lowercase with `/` separators.
+    pub path: String,
+    pub size: u64,
+    /// Lowercase hex SHA-256 of the contents.
+    pub sha256: String,
+}
+
+pub struct ModFingerprint;
+
+impl ModFingerprint {
+    /// Hashes of all content files under `dir`, sorted by path.
+    pub fn files(dir: &Path) -> Result<Vec<FileHash>> {
+        let mut files = Vec::new();
+        for entry in walkdir::WalkDir::new(dir)
+            .into_iter()
+            .filter_map(|e| e.ok())
+        {
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let Ok(relative) = entry.path().strip_prefix(dir) else {
+                continue;
+            };
+            let path = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().to_lowercase())
+                .collect::<Vec<_>>()
+                .join("/");
+            if IGNORED_FILES.contains(&path.as_str()) || Self::is_preview(&path) {
+                continue;
+            }
+            files.push(FileHash {
+                path,
+                size: entry.metadata().map(|m| m.len()).unwrap_or(0),
+                sha256: BlobStore::hash_file(entry.path())?,
+            });
+        }
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(files)
+    }
+
+    /// Fingerprint of a file list as returned by [`ModFingerprint::files`].
+    pub fn of_files(files: &[FileHash]) -> String {
+        let mut hasher = Sha256::new();
+        for file in files {
+            hasher.update(file.path.as_bytes());
+            hasher.update([0]);
+            hasher.update(file.sha256.as_bytes());
+            hasher.update(b"\n");
+        }
+        hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    pub fn of_dir(dir: &Path) -> Result<String> {
+        Ok(Self::of_files(&Self::files(dir)?))
+    }
+
+    /// Distinct content hashes of the textures in a file list, sorted.
+    pub fn texture_hashes(files: &[FileHash]) -> Vec<String> {
+        let mut hashes: Vec<String> = files
+            .iter()
+            .filter(|f| TEXTURE_EXTENSIONS.contains(&Self::extension(&f.path)))
+            .map(|f| f.sha256.clone())
+            .collect();
+        hashes.sort();
+        hashes.dedup();
+        hashes
+    }
+
+    /// Screenshots shipped for mod pages (`preview.png`, `Preview_2.jpg`, ...).
+    fn is_preview(path: &str) -> bool {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        name.starts_with("preview") && IMAGE_EXTENSIONS.contains(&Self::extension(name))
+    }
+
+    fn extension(path: &str) -> &str {
+        path.rsplit_once('.').map(|(_, ext)| ext).unwrap_or("")
+    }
+}
@@ -3,22 +3,220 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::Path;
 
+/// Share of a mod's recognised hashes a character needs to count as one of the
+/// characters it touches, unless it is the strongest match anyway.
+const MIN_CHARACTER_SHARE: f32 = 0.25;
+
+/// Character hash database for one game (`hashes/<game>.json`, or `hashes.json`).
+///
+/// Older databases only have the flat `characters` map; richer ones describe each hash
+/// in `hashes` and the characters themselves in `roster`. Both may be present, with
+/// `hashes` winning for a hash listed twice.
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct HashIndex {
+    #[serde(default)]
     pub characters: HashMap<String, String>, // hash -> Character Name
+    #[serde(default)]
+    pub hashes: HashMap<String, HashEntry>,
+    #[serde(default)]
+    pub roster: HashMap<String, CharacterInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HashEntry {
+    pub character: String,
+    /// Part of the model the hash belongs to: "body", "face", "hair", "weapon", ...
+    #[serde(default)]
+    pub component: Option<String>,
+    #[serde(default)]
+    pub buffer: Option<BufferType>,
+    /// Game versions the hash is valid for, matched by prefix ("4.1" covers "4.1.0").
+    /// Empty for all versions.
+    #[serde(default)]
+    pub versions: Vec<String>,
+}
+
+/// Which 3DMigoto resource a hash identifies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BufferType {
+    Ib,
+    Position,
+    Blend,
+    Texcoord,
+    Texture,
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CharacterInfo {
+    /// Other names mods and hash lists use for the character.
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub element: Option<String>,
+    #[serde(default)]
+    pub rarity: Option<u8>,
+}
+
+/// Result of voting over every hash found in a mod.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Identification {
+    /// Characters the mod touches, most votes first.
+    pub characters: Vec<CharacterVote>,
+    /// Distinct hashes the database recognised for this game version.
+    pub matched: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CharacterVote {
+    pub name: String,
+    pub votes: usize,
+    /// Components the mod replaces, sorted.
+    pub components: Vec<String>,
+}
+
+impl Identification {
+    /// The character with the most votes.
+    pub fn primary(&self) -> Option<&str> {
+        self.characters.first().map(|c| c.name.as_str())
+    }
+
+    /// Share of the recognised hashes that voted for the primary character, 0.0 if
+    /// none were recognised.
+    pub fn confidence(&self) -> f32 {
+        match self.characters.first() {
+            Some(top) if self.matched > 0 => top.votes as f32 / self.matched as f32,
+            _ => 0.0,
+        }
+    }
 }
 
 impl HashIndex {
+    /// The game's own database (`hashes/<game>.json`) if it has one, else the global
+    /// `hashes.json`. Missing or unreadable files give an empty index.
+    pub fn for_game(assets_root: &Path, game_id: &str) -> Self {
+        let game_hash_path = assets_root.join("hashes").join(format!("{}.json", game_id));
+        let global_hash_path = assets_root.join("hashes.json");
+        if game_hash_path.exists() {
+            Self::load(&game_hash_path).unwrap_or_default()
+        } else {
+            Self::load(&global_hash_path).unwrap_or_default()
+        }
+    }
+
     pub fn load(path: &Path) -> Result<Self> {
         if !path.exists() {
             return Ok(Self::default());
         }
         let content = std::fs::read_to_string(path)?;
-        let index: HashIndex = serde_json::from_str(&content)?;
+        let mut index: HashIndex = serde_json::from_str(&content)?;
+        // Mods write hashes in either case
+        index.characters = std::mem::take(&mut index.characters)
+            .into_iter()
+            .map(|(hash, name)| (hash.to_lowercase(), name))
+            .collect();
+        index.hashes = std::mem::take(&mut index.hashes)
+            .into_iter()
+            .map(|(hash, entry)| (hash.to_lowercase(), entry))
+            .collect();
         Ok(index)
     }
 
     pub fn identify(&self, hash: &str) -> Option<String> {
-        self.characters.get(hash).cloned()
+        self.lookup(hash)
+            .map(|entry| self.canonical_name(&entry.character))
+    }
+
+    /// Everything known about a hash. Entries from the flat map only name the character.
+    pub fn lookup(&self, hash: &str) -> Option<HashEntry> {
+        let hash = hash.to_lowercase();
+        self.hashes.get(&hash).cloned().or_else(|| {
+            self.characters.get(&hash).map(|name| HashEntry {
+                character: name.clone(),
+                component: None,
+                buffer: None,
+                versions: Vec::new(),
+            })
+        })
+    }
+
+    /// The roster name for a character name or one of its aliases.
+    pub fn canonical_name(&self, name: &str) -> String {
+        if self.roster.contains_key(name) {
+            return name.to_string();
+        }
+        self.roster
+            .iter()
+            .find(|(_, info)| info.aliases.iter().any(|a| a.eq_ignore_ascii_case(name)))
+            .map(|(canonical, _)| canonical.clone())
+            .unwrap_or_else(|| name.to_string())
+    }
+
+    pub fn character_info(&self, name: &str) -> Option<&CharacterInfo> {
+        self.roster.get(&self.canonical_name(name))
+    }
+
+    /// Votes each distinct recognised hash for its character. Hashes listed only for
+    /// other game versions are ignored; without a version every hash counts.
+    pub fn identify_mod(&self, hashes: &[String], game_version: Option<&str>) -> Identification {
+        let mut seen = std::collections::HashSet::new();
+        let mut votes: HashMap<String, CharacterVote> = HashMap::new();
+        let mut matched = 0;
+
+        for hash in hashes {
+            if !seen.insert(hash.to_lowercase()) {
+                continue;
+            }
+            let Some(entry) = self.lookup(hash) else {
+                continue;
+            };
+            if !Self::valid_for(&entry, game_version) {
+                continue;
+            }
+            matched += 1;
+            let name = self.canonical_name(&entry.character);
+            let vote = votes.entry(name.clone()).or_insert_with(|| CharacterVote {
+                name,
+                votes: 0,
+                components: Vec::new(),
+            });
+            vote.votes += 1;
+            if let Some(component) = entry.component {
+                if !vote.components.contains(&component) {
+                    vote.components.push(component);
+                }
+            }
+        }
+
+        let mut characters: Vec<CharacterVote> = votes.into_values().collect();
+        for character in &mut characters {
+            character.components.sort();
+        }
+        characters.sort_by(|a, b| b.votes.cmp(&a.votes).then_with(|| a.name.cmp(&b.name)));
+        // Stray hashes shared with another character should not list it
+        let strongest = characters.first().map(|c| c.votes).unwrap_or(0);
+        characters.retain(|c| {
+            c.votes == strongest || c.votes as f32 / matched as f32 >= MIN_CHARACTER_SHARE
+        });
+
+        Identification {
+            characters,
+            matched,
+        }
+    }
+
+    fn valid_for(entry: &HashEntry, game_version: Option<&str>) -> bool {
+        let Some(version) = game_version.filter(|v| !v.is_empty()) else {
+            return true;
+        };
+        entry.versions.is_empty()
+            || entry.versions.iter().any(|v| {
+                version == v
+                    || version
+                        .strip_prefix(v.as_str())
+                        .is_some_and(|rest| rest.starts_with('.'))
+            })
     }
 }
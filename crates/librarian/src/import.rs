@@ -1,29 +1,417 @@
+use crate::duplicates::{DuplicateFinder, DuplicatePolicy};
 use crate::error::{LibrarianError, Result};
-use crate::gamedata::hash_db::HashIndex;
-use crate::models::{ModCompatibility, ModConfig, ModMetadata, ModRecord};
+use crate::fingerprint::ModFingerprint;
+use crate::gamedata::hash_db::{HashIndex, Identification};
+use crate::keybinds::KeybindExtractor;
+use crate::models::{
+    Keybind, ModCompatibility, ModConfig, ModMetadata, ModRecord, ModSource, ModVersion,
+};
+use crate::passwords::{hash_file, PasswordStore};
+use crate::tagging::AutoTagger;
+use crate::versions::VersionHistory;
 use crate::Librarian;
 use chrono::Utc;
-use fs_engine::{extract_and_sanitize, Safety};
+use fs_engine::{
+    extract_and_sanitize_with, CancelToken, CaseNormalizer, ExtractOptions, ExtractionReport,
+    FsError, Safety, Transcoder,
+};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::io::BufRead;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use uuid::Uuid;
 
+/// Knobs for a single import. `Default` gives the same behaviour as [`Importer::import_mod`].
+#[derive(Clone, Default)]
+pub struct ImportOptions {
+    /// Progress callback, cancel token, zip-bomb limits and archive password for the
+    /// extraction step. A password that works is remembered for the source file.
+    pub extraction: ExtractOptions,
+    /// Repair normal maps with swapped or inverted channels (ZZZ 1.3+ broke older mods).
+    pub fix_normal_maps: bool,
+    /// Lowercase all file names and fix up INI references, for case-sensitive filesystems.
+    pub normalize_filenames: bool,
+    /// Link large files into the library's blob store instead of keeping private copies.
+    pub deduplicate: bool,
+    /// Install the archive as a new release of this mod: its id, folder, profile
+    /// membership, load order, tags and user-edited metadata are kept, and the
+    /// release it replaces is archived in the mod's version history.
+    pub replace: Option<Uuid>,
+    /// Leave the active profile alone, for callers that place the mod in profiles themselves.
+    pub skip_active_profile: bool,
+    /// Whether to stop when the archive looks like a mod already in the library.
+    pub duplicates: DuplicatePolicy,
+    /// Told when the import moves on to another step.
+    pub on_stage: Option<StageCallback>,
+    /// Named this when it ships no metadata, instead of after the archive or folder.
+    pub name: Option<String>,
+}
+
+impl ImportOptions {
+    fn stage(&self, stage: ImportStage) {
+        if let Some(cb) = &self.on_stage {
+            cb(stage);
+        }
+    }
+}
+
+/// Steps of an import, as reported to [`ImportOptions::on_stage`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportStage {
+    Downloading,
+    Extracting,
+    Identifying,
+}
+
+pub type StageCallback = Arc<dyn Fn(ImportStage) + Send + Sync>;
+
+/// What [`Importer::import_pack`] made of an archive. Mods committed before one of the
+/// pack failed stay imported, so a failure can come back alongside records.
+#[derive(Debug, Default)]
+pub struct PackImport {
+    pub records: Vec<ModRecord>,
+    pub failures: Vec<PackFailure>,
+}
+
+impl From<ModRecord> for PackImport {
+    fn from(record: ModRecord) -> Self {
+        Self {
+            records: vec![record],
+            failures: Vec::new(),
+        }
+    }
+}
+
+/// A mod of a pack that was not imported.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackFailure {
+    /// Folder inside the archive, as in [`ModSource::root`].
+    pub root: String,
+    pub error: String,
+}
+
+/// What [`Importer::identify_character_and_type`] read out of a mod's INI files.
+pub(crate) struct ModIdentity {
+    /// "Unknown" when no hash was recognised.
+    pub character: String,
+    pub mod_type: String,
+    pub hashes: Vec<String>,
+    pub identification: Identification,
+}
+
+impl ModIdentity {
+    /// Stores the identification on a record. A known character is kept when the new
+    /// files cannot be identified, since the user may have set it by hand.
+    pub(crate) fn apply(self, compatibility: &mut ModCompatibility) {
+        if self.character != "Unknown" || compatibility.character.is_empty() {
+            compatibility.character = self.character;
+        }
+        compatibility.hashes = self.hashes;
+        compatibility.confidence = self.identification.confidence();
+        compatibility.characters = self
+            .identification
+            .characters
+            .into_iter()
+            .map(|c| c.name)
+            .collect();
+    }
+}
+
 pub struct Importer;
 
 impl Importer {
-    /// Imports a mod archive (zip/7z) into the library transactionally.
+    /// Imports a mod archive (zip/7z) or folder into the library transactionally.
     pub async fn import_mod(
         librarian: &Librarian,
         archive_path: PathBuf,
         game_id: String,
     ) -> Result<ModRecord> {
-        let file_name = archive_path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("Unknown Mod")
-            .to_string();
+        Self::import_mod_with_options(librarian, archive_path, game_id, ImportOptions::default())
+            .await
+    }
+
+    /// Imports an already extracted mod folder. The folder is copied, not moved, and
+    /// filtered the same way as an archive.
+    pub async fn import_folder(
+        librarian: &Librarian,
+        folder: PathBuf,
+        game_id: String,
+        options: ImportOptions,
+    ) -> Result<ModRecord> {
+        Self::check_folder(&folder)?;
+        Self::import_mod_with_options(librarian, folder, game_id, options).await
+    }
+
+    /// Like [`Importer::import_folder`], but a folder holding several mods is split the
+    /// way [`Importer::import_pack`] splits an archive.
+    pub async fn import_folder_pack(
+        librarian: &Librarian,
+        folder: PathBuf,
+        game_id: String,
+        options: ImportOptions,
+    ) -> Result<PackImport> {
+        Self::check_folder(&folder)?;
+        Self::import_pack(librarian, folder, game_id, options).await
+    }
+
+    fn check_folder(folder: &Path) -> Result<()> {
+        if !folder.is_dir() {
+            return Err(LibrarianError::Validation(format!(
+                "Not a folder: {}",
+                folder.display()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Downloads a mod archive from a direct link and imports it. `on_download` gets the
+    /// bytes received so far and the total. The download is deleted afterwards, and the
+    /// extraction cancel token also stops the download.
+    pub async fn import_url<F>(
+        librarian: &Librarian,
+        url: &str,
+        game_id: String,
+        options: ImportOptions,
+        on_download: F,
+    ) -> Result<ModRecord>
+    where
+        F: FnMut(u64, u64) + Send,
+    {
+        Self::download_then(librarian, url, options, on_download, |path, options| {
+            Self::import_mod_with_options(librarian, path, game_id, options)
+        })
+        .await
+    }
+
+    /// Like [`Importer::import_url`], but a download holding several mods is split the
+    /// way [`Importer::import_pack`] splits an archive.
+    pub async fn import_url_pack<F>(
+        librarian: &Librarian,
+        url: &str,
+        game_id: String,
+        options: ImportOptions,
+        on_download: F,
+    ) -> Result<PackImport>
+    where
+        F: FnMut(u64, u64) + Send,
+    {
+        Self::download_then(librarian, url, options, on_download, |path, options| {
+            Self::import_pack(librarian, path, game_id, options)
+        })
+        .await
+    }
+
+    /// Downloads `url` into its own staging folder, hands the archive to `import` and
+    /// deletes the download whatever the outcome.
+    async fn download_then<T, F, I, Fut>(
+        librarian: &Librarian,
+        url: &str,
+        options: ImportOptions,
+        on_download: F,
+        import: I,
+    ) -> Result<T>
+    where
+        F: FnMut(u64, u64) + Send,
+        I: FnOnce(PathBuf, ImportOptions) -> Fut,
+        Fut: std::future::Future<Output = Result<T>>,
+    {
+        let download_dir = librarian
+            .games_root
+            .join(".yago_staging")
+            .join("downloads")
+            .join(Uuid::new_v4().to_string());
+        let archive_path = download_dir.join(Self::file_name_from_url(url));
 
+        options.stage(ImportStage::Downloading);
+        let cancel = options.extraction.cancel.clone();
+        let downloaded = tokio::select! {
+            result = quartermaster::download_file(url, &archive_path, on_download) => {
+                result.map_err(|e| LibrarianError::Download(e.to_string()))
+            }
+            _ = Self::cancelled(&cancel) => Err(LibrarianError::Fs(FsError::Cancelled)),
+        };
+        let result = match downloaded {
+            Ok(()) => import(archive_path, options).await,
+            Err(e) => Err(e),
+        };
+        let _ = std::fs::remove_dir_all(&download_dir);
+        result
+    }
+
+    /// Like [`Importer::import_mod`], but with progress, cancellation and extraction limits.
+    pub async fn import_mod_with_options(
+        librarian: &Librarian,
+        archive_path: PathBuf,
+        game_id: String,
+        options: ImportOptions,
+    ) -> Result<ModRecord> {
+        let (staging_dir, source) =
+            Self::stage(librarian, &archive_path, &game_id, &options).await?;
+        let name = options
+            .name
+            .clone()
+            .unwrap_or_else(|| Self::source_name(&archive_path));
+        Self::commit(
+            librarian,
+            &staging_dir,
+            &name,
+            &archive_path,
+            &game_id,
+            source,
+            &options,
+        )
+        .await
+    }
+
+    /// Like [`Importer::import_mod_with_options`], but an archive holding several
+    /// independent mods, such as an outfit pack, becomes one record per mod. The records
+    /// share the archive's [`ModSource`], each with its own `root`. A new release of an
+    /// existing mod is never split.
+    ///
+    /// Fails only when nothing was imported; otherwise the mods that could not be
+    /// imported are listed in [`PackImport::failures`].
+    pub async fn import_pack(
+        librarian: &Librarian,
+        archive_path: PathBuf,
+        game_id: String,
+        options: ImportOptions,
+    ) -> Result<PackImport> {
+        let (staging_dir, source) =
+            Self::stage(librarian, &archive_path, &game_id, &options).await?;
+        let roots = if options.replace.is_some() {
+            Vec::new()
+        } else {
+            Self::mod_roots(&staging_dir)
+        };
+        if roots.len() < 2 {
+            let name = options
+                .name
+                .clone()
+                .unwrap_or_else(|| Self::source_name(&archive_path));
+            return Self::commit(
+                librarian,
+                &staging_dir,
+                &name,
+                &archive_path,
+                &game_id,
+                source,
+                &options,
+            )
+            .await
+            .map(PackImport::from);
+        }
+        println!(
+            "Importer: {} holds {} separate mods",
+            archive_path.display(),
+            roots.len()
+        );
+
+        let relative_of = |root: &Path| {
+            root.strip_prefix(&staging_dir)
+                .unwrap_or(root)
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().to_string())
+                .collect::<Vec<_>>()
+                .join("/")
+        };
+        let mut pack = PackImport::default();
+        let mut first_error = None;
+        let mut roots = roots.into_iter();
+        for root in roots.by_ref() {
+            let relative = relative_of(&root);
+            let name = root
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| relative.clone());
+
+            // Each mod is committed from a staging folder of its own
+            let mod_staging = staging_dir.with_file_name(Uuid::new_v4().to_string());
+            let result = match std::fs::rename(&root, &mod_staging) {
+                Ok(()) => {
+                    let source = ModSource {
+                        root: relative.clone(),
+                        ..source.clone()
+                    };
+                    Self::commit(
+                        librarian,
+                        &mod_staging,
+                        &name,
+                        &archive_path,
+                        &game_id,
+                        source,
+                        &options,
+                    )
+                    .await
+                }
+                Err(e) => Err(e.into()),
+            };
+            let error = match result {
+                Ok(record) => {
+                    pack.records.push(record);
+                    continue;
+                }
+                Err(e) => e,
+            };
+            let _ = std::fs::remove_dir_all(&mod_staging);
+            // One outfit already in the library should not hold back the rest
+            let fatal = !matches!(error, LibrarianError::PossibleDuplicate(_));
+            if fatal {
+                eprintln!("Importer: {} of the pack failed: {}", relative, error);
+            } else {
+                println!("Importer: skipped {} of the pack: {}", relative, error);
+            }
+            pack.failures.push(PackFailure {
+                root: relative,
+                error: error.to_string(),
+            });
+            first_error.get_or_insert(error);
+            if fatal {
+                break;
+            }
+        }
+        for root in roots {
+            pack.failures.push(PackFailure {
+                root: relative_of(&root),
+                error: "Not imported after an earlier mod of the pack failed".to_string(),
+            });
+        }
+        let _ = std::fs::remove_dir_all(&staging_dir);
+
+        match first_error {
+            Some(e) if pack.records.is_empty() => Err(e),
+            _ => Ok(pack),
+        }
+    }
+
+    /// Runs [`Importer::stage_files`] on the blocking pool so a large archive does not
+    /// stall the other imports.
+    async fn stage(
+        librarian: &Librarian,
+        archive_path: &Path,
+        game_id: &str,
+        options: &ImportOptions,
+    ) -> Result<(PathBuf, ModSource)> {
+        let (librarian, archive_path, game_id, options) = (
+            librarian.clone(),
+            archive_path.to_path_buf(),
+            game_id.to_string(),
+            options.clone(),
+        );
+        Self::blocking(move || Self::stage_files(&librarian, &archive_path, &game_id, &options))
+            .await
+    }
+
+    /// Extracts (or copies) the source into a fresh staging folder and prepares the
+    /// files, returning the folder and where it came from. A ReShade preset is
+    /// installed right away and reported as an error.
+    fn stage_files(
+        librarian: &Librarian,
+        archive_path: &Path,
+        game_id: &str,
+        options: &ImportOptions,
+    ) -> Result<(PathBuf, ModSource)> {
         // Step A: Staging
         // We use the system temp dir, or a dedicated cache dir if we had access to app handle.
         // For Librarian, we might prefer a temp folder inside our library root to ensure atomic moves work (same filesystem).
@@ -36,10 +424,31 @@ impl Importer {
         let staging_dir = staging_root.join(staging_uuid.to_string());
         std::fs::create_dir_all(&staging_dir)?;
 
-        // Extract using strict sanitization
-        let extraction_report = extract_and_sanitize(&archive_path, &staging_dir).map_err(|e| {
+        // Extract using strict sanitization. Limit, cancellation and password errors stay
+        // typed so callers can tell them apart from a corrupt archive.
+        options.stage(ImportStage::Extracting);
+        // Hashed once, for the record and for the remembered password
+        let source = Self::source_of(archive_path);
+        let extracted = if archive_path.is_dir() {
+            fs_engine::copy_and_sanitize_with(archive_path, &staging_dir, &options.extraction)
+        } else {
+            Self::extract_to_staging(
+                librarian,
+                archive_path,
+                &source.archive_hash,
+                &staging_dir,
+                &options.extraction,
+            )
+        };
+        extracted.map_err(|e| {
             let _ = std::fs::remove_dir_all(&staging_dir);
-            LibrarianError::Io(std::io::Error::other(e.to_string()))
+            match e {
+                FsError::ArchiveLimit(_)
+                | FsError::Cancelled
+                | FsError::PasswordRequired(_)
+                | FsError::InvalidPassword(_) => LibrarianError::Fs(e),
+                other => LibrarianError::Io(std::io::Error::other(other.to_string())),
+            }
         })?;
 
         // Step A.5: Preset Detection (ReShade)
@@ -61,7 +470,7 @@ impl Importer {
 
         if let Some(preset_path) = potential_preset {
             // It's a preset!
-            let game_paths = librarian.game_paths(&game_id);
+            let game_paths = librarian.game_paths(game_id);
             let presets_dir = game_paths.root.join("reshade_presets");
             if !presets_dir.exists() {
                 std::fs::create_dir_all(&presets_dir)?;
@@ -81,18 +490,45 @@ impl Importer {
             return Err(LibrarianError::ImportedPreset(dest_name));
         }
 
+        // Step A.6: Texture Fixes
+        if options.fix_normal_maps {
+            Self::fix_normal_maps(&staging_dir);
+        }
+
+        // Step A.7: Case Normalization
+        if options.normalize_filenames {
+            Self::normalize_filenames(&staging_dir);
+        }
+
+        Ok((staging_dir, source))
+    }
+
+    /// Turns one staged mod into a record. `name` seeds the metadata when the mod
+    /// ships none.
+    async fn commit(
+        librarian: &Librarian,
+        staging_dir: &Path,
+        name: &str,
+        archive_path: &Path,
+        game_id: &str,
+        source: ModSource,
+        options: &ImportOptions,
+    ) -> Result<ModRecord> {
+        let file_name = name.to_string();
+
         // Step B: Metadata Logic
         let mod_json_path = staging_dir.join("mod.json");
         let modinfo_json_path = staging_dir.join("modinfo.json");
 
-        let (metadata, added_at) = if extraction_report.has_mod_json && mod_json_path.exists() {
+        let shipped_metadata = mod_json_path.exists() || modinfo_json_path.exists();
+        let (metadata, added_at) = if mod_json_path.exists() {
             let content = std::fs::read_to_string(&mod_json_path)?;
             (
                 serde_json::from_str::<ModMetadata>(&content)
                     .unwrap_or_else(|_| Self::generate_default_metadata(&file_name)),
                 Utc::now(),
             )
-        } else if extraction_report.has_modinfo_json && modinfo_json_path.exists() {
+        } else if modinfo_json_path.exists() {
             let content = std::fs::read_to_string(&modinfo_json_path)?;
             (
                 serde_json::from_str::<ModMetadata>(&content)
@@ -106,7 +542,7 @@ impl Importer {
             std::fs::write(&mod_json_path, content)?;
 
             // Get source file date
-            let source_date = std::fs::metadata(&archive_path)
+            let source_date = std::fs::metadata(archive_path)
                 .and_then(|m| m.modified())
                 .map(chrono::DateTime::<Utc>::from)
                 .unwrap_or_else(|_| Utc::now());
@@ -114,71 +550,118 @@ impl Importer {
             (meta, source_date)
         };
 
+        // Step C: Identification. The staged files are exactly what gets committed.
+        let _guard = librarian.lock_imports().await;
+        options.stage(ImportStage::Identifying);
+        let db = librarian.load_game_db(game_id).await?;
+        let game_version = db.games.get(game_id).map(|g| g.version.clone());
+        // Hashing reads every staged file, so it stays off the async runtime
+        let (mod_type, compatibility, auto_tags) = {
+            let (librarian, staging_dir, game_id) = (
+                librarian.clone(),
+                staging_dir.to_path_buf(),
+                game_id.to_string(),
+            );
+            Self::blocking(move || {
+                let identity = Self::identify_character_and_type(
+                    &staging_dir,
+                    &librarian.assets_root,
+                    &game_id,
+                    game_version.as_deref(),
+                );
+                let (fingerprint, texture_hashes) = Self::fingerprint(&staging_dir);
+                let mut compatibility = ModCompatibility {
+                    game: "Unknown".to_string(), // Inferred later?
+                    character: String::new(),
+                    hashes: Vec::new(),
+                    fingerprint,
+                    texture_hashes,
+                    characters: Vec::new(),
+                    confidence: 0.0,
+                };
+                let mod_type = identity.mod_type.clone();
+                identity.apply(&mut compatibility);
+                let auto_tags = AutoTagger::for_game(&librarian, &game_id)
+                    .tags_for(&staging_dir, &compatibility);
+                Ok((mod_type, compatibility, auto_tags))
+            })
+            .await?
+        };
+
+        if options.duplicates == DuplicatePolicy::Ask && options.replace.is_none() {
+            if let Some(existing) = DuplicateFinder::best_match(&db, &compatibility) {
+                let _ = std::fs::remove_dir_all(staging_dir);
+                return Err(LibrarianError::PossibleDuplicate(existing));
+            }
+        }
+
         // Step D: Commit (skipping nested root handling for now as sanitize handles paths)
         // If we want nested root stripping, we'd do it here, but let's stick to the prompt's logic.
 
+        if let Some(mod_id) = options.replace {
+            return Self::replace_mod_files(
+                librarian,
+                game_id,
+                mod_id,
+                staging_dir,
+                shipped_metadata.then_some(metadata.version),
+                compatibility,
+                auto_tags,
+                source,
+                options,
+            )
+            .await;
+        }
+
         let mod_id = Uuid::new_v4();
-        let game_paths = librarian.game_paths(&game_id);
-        let game_mods_dir = game_paths.mods;
+        // An instance sharing another's mods imports into the shared folder
+        let pool_id = librarian.mod_pool_id(game_id)?;
+        let game_mods_dir = librarian.game_paths(&pool_id).mods;
         if !game_mods_dir.exists() {
             std::fs::create_dir_all(&game_mods_dir)?;
         }
         let target_path = game_mods_dir.join(mod_id.to_string());
 
         // Rename/Move
-        // Try atomic rename first
-        if std::fs::rename(&staging_dir, &target_path).is_err() {
-            // If cross-device link error, copy and delete
-            if let Err(e) = Safety::copy_recursive_sync(&staging_dir, &target_path) {
-                let _ = std::fs::remove_dir_all(&staging_dir);
-                return Err(LibrarianError::Io(std::io::Error::other(e.to_string())));
-            }
-            let _ = std::fs::remove_dir_all(&staging_dir);
-        }
-
-        // Calculate Size
-        let size_bytes = Safety::get_dir_size(&target_path).unwrap_or(0);
-        let size_str = if size_bytes > 1024 * 1024 * 1024 {
-            format!("{:.1} GB", size_bytes as f64 / 1024.0 / 1024.0 / 1024.0)
-        } else if size_bytes > 1024 * 1024 {
-            format!("{:.1} MB", size_bytes as f64 / 1024.0 / 1024.0)
-        } else {
-            format!("{:.1} KB", size_bytes as f64 / 1024.0)
-        };
+        let (size_bytes, keybinds) = Self::place_files(staging_dir, &target_path).await?;
+        let size_str = Self::format_size(size_bytes);
 
         // Update DB
-        let mut db = librarian.load_game_db(&game_id).await?;
+        let mut db = librarian.load_game_db(game_id).await?;
 
-        let (character, mod_type, hashes) =
-            Self::identify_character_and_type(&target_path, &librarian.assets_root, &game_id);
         let mut config = ModConfig {
             tags: vec![mod_type],
-            keybinds: HashMap::new(),
+            keybinds,
+            auto_tags: Vec::new(),
         };
+        AutoTagger::set_auto_tags(&mut config, auto_tags);
         let mut metadata_final = metadata;
         Self::scan_for_nsfw(&mut metadata_final, &mut config, &file_name);
 
         let record = ModRecord {
             id: mod_id,
-            owner_game_id: game_id.clone(),
+            owner_game_id: pool_id,
             path: target_path,
             size: size_str,
+            size_bytes,
             meta: metadata_final,
-            compatibility: ModCompatibility {
-                game: "Unknown".to_string(), // Inferred later?
-                character,
-                hashes,
-                fingerprint: "".to_string(),
-            },
+            compatibility,
             config,
             enabled: true,
             added_at,
+            version_id: Uuid::new_v4(),
+            versions: Vec::new(),
+            source: Some(source),
         };
 
         db.mods.insert(mod_id, record.clone());
 
         // Update Active Profile (Append to Load Order & Enable by default)
-        if let Some(game_config) = db.games.get(&game_id) {
+        if let Some(game_config) = db
+            .games
+            .get(game_id)
+            .filter(|_| !options.skip_active_profile)
+        {
             if let Ok(p_uuid) = Uuid::parse_str(&game_config.active_profile_id) {
                 if let Some(profile) = db.profiles.get_mut(&p_uuid) {
                     profile.load_order.push(mod_id);
@@ -187,31 +670,394 @@ impl Importer {
             }
         }
 
-        librarian.save_game_db(&game_id, &db).await?;
+        librarian.save_game_db(game_id, &db).await?;
+        Self::finish_import(librarian, &record, options).await;
 
         Ok(record)
     }
 
-    fn identify_character_and_type(
-        staging_dir: &Path,
-        assets_root: &Path,
+    /// Swaps the files of an existing mod for a freshly staged release. The old folder
+    /// is moved aside first and put back if the new one cannot be moved in.
+    #[allow(clippy::too_many_arguments)]
+    async fn replace_mod_files(
+        librarian: &Librarian,
         game_id: &str,
-    ) -> (String, String, Vec<String>) {
-        let mut character = "Unknown".to_string();
-        let mut mod_type = "Unknown/Global".to_string();
-        let mut hashes = Vec::new();
+        mod_id: Uuid,
+        staging_dir: &Path,
+        shipped_version: Option<String>,
+        compatibility: ModCompatibility,
+        auto_tags: Vec<String>,
+        source: ModSource,
+        options: &ImportOptions,
+    ) -> Result<ModRecord> {
+        let mut db = librarian.load_game_db(game_id).await?;
+        let Some(existing) = db.mods.get(&mod_id).cloned() else {
+            let _ = std::fs::remove_dir_all(staging_dir);
+            return Err(LibrarianError::ModNotFound(mod_id.to_string()));
+        };
+
+        // The release being replaced is archived rather than deleted so it can be restored
+        let mut record = existing;
+        if record.version_id.is_nil() {
+            record.version_id = Uuid::new_v4();
+        }
+        let target_path = record.path.clone();
+        let archive_path =
+            VersionHistory::version_dir(librarian, game_id, mod_id, record.version_id);
+        let archived = target_path.exists();
+        if archived {
+            let moved = archive_path
+                .parent()
+                .map_or(Ok(()), std::fs::create_dir_all)
+                .and_then(|_| std::fs::rename(&target_path, &archive_path));
+            if let Err(e) = moved {
+                let _ = std::fs::remove_dir_all(staging_dir);
+                return Err(e.into());
+            }
+        }
+        let (size_bytes, keybinds) = match Self::place_files(staging_dir, &target_path).await {
+            Ok(moved) => moved,
+            Err(e) => {
+                if archived {
+                    let _ = std::fs::remove_dir_all(&target_path);
+                    let _ = std::fs::rename(&archive_path, &target_path);
+                }
+                return Err(e);
+            }
+        };
 
-        // 1. Try to load Hash DB
-        // Priority 1: Game-specific dynamic hash db
-        let game_hash_path = assets_root.join("hashes").join(format!("{}.json", game_id));
-        // Priority 2: Global fallback
-        let global_hash_path = assets_root.join("hashes.json");
+        // The old files may have been links into the blob store
+        if let Err(e) = librarian.blob_store().release(&mod_id.to_string()) {
+            eprintln!("Importer: failed to release blobs of {}: {}", mod_id, e);
+        }
 
-        let hash_index = if game_hash_path.exists() {
-            HashIndex::load(&game_hash_path).unwrap_or_default()
+        if archived {
+            record.versions.insert(
+                0,
+                ModVersion {
+                    id: record.version_id,
+                    meta: record.meta.clone(),
+                    path: archive_path,
+                    size: record.size.clone(),
+                    size_bytes: record.size_bytes,
+                    added_at: record.added_at,
+                },
+            );
+        }
+        record.version_id = Uuid::new_v4();
+        record.added_at = Utc::now();
+        record.meta.updated_at = None;
+
+        record.size_bytes = size_bytes;
+        record.size = Self::format_size(record.size_bytes);
+        if let Some(version) = shipped_version {
+            record.meta.version = version;
+        }
+        // A character the user assigned by hand survives a release nobody can identify
+        let character = if compatibility.character == "Unknown" {
+            record.compatibility.character.clone()
         } else {
-            HashIndex::load(&global_hash_path).unwrap_or_default()
+            compatibility.character.clone()
         };
+        record.compatibility = ModCompatibility {
+            character,
+            ..compatibility
+        };
+        AutoTagger::set_auto_tags(&mut record.config, auto_tags);
+        record.config.keybinds = keybinds;
+        record.source = Some(source);
+
+        db.mods.insert(mod_id, record.clone());
+        librarian.save_game_db(game_id, &db).await?;
+        Self::finish_import(librarian, &record, options).await;
+
+        Ok(record)
+    }
+
+    /// Fingerprint and texture hashes of a mod folder. A mod whose files cannot all be
+    /// read gets neither rather than wrong ones.
+    pub(crate) fn fingerprint(mod_dir: &Path) -> (String, Vec<String>) {
+        match ModFingerprint::files(mod_dir) {
+            Ok(files) => (
+                ModFingerprint::of_files(&files),
+                ModFingerprint::texture_hashes(&files),
+            ),
+            Err(e) => {
+                eprintln!(
+                    "Importer: failed to fingerprint {}: {}",
+                    mod_dir.display(),
+                    e
+                );
+                (String::new(), Vec::new())
+            }
+        }
+    }
+
+    /// Moves the staged files to `target_path` on the blocking pool and measures them:
+    /// their size and keybinds.
+    async fn place_files(
+        staging_dir: &Path,
+        target_path: &Path,
+    ) -> Result<(u64, HashMap<String, Keybind>)> {
+        let (staging_dir, target_path) = (staging_dir.to_path_buf(), target_path.to_path_buf());
+        Self::blocking(move || {
+            Self::move_into_place(&staging_dir, &target_path)?;
+            Ok((
+                Safety::get_dir_size(&target_path).unwrap_or(0),
+                KeybindExtractor::extract(&target_path),
+            ))
+        })
+        .await
+    }
+
+    fn move_into_place(staging_dir: &Path, target_path: &Path) -> Result<()> {
+        // Try atomic rename first
+        if std::fs::rename(staging_dir, target_path).is_err() {
+            // If cross-device link error, copy and delete
+            if let Err(e) = Safety::copy_recursive_sync(staging_dir, target_path) {
+                let _ = std::fs::remove_dir_all(staging_dir);
+                return Err(LibrarianError::Io(std::io::Error::other(e.to_string())));
+            }
+            let _ = std::fs::remove_dir_all(staging_dir);
+        }
+        Ok(())
+    }
+
+    /// Runs `work` on the blocking pool, for the file-heavy steps of an import.
+    pub(crate) async fn blocking<T, F>(work: F) -> Result<T>
+    where
+        F: FnOnce() -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        tokio::task::spawn_blocking(work)
+            .await
+            .map_err(|e| LibrarianError::Io(std::io::Error::other(e.to_string())))?
+    }
+
+    /// Post-commit work shared by new imports and replacements, run on the blocking
+    /// pool. Failures here only cost disk space or a preview; the mod is complete
+    /// either way.
+    async fn finish_import(librarian: &Librarian, record: &ModRecord, options: &ImportOptions) {
+        let (librarian, record, options) = (librarian.clone(), record.clone(), options.clone());
+        let _ = Self::blocking(move || {
+            Self::finish_files(&librarian, &record, &options);
+            Ok(())
+        })
+        .await;
+    }
+
+    fn finish_files(librarian: &Librarian, record: &ModRecord, options: &ImportOptions) {
+        if options.deduplicate {
+            match librarian.deduplicate_mod(record) {
+                Ok(report) if report.bytes_saved > 0 => println!(
+                    "Importer: {} shares {} with other mods",
+                    record.id,
+                    Self::format_size(report.bytes_saved)
+                ),
+                Ok(_) => {}
+                Err(e) => eprintln!("Importer: deduplication failed for {}: {}", record.id, e),
+            }
+        }
+
+        // Warm the thumbnail cache; a mod without decodable textures simply has none
+        if let Err(e) = librarian.ensure_mod_thumbnails(record) {
+            eprintln!(
+                "Importer: thumbnail generation failed for {}: {}",
+                record.id, e
+            );
+        }
+    }
+
+    /// Folders of a staged archive that are mods of their own. An INI at the top means
+    /// one mod, however many folders it has; otherwise every top-level folder holding
+    /// INIs is one, looking through folders that only wrap a single such folder.
+    pub(crate) fn mod_roots(staging_dir: &Path) -> Vec<PathBuf> {
+        if Self::has_ini(staging_dir, 1) {
+            return vec![staging_dir.to_path_buf()];
+        }
+        let mut children: Vec<PathBuf> = std::fs::read_dir(staging_dir)
+            .into_iter()
+            .flatten()
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.is_dir() && Self::has_ini(p, usize::MAX))
+            .collect();
+        children.sort();
+        match children.len() {
+            0 => vec![staging_dir.to_path_buf()],
+            1 => Self::mod_roots(&children[0]),
+            _ => children,
+        }
+    }
+
+    pub(crate) fn has_ini(dir: &Path, max_depth: usize) -> bool {
+        walkdir::WalkDir::new(dir)
+            .max_depth(max_depth)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .any(|e| {
+                e.file_type().is_file()
+                    && e.path()
+                        .extension()
+                        .is_some_and(|ext| ext.eq_ignore_ascii_case("ini"))
+            })
+    }
+
+    /// The archive (or folder) a whole import came from, under a new pack id.
+    fn source_of(archive_path: &Path) -> ModSource {
+        ModSource {
+            archive: Self::source_name(archive_path),
+            archive_hash: if archive_path.is_file() {
+                hash_file(archive_path).unwrap_or_default()
+            } else {
+                String::new()
+            },
+            pack_id: Uuid::new_v4(),
+            root: String::new(),
+        }
+    }
+
+    fn source_name(archive_path: &Path) -> String {
+        archive_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("Unknown Mod")
+            .to_string()
+    }
+
+    /// Last path segment of a download link, or "download" if it has none.
+    fn file_name_from_url(url: &str) -> String {
+        let path = url.split(['?', '#']).next().unwrap_or(url);
+        let path = path.split_once("://").map_or(path, |(_, rest)| rest);
+        let segment = path
+            .trim_end_matches('/')
+            .split_once('/')
+            .and_then(|(_, file)| file.rsplit('/').next())
+            .unwrap_or("");
+        let name = urlencoding::decode(segment)
+            .map(|n| n.to_string())
+            .unwrap_or_else(|_| segment.to_string());
+        let name = name.trim();
+        if name.is_empty() || name.contains(['/', '\\']) || matches!(name, "." | "..") {
+            "download".to_string()
+        } else {
+            name.to_string()
+        }
+    }
+
+    async fn cancelled(token: &CancelToken) {
+        while !token.is_cancelled() {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+    }
+
+    /// Extracts the archive, falling back to a password remembered from an earlier
+    /// import of the same file when the archive turns out to be encrypted.
+    fn extract_to_staging(
+        librarian: &Librarian,
+        archive_path: &Path,
+        archive_hash: &str,
+        staging_dir: &Path,
+        options: &ExtractOptions,
+    ) -> std::result::Result<ExtractionReport, FsError> {
+        let store = PasswordStore::new(&librarian.base_path);
+
+        match extract_and_sanitize_with(archive_path, staging_dir, options) {
+            Ok(report) => {
+                if let Some(password) = options
+                    .password
+                    .as_ref()
+                    .filter(|_| !archive_hash.is_empty())
+                {
+                    let _ = store.remember(archive_hash, password);
+                }
+                Ok(report)
+            }
+            Err(FsError::PasswordRequired(path)) if options.password.is_none() => {
+                let remembered = Some(archive_hash)
+                    .filter(|h| !h.is_empty())
+                    .and_then(|h| store.get(h));
+                let Some(password) = remembered else {
+                    return Err(FsError::PasswordRequired(path));
+                };
+
+                let _ = std::fs::remove_dir_all(staging_dir);
+                std::fs::create_dir_all(staging_dir)?;
+                let retry = ExtractOptions {
+                    password: Some(password),
+                    ..options.clone()
+                };
+                extract_and_sanitize_with(archive_path, staging_dir, &retry)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Rewrites normal maps whose channels are swapped or inverted. A texture that
+    /// fails to decode is left as-is; the mod may still work without the fix.
+    fn fix_normal_maps(staging_dir: &Path) {
+        let walker = walkdir::WalkDir::new(staging_dir);
+        for entry in walker.into_iter().filter_map(|e| e.ok()) {
+            let path = entry.path();
+            let is_texture = path
+                .extension()
+                .and_then(|s| s.to_str())
+                .map(|ext| matches!(ext.to_lowercase().as_str(), "dds" | "png"))
+                .unwrap_or(false);
+            if !entry.file_type().is_file() || !is_texture || !Transcoder::is_normal_map_name(path)
+            {
+                continue;
+            }
+            if let Err(e) = Transcoder::fix_normal_map(path) {
+                eprintln!("Importer: skipped normal map {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    fn normalize_filenames(staging_dir: &Path) {
+        match CaseNormalizer::normalize(staging_dir) {
+            Ok(report) => {
+                if report.renamed > 0 {
+                    println!(
+                        "Importer: lowercased {} entries, rewrote {} INI references",
+                        report.renamed, report.references_rewritten
+                    );
+                }
+                for collision in &report.collisions {
+                    eprintln!(
+                        "Importer: case collision at {}: kept {}, left {:?} as-is",
+                        collision.target.display(),
+                        collision.kept.display(),
+                        collision.conflicting
+                    );
+                }
+            }
+            Err(e) => eprintln!("Importer: filename normalization failed: {}", e),
+        }
+    }
+
+    pub fn format_size(size_bytes: u64) -> String {
+        if size_bytes > 1024 * 1024 * 1024 {
+            format!("{:.1} GB", size_bytes as f64 / 1024.0 / 1024.0 / 1024.0)
+        } else if size_bytes > 1024 * 1024 {
+            format!("{:.1} MB", size_bytes as f64 / 1024.0 / 1024.0)
+        } else {
+            format!("{:.1} KB", size_bytes as f64 / 1024.0)
+        }
+    }
+
+    /// Collects the `hash =` values of a mod's INI files and votes them against the
+    /// game's hash database.
+    pub(crate) fn identify_character_and_type(
+        staging_dir: &Path,
+        assets_root: &Path,
+        game_id: &str,
+        game_version: Option<&str>,
+    ) -> ModIdentity {
+        let mut hashes = Vec::new();
+
+        // 1. Load the game's hash DB, falling back to the global one
+        let hash_index = HashIndex::for_game(assets_root, game_id);
 
         // 2. Scan for INIs
         let walker = walkdir::WalkDir::new(staging_dir).max_depth(3);
@@ -227,10 +1073,6 @@ impl Importer {
                                 let hash = hash_val.trim().trim_matches('"');
                                 if !hash.is_empty() {
                                     hashes.push(hash.to_string());
-                                    if let Some(char_name) = hash_index.identify(hash) {
-                                        character = char_name;
-                                        mod_type = "Skin".to_string();
-                                    }
                                 }
                             }
                         }
@@ -239,10 +1081,26 @@ impl Importer {
             }
         }
 
-        (character, mod_type, hashes)
+        // 3. Every recognised hash votes, so one shared weapon hash cannot outvote a body
+        let identification = hash_index.identify_mod(&hashes, game_version);
+        let (character, mod_type) = match identification.primary() {
+            Some(name) => (name.to_string(), "Skin".to_string()),
+            None => ("Unknown".to_string(), "Unknown/Global".to_string()),
+        };
+
+        ModIdentity {
+            character,
+            mod_type,
+            hashes,
+            identification,
+        }
     }
 
-    fn scan_for_nsfw(metadata: &mut ModMetadata, config: &mut ModConfig, filename: &str) {
+    pub(crate) fn scan_for_nsfw(
+        metadata: &mut ModMetadata,
+        config: &mut ModConfig,
+        filename: &str,
+    ) {
         let nsfw_keywords = ["nsfw", "nude", "18+", "explicit", "bikini", "skimpy"];
         let mut is_nsfw = false;
 
@@ -265,7 +1123,7 @@ impl Importer {
         }
     }
 
-    fn generate_default_metadata(filename: &str) -> ModMetadata {
+    pub(crate) fn generate_default_metadata(filename: &str) -> ModMetadata {
         // Clever name: lisatoggle.zip -> Lisatoggle
         let stem = Path::new(filename)
             .file_stem()
@@ -292,6 +1150,7 @@ impl Importer {
             url: None,
             preview_image: None,
             description: Some(format!("Generated from {}", filename)),
+            updated_at: None,
         }
     }
 }
@@ -0,0 +1,324 @@
+//! Imports many mods at once.
+//!
+//! Each item runs as its own task, up to the queue's concurrency, with its own status
+//! and cancel token. Extraction, hashing and moving files run on the blocking pool, so
+//! up to that many imports progress side by side; the database writes at the end of
+//! each import still happen one at a time.
+
+use crate::error::LibrarianError;
+use crate::import::{ImportOptions, ImportStage, Importer, PackFailure};
+use crate::Librarian;
+use fs_engine::{CancelToken, FsError};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::sync::Semaphore;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+/// Imports running at once unless the caller asks for something else.
+pub const DEFAULT_IMPORT_CONCURRENCY: usize = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ImportSource {
+    Archive {
+        path: PathBuf,
+    },
+    Folder {
+        path: PathBuf,
+    },
+    /// A direct download link to an archive.
+    Url {
+        url: String,
+    },
+}
+
+impl ImportSource {
+    /// Links for http(s) addresses, folders for existing directories, archives otherwise.
+    pub fn detect(input: &str) -> Self {
+        let lower = input.to_lowercase();
+        if lower.starts_with("http://") || lower.starts_with("https://") {
+            return Self::Url {
+                url: input.to_string(),
+            };
+        }
+        let path = PathBuf::from(input);
+        if path.is_dir() {
+            Self::Folder { path }
+        } else {
+            Self::Archive { path }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum ImportStatus {
+    Queued,
+    /// `total` is 0 until the server has answered.
+    Downloading {
+        current: u64,
+        total: u64,
+    },
+    Extracting,
+    Identifying,
+    /// More than one mod when the archive was a pack.
+    Done {
+        mod_ids: Vec<Uuid>,
+    },
+    /// Some mods of a pack were imported, the others failed.
+    PartlyDone {
+        mod_ids: Vec<Uuid>,
+        failures: Vec<PackFailure>,
+    },
+    Failed {
+        error: String,
+    },
+    /// The archive held a ReShade preset, which was installed instead of a mod.
+    ImportedAsPreset {
+        preset: String,
+    },
+    Cancelled,
+}
+
+impl ImportStatus {
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            Self::Done { .. }
+                | Self::PartlyDone { .. }
+                | Self::Failed { .. }
+                | Self::ImportedAsPreset { .. }
+                | Self::Cancelled
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportItem {
+    pub id: Uuid,
+    pub game_id: String,
+    pub source: ImportSource,
+    pub status: ImportStatus,
+}
+
+/// Called with an item every time its status changes, from the item's task.
+pub type ImportUpdateCallback = Arc<dyn Fn(&ImportItem) + Send + Sync>;
+
+/// A batch import queue. Clones share the same items.
+#[derive(Clone)]
+pub struct ImportQueue {
+    inner: Arc<QueueInner>,
+}
+
+struct QueueInner {
+    permits: Semaphore,
+    items: Mutex<Vec<QueuedItem>>,
+    tasks: Mutex<Vec<JoinHandle<()>>>,
+    on_update: Option<ImportUpdateCallback>,
+}
+
+struct QueuedItem {
+    item: ImportItem,
+    cancel: CancelToken,
+}
+
+impl ImportQueue {
+    pub fn new(concurrency: usize, on_update: Option<ImportUpdateCallback>) -> Self {
+        Self {
+            inner: Arc::new(QueueInner {
+                permits: Semaphore::new(concurrency.max(1)),
+                items: Mutex::new(Vec::new()),
+                tasks: Mutex::new(Vec::new()),
+                on_update,
+            }),
+        }
+    }
+
+    /// Queues an import and starts it as soon as a slot is free. The options' cancel
+    /// token becomes the item's. Must be called from within a Tokio runtime.
+    pub fn enqueue(
+        &self,
+        librarian: &Librarian,
+        game_id: &str,
+        source: ImportSource,
+        options: ImportOptions,
+    ) -> Uuid {
+        let item = ImportItem {
+            id: Uuid::new_v4(),
+            game_id: game_id.to_string(),
+            source,
+            status: ImportStatus::Queued,
+        };
+        let id = item.id;
+        self.notify(&item);
+        self.inner.items.lock().unwrap().push(QueuedItem {
+            item: item.clone(),
+            cancel: options.extraction.cancel.clone(),
+        });
+
+        let task = tokio::spawn(self.clone().run(librarian.clone(), item, options));
+        let mut tasks = self.inner.tasks.lock().unwrap();
+        // Only unfinished tasks are worth waiting on; a long-lived queue would otherwise
+        // keep a handle for every import it ever ran
+        tasks.retain(|task| !task.is_finished());
+        tasks.push(task);
+        id
+    }
+
+    /// Snapshot of every item, in the order they were queued.
+    pub fn items(&self) -> Vec<ImportItem> {
+        let items = self.inner.items.lock().unwrap();
+        items.iter().map(|q| q.item.clone()).collect()
+    }
+
+    /// Cancels an item that has not finished. Returns whether there was one.
+    pub fn cancel(&self, id: Uuid) -> bool {
+        let items = self.inner.items.lock().unwrap();
+        match items
+            .iter()
+            .find(|q| q.item.id == id && !q.item.status.is_finished())
+        {
+            Some(queued) => {
+                queued.cancel.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn cancel_all(&self) {
+        for queued in self.inner.items.lock().unwrap().iter() {
+            queued.cancel.cancel();
+        }
+    }
+
+    /// Forgets finished items.
+    pub fn clear_finished(&self) {
+        self.inner
+            .items
+            .lock()
+            .unwrap()
+            .retain(|q| !q.item.status.is_finished());
+    }
+
+    /// Cancels every unfinished item and waits until they have all stopped, including
+    /// ones queued meanwhile. Imports already committing still finish.
+    pub async fn drain(&self) {
+        loop {
+            self.cancel_all();
+            let items = self.wait().await;
+            if items.iter().all(|item| item.status.is_finished()) {
+                return;
+            }
+        }
+    }
+
+    /// Waits until every queued item has finished, including ones queued meanwhile.
+    pub async fn wait(&self) -> Vec<ImportItem> {
+        loop {
+            let tasks: Vec<_> = std::mem::take(&mut *self.inner.tasks.lock().unwrap());
+            if tasks.is_empty() {
+                return self.items();
+            }
+            for task in tasks {
+                let _ = task.await;
+            }
+        }
+    }
+
+    async fn run(self, librarian: Librarian, item: ImportItem, mut options: ImportOptions) {
+        // The semaphore is never closed
+        let Ok(_permit) = self.inner.permits.acquire().await else {
+            return;
+        };
+        if options.extraction.cancel.is_cancelled() {
+            self.set_status(item.id, ImportStatus::Cancelled);
+            return;
+        }
+
+        let queue = self.clone();
+        let caller_stage = options.on_stage.take();
+        options.on_stage = Some(Arc::new(move |stage| {
+            let status = match stage {
+                ImportStage::Downloading => ImportStatus::Downloading {
+                    current: 0,
+                    total: 0,
+                },
+                ImportStage::Extracting => ImportStatus::Extracting,
+                ImportStage::Identifying => ImportStatus::Identifying,
+            };
+            queue.set_status(item.id, status);
+            if let Some(cb) = &caller_stage {
+                cb(stage);
+            }
+        }));
+
+        let game_id = item.game_id.clone();
+        let result = match item.source {
+            ImportSource::Archive { path } => {
+                Importer::import_pack(&librarian, path, game_id, options).await
+            }
+            ImportSource::Folder { path } => {
+                Importer::import_folder_pack(&librarian, path, game_id, options).await
+            }
+            ImportSource::Url { url } => {
+                let queue = self.clone();
+                let mut last_percent = None;
+                let on_download = move |current: u64, total: u64| {
+                    // One update per percent is plenty for a progress bar
+                    let percent = (current * 100).checked_div(total);
+                    if percent != last_percent {
+                        last_percent = percent;
+                        queue.set_status(item.id, ImportStatus::Downloading { current, total });
+                    }
+                };
+                Importer::import_url_pack(&librarian, &url, game_id, options, on_download).await
+            }
+        };
+
+        let status = match result {
+            Ok(pack) => {
+                let mod_ids = pack.records.iter().map(|r| r.id).collect();
+                if pack.failures.is_empty() {
+                    ImportStatus::Done { mod_ids }
+                } else {
+                    ImportStatus::PartlyDone {
+                        mod_ids,
+                        failures: pack.failures,
+                    }
+                }
+            }
+            Err(LibrarianError::ImportedPreset(preset)) => {
+                ImportStatus::ImportedAsPreset { preset }
+            }
+            Err(LibrarianError::Fs(FsError::Cancelled)) => ImportStatus::Cancelled,
+            Err(e) => {
+                eprintln!("Importer: queued import {} failed: {}", item.id, e);
+                ImportStatus::Failed {
+                    error: e.to_string(),
+                }
+            }
+        };
+        self.set_status(item.id, status);
+    }
+
+    fn set_status(&self, id: Uuid, status: ImportStatus) {
+        let updated = {
+            let mut items = self.inner.items.lock().unwrap();
+            let Some(queued) = items.iter_mut().find(|q| q.item.id == id) else {
+                return;
+            };
+            queued.item.status = status;
+            queued.item.clone()
+        };
+        self.notify(&updated);
+    }
+
+    fn notify(&self, item: &ImportItem) {
+        if let Some(cb) = &self.inner.on_update {
+            cb(item);
+        }
+    }
+}
@@ -0,0 +1,206 @@
+//! Several installs of the same game, such as the global and CN clients or a beta next
+//! to live.
+//!
+//! Each install is an instance with its own id, and so its own database, mods, profiles
+//! and prefix. Ids used to be the lowercased executable name, which stays the id of the
+//! first instance; later ones get the region or a number appended. An instance can use
+//! another's mods instead of its own (its `mod_pool`), with its own profiles on top.
+
+use crate::error::{LibrarianError, Result};
+use crate::models::{GameConfig, LibraryDatabase};
+use crate::storage::Librarian;
+use crate::template::GameTemplate;
+use std::collections::HashMap;
+use std::path::Path;
+
+pub struct GameInstances;
+
+impl GameInstances {
+    /// The preferred id of an instance: `base` (executable name or template id), with
+    /// the region appended when there is one.
+    pub fn instance_id(base: &str, region: Option<&str>) -> String {
+        let base = base.to_lowercase();
+        match region.map(str::trim).filter(|r| !r.is_empty()) {
+            Some(region) => {
+                let region: String = region
+                    .to_lowercase()
+                    .chars()
+                    .map(|c| {
+                        if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                            c
+                        } else {
+                            '_'
+                        }
+                    })
+                    .collect();
+                format!("{}@{}", base, region)
+            }
+            None => base,
+        }
+    }
+
+    /// `wanted`, or `wanted-2`, `wanted-3`... if an instance already has it.
+    pub async fn unique_id(librarian: &Librarian, wanted: &str) -> Result<String> {
+        let taken = librarian.discover_game_ids().await?;
+        if !taken.iter().any(|id| id == wanted) {
+            return Ok(wanted.to_string());
+        }
+        Ok((2..)
+            .map(|n| format!("{}-{}", wanted, n))
+            .find(|id| !taken.contains(id))
+            .unwrap_or_default())
+    }
+
+    /// The instance installed with this executable, if any.
+    pub async fn find_by_exe(librarian: &Librarian, exe_path: &Path) -> Result<Option<String>> {
+        for game_id in librarian.discover_game_ids().await? {
+            let db = librarian.load_game_db(&game_id).await?;
+            if db
+                .games
+                .get(&game_id)
+                .is_some_and(|g| g.exe_path == exe_path)
+            {
+                return Ok(Some(game_id));
+            }
+        }
+        Ok(None)
+    }
+
+    /// [`GameInstances::find_by_exe`] over databases already loaded.
+    pub fn find_loaded(dbs: &HashMap<String, LibraryDatabase>, exe_path: &Path) -> Option<String> {
+        dbs.iter()
+            .find(|(game_id, db)| {
+                db.games
+                    .get(*game_id)
+                    .is_some_and(|g| g.exe_path == exe_path)
+            })
+            .map(|(game_id, _)| game_id.clone())
+    }
+
+    /// After `game_id`'s mods changed in `dbs`, gives the same mods to the other loaded
+    /// instances of its pool, so none of them saves an old copy over the new one.
+    pub fn sync_loaded(dbs: &mut HashMap<String, LibraryDatabase>, game_id: &str) {
+        let Some(db) = dbs.get(game_id) else {
+            return;
+        };
+        let pool_id = db.mod_pool_id(game_id).to_string();
+        let mods = db.mods.clone();
+        for (id, other) in dbs.iter_mut() {
+            if id != game_id && other.mod_pool_id(id) == pool_id {
+                other.mods = mods.clone();
+            }
+        }
+    }
+
+    /// The instance's template, adjusted to its region. Games added before instances
+    /// existed have no template id and are matched by their own id.
+    pub fn template_for(
+        templates: &HashMap<String, GameTemplate>,
+        config: &GameConfig,
+    ) -> Option<GameTemplate> {
+        let template = match &config.template_id {
+            Some(id) => templates.get(&id.to_lowercase()),
+            None => templates.get(&config.id).or_else(|| {
+                config
+                    .id
+                    .strip_suffix(".exe")
+                    .and_then(|stem| templates.get(stem))
+            }),
+        }?;
+        Some(template.for_region(config.region.as_deref()))
+    }
+
+    /// Instances using `game_id`'s mods.
+    pub async fn pool_members(librarian: &Librarian, game_id: &str) -> Result<Vec<String>> {
+        let mut members = Vec::new();
+        for id in librarian.discover_game_ids().await? {
+            let db = librarian.load_game_db(&id).await?;
+            if db.games.get(&id).and_then(|g| g.mod_pool.as_deref()) == Some(game_id) {
+                members.push(id);
+            }
+        }
+        Ok(members)
+    }
+
+    /// Makes `game_id` use `pool`'s mods, taking its own mods along into the pool, or
+    /// with `None` gives it back a mod list of its own. The shared mods stay with the
+    /// pool, so its profiles start out empty.
+    pub async fn share_mods(
+        librarian: &Librarian,
+        game_id: &str,
+        pool: Option<&str>,
+    ) -> Result<()> {
+        let _guard = librarian.lock_imports().await;
+        let mut db = librarian.load_game_db(game_id).await?;
+        let current = db
+            .games
+            .get(game_id)
+            .ok_or_else(|| LibrarianError::Validation(format!("Game {} not found", game_id)))?
+            .mod_pool
+            .clone();
+        if current.as_deref() == pool {
+            return Ok(());
+        }
+
+        match pool {
+            Some(pool) => {
+                Self::check_pool(librarian, game_id, pool).await?;
+                let mut pool_db = librarian.load_game_db(pool).await?;
+                for (id, mut record) in db.mods.drain() {
+                    record.owner_game_id = pool.to_string();
+                    pool_db.mods.insert(id, record);
+                }
+                librarian.save_game_db(pool, &pool_db).await?;
+                db.mods = pool_db.mods;
+            }
+            None => {
+                db.mods.clear();
+                for profile in db.profiles.values_mut() {
+                    profile.enabled_mod_ids.clear();
+                    profile.load_order.clear();
+                    profile.pinned_versions.clear();
+                }
+            }
+        }
+        if let Some(config) = db.games.get_mut(game_id) {
+            config.mod_pool = pool.map(str::to_string);
+        }
+        librarian.save_game_db(game_id, &db).await?;
+        println!(
+            "Librarian: {} now uses the mods of {}",
+            game_id,
+            pool.unwrap_or(game_id)
+        );
+        Ok(())
+    }
+
+    /// Pools are one level deep: the pool keeps its own mods, and nothing may be using
+    /// the instance's mods when it joins one.
+    async fn check_pool(librarian: &Librarian, game_id: &str, pool: &str) -> Result<()> {
+        if pool == game_id {
+            return Err(LibrarianError::Validation(
+                "A game cannot share its own mods".to_string(),
+            ));
+        }
+        let pool_db = librarian.load_game_db(pool).await?;
+        let Some(pool_config) = pool_db.games.get(pool) else {
+            return Err(LibrarianError::Validation(format!(
+                "Game {} not found",
+                pool
+            )));
+        };
+        if let Some(owner) = &pool_config.mod_pool {
+            return Err(LibrarianError::Validation(format!(
+                "{} already uses the mods of {}",
+                pool, owner
+            )));
+        }
+        if let Some(member) = Self::pool_members(librarian, game_id).await?.first() {
+            return Err(LibrarianError::Validation(format!(
+                "{} uses the mods of {}",
+                member, game_id
+            )));
+        }
+        Ok(())
+    }
+}
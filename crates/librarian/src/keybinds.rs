@@ -0,0 +1,200 @@
+//! Keybinds read from mod INIs.
+//!
+//! 3DMigoto mods declare their hotkeys in `[Key...]` sections:
+//!
+//! ```ini
+//! ; Hat on/off
+//! [KeyHat]
+//! key = VK_DOWN
+//! type = cycle
+//! $hat = 0,1
+//! ```
+//!
+//! Each becomes a [`Keybind`] so the mod manager can list a mod's keys without the
+//! user opening its INI. The label is the nearest comment, or the section name.
+
+use crate::models::{KeyVariable, Keybind, KeybindKind};
+use ini_forge::{IniItem, Section};
+use std::collections::HashMap;
+use std::path::Path;
+
+pub struct KeybindExtractor;
+
+impl KeybindExtractor {
+    /// Keybinds of every INI under `mod_dir`, keyed by section name. A section name used
+    /// by more than one INI is prefixed with the path of the deeper INI.
+    pub fn extract(mod_dir: &Path) -> HashMap<String, Keybind> {
+        let mut found: Vec<(String, Keybind)> = Vec::new();
+        let mut inis: Vec<_> = walkdir::WalkDir::new(mod_dir)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| {
+                e.file_type().is_file()
+                    && e.path()
+                        .extension()
+                        .is_some_and(|ext| ext.eq_ignore_ascii_case("ini"))
+            })
+            .map(|e| e.into_path())
+            .collect();
+        // The mod's main INI usually sits at the top, so it keeps the plain names
+        inis.sort_by_key(|path| (path.components().count(), path.clone()));
+
+        for path in inis {
+            // Mods written on Windows are not always UTF-8
+            let Ok(bytes) = std::fs::read(&path) else {
+                continue;
+            };
+            let relative = path
+                .strip_prefix(mod_dir)
+                .unwrap_or(&path)
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().to_string())
+                .collect::<Vec<_>>()
+                .join("/");
+            found.extend(Self::from_ini(&String::from_utf8_lossy(&bytes), &relative));
+        }
+
+        let mut keybinds = HashMap::new();
+        for (section, keybind) in found {
+            let id = if keybinds.contains_key(&section) {
+                format!("{}:{}", keybind.ini, section)
+            } else {
+                section
+            };
+            keybinds.insert(id, keybind);
+        }
+        keybinds
+    }
+
+    /// Keybinds of one INI's contents, with their section names.
+    pub fn from_ini(content: &str, ini: &str) -> Vec<(String, Keybind)> {
+        let sections = Self::sections(content);
+        let mut keybinds = Vec::new();
+        for (index, section) in sections.iter().enumerate() {
+            if !section.name.to_lowercase().starts_with("key") {
+                continue;
+            }
+            // Comments above a header are parsed as the end of the previous section
+            let comment_above = index
+                .checked_sub(1)
+                .and_then(|i| Self::trailing_comment(&sections[i]));
+            if let Some(keybind) = Self::from_section(section, comment_above, ini) {
+                keybinds.push((section.name.clone(), keybind));
+            }
+        }
+        keybinds
+    }
+
+    /// The parser stops at the first line it does not understand; parsing resumes at
+    /// the next section so one odd line does not hide the keys after it.
+    fn sections(content: &str) -> Vec<Section> {
+        let mut sections = Vec::new();
+        let mut rest = content;
+        while let Ok((remaining, document)) = ini_forge::parser::parse_ini(rest) {
+            sections.extend(document.sections);
+            match remaining.find("\n[") {
+                Some(next) if !remaining.trim().is_empty() => rest = &remaining[next + 1..],
+                _ => break,
+            }
+        }
+        sections
+    }
+
+    fn from_section(
+        section: &Section,
+        comment_above: Option<String>,
+        ini: &str,
+    ) -> Option<Keybind> {
+        let mut keybind = Keybind {
+            label: String::new(),
+            variable: String::new(),
+            keys: Vec::new(),
+            back: Vec::new(),
+            kind: KeybindKind::default(),
+            variables: Vec::new(),
+            ini: ini.to_string(),
+        };
+        let mut comment_inside = None;
+
+        for item in &section.items {
+            match item {
+                IniItem::Pair { key, value } => {
+                    let key = key.trim();
+                    match key.to_lowercase().as_str() {
+                        "key" => keybind.keys.push(value.clone()),
+                        "back" => keybind.back.push(value.clone()),
+                        "type" => {
+                            keybind.kind = match value.to_lowercase().as_str() {
+                                "cycle" => KeybindKind::Cycle,
+                                "toggle" => KeybindKind::Toggle,
+                                "hold" => KeybindKind::Hold,
+                                _ => KeybindKind::Activate,
+                            }
+                        }
+                        _ if key.starts_with('$') => keybind.variables.push(KeyVariable {
+                            name: key.to_string(),
+                            values: value.split(',').map(|v| v.trim().to_string()).collect(),
+                        }),
+                        _ => {}
+                    }
+                }
+                IniItem::Comment(text) if comment_inside.is_none() && Self::is_label(text) => {
+                    comment_inside = Some(text.trim().to_string());
+                }
+                _ => {}
+            }
+        }
+        if keybind.keys.is_empty() {
+            return None;
+        }
+
+        keybind.variable = keybind
+            .variables
+            .first()
+            .map(|v| v.name.clone())
+            .unwrap_or_default();
+        keybind.label = comment_inside.or(comment_above).unwrap_or_else(|| {
+            let name = section.name.get(3..).unwrap_or("").trim();
+            if name.is_empty() {
+                section.name.clone()
+            } else {
+                name.to_string()
+            }
+        });
+        Some(keybind)
+    }
+
+    /// The last comment of a section if nothing but comments follow it.
+    fn trailing_comment(section: &Section) -> Option<String> {
+        section
+            .items
+            .iter()
+            .rev()
+            .map_while(|item| match item {
+                IniItem::Comment(text) => Some(text),
+                _ => None,
+            })
+            .find(|text| Self::is_label(text))
+            .map(|text| text.trim().to_string())
+    }
+
+    /// Separator lines (`; -----`) are not labels.
+    fn is_label(comment: &str) -> bool {
+        comment.chars().any(|c| c.is_alphanumeric())
+    }
+
+    /// A key combination in a form that compares equal however it was written:
+    /// lowercase, without `VK_` prefixes or `no_modifiers`, modifiers sorted.
+    pub fn normalize_key(key: &str) -> String {
+        let mut parts: Vec<String> = key
+            .split_whitespace()
+            .map(|part| {
+                let part = part.to_lowercase();
+                part.strip_prefix("vk_").map(str::to_string).unwrap_or(part)
+            })
+            .filter(|part| part != "no_modifiers")
+            .collect();
+        parts.sort();
+        parts.join(" ")
+    }
+}
@@ -1,25 +1,64 @@
+pub mod adoption;
+pub mod backend;
+pub mod bundles;
 pub mod catalog;
 pub mod cloud;
 pub mod discovery;
+pub mod duplicates;
 pub mod error;
+pub mod fingerprint;
 pub mod gamedata;
 pub mod import;
+pub mod import_queue;
+pub mod instances;
+pub mod keybinds;
 pub mod models;
+pub mod passwords;
+pub mod portable;
 pub mod queries;
+pub mod relocation;
 pub mod scanner;
 pub mod settings;
 pub mod storage;
+pub mod tagging;
 pub mod template;
+pub mod updates;
+pub mod versions;
+pub mod watcher;
 
+pub use adoption::{
+    AdoptMode, AdoptionCandidate, AdoptionFailure, AdoptionPreview, AdoptionReport, ModAdoption,
+};
+pub use backend::{BackendKind, JsonBackend, LibraryBackend, SqliteBackend};
+pub use bundles::{BundleImportReport, MissingMod, ProfileBundle, ProfileBundles};
 pub use catalog::{CatalogManager, RemoteCatalogEntry};
 pub use discovery::Discovery;
+pub use duplicates::{
+    DuplicateFinder, DuplicateMatch, DuplicatePair, DuplicatePolicy, DUPLICATE_THRESHOLD,
+};
 pub use error::{LibrarianError, Result};
-pub use import::Importer;
+pub use fingerprint::{FileHash, ModFingerprint};
+pub use import::{ImportOptions, ImportStage, Importer, PackFailure, PackImport};
+pub use import_queue::{
+    ImportItem, ImportQueue, ImportSource, ImportStatus, DEFAULT_IMPORT_CONCURRENCY,
+};
+pub use instances::GameInstances;
+pub use keybinds::KeybindExtractor;
 pub use models::{
-    FpsConfig, GameConfig, InjectionMethod, InstallStatus, LibraryDatabase, ModCompatibility,
-    ModConfig, ModMetadata, ModRecord, Profile, SandboxConfig,
+    FpsConfig, GameConfig, InjectionMethod, InstallStatus, KeyVariable, Keybind, KeybindKind,
+    LibraryDatabase, ModCompatibility, ModConfig, ModMetadata, ModRecord, ModSource, ModVersion,
+    Profile, SandboxConfig,
+};
+pub use portable::{PortableRoot, PORTABLE_MARKER, ROOT_TOKEN};
+pub use relocation::{
+    RelocationOptions, RelocationProgress, RelocationReport, RelocationStage, StorageRelocator,
+    StorageRoot,
 };
 pub use scanner::DiscoveredGame;
 pub use settings::{GlobalSettings, SettingsManager};
 pub use storage::{Librarian, TemplateRegistry};
-pub use template::GameTemplate;
+pub use tagging::{AutoTagger, TagCondition, TagRule, TagRuleStore, TagRules};
+pub use template::{GameTemplate, TemplateRegion};
+pub use updates::{GameBananaProvider, ModUpdate, ModUpdater, RemoteModVersion, UpdateProvider};
+pub use versions::{IniDiff, LineChange, VersionDiff, VersionHistory};
+pub use watcher::{ModSync, ModSyncEvent, ModWatcher};
@@ -99,6 +99,16 @@ pub struct GameConfig {
     #[serde(default)]
     pub supported_injection_methods: Vec<InjectionMethod>,
     pub remote_info: Option<RemoteInfo>,
+    /// Template the instance was made from. Older games have none and are matched by id.
+    #[serde(default)]
+    pub template_id: Option<String>,
+    /// Region or channel of the install, one of the template's `regions`. `None` is the
+    /// template's own default.
+    #[serde(default)]
+    pub region: Option<String>,
+    /// Another instance whose mods this one uses instead of keeping its own.
+    #[serde(default)]
+    pub mod_pool: Option<String>,
 }
 
 fn default_true() -> bool {
@@ -113,6 +123,10 @@ pub struct ModMetadata {
     pub url: Option<String>,
     pub preview_image: Option<String>,
     pub description: Option<String>,
+    /// Upload date of the installed file on the mod's site, set when the updater
+    /// installs a release. Lets re-uploads under the same version count as updates.
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -120,19 +134,66 @@ pub struct ModCompatibility {
     pub game: String,
     pub character: String,
     pub hashes: Vec<String>,
+    /// Tree hash of the mod's meaningful files, see [`crate::fingerprint`].
     pub fingerprint: String,
+    /// Content hashes of the mod's textures, for spotting near-duplicates.
+    #[serde(default)]
+    pub texture_hashes: Vec<String>,
+    /// Every character the mod's hashes point at, strongest first.
+    #[serde(default)]
+    pub characters: Vec<String>,
+    /// Share of the recognised hashes that agree on `character`, 0.0 to 1.0.
+    #[serde(default)]
+    pub confidence: f32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModConfig {
     pub tags: Vec<String>,
     pub keybinds: HashMap<String, Keybind>,
+    /// Tags the tagging rules added, see [`crate::tagging`]. Retagging replaces these
+    /// and leaves the rest of `tags` alone.
+    #[serde(default)]
+    pub auto_tags: Vec<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A `[Key...]` section of a mod's INI, see [`crate::keybinds`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Keybind {
     pub label: String,
+    /// First variable the key drives, empty if it drives none.
     pub variable: String,
+    /// Key combinations as written in the INI (`ctrl VK_UP`).
+    #[serde(default)]
+    pub keys: Vec<String>,
+    /// Combinations that step a cycle backwards.
+    #[serde(default)]
+    pub back: Vec<String>,
+    #[serde(default)]
+    pub kind: KeybindKind,
+    /// Every variable the key sets, with the values it steps through.
+    #[serde(default)]
+    pub variables: Vec<KeyVariable>,
+    /// INI the section is in, relative to the mod folder.
+    #[serde(default)]
+    pub ini: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeybindKind {
+    /// 3DMigoto's default: sets the values while the key is down.
+    #[default]
+    Activate,
+    Cycle,
+    Toggle,
+    Hold,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeyVariable {
+    pub name: String,
+    pub values: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -140,12 +201,57 @@ pub struct ModRecord {
     pub id: Uuid,
     pub owner_game_id: String, // Explicit association (e.g., "genshin")
     pub path: PathBuf,
+    /// `size_bytes` formatted for display.
     pub size: String,
+    /// Size of the folder at `path`. Zero on records from before this until the mods
+    /// watcher's startup scan fills it in.
+    #[serde(default)]
+    pub size_bytes: u64,
     pub meta: ModMetadata,
     pub compatibility: ModCompatibility,
     pub config: ModConfig,
     pub enabled: bool,
     pub added_at: DateTime<Utc>,
+    /// Id of the installed release; nil for records older than version history.
+    #[serde(default)]
+    pub version_id: Uuid,
+    /// Earlier releases kept for rollback, newest first.
+    #[serde(default)]
+    pub versions: Vec<ModVersion>,
+    /// Archive the installed release came from; unset for records older than this.
+    #[serde(default)]
+    pub source: Option<ModSource>,
+}
+
+/// Where a mod was imported from. Mods split out of one archive share everything
+/// but `root`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModSource {
+    /// File name of the archive, folder or download.
+    pub archive: String,
+    /// MD5 of the archive file; empty for folders.
+    #[serde(default)]
+    pub archive_hash: String,
+    /// Same for every record created by one import.
+    pub pack_id: Uuid,
+    /// Folder inside the archive holding this mod, `/`-separated; empty when the
+    /// archive was a single mod.
+    #[serde(default)]
+    pub root: String,
+}
+
+/// A previous release of a mod, archived when a newer one was installed over it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModVersion {
+    pub id: Uuid,
+    pub meta: ModMetadata,
+    /// Where the release's files are kept while it is not the active one.
+    pub path: PathBuf,
+    pub size: String,
+    #[serde(default)]
+    pub size_bytes: u64,
+    /// When this release was imported.
+    pub added_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -165,6 +271,9 @@ pub struct Profile {
     pub launch_args: Vec<String>,
     pub save_data_path: Option<PathBuf>,
     pub added_at: DateTime<Utc>,
+    /// Mods held at an archived release in this profile (mod id -> version id).
+    #[serde(default)]
+    pub pinned_versions: HashMap<Uuid, Uuid>,
 }
 
 impl Default for Profile {
@@ -183,6 +292,7 @@ impl Default for Profile {
             launch_args: vec![],
             save_data_path: None,
             added_at: Utc::now(),
+            pinned_versions: HashMap::new(),
         }
     }
 }
@@ -195,3 +305,52 @@ pub struct LibraryDatabase {
     pub profiles: HashMap<Uuid, Profile>,
     pub last_sync: Option<DateTime<Utc>>,
 }
+
+impl ModRecord {
+    /// Calls `f` on the mod's folder and on those of its archived releases.
+    pub fn for_each_path(&mut self, mut f: impl FnMut(&mut PathBuf)) {
+        f(&mut self.path);
+        for version in &mut self.versions {
+            f(&mut version.path);
+        }
+    }
+}
+
+impl LibraryDatabase {
+    /// The instance whose mods `game_id` uses: its mod pool, or the game itself.
+    pub fn mod_pool_id<'a>(&'a self, game_id: &'a str) -> &'a str {
+        self.games
+            .get(game_id)
+            .and_then(|g| g.mod_pool.as_deref())
+            .unwrap_or(game_id)
+    }
+
+    /// Calls `f` on every filesystem path the database stores. Runners are usually
+    /// referred to by folder name; a runner id only counts when it is a path.
+    pub fn for_each_path(&mut self, mut f: impl FnMut(&mut PathBuf)) {
+        for record in self.mods.values_mut() {
+            record.for_each_path(&mut f);
+        }
+        for config in self.games.values_mut() {
+            f(&mut config.install_path);
+            f(&mut config.exe_path);
+            if let Some(prefix) = &mut config.prefix_path {
+                f(prefix);
+            }
+            if let Some(runner) = config
+                .active_runner_id
+                .as_mut()
+                .filter(|id| id.contains(['/', '\\']))
+            {
+                let mut path = PathBuf::from(&*runner);
+                f(&mut path);
+                *runner = path.to_string_lossy().to_string();
+            }
+        }
+        for profile in self.profiles.values_mut() {
+            if let Some(save_data) = &mut profile.save_data_path {
+                f(save_data);
+            }
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use crate::error::Result;
+use fs_engine::Safety;
+use md5::{Digest, Md5};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Remembers archive passwords keyed by the MD5 of the source file, so re-importing
+/// the same download does not prompt again. These are the public passwords mod
+/// authors post next to their downloads, not user secrets.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ArchivePasswords {
+    pub passwords: HashMap<String, String>, // source md5 -> password
+}
+
+pub struct PasswordStore {
+    pub path: PathBuf,
+}
+
+impl PasswordStore {
+    pub fn new(base_path: &Path) -> Self {
+        Self {
+            path: base_path.join("archive_passwords.json"),
+        }
+    }
+
+    pub fn load(&self) -> Result<ArchivePasswords> {
+        if !self.path.exists() {
+            return Ok(ArchivePasswords::default());
+        }
+        let content = std::fs::read_to_string(&self.path)?;
+        Ok(serde_json::from_str(&content).unwrap_or_default())
+    }
+
+    pub fn get(&self, source_hash: &str) -> Option<String> {
+        self.load().ok()?.passwords.get(source_hash).cloned()
+    }
+
+    pub fn remember(&self, source_hash: &str, password: &str) -> Result<()> {
+        let mut store = self.load()?;
+        store
+            .passwords
+            .insert(source_hash.to_string(), password.to_string());
+        let content = serde_json::to_string_pretty(&store)?;
+        Safety::write_atomic(&self.path, content.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// MD5 of a file's content as lowercase hex.
+pub fn hash_file(path: &Path) -> Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Md5::new();
+    let mut buffer = vec![0u8; 1024 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
@@ -0,0 +1,100 @@
+//! Portable installs, run from a drive that moves between machines.
+//!
+//! A [`PORTABLE_MARKER`] file next to the executable switches portable mode on. All data
+//! then lives in a `data` folder beside the executable, and every stored path under that
+//! folder is written relative to [`ROOT_TOKEN`], so it resolves wherever the drive is
+//! mounted. Paths elsewhere, such as a game installed on the machine itself, stay
+//! absolute.
+
+use crate::models::LibraryDatabase;
+use crate::settings::GlobalSettings;
+use std::path::{Path, PathBuf};
+
+/// File next to the executable that turns portable mode on. Its contents are ignored.
+pub const PORTABLE_MARKER: &str = "yago.portable";
+
+/// Stands for the portable root at the start of a stored path.
+pub const ROOT_TOKEN: &str = "$YAGO_ROOT";
+
+/// Folder beside the executable holding a portable install's data.
+const DATA_DIR: &str = "data";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortableRoot {
+    root: PathBuf,
+}
+
+impl PortableRoot {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// The portable root of the running executable, if a marker sits next to it.
+    pub fn detect() -> Option<Self> {
+        let exe = std::env::current_exe().ok()?;
+        Self::detect_in(exe.parent()?)
+    }
+
+    /// The portable root for an executable in `exe_dir`, if the marker is there.
+    pub fn detect_in(exe_dir: &Path) -> Option<Self> {
+        exe_dir
+            .join(PORTABLE_MARKER)
+            .is_file()
+            .then(|| Self::new(exe_dir.join(DATA_DIR)))
+    }
+
+    /// Where a portable install keeps its data, in place of the app data folder.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// `path` as stored: under the root it becomes `$YAGO_ROOT/...` with `/` separators,
+    /// so the other platform can read it too. Other paths are returned unchanged.
+    pub fn tokenize(&self, path: &Path) -> PathBuf {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return path.to_path_buf();
+        };
+        let mut stored = ROOT_TOKEN.to_string();
+        for component in relative.components() {
+            stored.push('/');
+            stored.push_str(&component.as_os_str().to_string_lossy());
+        }
+        PathBuf::from(stored)
+    }
+
+    /// The reverse of [`PortableRoot::tokenize`].
+    pub fn resolve(&self, path: &Path) -> PathBuf {
+        let Some(stored) = path.to_str() else {
+            return path.to_path_buf();
+        };
+        let Some(rest) = stored.strip_prefix(ROOT_TOKEN) else {
+            return path.to_path_buf();
+        };
+        if !rest.is_empty() && !rest.starts_with(['/', '\\']) {
+            return path.to_path_buf();
+        }
+        rest.split(['/', '\\'])
+            .filter(|part| !part.is_empty())
+            .fold(self.root.clone(), |resolved, part| resolved.join(part))
+    }
+
+    pub fn tokenize_db(&self, db: &mut LibraryDatabase) {
+        db.for_each_path(|path| *path = self.tokenize(path));
+    }
+
+    pub fn resolve_db(&self, db: &mut LibraryDatabase) {
+        db.for_each_path(|path| *path = self.resolve(path));
+    }
+
+    pub fn tokenize_settings(&self, settings: &mut GlobalSettings) {
+        for path in settings.paths_mut() {
+            *path = self.tokenize(path);
+        }
+    }
+
+    pub fn resolve_settings(&self, settings: &mut GlobalSettings) {
+        for path in settings.paths_mut() {
+            *path = self.resolve(path);
+        }
+    }
+}
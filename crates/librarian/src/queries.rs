@@ -1,8 +1,14 @@
-use crate::models::LibraryDatabase;
-use serde::Serialize;
-use std::collections::HashMap;
+use crate::keybinds::KeybindExtractor;
+use crate::models::{LibraryDatabase, ModRecord, Profile};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use uuid::Uuid;
 
+/// Characters that do not form a cycle group; their overrides always collide.
+pub(crate) const GLOBAL_CHARACTERS: &[&str] = &["Unknown", "Global/Other"];
+
 #[derive(Serialize, Clone, Debug)]
 pub struct ModSnippet {
     pub id: String,
@@ -17,6 +23,95 @@ pub struct CharacterGroup {
     pub active_cycle: Vec<String>, // List of enabled mod IDs for this character
 }
 
+/// Filters, ordering and paging for [`Queries::search_mods`]. Every filter left at its
+/// default matches all mods.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct ModQuery {
+    /// Words that must all appear in the name, author or description.
+    pub text: String,
+    /// Tags the mod must all carry, case-insensitive.
+    pub tags: Vec<String>,
+    pub character: Option<String>,
+    /// Profile for `enabled`, `conflicting` and load order; the active one if unset.
+    pub profile_id: Option<Uuid>,
+    pub enabled: Option<bool>,
+    pub nsfw: Option<bool>,
+    /// Whether the mod overrides a hash that another enabled mod also overrides.
+    pub conflicting: Option<bool>,
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    pub added_after: Option<DateTime<Utc>>,
+    pub added_before: Option<DateTime<Utc>>,
+    pub sort: ModSort,
+    pub descending: bool,
+    pub offset: usize,
+    /// Page size; everything after `offset` if unset.
+    pub limit: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ModSort {
+    #[default]
+    Name,
+    Author,
+    Character,
+    Size,
+    AddedAt,
+    LoadOrder,
+    /// Best text matches first; by name when there is no text.
+    Relevance,
+}
+
+/// A named query kept in the settings so the mod manager can offer it again.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SavedSearch {
+    pub id: Uuid,
+    pub name: String,
+    /// Game the search belongs to; shown for every game if unset.
+    pub game_id: Option<String>,
+    pub query: ModQuery,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ModHit {
+    pub id: Uuid,
+    pub name: String,
+    pub author: String,
+    pub version: String,
+    pub character: String,
+    /// Every character the mod touches, strongest first.
+    pub characters: Vec<String>,
+    pub tags: Vec<String>,
+    pub size: String,
+    pub size_bytes: u64,
+    pub added_at: DateTime<Utc>,
+    pub enabled: bool,
+    pub nsfw: bool,
+    /// Position in the profile's load order, if listed.
+    pub load_order: Option<usize>,
+    /// Enabled mods overriding at least one of the same hashes.
+    pub conflicts_with: Vec<Uuid>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ModQueryResult {
+    /// Matches before paging.
+    pub total: usize,
+    pub offset: usize,
+    pub hits: Vec<ModHit>,
+}
+
+/// One key bound by more than one enabled mod.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct KeyCollision {
+    /// Normalized key, see [`KeybindExtractor::normalize_key`].
+    pub key: String,
+    /// Mods binding it, in load order.
+    pub mods: Vec<Uuid>,
+}
+
 pub struct Queries;
 
 impl Queries {
@@ -34,7 +129,7 @@ impl Queries {
             .and_then(|id| db.profiles.get(&id));
 
         for mod_record in db.mods.values() {
-            if mod_record.owner_game_id != game_id {
+            if mod_record.owner_game_id != db.mod_pool_id(game_id) {
                 continue;
             }
 
@@ -44,8 +139,6 @@ impl Queries {
                 continue;
             }
 
-            let character_name = mod_record.compatibility.character.clone();
-
             let is_enabled = active_profile
                 .map(|p| p.enabled_mod_ids.contains(&mod_record.id))
                 .unwrap_or(false);
@@ -57,14 +150,281 @@ impl Queries {
                 enabled: is_enabled,
             };
 
-            let group = roster.entry(character_name).or_default();
-
-            if is_enabled {
-                group.active_cycle.push(snippet.id.clone());
+            // A mod touching several characters is listed under each of them
+            let characters = if mod_record.compatibility.characters.is_empty() {
+                std::slice::from_ref(&mod_record.compatibility.character)
+            } else {
+                mod_record.compatibility.characters.as_slice()
+            };
+            for character_name in characters {
+                let group = roster.entry(character_name.clone()).or_default();
+                if is_enabled {
+                    group.active_cycle.push(snippet.id.clone());
+                }
+                group.skins.push(snippet.clone());
             }
-            group.skins.push(snippet);
         }
 
         roster
     }
+
+    /// Mods of a game matching `query`, sorted and paged.
+    pub fn search_mods(db: &LibraryDatabase, game_id: &str, query: &ModQuery) -> ModQueryResult {
+        let profile = match query.profile_id {
+            Some(id) => db.profiles.get(&id),
+            None => db
+                .games
+                .get(game_id)
+                .and_then(|g| Uuid::parse_str(&g.active_profile_id).ok())
+                .and_then(|id| db.profiles.get(&id)),
+        };
+        let conflicts = profile.map(|p| Self::conflicts(db, p)).unwrap_or_default();
+        let words: Vec<String> = query
+            .text
+            .split_whitespace()
+            .map(|w| w.to_lowercase())
+            .collect();
+        let tags: Vec<String> = query.tags.iter().map(|t| t.to_lowercase()).collect();
+
+        let mut hits: Vec<(u32, ModHit)> = Vec::new();
+        for record in db.mods.values() {
+            if record.owner_game_id != db.mod_pool_id(game_id) {
+                continue;
+            }
+            let Some(score) = Self::text_score(record, &words) else {
+                continue;
+            };
+            let hit = Self::hit(record, profile, &conflicts);
+            if Self::matches(&hit, query, &tags) {
+                hits.push((score, hit));
+            }
+        }
+
+        hits.sort_by(|(score_a, a), (score_b, b)| {
+            let order = match query.sort {
+                ModSort::Name => Ordering::Equal,
+                ModSort::Author => a.author.to_lowercase().cmp(&b.author.to_lowercase()),
+                ModSort::Character => a.character.cmp(&b.character),
+                ModSort::Size => a.size_bytes.cmp(&b.size_bytes),
+                ModSort::AddedAt => a.added_at.cmp(&b.added_at),
+                // Mods outside the load order go last
+                ModSort::LoadOrder => a
+                    .load_order
+                    .unwrap_or(usize::MAX)
+                    .cmp(&b.load_order.unwrap_or(usize::MAX)),
+                ModSort::Relevance => score_b.cmp(score_a),
+            };
+            let order = if query.descending {
+                order.reverse()
+            } else {
+                order
+            };
+            // Name, then id, keeps pages stable between calls
+            order
+                .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+                .then_with(|| a.id.cmp(&b.id))
+        });
+
+        let total = hits.len();
+        let hits = hits
+            .into_iter()
+            .skip(query.offset)
+            .take(query.limit.unwrap_or(usize::MAX))
+            .map(|(_, hit)| hit)
+            .collect();
+        ModQueryResult {
+            total,
+            offset: query.offset,
+            hits,
+        }
+    }
+
+    /// For each mod enabled in the profile, the other enabled mods overriding one of
+    /// its hashes. Skins of the same character are cycled, not in conflict.
+    pub fn conflicts(db: &LibraryDatabase, profile: &Profile) -> HashMap<Uuid, Vec<Uuid>> {
+        let mut providers: HashMap<String, Vec<&ModRecord>> = HashMap::new();
+        for id in &profile.enabled_mod_ids {
+            let Some(record) = db.mods.get(id) else {
+                continue;
+            };
+            let hashes: HashSet<String> = record
+                .compatibility
+                .hashes
+                .iter()
+                .map(|h| h.to_lowercase())
+                .collect();
+            for hash in hashes {
+                providers.entry(hash).or_default().push(record);
+            }
+        }
+
+        let mut conflicts: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+        for records in providers.values() {
+            for a in records {
+                for b in records {
+                    let character = &a.compatibility.character;
+                    let cycled = character == &b.compatibility.character
+                        && !GLOBAL_CHARACTERS.contains(&character.as_str());
+                    if a.id == b.id || cycled {
+                        continue;
+                    }
+                    let others = conflicts.entry(a.id).or_default();
+                    if !others.contains(&b.id) {
+                        others.push(b.id);
+                    }
+                }
+            }
+        }
+        for others in conflicts.values_mut() {
+            others.sort();
+        }
+        conflicts
+    }
+
+    /// Keys bound by more than one mod enabled in the profile, by key. As with hash
+    /// conflicts, skins of the same character are cycled and may share keys.
+    pub fn key_collisions(db: &LibraryDatabase, profile: &Profile) -> Vec<KeyCollision> {
+        let mut bound: BTreeMap<String, Vec<&ModRecord>> = BTreeMap::new();
+        for record in Self::enabled_in_order(db, profile) {
+            let keys: HashSet<String> = record
+                .config
+                .keybinds
+                .values()
+                .flat_map(|k| k.keys.iter().chain(&k.back))
+                .map(|key| KeybindExtractor::normalize_key(key))
+                .filter(|key| !key.is_empty())
+                .collect();
+            for key in keys {
+                bound.entry(key).or_default().push(record);
+            }
+        }
+
+        bound
+            .into_iter()
+            .filter(|(_, records)| {
+                let character = &records[0].compatibility.character;
+                let cycled = !GLOBAL_CHARACTERS.contains(&character.as_str())
+                    && records
+                        .iter()
+                        .all(|r| &r.compatibility.character == character);
+                records.len() > 1 && !cycled
+            })
+            .map(|(key, records)| KeyCollision {
+                key,
+                mods: records.iter().map(|r| r.id).collect(),
+            })
+            .collect()
+    }
+
+    /// Other mods split out of the same archive as `mod_id`, by name.
+    pub fn pack_siblings(db: &LibraryDatabase, mod_id: Uuid) -> Vec<Uuid> {
+        let Some(pack_id) = db
+            .mods
+            .get(&mod_id)
+            .and_then(|r| r.source.as_ref())
+            .map(|s| s.pack_id)
+        else {
+            return Vec::new();
+        };
+        let mut siblings: Vec<&ModRecord> = db
+            .mods
+            .values()
+            .filter(|r| r.id != mod_id && r.source.as_ref().is_some_and(|s| s.pack_id == pack_id))
+            .collect();
+        siblings.sort_by(|a, b| a.meta.name.cmp(&b.meta.name));
+        siblings.iter().map(|r| r.id).collect()
+    }
+
+    /// Enabled mods of the profile in load order, then any enabled mods it does not order.
+    fn enabled_in_order<'a>(db: &'a LibraryDatabase, profile: &Profile) -> Vec<&'a ModRecord> {
+        let enabled: HashSet<&Uuid> = profile.enabled_mod_ids.iter().collect();
+        let mut seen = HashSet::new();
+        profile
+            .load_order
+            .iter()
+            .chain(&profile.enabled_mod_ids)
+            .filter(|id| enabled.contains(id) && seen.insert(**id))
+            .filter_map(|id| db.mods.get(id))
+            .collect()
+    }
+
+    /// `None` if a word is missing; otherwise higher for matches in the name.
+    fn text_score(record: &ModRecord, words: &[String]) -> Option<u32> {
+        let name = record.meta.name.to_lowercase();
+        let author = record.meta.author.to_lowercase();
+        let description = record
+            .meta
+            .description
+            .as_deref()
+            .unwrap_or("")
+            .to_lowercase();
+
+        let mut score = 0;
+        for word in words {
+            score += if name
+                .split_whitespace()
+                .any(|w| w.starts_with(word.as_str()))
+            {
+                4
+            } else if name.contains(word.as_str()) {
+                3
+            } else if author.contains(word.as_str()) {
+                2
+            } else if description.contains(word.as_str()) {
+                1
+            } else {
+                return None;
+            };
+        }
+        Some(score)
+    }
+
+    fn hit(
+        record: &ModRecord,
+        profile: Option<&Profile>,
+        conflicts: &HashMap<Uuid, Vec<Uuid>>,
+    ) -> ModHit {
+        ModHit {
+            id: record.id,
+            name: record.meta.name.clone(),
+            author: record.meta.author.clone(),
+            version: record.meta.version.clone(),
+            character: record.compatibility.character.clone(),
+            characters: record.compatibility.characters.clone(),
+            tags: record.config.tags.clone(),
+            size: record.size.clone(),
+            size_bytes: record.size_bytes,
+            added_at: record.added_at,
+            enabled: profile.is_some_and(|p| p.enabled_mod_ids.contains(&record.id)),
+            nsfw: record
+                .config
+                .tags
+                .iter()
+                .any(|t| t.eq_ignore_ascii_case("nsfw")),
+            load_order: profile.and_then(|p| p.load_order.iter().position(|id| *id == record.id)),
+            conflicts_with: conflicts.get(&record.id).cloned().unwrap_or_default(),
+        }
+    }
+
+    fn matches(hit: &ModHit, query: &ModQuery, tags: &[String]) -> bool {
+        let has_tags = tags
+            .iter()
+            .all(|tag| hit.tags.iter().any(|t| t.to_lowercase() == *tag));
+        let character = query.character.as_ref().is_none_or(|c| {
+            c.eq_ignore_ascii_case(&hit.character)
+                || hit.characters.iter().any(|h| c.eq_ignore_ascii_case(h))
+        });
+
+        has_tags
+            && character
+            && query.enabled.is_none_or(|e| e == hit.enabled)
+            && query.nsfw.is_none_or(|n| n == hit.nsfw)
+            && query
+                .conflicting
+                .is_none_or(|c| c != hit.conflicts_with.is_empty())
+            && query.min_size.is_none_or(|min| hit.size_bytes >= min)
+            && query.max_size.is_none_or(|max| hit.size_bytes <= max)
+            && query.added_after.is_none_or(|t| hit.added_at >= t)
+            && query.added_before.is_none_or(|t| hit.added_at < t)
+    }
 }
@@ -0,0 +1,263 @@
+//! Moves the mods, runners or prefixes folder somewhere else, such as a bigger disk.
+//!
+//! [`Librarian::update_roots`] only changes where new data goes. Relocating copies the
+//! folder over (resumably), checks the copy, rewrites every path the game databases
+//! store under it, points the librarian at the new place and only then deletes the
+//! original. An interrupted relocation can simply be started again.
+
+use crate::error::{LibrarianError, Result};
+use crate::import_queue::ImportQueue;
+use crate::models::LibraryDatabase;
+use crate::Librarian;
+use fs_engine::{CancelToken, Mirror, MirrorOptions, MirrorProgress, MirrorReport};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Left in the destination while a relocation is under way, holding the source path,
+/// so a second attempt knows the half-filled folder is its own.
+const MARKER_FILE: &str = ".yago_relocation";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageRoot {
+    Mods,
+    Runners,
+    Prefixes,
+}
+
+impl StorageRoot {
+    pub fn path(self, librarian: &Librarian) -> &Path {
+        match self {
+            Self::Mods => &librarian.mods_root,
+            Self::Runners => &librarian.runners_root,
+            Self::Prefixes => &librarian.prefixes_root,
+        }
+    }
+
+    fn set_path(self, librarian: &mut Librarian, path: PathBuf) {
+        match self {
+            Self::Mods => librarian.mods_root = path,
+            Self::Runners => librarian.runners_root = path,
+            Self::Prefixes => librarian.prefixes_root = path,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RelocationStage {
+    Copying,
+    Verifying,
+    Rewriting,
+    Removing,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelocationProgress {
+    pub root: StorageRoot,
+    pub stage: RelocationStage,
+    /// File counts are only filled in while copying.
+    pub files_done: u64,
+    pub files_total: u64,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+}
+
+pub type RelocationCallback = Arc<dyn Fn(&RelocationProgress) + Send + Sync>;
+
+#[derive(Clone, Default)]
+pub struct RelocationOptions {
+    /// Stops the copy. What was copied stays in the destination for the next attempt.
+    pub cancel: CancelToken,
+    pub on_progress: Option<RelocationCallback>,
+    /// Imports still waiting in this queue would commit under the old folder; they are
+    /// cancelled and drained before anything is copied.
+    pub imports: Option<ImportQueue>,
+    /// Leave the original for the caller to delete with [`StorageRelocator::remove_source`],
+    /// once it has saved the new location.
+    pub keep_source: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelocationReport {
+    pub root: StorageRoot,
+    pub from: PathBuf,
+    pub to: PathBuf,
+    pub copy: MirrorReport,
+    /// Stored paths that now point into the new folder.
+    pub paths_rewritten: usize,
+    /// False when the original could not be deleted; everything already uses the copy.
+    pub source_removed: bool,
+}
+
+pub struct StorageRelocator;
+
+impl StorageRelocator {
+    /// Moves `root` to `destination`, which must be empty, missing, or left from an
+    /// interrupted move of the same folder. The caller saves the new location in its
+    /// settings afterwards; the librarian itself already uses it. Set
+    /// [`RelocationOptions::keep_source`] to save it before the original is gone.
+    pub async fn relocate(
+        librarian: &mut Librarian,
+        root: StorageRoot,
+        destination: PathBuf,
+        options: RelocationOptions,
+    ) -> Result<RelocationReport> {
+        let from = root.path(librarian).to_path_buf();
+        Self::check_destination(&from, &destination)?;
+        let report_stage = |stage, progress: &MirrorProgress| {
+            if let Some(cb) = &options.on_progress {
+                cb(&RelocationProgress {
+                    root,
+                    stage,
+                    files_done: progress.files_done,
+                    files_total: progress.files_total,
+                    bytes_done: progress.bytes_done,
+                    bytes_total: progress.bytes_total,
+                });
+            }
+        };
+
+        if let Some(queue) = &options.imports {
+            queue.drain().await;
+        }
+
+        let mut copy = MirrorReport::default();
+        let marker = destination.join(MARKER_FILE);
+        if from.is_dir() {
+            std::fs::create_dir_all(&destination)?;
+            std::fs::write(&marker, from.to_string_lossy().as_bytes())?;
+
+            report_stage(RelocationStage::Copying, &MirrorProgress::default());
+            let mirror_options = MirrorOptions {
+                cancel: options.cancel.clone(),
+                on_progress: options.on_progress.clone().map(|cb| {
+                    Arc::new(move |progress: &MirrorProgress| {
+                        cb(&RelocationProgress {
+                            root,
+                            stage: RelocationStage::Copying,
+                            files_done: progress.files_done,
+                            files_total: progress.files_total,
+                            bytes_done: progress.bytes_done,
+                            bytes_total: progress.bytes_total,
+                        })
+                    }) as fs_engine::MirrorProgressCallback
+                }),
+            };
+            let (source, target) = (from.clone(), destination.clone());
+            copy = tokio::task::spawn_blocking(move || {
+                Mirror::copy_tree(&source, &target, &mirror_options)
+            })
+            .await
+            .map_err(|e| LibrarianError::Io(std::io::Error::other(e.to_string())))??;
+
+            report_stage(RelocationStage::Verifying, &MirrorProgress::default());
+            let (source, target) = (from.clone(), destination.clone());
+            tokio::task::spawn_blocking(move || Mirror::verify(&source, &target))
+                .await
+                .map_err(|e| LibrarianError::Io(std::io::Error::other(e.to_string())))??;
+        }
+
+        report_stage(RelocationStage::Rewriting, &MirrorProgress::default());
+        let mut paths_rewritten = 0;
+        {
+            // No import may commit a path under the old folder meanwhile
+            let _guard = librarian.lock_imports().await;
+            for game_id in librarian.discover_game_ids().await? {
+                let mut db = librarian.load_game_db(&game_id).await?;
+                let rewritten = Self::rewrite_paths(&mut db, &from, &destination);
+                if rewritten > 0 {
+                    librarian.save_game_db(&game_id, &db).await?;
+                    paths_rewritten += rewritten;
+                }
+            }
+        }
+        root.set_path(librarian, destination.clone());
+
+        let _ = std::fs::remove_file(&marker);
+        println!(
+            "Librarian: moved {} to {} ({} paths rewritten)",
+            from.display(),
+            destination.display(),
+            paths_rewritten
+        );
+
+        let mut report = RelocationReport {
+            root,
+            from,
+            to: destination,
+            copy,
+            paths_rewritten,
+            source_removed: false,
+        };
+        if !options.keep_source {
+            report_stage(RelocationStage::Removing, &MirrorProgress::default());
+            Self::remove_source(&mut report);
+        }
+        Ok(report)
+    }
+
+    /// Deletes the original folder of a finished relocation and records whether it
+    /// is gone.
+    pub fn remove_source(report: &mut RelocationReport) -> bool {
+        report.source_removed = match std::fs::remove_dir_all(&report.from) {
+            Ok(()) => true,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => true,
+            Err(e) => {
+                eprintln!(
+                    "Librarian: moved {} but could not delete it: {}",
+                    report.from.display(),
+                    e
+                );
+                false
+            }
+        };
+        report.source_removed
+    }
+
+    /// Points every stored path under `from` at the same place under `to`. Returns how
+    /// many paths changed.
+    pub fn rewrite_paths(db: &mut LibraryDatabase, from: &Path, to: &Path) -> usize {
+        let mut rewritten = 0;
+        db.for_each_path(|path| {
+            if let Ok(relative) = path.strip_prefix(from) {
+                *path = to.join(relative);
+                rewritten += 1;
+            }
+        });
+        rewritten
+    }
+
+    fn check_destination(from: &Path, destination: &Path) -> Result<()> {
+        if !destination.is_absolute() {
+            return Err(LibrarianError::Validation(format!(
+                "Not an absolute path: {}",
+                destination.display()
+            )));
+        }
+        if destination.starts_with(from) || from.starts_with(destination) {
+            return Err(LibrarianError::Validation(format!(
+                "Cannot move {} into {}",
+                from.display(),
+                destination.display()
+            )));
+        }
+
+        let Ok(mut entries) = std::fs::read_dir(destination) else {
+            return Ok(());
+        };
+        if entries.next().is_none() {
+            return Ok(());
+        }
+        let resuming = std::fs::read_to_string(destination.join(MARKER_FILE))
+            .is_ok_and(|source| Path::new(&source) == from);
+        if resuming {
+            return Ok(());
+        }
+        Err(LibrarianError::Validation(format!(
+            "Destination is not empty: {}",
+            destination.display()
+        )))
+    }
+}
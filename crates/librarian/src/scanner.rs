@@ -6,6 +6,9 @@ use std::path::{Path, PathBuf};
 pub struct DiscoveredGame {
     pub template_id: String,
     pub path: PathBuf,
+    /// Set when the executable is one of the template's other regions.
+    #[serde(default)]
+    pub region: Option<String>,
 }
 
 pub fn scan(templates: &[GameTemplate]) -> Vec<DiscoveredGame> {
@@ -103,14 +106,15 @@ pub fn scan_roots(
 
                     let fname = entry.file_name().to_string_lossy();
                     for t in templates {
-                        if t.executables
-                            .iter()
-                            .any(|exe| exe.eq_ignore_ascii_case(&fname))
+                        if let Some((_, region)) = t
+                            .all_executables()
+                            .find(|(exe, _)| exe.eq_ignore_ascii_case(&fname))
                         {
                             seen_paths.insert(path.clone());
                             found.push(DiscoveredGame {
                                 template_id: t.id.clone(),
                                 path: path.clone(),
+                                region: region.map(str::to_string),
                             });
                         }
                     }
@@ -137,12 +141,13 @@ fn scan_linux(templates: &[GameTemplate]) -> Vec<DiscoveredGame> {
 #[doc(hidden)]
 pub fn check_path(path: &Path, templates: &[GameTemplate], results: &mut Vec<DiscoveredGame>) {
     for t in templates {
-        for exe in &t.executables {
+        for (exe, region) in t.all_executables() {
             let exe_path = path.join(exe);
             if exe_path.exists() && exe_path.is_file() {
                 results.push(DiscoveredGame {
                     template_id: t.id.clone(),
                     path: exe_path,
+                    region: region.map(str::to_string),
                 });
                 break;
             }
@@ -156,6 +161,7 @@ pub fn check_path(path: &Path, templates: &[GameTemplate], results: &mut Vec<Dis
                     results.push(DiscoveredGame {
                         template_id: t.id.clone(),
                         path: stem_path,
+                        region: region.map(str::to_string),
                     });
                     break;
                 }
@@ -1,7 +1,10 @@
+use crate::backend::BackendKind;
 use crate::error::Result;
+use crate::portable::PortableRoot;
+use crate::queries::SavedSearch;
+use fs_engine::{BackupRotation, RestoredBackup, Safety, DEFAULT_BACKUP_COUNT};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
-use tokio::fs;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GlobalSettings {
@@ -18,6 +21,16 @@ pub struct GlobalSettings {
     pub stream_safe: bool,
     pub nsfw_behavior: String, // "blur" | "hide"
     pub close_on_launch: bool,
+    /// Store identical mod files once, via hardlinks into the library's blob store.
+    #[serde(default)]
+    pub deduplicate_mods: bool,
+    /// Mod manager searches the user named and kept.
+    #[serde(default)]
+    pub saved_searches: Vec<SavedSearch>,
+    /// Where game databases are stored. Unset until the first start of a build that
+    /// knows the setting, which records what the library already uses.
+    #[serde(default)]
+    pub library_backend: Option<BackendKind>,
 }
 
 impl Default for GlobalSettings {
@@ -36,36 +49,149 @@ impl Default for GlobalSettings {
             stream_safe: true,
             nsfw_behavior: "blur".to_string(),
             close_on_launch: false,
+            deduplicate_mods: false,
+            saved_searches: Vec::new(),
+            library_backend: None,
         }
     }
 }
 
+impl GlobalSettings {
+    /// `yago_storage_path`, or the app data folder when it is unset.
+    pub fn storage_root(&self, app_data_dir: &Path) -> PathBuf {
+        if self.yago_storage_path.as_os_str().is_empty() {
+            app_data_dir.to_path_buf()
+        } else {
+            self.yago_storage_path.clone()
+        }
+    }
+
+    /// `runners_path`, or `runners` in the storage root when it is unset.
+    pub fn runners_dir(&self, app_data_dir: &Path) -> PathBuf {
+        if self.runners_path.as_os_str().is_empty() {
+            self.storage_root(app_data_dir).join("runners")
+        } else {
+            self.runners_path.clone()
+        }
+    }
+
+    /// `prefixes_path`, or `prefixes` in the storage root when it is unset.
+    pub fn prefixes_dir(&self, app_data_dir: &Path) -> PathBuf {
+        if self.prefixes_path.as_os_str().is_empty() {
+            self.storage_root(app_data_dir).join("prefixes")
+        } else {
+            self.prefixes_path.clone()
+        }
+    }
+
+    /// Every folder setting, for rewriting them together.
+    pub fn paths_mut(&mut self) -> [&mut PathBuf; 8] {
+        [
+            &mut self.steam_compat_tools_path,
+            &mut self.wine_prefix_path,
+            &mut self.yago_storage_path,
+            &mut self.default_games_path,
+            &mut self.mods_path,
+            &mut self.runners_path,
+            &mut self.prefixes_path,
+            &mut self.cache_path,
+        ]
+    }
+}
+
 pub struct SettingsManager {
     pub config_path: PathBuf,
+    portable: Option<PortableRoot>,
 }
 
 impl SettingsManager {
     pub fn new(app_data_dir: PathBuf) -> Self {
         Self {
             config_path: app_data_dir.join("settings.json"),
+            portable: None,
+        }
+    }
+
+    /// Settings kept in the portable root, with the folders in it stored relative to it.
+    pub fn portable(root: PortableRoot) -> Self {
+        Self {
+            config_path: root.root().join("settings.json"),
+            portable: Some(root),
         }
     }
 
+    fn parse(&self, content: &[u8]) -> Result<GlobalSettings> {
+        let mut settings: GlobalSettings = serde_json::from_slice(content)?;
+        if let Some(portable) = &self.portable {
+            portable.resolve_settings(&mut settings);
+        }
+        Ok(settings)
+    }
+
+    /// Settings are saved rarely, so every save is backed up.
+    pub fn backups(&self) -> BackupRotation {
+        let dir = self
+            .config_path
+            .parent()
+            .map(|p| p.join("backups"))
+            .unwrap_or_else(|| PathBuf::from("backups"));
+        BackupRotation::new(dir, DEFAULT_BACKUP_COUNT)
+    }
+
     pub async fn load(&self) -> Result<GlobalSettings> {
         if !self.config_path.exists() {
             let default = GlobalSettings::default();
             self.save(&default).await?;
             return Ok(default);
         }
+        Ok(self.read_or_recover()?.0)
+    }
 
-        let content = fs::read_to_string(&self.config_path).await?;
-        let settings: GlobalSettings = serde_json::from_str(&content).unwrap_or_default();
-        Ok(settings)
+    /// Reads the settings without touching the async runtime, for app startup. A damaged
+    /// file is replaced by its newest valid backup, which is returned for reporting;
+    /// without one the defaults are used.
+    pub fn read_or_recover(&self) -> Result<(GlobalSettings, Option<RestoredBackup>)> {
+        if !self.config_path.exists() {
+            return Ok((GlobalSettings::default(), None));
+        }
+
+        let content = std::fs::read(&self.config_path)?;
+        if let Ok(settings) = self.parse(&content) {
+            return Ok((settings, None));
+        }
+
+        let restored = self.backups().restore(&self.config_path, |data| {
+            serde_json::from_slice::<GlobalSettings>(data).is_ok()
+        })?;
+        match restored {
+            Some(restored) => {
+                eprintln!(
+                    "Settings WARNING: settings.json was damaged, restored {}",
+                    restored.backup.display()
+                );
+                let content = std::fs::read(&self.config_path)?;
+                Ok((self.parse(&content)?, Some(restored)))
+            }
+            None => {
+                eprintln!("Settings WARNING: settings.json is damaged, using defaults");
+                Ok((GlobalSettings::default(), None))
+            }
+        }
     }
 
     pub async fn save(&self, settings: &GlobalSettings) -> Result<()> {
-        let content = serde_json::to_string_pretty(settings)?;
-        fs::write(&self.config_path, content).await?;
+        let content = match &self.portable {
+            Some(portable) => {
+                let mut stored = settings.clone();
+                portable.tokenize_settings(&mut stored);
+                serde_json::to_string_pretty(&stored)?
+            }
+            None => serde_json::to_string_pretty(settings)?,
+        };
+        Safety::write_atomic(&self.config_path, content.as_bytes())?;
+        if let Err(e) = self.backups().snapshot(&self.config_path) {
+            eprintln!("Settings WARNING: Failed to back up settings.json: {}", e);
+        }
         Ok(())
     }
 }
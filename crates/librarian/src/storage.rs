@@ -1,9 +1,15 @@
+use crate::backend::{BackendKind, JsonBackend, LibraryBackend, SqliteBackend, UnavailableBackend};
 use crate::error::{LibrarianError, Result};
-use crate::models::{LibraryDatabase, Profile};
+use crate::models::{LibraryDatabase, ModRecord, Profile};
+use crate::portable::PortableRoot;
 use crate::template::GameTemplate;
 use chrono::Utc;
-use std::collections::HashMap;
+use fs_engine::{
+    BlobStore, GcReport, IngestReport, RestoredBackup, ThumbnailOptions, ThumbnailSet, Thumbnailer,
+};
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
+use std::sync::Arc;
 use tokio::fs;
 use uuid::Uuid;
 
@@ -23,6 +29,8 @@ pub struct LibrarianConfig {
     pub runners_path: Option<PathBuf>,
     pub prefixes_path: Option<PathBuf>,
     pub cache_path: Option<PathBuf>,
+    /// Set when running portable; stored paths under its root are kept relative to it.
+    pub portable: Option<PortableRoot>,
 }
 
 #[derive(Clone)]
@@ -37,6 +45,11 @@ pub struct Librarian {
     pub prefixes_root: PathBuf,
     pub cache_root: PathBuf,
     pub mods_root: PathBuf,
+    backend: Arc<dyn LibraryBackend>,
+    portable: Option<PortableRoot>,
+    /// Held by imports while they read and write a game database, so imports running
+    /// side by side do not overwrite each other's records. Shared by clones.
+    import_lock: Arc<tokio::sync::Mutex<()>>,
 }
 
 impl Librarian {
@@ -72,12 +85,26 @@ impl Librarian {
                 .as_ref()
                 .cloned()
                 .unwrap_or_else(|| config.base_path.join("mods")),
+            backend: Arc::new(JsonBackend::new(config.base_path.join("games"))),
+            portable: None,
+            import_lock: Arc::new(tokio::sync::Mutex::new(())),
         };
-        s.update_roots(config);
+        s.set_roots(config);
         s
     }
 
+    pub(crate) async fn lock_imports(&self) -> tokio::sync::MutexGuard<'_, ()> {
+        self.import_lock.lock().await
+    }
+
+    /// Points the librarian at new storage folders, reopening the backend in use there.
     pub fn update_roots(&mut self, config: LibrarianConfig) {
+        self.set_roots(config);
+        // Failures are logged and leave the backend unavailable
+        let _ = self.open_backend(self.backend.kind());
+    }
+
+    fn set_roots(&mut self, config: LibrarianConfig) {
         let base = config.base_path;
         self.base_path = base.clone();
         self.games_root = base.join("games");
@@ -94,6 +121,78 @@ impl Librarian {
             .unwrap_or_else(|| base.join("prefixes"));
         self.cache_root = config.cache_path.unwrap_or_else(|| base.join("cache"));
         self.mods_root = config.mods_path.unwrap_or_else(|| base.join("mods"));
+        self.portable = config.portable;
+    }
+
+    /// The backend a library that never chose one is on: SQLite when a database was
+    /// written (earlier builds moved every library there), JSON otherwise.
+    pub fn detect_backend(&self) -> BackendKind {
+        if self.library_db_path().is_file() {
+            BackendKind::Sqlite
+        } else {
+            BackendKind::Json
+        }
+    }
+
+    /// Switches to the `kind` of backend, as chosen in the settings. Returns the games
+    /// moved into SQLite on the way. Going back to JSON is refused once games were
+    /// moved to SQLite, since their `game.json` files stopped being updated. When the
+    /// backend cannot be opened, every later call fails with the reason rather than
+    /// reading another copy of the library.
+    pub fn open_backend(&mut self, kind: BackendKind) -> Result<Vec<String>> {
+        let opened = match kind {
+            BackendKind::Sqlite => self.use_sqlite(),
+            BackendKind::Json => self.use_json().map(|_| Vec::new()),
+        };
+        if let Err(e) = &opened {
+            self.backend = Self::unavailable(kind, e);
+        }
+        opened
+    }
+
+    fn use_json(&mut self) -> Result<()> {
+        if self.library_db_path().is_file() {
+            let moved = SqliteBackend::open(self.library_db_path())?.game_ids()?;
+            if !moved.is_empty() {
+                return Err(LibrarianError::Validation(format!(
+                    "{} game(s) are stored in {}; their game.json files are out of date",
+                    moved.len(),
+                    self.library_db_path().display()
+                )));
+            }
+        }
+        self.backend = Arc::new(JsonBackend::new(self.games_root.clone()));
+        Ok(())
+    }
+
+    fn unavailable(kind: BackendKind, error: impl std::fmt::Display) -> Arc<dyn LibraryBackend> {
+        eprintln!(
+            "Librarian ERROR: Failed to open the library database: {}",
+            error
+        );
+        Arc::new(UnavailableBackend::new(kind, error.to_string()))
+    }
+
+    pub fn portable(&self) -> Option<&PortableRoot> {
+        self.portable.as_ref()
+    }
+
+    /// SQLite database used by the [`BackendKind::Sqlite`] backend.
+    pub fn library_db_path(&self) -> PathBuf {
+        self.base_path.join("library.sqlite")
+    }
+
+    pub fn backend_kind(&self) -> BackendKind {
+        self.backend.kind()
+    }
+
+    /// Switches to the SQLite backend, first importing every game whose `game.json`
+    /// has not been imported yet. Returns the ids of the imported games.
+    pub fn use_sqlite(&mut self) -> Result<Vec<String>> {
+        let backend = SqliteBackend::open(self.library_db_path())?;
+        let imported = backend.import_json(&JsonBackend::new(self.games_root.clone()))?;
+        self.backend = Arc::new(backend);
+        Ok(imported)
     }
 
     pub fn ensure_core_dirs(&self) -> Result<()> {
@@ -135,45 +234,65 @@ impl Librarian {
             .join(profile_id.to_string())
     }
 
-    pub async fn load_game_db(&self, game_id: &str) -> Result<LibraryDatabase> {
-        let paths = self.game_paths(game_id);
+    /// Cache folder holding a mod's generated thumbnails.
+    pub fn mod_thumbnail_dir(&self, mod_id: &Uuid) -> PathBuf {
+        self.cache_root.join("thumbnails").join(mod_id.to_string())
+    }
 
-        println!("Librarian: Loading database for {}", game_id);
+    /// Thumbnails for a mod's most representative texture, regenerated when the
+    /// mod's files changed since the last call.
+    pub fn ensure_mod_thumbnails(&self, record: &ModRecord) -> Result<Option<ThumbnailSet>> {
+        let cache_dir = self.mod_thumbnail_dir(&record.id);
+        Ok(Thumbnailer::ensure(
+            &record.path,
+            &cache_dir,
+            &ThumbnailOptions::default(),
+        )?)
+    }
 
-        let mut db = if paths.db.exists() {
-            let content = fs::read_to_string(&paths.db).await?;
-            serde_json::from_str::<LibraryDatabase>(&content).map_err(|e| {
-                eprintln!(
-                    "Librarian ERROR: Failed to parse game.json for {}: {}",
-                    game_id, e
-                );
-                LibrarianError::Io(std::io::Error::new(
-                    std::io::ErrorKind::InvalidData,
-                    e.to_string(),
-                ))
-            })?
-        } else {
-            LibraryDatabase {
-                version: "1.0".to_string(),
-                ..Default::default()
-            }
-        };
+    /// Content-addressed store shared by all mods. It lives inside the mods root so its
+    /// blobs are on the same filesystem as the mods linking to them; the leading dot
+    /// keeps it out of game discovery and the mods watcher.
+    pub fn blob_store(&self) -> BlobStore {
+        BlobStore::new(self.mods_root.join(".yago_store"))
+    }
 
-        let mut needs_save = false;
+    /// Replaces the mod's large files with links into the blob store.
+    pub fn deduplicate_mod(&self, record: &ModRecord) -> Result<IngestReport> {
+        Ok(self
+            .blob_store()
+            .ingest(&record.id.to_string(), &record.path)?)
+    }
 
-        // Load Profiles separately
-        if paths.profiles.exists() {
-            let content = fs::read_to_string(&paths.profiles).await?;
-            if let Ok(profiles) =
-                serde_json::from_str::<HashMap<uuid::Uuid, crate::models::Profile>>(&content)
-            {
-                db.profiles = profiles;
-            } else {
-                eprintln!("Librarian WARNING: Failed to parse profiles.json for {}, resetting to default.", game_id);
-                needs_save = true;
+    /// Removes blobs no longer referenced by any mod record of any game on disk.
+    pub async fn collect_blob_garbage(&self) -> Result<GcReport> {
+        let mut live = HashSet::new();
+        for game_id in self.discover_game_ids().await? {
+            let db = self.load_game_db(&game_id).await?;
+            live.extend(db.mods.keys().map(|id| id.to_string()));
+        }
+        Ok(self.blob_store().collect_garbage(&live)?)
+    }
+
+    pub async fn load_game_db(&self, game_id: &str) -> Result<LibraryDatabase> {
+        println!("Librarian: Loading database for {}", game_id);
+
+        let mut db = self.read_db(game_id)?.unwrap_or_else(|| LibraryDatabase {
+            version: "1.0".to_string(),
+            ..Default::default()
+        });
+        if let Some(pool) = db.games.get(game_id).and_then(|g| g.mod_pool.clone()) {
+            match self.read_db(&pool)? {
+                Some(pool_db) => db.mods = pool_db.mods,
+                None => eprintln!(
+                    "Librarian WARNING: {} shares the mods of {}, which is gone",
+                    game_id, pool
+                ),
             }
         }
 
+        let mut needs_save = false;
+
         // Ensure at least one profile exists and is active
         if db.profiles.is_empty() && !db.games.is_empty() {
             let default_p = crate::models::Profile::default();
@@ -198,40 +317,111 @@ impl Librarian {
     }
 
     pub async fn save_game_db(&self, game_id: &str, db: &LibraryDatabase) -> Result<()> {
+        // The game folder also holds profile data, whatever the backend
         let paths = self.game_paths(game_id);
         if !paths.root.exists() {
             fs::create_dir_all(&paths.root).await?;
         }
+        let Some(pool) = db.games.get(game_id).and_then(|g| g.mod_pool.as_deref()) else {
+            return self.write_db(game_id, db);
+        };
 
-        // 1. Save game.json (Games + Mods)
-        let mut db_clone = db.clone();
-        db_clone.profiles = HashMap::new(); // Don't save profiles in game.json
+        // The mods belong to the pool's database, the rest to this instance's
+        let mut pool_db = self
+            .read_db(pool)?
+            .ok_or_else(|| LibrarianError::Validation(format!("Mod pool {} not found", pool)))?;
+        pool_db.mods = db.mods.clone();
+        self.write_db(pool, &pool_db)?;
+        let own = LibraryDatabase {
+            mods: HashMap::new(),
+            ..db.clone()
+        };
+        self.write_db(game_id, &own)
+    }
 
-        let content = serde_json::to_string_pretty(&db_clone)?;
-        fs::write(&paths.db, content).await?;
+    /// The stored database as it is, without a mod pool's mods.
+    fn read_db(&self, game_id: &str) -> Result<Option<LibraryDatabase>> {
+        let mut db = self.backend.load(game_id)?;
+        if let (Some(portable), Some(db)) = (&self.portable, &mut db) {
+            portable.resolve_db(db);
+        }
+        Ok(db)
+    }
 
-        // 2. Save profiles.json
-        if !db.profiles.is_empty() {
-            let p_content = serde_json::to_string_pretty(&db.profiles)?;
-            fs::write(&paths.profiles, p_content).await?;
+    fn write_db(&self, game_id: &str, db: &LibraryDatabase) -> Result<()> {
+        match &self.portable {
+            Some(portable) => {
+                let mut stored = db.clone();
+                portable.tokenize_db(&mut stored);
+                self.backend.save(game_id, &stored)
+            }
+            None => self.backend.save(game_id, db),
         }
+    }
 
-        Ok(())
+    /// The instance whose mods `game_id` uses, see [`LibraryDatabase::mod_pool_id`].
+    pub fn mod_pool_id(&self, game_id: &str) -> Result<String> {
+        Ok(match self.backend.load(game_id)? {
+            Some(db) => db.mod_pool_id(game_id).to_string(),
+            None => game_id.to_string(),
+        })
+    }
+
+    /// Damaged database files restored from a backup since the last call, for the UI
+    /// to report.
+    pub fn take_recoveries(&self) -> Vec<RestoredBackup> {
+        self.backend.take_recoveries()
+    }
+
+    pub fn has_game_db(&self, game_id: &str) -> Result<bool> {
+        self.backend.has_game(game_id)
+    }
+
+    /// Removes the game's stored database. Its folders are left to the caller.
+    pub fn delete_game_db(&self, game_id: &str) -> Result<()> {
+        self.backend.delete(game_id)
+    }
+
+    pub fn find_mods_by_character(&self, game_id: &str, character: &str) -> Result<Vec<ModRecord>> {
+        self.backend
+            .mods_by_character(&self.mod_pool_id(game_id)?, character)
+            .map(|mods| self.resolve_mods(mods))
     }
 
-    /// Discovers all game IDs by scanning the games_root directory
+    pub fn find_mods_by_tag(&self, game_id: &str, tag: &str) -> Result<Vec<ModRecord>> {
+        self.backend
+            .mods_by_tag(&self.mod_pool_id(game_id)?, tag)
+            .map(|mods| self.resolve_mods(mods))
+    }
+
+    pub fn find_mods_by_hash(&self, game_id: &str, hash: &str) -> Result<Vec<ModRecord>> {
+        self.backend
+            .mods_by_hash(&self.mod_pool_id(game_id)?, hash)
+            .map(|mods| self.resolve_mods(mods))
+    }
+
+    fn resolve_mods(&self, mut mods: Vec<ModRecord>) -> Vec<ModRecord> {
+        if let Some(portable) = &self.portable {
+            for record in &mut mods {
+                record.for_each_path(|path| *path = portable.resolve(path));
+            }
+        }
+        mods
+    }
+
+    /// Discovers all game IDs from the games_root directory and the backend
     pub async fn discover_game_ids(&self) -> Result<Vec<String>> {
+        let mut ids = self.backend.game_ids()?;
         if !self.games_root.exists() {
-            return Ok(vec![]);
+            return Ok(ids);
         }
 
-        let mut ids = vec![];
         let mut entries = fs::read_dir(&self.games_root).await?;
 
         while let Some(entry) = entries.next_entry().await? {
             if entry.file_type().await?.is_dir() {
                 if let Some(id) = entry.file_name().to_str() {
-                    if !id.starts_with('.') {
+                    if !id.starts_with('.') && !ids.iter().any(|i| i == id) {
                         ids.push(id.to_string());
                     }
                 }
@@ -299,11 +489,10 @@ impl TemplateRegistry {
         let template_vec = crate::template::load_templates(&self.templates_root)?;
 
         for template in template_vec {
-            // Index by executables
-            if !template.executables.is_empty() {
-                for exe in &template.executables {
-                    templates.insert(exe.to_lowercase(), template.clone());
-                }
+            // Index by executables, the other regions' included
+            let region_exes = template.regions.iter().flat_map(|r| &r.executables);
+            for exe in template.executables.iter().chain(region_exes) {
+                templates.insert(exe.to_lowercase(), template.clone());
             }
 
             // Also index by normalized template ID (usually the filename stem)
@@ -0,0 +1,431 @@
+//! Rule-driven tags for mods.
+//!
+//! A rule names a tag and the condition under which a mod gets it: INI sections,
+//! entries or lines, files in the mod, components from the hash database or how many
+//! characters it touches. The rules live in `tag_rules.json` next to the library so
+//! users can edit them; [`AutoTagger::retag`] applies changed rules to mods already
+//! imported.
+
+use crate::error::Result;
+use crate::gamedata::hash_db::HashIndex;
+use crate::models::{ModCompatibility, ModConfig};
+use crate::Librarian;
+use fs_engine::Safety;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TagRule {
+    pub tag: String,
+    #[serde(default = "enabled")]
+    pub enabled: bool,
+    pub when: TagCondition,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+/// Patterns are case-insensitive globs where `*` matches any run of characters and
+/// `?` a single one.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TagCondition {
+    /// An INI section name matches, e.g. `shaderoverride*`.
+    Section {
+        pattern: String,
+    },
+    /// An INI `key = value` entry matches; left out parts match anything.
+    Entry {
+        #[serde(default)]
+        section: Option<String>,
+        #[serde(default)]
+        key: Option<String>,
+        #[serde(default)]
+        value: Option<String>,
+    },
+    /// Any INI line, comments included.
+    Line {
+        pattern: String,
+    },
+    /// A file path relative to the mod folder, with `/` separators.
+    File {
+        pattern: String,
+    },
+    /// One of the mod's hashes belongs to this part of a character model.
+    Component {
+        component: String,
+    },
+    /// The mod touches at least this many characters.
+    Characters {
+        min: usize,
+    },
+    All {
+        conditions: Vec<TagCondition>,
+    },
+    Any {
+        conditions: Vec<TagCondition>,
+    },
+    Not {
+        condition: Box<TagCondition>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TagRules {
+    pub rules: Vec<TagRule>,
+}
+
+impl Default for TagRules {
+    fn default() -> Self {
+        use TagCondition::*;
+        let section = |pattern: &str| Section {
+            pattern: pattern.to_string(),
+        };
+        let file = |pattern: &str| File {
+            pattern: pattern.to_string(),
+        };
+        // Mods may only install INIs, meshes and textures, so buffers, shaders and
+        // effects are recognised by the INI entries that load them
+        let reference = |key: Option<&str>, value: &str| Entry {
+            section: None,
+            key: key.map(str::to_string),
+            value: Some(value.to_string()),
+        };
+        let key_type = |value: &str| Entry {
+            section: Some("key*".to_string()),
+            key: Some("type".to_string()),
+            value: Some(value.to_string()),
+        };
+        let rule = |tag: &str, when| TagRule {
+            tag: tag.to_string(),
+            enabled: true,
+            when,
+        };
+        let has_model = Any {
+            conditions: vec![file("*.ib"), reference(Some("filename"), "*.buf")],
+        };
+
+        Self {
+            rules: vec![
+                rule(
+                    "UI",
+                    Any {
+                        conditions: vec![
+                            Component {
+                                component: "ui".to_string(),
+                            },
+                            section("textureoverrideui*"),
+                            section("textureoverride*ui"),
+                        ],
+                    },
+                ),
+                rule(
+                    "Weapon",
+                    Any {
+                        conditions: vec![
+                            Component {
+                                component: "weapon".to_string(),
+                            },
+                            section("textureoverride*weapon*"),
+                        ],
+                    },
+                ),
+                rule(
+                    "Effect/Shader",
+                    Any {
+                        conditions: vec![
+                            section("shaderoverride*"),
+                            section("shaderregex*"),
+                            reference(None, "*.hlsl"),
+                        ],
+                    },
+                ),
+                rule(
+                    "Requires ReShade",
+                    Any {
+                        conditions: vec![
+                            reference(Some("techniques"), "*.fx*"),
+                            reference(Some("effectsearchpaths"), "*"),
+                        ],
+                    },
+                ),
+                rule("Toggles", key_type("toggle")),
+                rule("Cycle Keys", key_type("cycle")),
+                rule(
+                    "Merged",
+                    Any {
+                        conditions: vec![
+                            Line {
+                                pattern: "; merged mod*".to_string(),
+                            },
+                            Entry {
+                                section: Some("constants".to_string()),
+                                key: Some("global persist $swapvar".to_string()),
+                                value: None,
+                            },
+                        ],
+                    },
+                ),
+                rule("Multi-Character", Characters { min: 2 }),
+                rule("Model", has_model.clone()),
+                rule(
+                    "Texture Swap",
+                    All {
+                        conditions: vec![
+                            file("*.dds"),
+                            Not {
+                                condition: Box::new(has_model),
+                            },
+                        ],
+                    },
+                ),
+            ],
+        }
+    }
+}
+
+/// Reads and writes `tag_rules.json`. Without the file the built-in rules apply.
+pub struct TagRuleStore {
+    pub path: PathBuf,
+}
+
+impl TagRuleStore {
+    pub fn new(base_path: &Path) -> Self {
+        Self {
+            path: base_path.join("tag_rules.json"),
+        }
+    }
+
+    pub fn load(&self) -> Result<TagRules> {
+        if !self.path.exists() {
+            return Ok(TagRules::default());
+        }
+        let content = std::fs::read_to_string(&self.path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    pub fn save(&self, rules: &TagRules) -> Result<()> {
+        let content = serde_json::to_string_pretty(rules)?;
+        Safety::write_atomic(&self.path, content.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// What the rules look at, read once per mod.
+#[derive(Debug, Default)]
+struct ModFeatures {
+    files: Vec<String>,
+    sections: HashSet<String>,
+    /// (section, key, value)
+    entries: Vec<(String, String, String)>,
+    lines: Vec<String>,
+    components: HashSet<String>,
+    characters: usize,
+}
+
+pub struct AutoTagger {
+    rules: TagRules,
+    hash_index: HashIndex,
+}
+
+impl AutoTagger {
+    pub fn new(rules: TagRules, hash_index: HashIndex) -> Self {
+        Self { rules, hash_index }
+    }
+
+    /// The user's rules and the game's hash database. Unreadable rules fall back to the
+    /// built-in ones so a typo does not stop imports.
+    pub fn for_game(librarian: &Librarian, game_id: &str) -> Self {
+        let rules = TagRuleStore::new(&librarian.base_path)
+            .load()
+            .unwrap_or_else(|e| {
+                eprintln!(
+                    "Librarian WARNING: Failed to read tag rules, using defaults: {}",
+                    e
+                );
+                TagRules::default()
+            });
+        Self::new(rules, HashIndex::for_game(&librarian.assets_root, game_id))
+    }
+
+    /// Tags whose rules match the mod in `mod_dir`, in rule order.
+    pub fn tags_for(&self, mod_dir: &Path, compatibility: &ModCompatibility) -> Vec<String> {
+        let features = self.features(mod_dir, compatibility);
+        let mut tags: Vec<String> = Vec::new();
+        for rule in self.rules.rules.iter().filter(|r| r.enabled) {
+            if Self::matches(&rule.when, &features)
+                && !tags.iter().any(|t| t.eq_ignore_ascii_case(&rule.tag))
+            {
+                tags.push(rule.tag.clone());
+            }
+        }
+        tags
+    }
+
+    /// Replaces the tags earlier rules added with `auto_tags`. A rule tag the user
+    /// removed stays removed. Returns whether `tags` changed.
+    pub fn set_auto_tags(config: &mut ModConfig, auto_tags: Vec<String>) -> bool {
+        let before = config.tags.clone();
+        let contains =
+            |list: &[String], tag: &str| list.iter().any(|t| t.eq_ignore_ascii_case(tag));
+
+        config
+            .tags
+            .retain(|t| !contains(&config.auto_tags, t) || contains(&auto_tags, t));
+        for tag in &auto_tags {
+            let removed_by_user = contains(&config.auto_tags, tag) && !contains(&before, tag);
+            if !removed_by_user && !contains(&config.tags, tag) {
+                config.tags.push(tag.clone());
+            }
+        }
+        config.auto_tags = auto_tags;
+        config.tags != before
+    }
+
+    /// Re-runs the rules over every mod of a game and saves the ones whose tags
+    /// changed. Returns their ids.
+    pub async fn retag(librarian: &Librarian, game_id: &str) -> Result<Vec<Uuid>> {
+        let tagger = Self::for_game(librarian, game_id);
+        let mut db = librarian.load_game_db(game_id).await?;
+        let mut changed = Vec::new();
+        for record in db.mods.values_mut() {
+            if !record.path.is_dir() {
+                continue;
+            }
+            let auto_tags = tagger.tags_for(&record.path, &record.compatibility);
+            let previous = record.config.auto_tags.clone();
+            if Self::set_auto_tags(&mut record.config, auto_tags)
+                || record.config.auto_tags != previous
+            {
+                changed.push(record.id);
+            }
+        }
+        if !changed.is_empty() {
+            librarian.save_game_db(game_id, &db).await?;
+        }
+        println!("Librarian: Retagged {} mods of {}", changed.len(), game_id);
+        Ok(changed)
+    }
+
+    fn features(&self, mod_dir: &Path, compatibility: &ModCompatibility) -> ModFeatures {
+        let mut features = ModFeatures {
+            characters: if compatibility.characters.is_empty() {
+                usize::from(compatibility.character != "Unknown")
+            } else {
+                compatibility.characters.len()
+            },
+            components: compatibility
+                .hashes
+                .iter()
+                .filter_map(|h| self.hash_index.lookup(h)?.component)
+                .map(|c| c.to_lowercase())
+                .collect(),
+            ..Default::default()
+        };
+
+        for entry in walkdir::WalkDir::new(mod_dir)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+        {
+            let Ok(relative) = entry.path().strip_prefix(mod_dir) else {
+                continue;
+            };
+            let path = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().to_lowercase())
+                .collect::<Vec<_>>()
+                .join("/");
+            if path.ends_with(".ini") {
+                // Mods written on Windows are not always UTF-8
+                if let Ok(bytes) = std::fs::read(entry.path()) {
+                    Self::read_ini(&String::from_utf8_lossy(&bytes), &mut features);
+                }
+            }
+            features.files.push(path);
+        }
+        features
+    }
+
+    fn read_ini(content: &str, features: &mut ModFeatures) {
+        let mut section = String::new();
+        for line in content.lines() {
+            let line = line.trim().to_lowercase();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name.trim().to_string();
+                features.sections.insert(section.clone());
+            } else if !line.starts_with(';') {
+                if let Some((key, value)) = line.split_once('=') {
+                    features.entries.push((
+                        section.clone(),
+                        key.trim().to_string(),
+                        value.trim().to_string(),
+                    ));
+                }
+            }
+            features.lines.push(line);
+        }
+    }
+
+    fn matches(condition: &TagCondition, features: &ModFeatures) -> bool {
+        match condition {
+            TagCondition::Section { pattern } => {
+                features.sections.iter().any(|s| glob_match(pattern, s))
+            }
+            TagCondition::Entry {
+                section,
+                key,
+                value,
+            } => features.entries.iter().any(|(s, k, v)| {
+                let part = |pattern: &Option<String>, text: &str| {
+                    pattern.as_ref().is_none_or(|p| glob_match(p, text))
+                };
+                part(section, s) && part(key, k) && part(value, v)
+            }),
+            TagCondition::Line { pattern } => features.lines.iter().any(|l| glob_match(pattern, l)),
+            TagCondition::File { pattern } => features.files.iter().any(|f| glob_match(pattern, f)),
+            TagCondition::Component { component } => {
+                features.components.contains(&component.to_lowercase())
+            }
+            TagCondition::Characters { min } => features.characters >= *min,
+            TagCondition::All { conditions } => {
+                conditions.iter().all(|c| Self::matches(c, features))
+            }
+            TagCondition::Any { conditions } => {
+                conditions.iter().any(|c| Self::matches(c, features))
+            }
+            TagCondition::Not { condition } => !Self::matches(condition, features),
+        }
+    }
+}
+
+/// Case-insensitive glob match of the whole of `text`.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Where the last `*` was and how much text it has swallowed so far
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
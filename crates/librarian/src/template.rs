@@ -76,6 +76,95 @@ pub struct GameTemplate {
     pub sophon_channel_id: String,
     #[serde(default)]
     pub sophon_sub_channel_id: String,
+
+    // Other regions and channels of the same game (e.g., "cn", "beta")
+    #[serde(default)]
+    pub regions: Vec<TemplateRegion>,
+}
+
+/// A region or channel of a game, such as the CN client or a beta. Empty fields fall
+/// back to the template's.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct TemplateRegion {
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub executables: Vec<String>,
+    #[serde(default)]
+    pub sophon_branch: String,
+    #[serde(default)]
+    pub sophon_package_id: String,
+    #[serde(default)]
+    pub sophon_password: String,
+    #[serde(default)]
+    pub sophon_plat_app: String,
+    #[serde(default)]
+    pub sophon_game_biz: String,
+    #[serde(default)]
+    pub sophon_launcher_id: String,
+    #[serde(default)]
+    pub sophon_channel_id: String,
+    #[serde(default)]
+    pub sophon_sub_channel_id: String,
+}
+
+impl GameTemplate {
+    pub fn region(&self, id: &str) -> Option<&TemplateRegion> {
+        self.regions.iter().find(|r| r.id.eq_ignore_ascii_case(id))
+    }
+
+    /// Every executable name of the game, each with the region it belongs to (`None` for
+    /// the default).
+    pub fn all_executables(&self) -> impl Iterator<Item = (&str, Option<&str>)> {
+        let default = self.executables.iter().map(|exe| (exe.as_str(), None));
+        let regions = self.regions.iter().flat_map(|r| {
+            r.executables
+                .iter()
+                .map(move |exe| (exe.as_str(), Some(r.id.as_str())))
+        });
+        default.chain(regions)
+    }
+
+    /// The region whose client has this executable name, if it is not the default's.
+    pub fn region_for_exe(&self, exe_name: &str) -> Option<&TemplateRegion> {
+        self.regions.iter().find(|r| {
+            r.executables
+                .iter()
+                .any(|exe| exe.eq_ignore_ascii_case(exe_name))
+        })
+    }
+
+    /// The template as seen by an install of `region`: its executables and Sophon
+    /// settings replace the defaults. An unknown or missing region changes nothing.
+    pub fn for_region(&self, region: Option<&str>) -> GameTemplate {
+        let mut template = self.clone();
+        let Some(region) = region.and_then(|id| self.region(id)) else {
+            return template;
+        };
+        if !region.executables.is_empty() {
+            template.executables = region.executables.clone();
+        }
+        let overrides = [
+            (&mut template.sophon_branch, &region.sophon_branch),
+            (&mut template.sophon_package_id, &region.sophon_package_id),
+            (&mut template.sophon_password, &region.sophon_password),
+            (&mut template.sophon_plat_app, &region.sophon_plat_app),
+            (&mut template.sophon_game_biz, &region.sophon_game_biz),
+            (&mut template.sophon_launcher_id, &region.sophon_launcher_id),
+            (&mut template.sophon_channel_id, &region.sophon_channel_id),
+            (
+                &mut template.sophon_sub_channel_id,
+                &region.sophon_sub_channel_id,
+            ),
+        ];
+        for (field, value) in overrides {
+            if !value.is_empty() {
+                *field = value.clone();
+            }
+        }
+        template
+    }
 }
 
 pub fn load_templates(dir: &Path) -> Result<Vec<GameTemplate>> {
@@ -0,0 +1,379 @@
+//! Update checks for mods installed from a mod site.
+//!
+//! A mod is tracked when its `meta.url` is recognised by one of the updater's
+//! providers. Installing an update re-imports the new archive into the existing
+//! record, so the mod keeps its id, folder, profiles and load order position.
+
+use crate::error::{LibrarianError, Result};
+use crate::import::{ImportOptions, Importer};
+use crate::models::ModRecord;
+use crate::Librarian;
+use chrono::{DateTime, Utc};
+use futures_util::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+const USER_AGENT: &str = "YAGO/0.1 (mod updater)";
+
+/// The newest downloadable release of a mod on its site.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RemoteModVersion {
+    /// The site's id for the mod, as parsed from its URL.
+    pub item_id: String,
+    /// Version string the author published, if any.
+    pub version: Option<String>,
+    /// Upload date of the file.
+    pub updated_at: Option<DateTime<Utc>>,
+    pub download_url: String,
+    pub file_name: String,
+}
+
+/// A mod site that can be polled for new releases.
+pub trait UpdateProvider: Send + Sync {
+    /// Short name shown next to update notices, e.g. `"GameBanana"`.
+    fn name(&self) -> &str;
+
+    /// The site's id for the mod behind `url`, or `None` if the URL is not on this site.
+    fn item_id(&self, url: &str) -> Option<String>;
+
+    /// The newest release of `item_id`.
+    fn latest<'a>(&'a self, item_id: &'a str) -> BoxFuture<'a, Result<RemoteModVersion>>;
+}
+
+/// Result of checking one tracked mod.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModUpdate {
+    pub mod_id: Uuid,
+    pub name: String,
+    pub provider: String,
+    pub installed_version: String,
+    pub remote: RemoteModVersion,
+    /// Whether `remote` is newer than what is installed.
+    pub available: bool,
+}
+
+#[derive(Deserialize)]
+struct GbProfilePage {
+    #[serde(rename = "_sVersion", default)]
+    version: Option<String>,
+    #[serde(rename = "_aFiles", default)]
+    files: Vec<GbFile>,
+}
+
+#[derive(Deserialize)]
+struct GbFile {
+    #[serde(rename = "_sFile")]
+    file_name: String,
+    #[serde(rename = "_tsDateAdded", default)]
+    date_added: Option<i64>,
+    #[serde(rename = "_sDownloadUrl")]
+    download_url: String,
+    #[serde(rename = "_sVersion", default)]
+    version: Option<String>,
+}
+
+/// GameBanana's public API. Mods are identified by the numeric id in
+/// `https://gamebanana.com/mods/<id>`.
+#[derive(Debug, Clone)]
+pub struct GameBananaProvider {
+    http: reqwest::Client,
+    base_url: String,
+}
+
+impl Default for GameBananaProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GameBananaProvider {
+    pub const DEFAULT_BASE_URL: &'static str = "https://gamebanana.com/apiv11";
+
+    pub fn new() -> Self {
+        Self::with_base_url(Self::DEFAULT_BASE_URL)
+    }
+
+    /// Talks to an API mirror or a local stand-in instead of gamebanana.com.
+    pub fn with_base_url(base_url: impl Into<String>) -> Self {
+        Self {
+            http: http_client(),
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+        }
+    }
+
+    async fn fetch(&self, item_id: &str) -> Result<RemoteModVersion> {
+        let url = format!("{}/Mod/{}/ProfilePage", self.base_url, item_id);
+        let page: GbProfilePage = self
+            .http
+            .get(&url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        // Mods often keep old files around; the newest upload is the current release
+        let file = page
+            .files
+            .into_iter()
+            .max_by_key(|f| f.date_added.unwrap_or(0))
+            .ok_or_else(|| {
+                LibrarianError::Validation(format!("GameBanana mod {} has no files", item_id))
+            })?;
+
+        Ok(RemoteModVersion {
+            item_id: item_id.to_string(),
+            version: file
+                .version
+                .or(page.version)
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty()),
+            updated_at: file
+                .date_added
+                .and_then(|ts| DateTime::<Utc>::from_timestamp(ts, 0)),
+            download_url: file.download_url,
+            file_name: file.file_name,
+        })
+    }
+}
+
+impl UpdateProvider for GameBananaProvider {
+    fn name(&self) -> &str {
+        "GameBanana"
+    }
+
+    fn item_id(&self, url: &str) -> Option<String> {
+        let url = reqwest::Url::parse(url.trim()).ok()?;
+        let host = url.host_str()?;
+        if host != "gamebanana.com" && !host.ends_with(".gamebanana.com") {
+            return None;
+        }
+        // /mods/<id> and /mods/download/<id>
+        let segments: Vec<&str> = url.path_segments()?.filter(|s| !s.is_empty()).collect();
+        if !segments.first()?.eq_ignore_ascii_case("mods") {
+            return None;
+        }
+        segments[1..]
+            .iter()
+            .find(|s| !s.eq_ignore_ascii_case("download"))
+            .filter(|s| s.chars().all(|c| c.is_ascii_digit()))
+            .map(|s| s.to_string())
+    }
+
+    fn latest<'a>(&'a self, item_id: &'a str) -> BoxFuture<'a, Result<RemoteModVersion>> {
+        Box::pin(self.fetch(item_id))
+    }
+}
+
+/// Checks tracked mods against their sites and installs newer releases.
+pub struct ModUpdater {
+    providers: Vec<Box<dyn UpdateProvider>>,
+    http: reqwest::Client,
+}
+
+impl Default for ModUpdater {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ModUpdater {
+    /// An updater that knows the built-in mod sites.
+    pub fn new() -> Self {
+        Self::with_providers(vec![Box::new(GameBananaProvider::new())])
+    }
+
+    pub fn with_providers(providers: Vec<Box<dyn UpdateProvider>>) -> Self {
+        Self {
+            providers,
+            http: http_client(),
+        }
+    }
+
+    /// The provider responsible for `url` and the mod's id on that site.
+    pub fn resolve(&self, url: &str) -> Option<(&dyn UpdateProvider, String)> {
+        self.providers
+            .iter()
+            .find_map(|p| p.item_id(url).map(|id| (p.as_ref(), id)))
+    }
+
+    /// Polls the mod's site. `None` when the mod has no URL any provider recognises.
+    pub async fn check(&self, record: &ModRecord) -> Result<Option<ModUpdate>> {
+        let Some(url) = record.meta.url.as_deref() else {
+            return Ok(None);
+        };
+        let Some((provider, item_id)) = self.resolve(url) else {
+            return Ok(None);
+        };
+
+        let remote = provider.latest(&item_id).await?;
+        let installed_at = record.meta.updated_at.unwrap_or(record.added_at);
+        Ok(Some(ModUpdate {
+            mod_id: record.id,
+            name: record.meta.name.clone(),
+            provider: provider.name().to_string(),
+            installed_version: record.meta.version.clone(),
+            available: is_newer(&record.meta.version, installed_at, &remote),
+            remote,
+        }))
+    }
+
+    /// Checks every tracked mod of a game. A mod whose site cannot be reached is
+    /// logged and left out rather than failing the whole check.
+    pub async fn check_game(&self, librarian: &Librarian, game_id: &str) -> Result<Vec<ModUpdate>> {
+        let db = librarian.load_game_db(game_id).await?;
+        let mut mods: Vec<&ModRecord> = db.mods.values().collect();
+        mods.sort_by_key(|m| m.added_at);
+
+        let mut updates = Vec::new();
+        for record in mods {
+            match self.check(record).await {
+                Ok(Some(update)) => updates.push(update),
+                Ok(None) => {}
+                Err(e) => eprintln!(
+                    "Librarian WARNING: Update check failed for {}: {}",
+                    record.meta.name, e
+                ),
+            }
+        }
+        Ok(updates)
+    }
+
+    /// Downloads `update.remote` and installs it over the existing mod. `options.replace`
+    /// is always set to the updated mod.
+    pub async fn apply(
+        &self,
+        librarian: &Librarian,
+        game_id: &str,
+        update: &ModUpdate,
+        options: ImportOptions,
+    ) -> Result<ModRecord> {
+        let staging_root = librarian.games_root.join(".yago_staging");
+        std::fs::create_dir_all(&staging_root)?;
+        let download_dir = staging_root.join(format!("{}.download", Uuid::new_v4()));
+        std::fs::create_dir_all(&download_dir)?;
+
+        let result = self
+            .install(librarian, game_id, update, options, &download_dir)
+            .await;
+        let _ = std::fs::remove_dir_all(&download_dir);
+        result
+    }
+
+    async fn install(
+        &self,
+        librarian: &Librarian,
+        game_id: &str,
+        update: &ModUpdate,
+        options: ImportOptions,
+        download_dir: &Path,
+    ) -> Result<ModRecord> {
+        let archive = self.download(&update.remote, download_dir).await?;
+        let options = ImportOptions {
+            replace: Some(update.mod_id),
+            ..options
+        };
+        Importer::import_mod_with_options(librarian, archive, game_id.to_string(), options).await?;
+
+        let mut db = librarian.load_game_db(game_id).await?;
+        let record = db
+            .mods
+            .get_mut(&update.mod_id)
+            .ok_or_else(|| LibrarianError::ModNotFound(update.mod_id.to_string()))?;
+        if let Some(version) = &update.remote.version {
+            record.meta.version = version.clone();
+        }
+        record.meta.updated_at = update.remote.updated_at.or_else(|| Some(Utc::now()));
+        let record = record.clone();
+        librarian.save_game_db(game_id, &db).await?;
+
+        println!(
+            "Librarian: Updated {} to {}",
+            record.meta.name, record.meta.version
+        );
+        Ok(record)
+    }
+
+    async fn download(&self, remote: &RemoteModVersion, dir: &Path) -> Result<PathBuf> {
+        // The importer picks the extractor by extension, so keep the site's file name
+        let file_name = Path::new(&remote.file_name)
+            .file_name()
+            .map(|n| n.to_os_string())
+            .unwrap_or_else(|| format!("{}.zip", remote.item_id).into());
+        let path = dir.join(file_name);
+
+        let mut response = self
+            .http
+            .get(&remote.download_url)
+            .send()
+            .await?
+            .error_for_status()?;
+        let mut file = std::fs::File::create(&path)?;
+        while let Some(chunk) = response.chunk().await? {
+            file.write_all(&chunk)?;
+        }
+        file.sync_all()?;
+        Ok(path)
+    }
+}
+
+/// Whether `remote` is a newer release than the installed one. Versions are compared
+/// by their numeric parts; when they are equal or not numeric, a file uploaded after
+/// the installed one counts as an update (authors often re-upload without bumping).
+pub fn is_newer(
+    installed_version: &str,
+    installed_at: DateTime<Utc>,
+    remote: &RemoteModVersion,
+) -> bool {
+    let ordering = remote
+        .version
+        .as_deref()
+        .and_then(|v| compare_versions(v, installed_version));
+    match (ordering, remote.updated_at) {
+        (Some(Ordering::Greater), _) => true,
+        (Some(Ordering::Less), _) => false,
+        (_, Some(uploaded)) => uploaded > installed_at,
+        (Some(Ordering::Equal), None) => false,
+        // Neither numbers nor dates to go on; any other version string is news
+        (None, None) => remote
+            .version
+            .as_deref()
+            .is_some_and(|v| !v.trim().eq_ignore_ascii_case(installed_version.trim())),
+    }
+}
+
+/// Compares dotted version strings like `v1.2`, `1.10.3` or `2.0-beta` by their
+/// numeric parts. `None` if either has a part without digits.
+pub fn compare_versions(a: &str, b: &str) -> Option<Ordering> {
+    let a = numeric_parts(a)?;
+    let b = numeric_parts(b)?;
+    for i in 0..a.len().max(b.len()) {
+        let ordering = a.get(i).unwrap_or(&0).cmp(b.get(i).unwrap_or(&0));
+        if ordering != Ordering::Equal {
+            return Some(ordering);
+        }
+    }
+    Some(Ordering::Equal)
+}
+
+fn numeric_parts(version: &str) -> Option<Vec<u64>> {
+    let version = version.trim().trim_start_matches(['v', 'V']);
+    // Pre-release suffixes ("-beta", " hotfix") do not take part in the comparison
+    let core = version.split(['-', ' ', '+']).next()?;
+    core.split(['.', '_'])
+        .map(|part| {
+            let digits: String = part.chars().take_while(|c| c.is_ascii_digit()).collect();
+            digits.parse::<u64>().ok()
+        })
+        .collect()
+}
+
+fn http_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .user_agent(USER_AGENT)
+        .build()
+        .unwrap_or_default()
+}
@@ -0,0 +1,451 @@
+//! Version history of mods.
+//!
+//! The active release of a mod always lives at `ModRecord::path`, which is what gets
+//! deployed. Releases it replaced are kept under `<game mods>/.versions/<mod id>/<version id>`
+//! (the leading dot keeps them out of the mods watcher) until they are pruned.
+
+use crate::error::{LibrarianError, Result};
+use crate::import::Importer;
+use crate::instances::GameInstances;
+use crate::keybinds::KeybindExtractor;
+use crate::models::{LibraryDatabase, ModRecord, ModVersion, Profile};
+use crate::tagging::AutoTagger;
+use crate::Librarian;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// INIs larger than this (lines of one times lines of the other) are reported as
+/// changed without a line diff.
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+/// Differences between the file trees of two releases, paths relative to the mod root.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionDiff {
+    pub added: Vec<PathBuf>,
+    pub removed: Vec<PathBuf>,
+    pub changed: Vec<PathBuf>,
+    /// Line diffs of the changed INIs.
+    pub inis: Vec<IniDiff>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IniDiff {
+    pub path: PathBuf,
+    pub lines: Vec<LineChange>,
+    /// Set when the files were too large to diff line by line.
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LineChange {
+    /// `line` is 1-based in the older release.
+    Removed { line: usize, text: String },
+    /// `line` is 1-based in the newer release.
+    Added { line: usize, text: String },
+}
+
+impl ModRecord {
+    /// Files of `version_id`, whether it is the active release or an archived one.
+    pub fn version_path(&self, version_id: Uuid) -> Option<&Path> {
+        if version_id == self.version_id {
+            return Some(&self.path);
+        }
+        self.versions
+            .iter()
+            .find(|v| v.id == version_id)
+            .map(|v| v.path.as_path())
+    }
+
+    /// Files to deploy for `profile`: its pinned release if it still exists, otherwise
+    /// the active one.
+    pub fn path_for_profile(&self, profile: &Profile) -> &Path {
+        profile
+            .pinned_versions
+            .get(&self.id)
+            .and_then(|version_id| self.version_path(*version_id))
+            .unwrap_or(&self.path)
+    }
+}
+
+pub struct VersionHistory;
+
+impl VersionHistory {
+    /// Folder holding the archived releases of a mod.
+    pub fn versions_dir(librarian: &Librarian, game_id: &str, mod_id: Uuid) -> PathBuf {
+        librarian
+            .game_paths(game_id)
+            .mods
+            .join(".versions")
+            .join(mod_id.to_string())
+    }
+
+    pub(crate) fn version_dir(
+        librarian: &Librarian,
+        game_id: &str,
+        mod_id: Uuid,
+        version_id: Uuid,
+    ) -> PathBuf {
+        Self::versions_dir(librarian, game_id, mod_id).join(version_id.to_string())
+    }
+
+    /// Makes an archived release the active one. The files trade places, so the mod
+    /// stays at the same path and the replaced release joins the history.
+    pub async fn switch(
+        librarian: &Librarian,
+        game_id: &str,
+        mod_id: Uuid,
+        version_id: Uuid,
+    ) -> Result<ModRecord> {
+        let mut db = librarian.load_game_db(game_id).await?;
+        let game_version = db.games.get(game_id).map(|g| g.version.clone());
+        let record = Self::record_mut(&mut db, mod_id)?;
+        if record.version_id == version_id {
+            return Ok(record.clone());
+        }
+        let index = record
+            .versions
+            .iter()
+            .position(|v| v.id == version_id)
+            .ok_or_else(|| {
+                LibrarianError::ModNotFound(format!("{} version {}", mod_id, version_id))
+            })?;
+
+        if record.version_id.is_nil() {
+            record.version_id = Uuid::new_v4();
+        }
+        let target = record.versions[index].clone();
+        if !target.path.is_dir() {
+            return Err(LibrarianError::Validation(format!(
+                "Files of version {} are missing at {}",
+                target.meta.version,
+                target.path.display()
+            )));
+        }
+
+        // Move the active release into its archive slot first so a failure can be undone
+        let active_archive = Self::version_dir(librarian, game_id, mod_id, record.version_id);
+        std::fs::create_dir_all(Self::versions_dir(librarian, game_id, mod_id))?;
+        std::fs::rename(&record.path, &active_archive)?;
+        if let Err(e) = std::fs::rename(&target.path, &record.path) {
+            let _ = std::fs::rename(&active_archive, &record.path);
+            return Err(e.into());
+        }
+
+        // The blob manifest describes the files that were active
+        if let Err(e) = librarian.blob_store().release(&mod_id.to_string()) {
+            eprintln!(
+                "Librarian WARNING: Failed to release blobs of {}: {}",
+                mod_id, e
+            );
+        }
+
+        record.versions[index] = ModVersion {
+            id: record.version_id,
+            meta: record.meta.clone(),
+            path: active_archive,
+            size: record.size.clone(),
+            size_bytes: record.size_bytes,
+            added_at: record.added_at,
+        };
+        record.version_id = target.id;
+        record.meta = target.meta;
+        record.size = target.size;
+        record.size_bytes = target.size_bytes;
+        record.added_at = target.added_at;
+        record
+            .versions
+            .sort_by_key(|v| std::cmp::Reverse(v.added_at));
+
+        // Everything import derived from the files has to describe the new release;
+        // hashing reads every file, so it stays off the async runtime
+        let (compatibility, auto_tags) = {
+            let (librarian, path, game_id) =
+                (librarian.clone(), record.path.clone(), game_id.to_string());
+            let mut compatibility = record.compatibility.clone();
+            Importer::blocking(move || {
+                let (fingerprint, texture_hashes) = Importer::fingerprint(&path);
+                compatibility.fingerprint = fingerprint;
+                compatibility.texture_hashes = texture_hashes;
+                Importer::identify_character_and_type(
+                    &path,
+                    &librarian.assets_root,
+                    &game_id,
+                    game_version.as_deref(),
+                )
+                .apply(&mut compatibility);
+                let auto_tags =
+                    AutoTagger::for_game(&librarian, &game_id).tags_for(&path, &compatibility);
+                Ok((compatibility, auto_tags))
+            })
+            .await?
+        };
+        record.compatibility = compatibility;
+        AutoTagger::set_auto_tags(&mut record.config, auto_tags);
+        record.config.keybinds = KeybindExtractor::extract(&record.path);
+
+        let record = record.clone();
+        librarian.save_game_db(game_id, &db).await?;
+        println!(
+            "Librarian: Switched {} to version {}",
+            record.meta.name, record.meta.version
+        );
+        Ok(record)
+    }
+
+    /// Deletes archived releases beyond the newest `keep`. Releases pinned by a profile
+    /// of any instance sharing the mod are kept regardless. Returns the ids of the
+    /// deleted releases.
+    pub async fn prune(
+        librarian: &Librarian,
+        game_id: &str,
+        mod_id: Uuid,
+        keep: usize,
+    ) -> Result<Vec<Uuid>> {
+        let mut db = librarian.load_game_db(game_id).await?;
+        let pinned = Self::pinned_anywhere(librarian, game_id, &db, mod_id).await?;
+        let record = Self::record_mut(&mut db, mod_id)?;
+
+        let mut removed = Vec::new();
+        let mut retained = Vec::new();
+        for version in std::mem::take(&mut record.versions) {
+            // Pinned releases do not count towards `keep`
+            let unpinned_kept = retained
+                .iter()
+                .filter(|v: &&ModVersion| !pinned.contains(&v.id))
+                .count();
+            if pinned.contains(&version.id) || unpinned_kept < keep {
+                retained.push(version);
+            } else {
+                removed.push(version);
+            }
+        }
+        record.versions = retained;
+        if removed.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Forget the releases before deleting them, so a failure leaves stray folders
+        // rather than records pointing at nothing
+        librarian.save_game_db(game_id, &db).await?;
+        for version in &removed {
+            if version.path.exists() {
+                if let Err(e) = std::fs::remove_dir_all(&version.path) {
+                    eprintln!(
+                        "Librarian: Failed to delete release {:?}: {}",
+                        version.path, e
+                    );
+                }
+            }
+        }
+        Ok(removed.into_iter().map(|v| v.id).collect())
+    }
+
+    /// Releases of `mod_id` pinned by the profiles of `game_id` and of every other
+    /// instance in its mod pool.
+    async fn pinned_anywhere(
+        librarian: &Librarian,
+        game_id: &str,
+        db: &LibraryDatabase,
+        mod_id: Uuid,
+    ) -> Result<HashSet<Uuid>> {
+        let pool_id = db.mod_pool_id(game_id).to_string();
+        let mut instances = GameInstances::pool_members(librarian, &pool_id).await?;
+        instances.push(pool_id);
+
+        let pinned_in = |db: &LibraryDatabase| -> Vec<Uuid> {
+            db.profiles
+                .values()
+                .filter_map(|p| p.pinned_versions.get(&mod_id).copied())
+                .collect()
+        };
+        let mut pinned: HashSet<Uuid> = pinned_in(db).into_iter().collect();
+        for instance in instances.iter().filter(|id| id.as_str() != game_id) {
+            let other = librarian.load_game_db(instance).await?;
+            pinned.extend(pinned_in(&other));
+        }
+        Ok(pinned)
+    }
+
+    /// Holds `mod_id` at `version_id` in a profile, or releases the pin with `None`.
+    pub async fn pin(
+        librarian: &Librarian,
+        game_id: &str,
+        profile_id: Uuid,
+        mod_id: Uuid,
+        version_id: Option<Uuid>,
+    ) -> Result<Profile> {
+        let mut db = librarian.load_game_db(game_id).await?;
+        let version_id = match version_id {
+            Some(version_id) => {
+                let record = Self::record_mut(&mut db, mod_id)?;
+                // Records from before version history get an id once something refers to it
+                if record.version_id.is_nil() {
+                    record.version_id = Uuid::new_v4();
+                }
+                Some(if version_id.is_nil() {
+                    record.version_id
+                } else {
+                    version_id
+                })
+            }
+            None => None,
+        };
+        if let Some(version_id) = version_id {
+            let record = Self::record_mut(&mut db, mod_id)?;
+            if record.version_path(version_id).is_none() {
+                return Err(LibrarianError::ModNotFound(format!(
+                    "{} version {}",
+                    mod_id, version_id
+                )));
+            }
+        }
+        let profile = db.profiles.get_mut(&profile_id).ok_or_else(|| {
+            LibrarianError::ProfileError(format!("Profile {} not found", profile_id))
+        })?;
+        match version_id {
+            Some(version_id) => profile.pinned_versions.insert(mod_id, version_id),
+            None => profile.pinned_versions.remove(&mod_id),
+        };
+        let profile = profile.clone();
+        librarian.save_game_db(game_id, &db).await?;
+        Ok(profile)
+    }
+
+    /// Compares the files of two releases of a mod, `from` being the older one.
+    pub fn diff(record: &ModRecord, from: Uuid, to: Uuid) -> Result<VersionDiff> {
+        let missing =
+            |id: Uuid| LibrarianError::ModNotFound(format!("{} version {}", record.id, id));
+        let old_root = record.version_path(from).ok_or_else(|| missing(from))?;
+        let new_root = record.version_path(to).ok_or_else(|| missing(to))?;
+        let old_files = Self::list_files(old_root);
+        let new_files = Self::list_files(new_root);
+
+        let mut diff = VersionDiff::default();
+        for relative in old_files.keys() {
+            if !new_files.contains_key(relative) {
+                diff.removed.push(relative.clone());
+            }
+        }
+        for (relative, new_path) in &new_files {
+            let Some(old_path) = old_files.get(relative) else {
+                diff.added.push(relative.clone());
+                continue;
+            };
+            if Self::same_contents(old_path, new_path)? {
+                continue;
+            }
+            diff.changed.push(relative.clone());
+            if Self::is_ini(relative) {
+                diff.inis
+                    .push(Self::diff_ini(relative, old_path, new_path)?);
+            }
+        }
+        Ok(diff)
+    }
+
+    fn record_mut(db: &mut LibraryDatabase, mod_id: Uuid) -> Result<&mut ModRecord> {
+        db.mods
+            .get_mut(&mod_id)
+            .ok_or_else(|| LibrarianError::ModNotFound(mod_id.to_string()))
+    }
+
+    /// Files under `root` keyed by relative path, sorted so diffs are stable.
+    fn list_files(root: &Path) -> BTreeMap<PathBuf, PathBuf> {
+        walkdir::WalkDir::new(root)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .filter_map(|e| {
+                let relative = e.path().strip_prefix(root).ok()?.to_path_buf();
+                Some((relative, e.path().to_path_buf()))
+            })
+            .collect()
+    }
+
+    fn same_contents(a: &Path, b: &Path) -> Result<bool> {
+        if std::fs::metadata(a)?.len() != std::fs::metadata(b)?.len() {
+            return Ok(false);
+        }
+        let mut a = std::io::BufReader::new(std::fs::File::open(a)?);
+        let mut b = std::io::BufReader::new(std::fs::File::open(b)?);
+        let (mut buf_a, mut buf_b) = (vec![0u8; 64 * 1024], vec![0u8; 64 * 1024]);
+        loop {
+            let read = a.read(&mut buf_a)?;
+            if read == 0 {
+                return Ok(true);
+            }
+            b.read_exact(&mut buf_b[..read])?;
+            if buf_a[..read] != buf_b[..read] {
+                return Ok(false);
+            }
+        }
+    }
+
+    fn is_ini(path: &Path) -> bool {
+        path.extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| e.eq_ignore_ascii_case("ini"))
+    }
+
+    fn diff_ini(relative: &Path, old_path: &Path, new_path: &Path) -> Result<IniDiff> {
+        let old = String::from_utf8_lossy(&std::fs::read(old_path)?).to_string();
+        let new = String::from_utf8_lossy(&std::fs::read(new_path)?).to_string();
+        let old: Vec<&str> = old.lines().collect();
+        let new: Vec<&str> = new.lines().collect();
+
+        let truncated = old.len().saturating_mul(new.len()) > MAX_DIFF_CELLS;
+        Ok(IniDiff {
+            path: relative.to_path_buf(),
+            lines: if truncated {
+                Vec::new()
+            } else {
+                Self::diff_lines(&old, &new)
+            },
+            truncated,
+        })
+    }
+
+    /// Longest-common-subsequence line diff.
+    fn diff_lines(old: &[&str], new: &[&str]) -> Vec<LineChange> {
+        let width = new.len() + 1;
+        // lcs[i * width + j]: length of the LCS of old[i..] and new[j..]
+        let mut lcs = vec![0u32; (old.len() + 1) * width];
+        for i in (0..old.len()).rev() {
+            for j in (0..new.len()).rev() {
+                lcs[i * width + j] = if old[i] == new[j] {
+                    lcs[(i + 1) * width + j + 1] + 1
+                } else {
+                    lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+                };
+            }
+        }
+
+        let mut changes = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < old.len() || j < new.len() {
+            if i < old.len() && j < new.len() && old[i] == new[j] {
+                i += 1;
+                j += 1;
+            } else if i < old.len()
+                && (j == new.len() || lcs[(i + 1) * width + j] >= lcs[i * width + j + 1])
+            {
+                changes.push(LineChange::Removed {
+                    line: i + 1,
+                    text: old[i].to_string(),
+                });
+                i += 1;
+            } else {
+                changes.push(LineChange::Added {
+                    line: j + 1,
+                    text: new[j].to_string(),
+                });
+                j += 1;
+            }
+        }
+        changes
+    }
+}
@@ -0,0 +1,315 @@
+//! Keeps mod records in step with the mods folders on disk.
+//!
+//! `ModWatcher` reports which mod folders changed; `ModSync` folds those changes into a
+//! `LibraryDatabase`. They are separate so the app can apply changes to its in-memory
+//! databases (and save them) under its own locks.
+
+use crate::error::Result;
+use crate::import::Importer;
+use crate::keybinds::KeybindExtractor;
+use crate::models::{LibraryDatabase, ModCompatibility, ModConfig, ModMetadata, ModRecord};
+use crate::Librarian;
+use chrono::Utc;
+use fs_engine::{DirWatcher, Safety};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ModSyncEvent {
+    /// A folder without a record was adopted as a new mod.
+    Added {
+        game_id: String,
+        mod_id: Uuid,
+        name: String,
+    },
+    /// Size, hashes or character identification changed.
+    Updated { game_id: String, mod_id: Uuid },
+    /// The record's folder is gone. The record is kept so a move can be repaired.
+    Missing { game_id: String, mod_id: Uuid },
+}
+
+pub struct ModSync;
+
+impl ModSync {
+    /// Reconciles the given mod folders (direct children of the game's mods folder)
+    /// with `db`. The caller saves `db` if any events come back.
+    pub fn sync_folders(
+        librarian: &Librarian,
+        game_id: &str,
+        db: &mut LibraryDatabase,
+        folders: &[PathBuf],
+    ) -> Vec<ModSyncEvent> {
+        let mut events = Vec::new();
+        let game_version = db.games.get(game_id).map(|g| g.version.clone());
+        for folder in folders {
+            let existing = db.mods.values().find(|m| m.path == *folder).map(|m| m.id);
+
+            match existing {
+                Some(mod_id) if !folder.is_dir() => events.push(ModSyncEvent::Missing {
+                    game_id: game_id.to_string(),
+                    mod_id,
+                }),
+                Some(mod_id) => {
+                    let Some(record) = db.mods.get_mut(&mod_id) else {
+                        continue;
+                    };
+                    if Self::refresh(librarian, game_id, game_version.as_deref(), record) {
+                        events.push(ModSyncEvent::Updated {
+                            game_id: game_id.to_string(),
+                            mod_id,
+                        });
+                    }
+                }
+                // A record for this folder's id lives elsewhere, so it is no stray
+                None if Self::folder_id(folder).is_some_and(|id| db.mods.contains_key(&id)) => {}
+                None if folder.is_dir() => {
+                    let record = Self::adopt(librarian, game_id, db, folder);
+                    events.push(ModSyncEvent::Added {
+                        game_id: game_id.to_string(),
+                        mod_id: record.id,
+                        name: record.meta.name,
+                    });
+                }
+                None => {}
+            }
+        }
+        events
+    }
+
+    /// [`ModSync::sync_folders`] against the stored database rather than a cached copy,
+    /// saving it if anything changed. Holds the import lock, so a folder an import is
+    /// committing is only seen together with its record.
+    pub async fn sync_stored(
+        librarian: &Librarian,
+        game_id: &str,
+        folders: &[PathBuf],
+    ) -> Result<(LibraryDatabase, Vec<ModSyncEvent>)> {
+        let _guard = librarian.lock_imports().await;
+        let mut db = librarian.load_game_db(game_id).await?;
+        let events = Self::sync_folders(librarian, game_id, &mut db, folders);
+        if !events.is_empty() {
+            librarian.save_game_db(game_id, &db).await?;
+        }
+        Ok((db, events))
+    }
+
+    /// Checks every folder in the game's mods folder and every record's folder, for
+    /// catching up on changes made while the app was closed.
+    pub fn scan(
+        librarian: &Librarian,
+        game_id: &str,
+        db: &mut LibraryDatabase,
+    ) -> Vec<ModSyncEvent> {
+        // An instance sharing another's mods scans the shared folder
+        let pool_id = db.mod_pool_id(game_id).to_string();
+        let mods_dir = librarian.game_paths(&pool_id).mods;
+        let mut folders: Vec<PathBuf> = std::fs::read_dir(&mods_dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|e| e.ok())
+                    .filter(|e| e.path().is_dir())
+                    .filter(|e| !e.file_name().to_string_lossy().starts_with('.'))
+                    .map(|e| e.path())
+                    .collect()
+            })
+            .unwrap_or_default();
+        for record in db.mods.values() {
+            if record.owner_game_id == pool_id && !folders.contains(&record.path) {
+                folders.push(record.path.clone());
+            }
+        }
+        folders.sort();
+        Self::sync_folders(librarian, game_id, db, &folders)
+    }
+
+    /// The mod id a folder is named after, as imports name them.
+    fn folder_id(folder: &Path) -> Option<Uuid> {
+        Uuid::parse_str(&folder.file_name()?.to_string_lossy()).ok()
+    }
+
+    /// Recomputes what import derived from the files. Returns whether anything changed.
+    fn refresh(
+        librarian: &Librarian,
+        game_id: &str,
+        game_version: Option<&str>,
+        record: &mut ModRecord,
+    ) -> bool {
+        let size_bytes = Safety::get_dir_size(&record.path).unwrap_or(0);
+        let size = Importer::format_size(size_bytes);
+        let identity = Importer::identify_character_and_type(
+            &record.path,
+            &librarian.assets_root,
+            game_id,
+            game_version,
+        );
+        let mod_type = identity.mod_type.clone();
+
+        let mut changed = false;
+        if record.size != size {
+            record.size = size;
+            changed = true;
+        }
+        // Only the exact figure moved, e.g. a record from before it was stored
+        let resized = record.size_bytes != size_bytes;
+        record.size_bytes = size_bytes;
+        let before = record.compatibility.clone();
+        identity.apply(&mut record.compatibility);
+        let after = &record.compatibility;
+        if after.character != before.character
+            || after.hashes != before.hashes
+            || after.characters != before.characters
+            || after.confidence != before.confidence
+        {
+            changed = true;
+        }
+        // Only replace the placeholder type; anything else may be a user's tag
+        if mod_type != "Unknown/Global" {
+            if let Some(tag) = record
+                .config
+                .tags
+                .iter_mut()
+                .find(|t| t.as_str() == "Unknown/Global")
+            {
+                *tag = mod_type;
+                changed = true;
+            }
+        }
+        let keybinds = KeybindExtractor::extract(&record.path);
+        if record.config.keybinds != keybinds {
+            record.config.keybinds = keybinds;
+            changed = true;
+        }
+        // Hashing every file is too slow for each startup scan, so only mods whose
+        // contents visibly moved get a new fingerprint. Legacy records are filled in
+        // by the duplicate report.
+        if changed && !record.compatibility.fingerprint.is_empty() {
+            Self::refingerprint(record);
+        }
+        changed || resized
+    }
+
+    fn refingerprint(record: &mut ModRecord) {
+        let (fingerprint, texture_hashes) = Importer::fingerprint(&record.path);
+        record.compatibility.fingerprint = fingerprint;
+        record.compatibility.texture_hashes = texture_hashes;
+    }
+
+    /// Creates a record for a folder dropped into the mods folder by hand and appends it
+    /// to the active profile, the same way an import would.
+    fn adopt(
+        librarian: &Librarian,
+        game_id: &str,
+        db: &mut LibraryDatabase,
+        folder: &Path,
+    ) -> ModRecord {
+        let folder_name = folder
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "Unknown Mod".to_string());
+
+        // A folder named after a mod id is most likely a record that was lost
+        let mod_id = Self::folder_id(folder)
+            .filter(|id| !db.mods.contains_key(id))
+            .unwrap_or_else(Uuid::new_v4);
+
+        let meta = ["mod.json", "modinfo.json"]
+            .iter()
+            .filter_map(|name| std::fs::read_to_string(folder.join(name)).ok())
+            .find_map(|content| serde_json::from_str::<ModMetadata>(&content).ok())
+            .unwrap_or_else(|| Importer::generate_default_metadata(&folder_name));
+        let added_at = std::fs::metadata(folder)
+            .and_then(|m| m.modified())
+            .map(chrono::DateTime::<Utc>::from)
+            .unwrap_or_else(|_| Utc::now());
+
+        let mut record = ModRecord {
+            id: mod_id,
+            owner_game_id: db.mod_pool_id(game_id).to_string(),
+            path: folder.to_path_buf(),
+            size: String::new(),
+            size_bytes: 0,
+            meta,
+            compatibility: ModCompatibility {
+                game: "Unknown".to_string(),
+                character: "Unknown".to_string(),
+                hashes: Vec::new(),
+                fingerprint: "".to_string(),
+                texture_hashes: Vec::new(),
+                characters: Vec::new(),
+                confidence: 0.0,
+            },
+            config: ModConfig {
+                tags: vec!["Unknown/Global".to_string()],
+                keybinds: HashMap::new(),
+                auto_tags: Vec::new(),
+            },
+            enabled: true,
+            added_at,
+            version_id: Uuid::new_v4(),
+            versions: Vec::new(),
+            source: None,
+        };
+        let game_version = db.games.get(game_id).map(|g| g.version.clone());
+        Self::refresh(librarian, game_id, game_version.as_deref(), &mut record);
+        Self::refingerprint(&mut record);
+        Importer::scan_for_nsfw(&mut record.meta, &mut record.config, &folder_name);
+
+        db.mods.insert(mod_id, record.clone());
+        if let Some(game_config) = db.games.get(game_id) {
+            if let Ok(p_uuid) = Uuid::parse_str(&game_config.active_profile_id) {
+                if let Some(profile) = db.profiles.get_mut(&p_uuid) {
+                    if !profile.load_order.contains(&mod_id) {
+                        profile.load_order.push(mod_id);
+                        profile.enabled_mod_ids.push(mod_id);
+                    }
+                }
+            }
+        }
+        record
+    }
+}
+
+/// Watches the library's mods root and reports changed mod folders per game.
+pub struct ModWatcher {
+    _watcher: DirWatcher,
+}
+
+impl ModWatcher {
+    /// `on_change` gets a game id and the mod folders under it that changed, once the
+    /// folders have been quiet for `debounce`. It runs on the watcher's own thread.
+    pub fn start<F>(librarian: &Librarian, debounce: Duration, on_change: F) -> Result<Self>
+    where
+        F: Fn(String, Vec<PathBuf>) + Send + 'static,
+    {
+        let mods_root = librarian.mods_root.clone();
+        std::fs::create_dir_all(&mods_root)?;
+
+        let root = mods_root.clone();
+        let mut watcher = DirWatcher::with_depth(debounce, 2, move |changes| {
+            let mut by_game: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+            for change in changes {
+                let Ok(relative) = change.entry.strip_prefix(&root) else {
+                    continue;
+                };
+                let mut components = relative.components();
+                let (Some(game), Some(_)) = (components.next(), components.next()) else {
+                    continue;
+                };
+                by_game
+                    .entry(game.as_os_str().to_string_lossy().to_string())
+                    .or_default()
+                    .push(change.entry);
+            }
+            for (game_id, folders) in by_game {
+                on_change(game_id, folders);
+            }
+        })?;
+        watcher.watch(&mods_root)?;
+
+        Ok(Self { _watcher: watcher })
+    }
+}
@@ -0,0 +1,197 @@
+mod common;
+
+use common::setup;
+use librarian::models::Profile;
+use librarian::*;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+use uuid::Uuid;
+
+async fn add_game(librarian: &Librarian, game_id: &str) -> Uuid {
+    let profile = Profile {
+        id: Uuid::new_v4(),
+        ..Default::default()
+    };
+    let profile_id = profile.id;
+    let mut db = librarian.load_game_db(game_id).await.unwrap();
+    db.profiles.insert(profile_id, profile);
+    db.games.insert(
+        game_id.to_string(),
+        common::game_config(game_id, &profile_id.to_string()),
+    );
+    librarian.save_game_db(game_id, &db).await.unwrap();
+    profile_id
+}
+
+fn write_mod(dir: &Path, hash: &str) {
+    fs::create_dir_all(dir).unwrap();
+    fs::write(
+        dir.join("mod.ini"),
+        format!("[TextureOverrideBody]\nhash = {hash}\n"),
+    )
+    .unwrap();
+    fs::write(dir.join("body.dds"), format!("dds {hash}")).unwrap();
+}
+
+#[tokio::test]
+async fn test_preview_lists_unmanaged_mods() {
+    let dir = tempdir().unwrap();
+    let librarian = setup(dir.path());
+    add_game(&librarian, "gi").await;
+
+    // Already in the library and enabled
+    let library_mod = dir.path().join("Downloads/Old Kimono");
+    write_mod(&library_mod, "aaaa1111");
+    Importer::import_folder(
+        &librarian,
+        library_mod,
+        "gi".into(),
+        ImportOptions::default(),
+    )
+    .await
+    .unwrap();
+
+    let mods_dir = dir.path().join("Game/Mods");
+    write_mod(&mods_dir.join("Raiden Kimono"), "aaaa1111");
+    write_mod(&mods_dir.join("DISABLED_Ayaka"), "aaaa1111");
+    write_mod(&mods_dir.join("Characters/Hu Tao"), "bbbb2222");
+    write_mod(&mods_dir.join("Characters/Nahida"), "cccc3333");
+    fs::create_dir_all(mods_dir.join("Screenshots")).unwrap();
+    fs::write(mods_dir.join("Screenshots/shot.png"), "png").unwrap();
+    fs::write(mods_dir.join("notes.txt"), "notes").unwrap();
+    assert!(ModAdoption::is_unmanaged(&mods_dir));
+
+    let preview = ModAdoption::preview(&librarian, "gi", &mods_dir)
+        .await
+        .unwrap();
+    let names: Vec<(&str, bool)> = preview
+        .candidates
+        .iter()
+        .map(|c| (c.name.as_str(), c.enabled))
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            ("Hu Tao", true),
+            ("Nahida", true),
+            ("Ayaka", false),
+            ("Raiden Kimono", true),
+        ]
+    );
+    assert_eq!(
+        preview.skipped,
+        vec![mods_dir.join("Screenshots"), mods_dir.join("notes.txt")]
+    );
+
+    let raiden = &preview.candidates[3];
+    assert_eq!(raiden.conflicts_with, vec!["Old Kimono"]);
+    // Same files as the library's copy
+    assert_eq!(raiden.duplicate_of.as_ref().unwrap().name, "Old Kimono");
+    assert!(preview.candidates[0].duplicate_of.is_none());
+    // Disabled folders are not deployed, so they collide with nothing
+    assert!(preview.candidates[2].conflicts_with.is_empty());
+    assert!(preview.candidates[0].conflicts_with.is_empty());
+    // Nothing was touched
+    assert!(mods_dir.join("Raiden Kimono/mod.ini").exists());
+
+    assert_eq!(ModAdoption::strip_disabled("disabled Hat"), "Hat");
+    assert_eq!(ModAdoption::strip_disabled("Disabledness"), "ness");
+    assert_eq!(ModAdoption::strip_disabled("DISABLED"), "DISABLED");
+    assert!(!ModAdoption::is_unmanaged(&dir.path().join("Game/Missing")));
+
+    // Entries the preview skips do not block deployment; they are set aside instead
+    let leftovers = dir.path().join("Other/Mods");
+    fs::create_dir_all(leftovers.join("Screenshots")).unwrap();
+    fs::write(leftovers.join("notes.txt"), "notes").unwrap();
+    assert!(!ModAdoption::is_unmanaged(&leftovers));
+    let moved = ModAdoption::set_aside_leftovers(&leftovers).unwrap();
+    assert_eq!(moved.len(), 2);
+    let backup = ModAdoption::backup_dir(&leftovers);
+    assert_eq!(backup, dir.path().join("Other/Mods.yago-adopted"));
+    assert_eq!(
+        fs::read_to_string(backup.join("notes.txt")).unwrap(),
+        "notes"
+    );
+    assert_eq!(fs::read_dir(&leftovers).unwrap().count(), 0);
+    // A folder holding mods is left for adoption
+    assert!(ModAdoption::set_aside_leftovers(&mods_dir)
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_adopt_keeps_enabled_state() {
+    let dir = tempdir().unwrap();
+    let librarian = setup(dir.path());
+    let profile_id = add_game(&librarian, "gi").await;
+
+    let mods_dir = dir.path().join("Game/Mods");
+    write_mod(&mods_dir.join("Raiden Kimono"), "aaaa1111");
+    write_mod(&mods_dir.join("DISABLED_Pack/Ayaka"), "bbbb2222");
+    write_mod(&mods_dir.join("Hu Tao"), "cccc3333");
+
+    let report = ModAdoption::adopt(
+        &librarian,
+        "gi",
+        &mods_dir,
+        &[
+            mods_dir.join("Raiden Kimono"),
+            mods_dir.join("DISABLED_Pack/Ayaka"),
+            dir.path().join("Elsewhere"),
+        ],
+        AdoptMode::Move,
+        ImportOptions::default(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(report.adopted.len(), 2);
+    assert_eq!(report.failed[0].path, dir.path().join("Elsewhere"));
+    let raiden = &report.adopted[0];
+    let ayaka = &report.adopted[1];
+    assert_eq!(raiden.meta.name, "Raiden Kimono");
+    assert_eq!(ayaka.meta.name, "Ayaka");
+    assert!(ayaka.path.join("mod.ini").exists());
+
+    let db = librarian.load_game_db("gi").await.unwrap();
+    let profile = &db.profiles[&profile_id];
+    assert_eq!(profile.load_order, vec![raiden.id, ayaka.id]);
+    assert_eq!(profile.enabled_mod_ids, vec![raiden.id]);
+
+    // Moved out of the game, including the pack folder left empty, into the backup
+    assert!(!mods_dir.join("Raiden Kimono").exists());
+    assert!(!mods_dir.join("DISABLED_Pack").exists());
+    assert!(mods_dir.join("Hu Tao/mod.ini").exists());
+    let backup = report.backup.clone().unwrap();
+    assert_eq!(backup, ModAdoption::backup_dir(&mods_dir));
+    assert!(backup.join("Raiden Kimono/mod.ini").exists());
+    assert!(backup.join("DISABLED_Pack/Ayaka/mod.ini").exists());
+
+    // A second folder of the same name does not overwrite the first backup
+    write_mod(&mods_dir.join("Raiden Kimono"), "dddd4444");
+    ModAdoption::adopt(
+        &librarian,
+        "gi",
+        &mods_dir,
+        &[mods_dir.join("Raiden Kimono")],
+        AdoptMode::Move,
+        ImportOptions::default(),
+    )
+    .await
+    .unwrap();
+    assert!(backup.join("Raiden Kimono/mod.ini").exists());
+    assert!(backup.join("Raiden Kimono (2)/mod.ini").exists());
+
+    let report = ModAdoption::adopt(
+        &librarian,
+        "gi",
+        &mods_dir,
+        &[mods_dir.join("Hu Tao")],
+        AdoptMode::Copy,
+        ImportOptions::default(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(report.adopted.len(), 1);
+    assert!(mods_dir.join("Hu Tao/mod.ini").exists());
+}
@@ -0,0 +1,206 @@
+mod common;
+
+use chrono::{Duration, Utc};
+use common::setup;
+use librarian::models::{
+    LibraryDatabase, ModCompatibility, ModConfig, ModMetadata, ModRecord, Profile,
+};
+use librarian::*;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tempfile::tempdir;
+use uuid::Uuid;
+
+fn record(name: &str, character: &str, tags: &[&str], hashes: &[&str], age: i64) -> ModRecord {
+    ModRecord {
+        id: Uuid::new_v4(),
+        owner_game_id: "gi".to_string(),
+        path: PathBuf::from("/mods").join(name),
+        size: "1 MB".to_string(),
+        size_bytes: 1 << 20,
+        meta: ModMetadata {
+            name: name.to_string(),
+            version: "1.0".to_string(),
+            author: "Unknown".to_string(),
+            url: None,
+            preview_image: None,
+            description: None,
+            updated_at: None,
+        },
+        compatibility: ModCompatibility {
+            game: "Unknown".to_string(),
+            character: character.to_string(),
+            hashes: hashes.iter().map(|h| h.to_string()).collect(),
+            fingerprint: String::new(),
+            texture_hashes: Vec::new(),
+            characters: Vec::new(),
+            confidence: 0.0,
+        },
+        config: ModConfig {
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            keybinds: HashMap::new(),
+            auto_tags: Vec::new(),
+        },
+        enabled: true,
+        added_at: Utc::now() - Duration::minutes(age),
+        version_id: Uuid::nil(),
+        versions: Vec::new(),
+        source: None,
+    }
+}
+
+fn library(mods: &[ModRecord]) -> LibraryDatabase {
+    let profile = Profile::default();
+    LibraryDatabase {
+        version: "1.0".to_string(),
+        profiles: HashMap::from([(profile.id, profile)]),
+        mods: mods.iter().map(|m| (m.id, m.clone())).collect(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_sqlite_roundtrip_and_indexed_queries() {
+    let dir = tempdir().unwrap();
+    let backend = SqliteBackend::open(dir.path().join("library.sqlite")).unwrap();
+    assert_eq!(backend.schema_version().unwrap(), 1);
+
+    let raiden = record("Raiden Kimono", "Raiden", &["Skin"], &["AB12cd34"], 2);
+    let raiden_alt = record("Raiden Swimsuit", "raiden", &["Skin", "NSFW"], &[], 1);
+    let ui = record("Clean UI", "Unknown", &["UI"], &["ab12cd34"], 0);
+    let mut db = library(&[raiden.clone(), raiden_alt.clone(), ui.clone()]);
+    backend.save("gi", &db).unwrap();
+    backend.save("hsr", &library(&[])).unwrap();
+
+    assert_eq!(backend.game_ids().unwrap(), vec!["gi", "hsr"]);
+    let loaded = backend.load("gi").unwrap().unwrap();
+    assert_eq!(loaded.mods.len(), 3);
+    assert_eq!(loaded.profiles.len(), 1);
+    assert_eq!(loaded.mods[&raiden.id].meta.name, "Raiden Kimono");
+    assert!(backend.load("zzz").unwrap().is_none());
+
+    let names =
+        |mods: Vec<ModRecord>| -> Vec<String> { mods.into_iter().map(|m| m.meta.name).collect() };
+    assert_eq!(
+        names(backend.mods_by_character("gi", "RAIDEN").unwrap()),
+        vec!["Raiden Kimono", "Raiden Swimsuit"]
+    );
+    assert_eq!(
+        names(backend.mods_by_tag("gi", "nsfw").unwrap()),
+        vec!["Raiden Swimsuit"]
+    );
+    assert_eq!(
+        names(backend.mods_by_hash("gi", "ab12cd34").unwrap()),
+        vec!["Raiden Kimono", "Clean UI"]
+    );
+    assert!(backend.mods_by_tag("hsr", "Skin").unwrap().is_empty());
+
+    // Saving again updates changed rows and drops removed ones along with their index rows
+    db.mods.remove(&raiden_alt.id);
+    db.mods.get_mut(&ui.id).unwrap().config.tags = vec!["Skin".to_string()];
+    backend.save("gi", &db).unwrap();
+    assert_eq!(backend.load("gi").unwrap().unwrap().mods.len(), 2);
+    assert_eq!(
+        names(backend.mods_by_tag("gi", "Skin").unwrap()),
+        vec!["Raiden Kimono", "Clean UI"]
+    );
+    assert!(backend.mods_by_tag("gi", "NSFW").unwrap().is_empty());
+
+    backend.delete("gi").unwrap();
+    assert!(!backend.has_game("gi").unwrap());
+    assert!(backend
+        .mods_by_character("gi", "Raiden")
+        .unwrap()
+        .is_empty());
+
+    // Reopening runs no migration twice
+    let reopened = SqliteBackend::open(dir.path().join("library.sqlite")).unwrap();
+    assert_eq!(reopened.schema_version().unwrap(), 1);
+    assert!(reopened.has_game("hsr").unwrap());
+}
+
+#[tokio::test]
+async fn test_use_sqlite_imports_json_once() {
+    let dir = tempdir().unwrap();
+    let mut librarian = setup(dir.path());
+    assert_eq!(librarian.backend_kind(), BackendKind::Json);
+
+    let kimono = record("Raiden Kimono", "Raiden", &["Skin"], &[], 0);
+    librarian
+        .save_game_db("gi", &library(std::slice::from_ref(&kimono)))
+        .await
+        .unwrap();
+    let json_path = librarian.game_paths("gi").db;
+    let json_before = std::fs::read_to_string(&json_path).unwrap();
+
+    assert_eq!(librarian.use_sqlite().unwrap(), vec!["gi"]);
+    assert_eq!(librarian.backend_kind(), BackendKind::Sqlite);
+    let db = librarian.load_game_db("gi").await.unwrap();
+    assert_eq!(db.mods[&kimono.id].meta.name, "Raiden Kimono");
+    assert_eq!(
+        librarian
+            .find_mods_by_character("gi", "raiden")
+            .unwrap()
+            .len(),
+        1
+    );
+
+    // Later saves go to SQLite only and are not overwritten by a second import
+    let mut db = db;
+    db.mods.clear();
+    librarian.save_game_db("gi", &db).await.unwrap();
+    assert_eq!(std::fs::read_to_string(&json_path).unwrap(), json_before);
+    assert!(librarian.use_sqlite().unwrap().is_empty());
+    assert!(librarian.load_game_db("gi").await.unwrap().mods.is_empty());
+    assert_eq!(librarian.discover_game_ids().await.unwrap(), vec!["gi"]);
+}
+
+#[tokio::test]
+async fn test_backend_never_falls_back_to_stale_json() {
+    let dir = tempdir().unwrap();
+    let mut librarian = setup(dir.path());
+    assert_eq!(librarian.detect_backend(), BackendKind::Json);
+    assert!(librarian
+        .open_backend(BackendKind::Json)
+        .unwrap()
+        .is_empty());
+
+    let kimono = record("Raiden Kimono", "Raiden", &["Skin"], &[], 0);
+    librarian
+        .save_game_db("gi", &library(std::slice::from_ref(&kimono)))
+        .await
+        .unwrap();
+    assert_eq!(
+        librarian.open_backend(BackendKind::Sqlite).unwrap(),
+        vec!["gi"]
+    );
+    assert_eq!(librarian.detect_backend(), BackendKind::Sqlite);
+
+    // The game.json files stopped following the library, so going back is refused
+    let err = librarian.open_backend(BackendKind::Json).unwrap_err();
+    assert!(matches!(err, LibrarianError::Validation(_)));
+    assert_eq!(librarian.backend_kind(), BackendKind::Json);
+    assert!(matches!(
+        librarian.load_game_db("gi").await.unwrap_err(),
+        LibrarianError::BackendUnavailable(_)
+    ));
+
+    // A database that cannot be opened leaves the library unavailable, not on JSON
+    librarian.open_backend(BackendKind::Sqlite).unwrap();
+    let moved = dir.path().join("elsewhere");
+    std::fs::create_dir_all(moved.join("library.sqlite")).unwrap();
+    librarian.update_roots(storage::LibrarianConfig {
+        base_path: moved,
+        games_install_path: None,
+        mods_path: None,
+        runners_path: None,
+        prefixes_path: None,
+        cache_path: None,
+        portable: None,
+    });
+    assert_eq!(librarian.backend_kind(), BackendKind::Sqlite);
+    assert!(matches!(
+        librarian.discover_game_ids().await.unwrap_err(),
+        LibrarianError::BackendUnavailable(_)
+    ));
+}
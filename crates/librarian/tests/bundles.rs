@@ -0,0 +1,104 @@
+mod common;
+
+use common::{setup, write_zip};
+use librarian::models::Profile;
+use librarian::*;
+use tempfile::tempdir;
+
+async fn import(librarian: &Librarian, archive: &std::path::Path) -> ModRecord {
+    Importer::import_mod(librarian, archive.to_path_buf(), "gi".into())
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_bundle_roundtrip_matches_and_imports_mods() {
+    let archives = tempdir().unwrap();
+    let kimono = archives.path().join("Kimono.zip");
+    write_zip(&kimono, &[("mod.ini", b"; kimono"), ("body.dds", b"dds")]);
+    let hair = archives.path().join("Hair.zip");
+    write_zip(&hair, &[("hair.ini", b"; hair")]);
+
+    // Exporting machine
+    let dir_a = tempdir().unwrap();
+    let a = setup(dir_a.path());
+    let kimono_a = import(&a, &kimono).await;
+    let hair_a = import(&a, &hair).await;
+    let mut db = a.load_game_db("gi").await.unwrap();
+    let hair_record = db.mods.get_mut(&hair_a.id).unwrap();
+    hair_record.meta.name = "Long Hair".to_string();
+    hair_record.meta.url = Some("https://gamebanana.com/mods/77".to_string());
+    let profile = Profile {
+        name: "Photo mode".to_string(),
+        load_order: vec![hair_a.id, kimono_a.id],
+        enabled_mod_ids: vec![hair_a.id],
+        launch_args: vec!["-popupwindow".to_string()],
+        use_gamemode: true,
+        ..Default::default()
+    };
+    db.profiles.insert(profile.id, profile.clone());
+    a.save_game_db("gi", &db).await.unwrap();
+
+    let embedded = dir_a.path().join("photo.yagobundle");
+    let bundle = ProfileBundles::export(&a, "gi", profile.id, &embedded, true)
+        .await
+        .unwrap();
+    assert_eq!(bundle.mods.len(), 2);
+    assert_eq!(bundle.mods[0].meta.name, "Long Hair");
+    assert_eq!(bundle.mods[0].fingerprint, hair_a.compatibility.fingerprint);
+    assert_eq!(bundle.mods[1].files.len(), 2);
+    assert_eq!(
+        ProfileBundles::read_manifest(&embedded).unwrap().mods[1].id,
+        kimono_a.id
+    );
+    let bare = dir_a.path().join("photo-bare.yagobundle");
+    ProfileBundles::export(&a, "gi", profile.id, &bare, false)
+        .await
+        .unwrap();
+
+    // A machine that already has the kimono under a different id
+    let dir_b = tempdir().unwrap();
+    let b = setup(dir_b.path());
+    let kimono_b = import(&b, &kimono).await;
+    let report = ProfileBundles::import(&b, "gi", &embedded, ImportOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(report.matched, vec![kimono_b.id]);
+    assert_eq!(report.imported.len(), 1);
+    assert!(report.missing.is_empty());
+    let hair_b = report.imported[0];
+    assert_eq!(report.profile.name, "Photo mode");
+    assert_eq!(report.profile.load_order, vec![hair_b, kimono_b.id]);
+    assert_eq!(report.profile.enabled_mod_ids, vec![hair_b]);
+    assert_eq!(report.profile.launch_args, vec!["-popupwindow"]);
+    assert!(report.profile.use_gamemode);
+
+    let db = b.load_game_db("gi").await.unwrap();
+    assert_eq!(db.mods.len(), 2);
+    assert_eq!(db.mods[&hair_b].meta.name, "Long Hair");
+    assert_eq!(
+        db.mods[&hair_b].compatibility.fingerprint,
+        hair_a.compatibility.fingerprint
+    );
+    assert!(db.profiles.contains_key(&report.profile.id));
+
+    // Without embedded archives, unknown mods are listed with where to get them
+    let dir_c = tempdir().unwrap();
+    let c = setup(dir_c.path());
+    let report = ProfileBundles::import(&c, "gi", &bare, ImportOptions::default())
+        .await
+        .unwrap();
+    assert!(report.matched.is_empty() && report.imported.is_empty());
+    assert_eq!(report.missing.len(), 2);
+    assert_eq!(report.missing[0].name, "Long Hair");
+    assert_eq!(
+        report.missing[0].url.as_deref(),
+        Some("https://gamebanana.com/mods/77")
+    );
+    assert!(report.profile.load_order.is_empty());
+
+    assert!(matches!(
+        ProfileBundles::import(&c, "hsr", &bare, ImportOptions::default()).await,
+        Err(LibrarianError::Validation(_))
+    ));
+}
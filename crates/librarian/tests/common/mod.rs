@@ -0,0 +1,88 @@
+//! Fixtures shared by the integration tests. Each test file is its own crate and uses
+//! only some of them.
+#![allow(dead_code)]
+
+use chrono::Utc;
+use librarian::models::{GameConfig, InjectionMethod, InstallStatus, SandboxConfig};
+use librarian::storage::LibrarianConfig;
+use librarian::Librarian;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// A librarian keeping everything under `dir`.
+pub fn setup(dir: &Path) -> Librarian {
+    let config = LibrarianConfig {
+        base_path: dir.to_path_buf(),
+        mods_path: None,
+        runners_path: None,
+        prefixes_path: None,
+        cache_path: None,
+        games_install_path: None,
+        portable: None,
+    };
+    let librarian = Librarian::new(config);
+    librarian.ensure_core_dirs().unwrap();
+    librarian
+}
+
+pub fn zip_bytes(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    for (name, content) in files {
+        zip.start_file(*name, zip::write::SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(content).unwrap();
+    }
+    zip.finish().unwrap().into_inner()
+}
+
+pub fn write_zip(path: &Path, files: &[(&str, &[u8])]) {
+    File::create(path)
+        .unwrap()
+        .write_all(&zip_bytes(files))
+        .unwrap();
+}
+
+/// An installed Genshin Impact with `profile_id` active and no install paths.
+pub fn game_config(game_id: &str, profile_id: &str) -> GameConfig {
+    GameConfig {
+        id: game_id.to_string(),
+        active_profile_id: profile_id.to_string(),
+        name: "Genshin Impact".into(),
+        short_name: game_id.into(),
+        developer: "dev".into(),
+        description: "desc".into(),
+        install_path: PathBuf::new(),
+        exe_path: PathBuf::new(),
+        exe_name: "GenshinImpact.exe".into(),
+        version: "1.0".into(),
+        remote_version: None,
+        installed_components: vec![],
+        size: "0".into(),
+        color: "".into(),
+        accent_color: "".into(),
+        cover_image: "".into(),
+        icon: "".into(),
+        logo_initial: "".into(),
+        enabled: true,
+        added_at: Utc::now(),
+        launch_args: vec![],
+        fps_config: None,
+        injection_method: InjectionMethod::None,
+        install_status: InstallStatus::Installed,
+        auto_update: false,
+        active_runner_id: None,
+        prefix_path: None,
+        sandbox: SandboxConfig::default(),
+        loader_repo: None,
+        hash_db_url: None,
+        patch_logic: None,
+        enable_linux_shield: true,
+        supported_injection_methods: vec![],
+        modloader_enabled: true,
+        remote_info: None,
+        template_id: None,
+        region: None,
+        mod_pool: None,
+    }
+}
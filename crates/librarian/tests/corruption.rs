@@ -14,6 +14,7 @@ async fn test_corrupted_json_handling() {
         prefixes_path: None,
         cache_path: None,
         games_install_path: None,
+        portable: None,
     };
     let librarian = Librarian::new(config);
     librarian.ensure_core_dirs().unwrap();
@@ -30,6 +31,95 @@ async fn test_corrupted_json_handling() {
     assert!(result.is_err());
 }
 
+#[tokio::test]
+async fn test_corrupted_json_recovers_from_backup() {
+    let dir = tempdir().unwrap();
+    let config = LibrarianConfig {
+        base_path: dir.path().to_path_buf(),
+        mods_path: None,
+        runners_path: None,
+        prefixes_path: None,
+        cache_path: None,
+        games_install_path: None,
+        portable: None,
+    };
+    let librarian = Librarian::new(config);
+    librarian.ensure_core_dirs().unwrap();
+
+    let game_id = "test.exe";
+    let profile = models::Profile::default();
+    let db = LibraryDatabase {
+        version: "1.0".to_string(),
+        profiles: HashMap::from([(profile.id, profile.clone())]),
+        ..Default::default()
+    };
+    librarian.save_game_db(game_id, &db).await.unwrap();
+
+    // A half-written file, as left by a crash in an older version
+    let paths = librarian.game_paths(game_id);
+    fs::write(&paths.db, "{\"version\": \"1.0\", \"ga").unwrap();
+    fs::write(&paths.profiles, "").unwrap();
+
+    let loaded = librarian.load_game_db(game_id).await.unwrap();
+    assert_eq!(loaded.version, "1.0");
+    assert!(loaded.profiles.contains_key(&profile.id));
+
+    let recoveries = librarian.take_recoveries();
+    assert_eq!(recoveries.len(), 2);
+    assert_eq!(recoveries[0].file, paths.db);
+    assert!(recoveries[0].corrupt_copy.as_ref().unwrap().exists());
+    assert!(librarian.take_recoveries().is_empty());
+}
+
+#[tokio::test]
+async fn test_corrupted_sqlite_recovers_from_backup() {
+    let dir = tempdir().unwrap();
+    let config = LibrarianConfig {
+        base_path: dir.path().to_path_buf(),
+        mods_path: None,
+        runners_path: None,
+        prefixes_path: None,
+        cache_path: None,
+        games_install_path: None,
+        portable: None,
+    };
+    let mut librarian = Librarian::new(config);
+    librarian.ensure_core_dirs().unwrap();
+    librarian.open_backend(BackendKind::Sqlite).unwrap();
+
+    let game_id = "test.exe";
+    let profile = models::Profile::default();
+    let db = LibraryDatabase {
+        version: "1.0".to_string(),
+        profiles: HashMap::from([(profile.id, profile.clone())]),
+        ..Default::default()
+    };
+    librarian.save_game_db(game_id, &db).await.unwrap();
+    let path = librarian.library_db_path();
+    let backend = SqliteBackend::open(&path).unwrap();
+    assert_eq!(backend.backups().backups(&path).unwrap().len(), 1);
+
+    // Overwritten by something that is no database at all
+    fs::write(&path, vec![0x5a; 8192]).unwrap();
+    librarian.open_backend(BackendKind::Sqlite).unwrap();
+    let loaded = librarian.load_game_db(game_id).await.unwrap();
+    assert!(loaded.profiles.contains_key(&profile.id));
+
+    let recoveries = librarian.take_recoveries();
+    assert_eq!(recoveries.len(), 1);
+    assert_eq!(recoveries[0].file, path);
+    assert!(recoveries[0].corrupt_copy.as_ref().unwrap().exists());
+
+    // Without an intact backup the library is unavailable rather than empty
+    for backup in backend.backups().backups(&path).unwrap() {
+        fs::remove_file(backup).unwrap();
+    }
+    fs::write(&path, vec![0x5a; 8192]).unwrap();
+    let err = librarian.open_backend(BackendKind::Sqlite).unwrap_err();
+    assert!(matches!(err, LibrarianError::BackendUnavailable(_)));
+    assert!(librarian.load_game_db(game_id).await.is_err());
+}
+
 #[tokio::test]
 async fn test_template_match_by_multiple_exes() {
     let dir = tempdir().unwrap();
@@ -40,6 +130,7 @@ async fn test_template_match_by_multiple_exes() {
         prefixes_path: None,
         cache_path: None,
         games_install_path: None,
+        portable: None,
     };
     let librarian = Librarian::new(config);
     librarian.ensure_core_dirs().unwrap();
@@ -1,3 +1,4 @@
+use fs_engine::test_support::{build_pe, PeSpec};
 use librarian::models::InjectionMethod;
 use librarian::storage::LibrarianConfig;
 use librarian::*;
@@ -16,6 +17,7 @@ async fn test_discovery_with_template_settings() {
         prefixes_path: None,
         cache_path: None,
         games_install_path: None,
+        portable: None,
     };
     let librarian = Librarian::new(config);
     librarian.ensure_core_dirs().unwrap();
@@ -121,6 +123,7 @@ async fn test_game_discovery() {
         prefixes_path: None,
         cache_path: None,
         games_install_path: None,
+        portable: None,
     };
     let librarian = Librarian::new(config);
     librarian.ensure_core_dirs().unwrap();
@@ -136,3 +139,58 @@ async fn test_game_discovery() {
     assert!(ids.contains(&"game1.exe".to_string()));
     assert!(ids.contains(&"game2.exe".to_string()));
 }
+
+#[tokio::test]
+async fn test_discovery_identifies_renamed_exe_by_version_resource() {
+    let dir = tempdir().unwrap();
+    let config = LibrarianConfig {
+        base_path: dir.path().to_path_buf(),
+        mods_path: None,
+        runners_path: None,
+        prefixes_path: None,
+        cache_path: None,
+        games_install_path: None,
+        portable: None,
+    };
+    let librarian = Librarian::new(config);
+    librarian.ensure_core_dirs().unwrap();
+
+    // No config.ini next to the game, and the exe has been renamed
+    let game_dir = dir.path().join("game");
+    std::fs::create_dir(&game_dir).unwrap();
+    let exe_path = game_dir.join("Launcher-Copy.exe");
+    std::fs::write(
+        &exe_path,
+        build_pe(&PeSpec {
+            strings: &[
+                ("OriginalFilename", "TestGame.exe"),
+                ("ProductName", "Test Game"),
+            ],
+            ..Default::default()
+        }),
+    )
+    .unwrap();
+
+    let mut templates = HashMap::new();
+    templates.insert(
+        "testgame".to_string(),
+        GameTemplate {
+            id: "testgame".to_string(),
+            name: "Test Game".to_string(),
+            executables: vec!["TestGame.exe".to_string()],
+            launch_args: Some(vec!["-custom".to_string()]),
+            ..Default::default()
+        },
+    );
+
+    let id = Discovery::add_game_by_path(&librarian, exe_path, &templates)
+        .await
+        .unwrap();
+    let db = librarian.load_game_db(&id).await.unwrap();
+    let config = db.games.get(&id).unwrap();
+
+    assert_eq!(id, "launcher-copy.exe");
+    assert_eq!(config.name, "Test Game");
+    assert_eq!(config.launch_args, vec!["-custom".to_string()]);
+    assert_eq!(config.version, "Unknown");
+}
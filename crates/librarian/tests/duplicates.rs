@@ -0,0 +1,113 @@
+mod common;
+
+use common::{setup, write_zip};
+use librarian::*;
+use tempfile::tempdir;
+
+async fn import(
+    librarian: &Librarian,
+    archive: &std::path::Path,
+    duplicates: DuplicatePolicy,
+) -> Result<ModRecord> {
+    let options = ImportOptions {
+        duplicates,
+        ..Default::default()
+    };
+    Importer::import_mod_with_options(librarian, archive.to_path_buf(), "gi".into(), options).await
+}
+
+const INI: &[u8] =
+    b"[TextureOverrideBody]\nhash = aaaa1111\n\n[TextureOverrideHair]\nhash = bbbb2222\n";
+
+#[tokio::test]
+async fn test_fingerprint_ignores_metadata_and_previews() {
+    let dir = tempdir().unwrap();
+    let librarian = setup(dir.path());
+
+    let plain = dir.path().join("Kimono.zip");
+    write_zip(&plain, &[("mod.ini", INI), ("body.dds", b"dds")]);
+    let repacked = dir.path().join("Kimono (repack).zip");
+    write_zip(
+        &repacked,
+        &[
+            ("Kimono/mod.ini", INI),
+            ("Kimono/body.dds", b"dds"),
+            ("Kimono/preview.png", b"png"),
+            ("Kimono/mod.json", br#"{"name": "Kimono Raiden"}"#),
+        ],
+    );
+
+    let first = import(&librarian, &plain, DuplicatePolicy::KeepBoth)
+        .await
+        .unwrap();
+    let second = import(&librarian, &repacked, DuplicatePolicy::KeepBoth)
+        .await
+        .unwrap();
+    assert_eq!(first.compatibility.fingerprint.len(), 64);
+    assert_eq!(
+        first.compatibility.fingerprint,
+        second.compatibility.fingerprint
+    );
+    assert_eq!(first.compatibility.texture_hashes.len(), 1);
+
+    // Asking stops the import and leaves nothing behind
+    let err = import(&librarian, &plain, DuplicatePolicy::Ask)
+        .await
+        .unwrap_err();
+    let LibrarianError::PossibleDuplicate(existing) = &err else {
+        panic!("expected a duplicate, got {err}");
+    };
+    assert_eq!(existing.similarity, 1.0);
+    assert!(err.to_string().contains("(100% identical)"));
+    assert_eq!(librarian.load_game_db("gi").await.unwrap().mods.len(), 2);
+
+    let report = DuplicateFinder::report(&librarian, "gi", DUPLICATE_THRESHOLD)
+        .await
+        .unwrap();
+    assert_eq!(report.len(), 1);
+    assert_eq!(report[0].first.mod_id, first.id);
+    assert_eq!(report[0].second.mod_id, second.id);
+}
+
+#[tokio::test]
+async fn test_similarity_from_shared_textures_and_hashes() {
+    let dir = tempdir().unwrap();
+    let librarian = setup(dir.path());
+
+    let textures: Vec<(String, Vec<u8>)> = (0..10)
+        .map(|i| (format!("tex{i}.dds"), format!("texture {i}").into_bytes()))
+        .collect();
+    let mut original: Vec<(&str, &[u8])> = vec![("mod.ini", INI)];
+    original.extend(textures.iter().map(|(n, c)| (n.as_str(), c.as_slice())));
+    let archive = dir.path().join("Original.zip");
+    write_zip(&archive, &original);
+    let existing = import(&librarian, &archive, DuplicatePolicy::Ask)
+        .await
+        .unwrap();
+
+    // One texture fixed in a re-upload: 9 of 11 textures shared, all hashes shared
+    let mut fixed = original.clone();
+    fixed[1] = ("tex0.dds", b"fixed texture");
+    let archive = dir.path().join("Fixed.zip");
+    write_zip(&archive, &fixed);
+    match import(&librarian, &archive, DuplicatePolicy::Ask).await {
+        Err(LibrarianError::PossibleDuplicate(found)) => {
+            assert_eq!(found.mod_id, existing.id);
+            assert!(found.similarity > 0.9 && found.similarity < 1.0);
+        }
+        other => panic!("expected a duplicate, got {other:?}"),
+    }
+
+    // Same character overrides with different textures is a different mod
+    let other_textures: Vec<(&str, &[u8])> = vec![
+        ("mod.ini", INI),
+        ("tex0.dds", b"another outfit"),
+        ("tex1.dds", b"another outfit 2"),
+    ];
+    let archive = dir.path().join("Other.zip");
+    write_zip(&archive, &other_textures);
+    let other = import(&librarian, &archive, DuplicatePolicy::Ask)
+        .await
+        .unwrap();
+    assert!(DuplicateFinder::similarity(&existing.compatibility, &other.compatibility) < 0.6);
+}
@@ -0,0 +1,368 @@
+mod common;
+
+use common::{setup, write_zip};
+use fs_engine::{DdsFormat, DdsHeader, DdsTexture};
+use librarian::*;
+use std::fs::{self, File};
+use std::io::Write;
+use tempfile::tempdir;
+
+#[tokio::test]
+async fn test_import_cancelled_cleans_staging() {
+    let dir = tempdir().unwrap();
+    let librarian = setup(dir.path());
+
+    let archive = dir.path().join("mod.zip");
+    write_zip(&archive, &[("mod.ini", b"[TextureOverride]")]);
+
+    let options = ImportOptions::default();
+    options.extraction.cancel.cancel();
+
+    let result =
+        Importer::import_mod_with_options(&librarian, archive, "test".into(), options).await;
+    assert!(matches!(
+        result,
+        Err(LibrarianError::Fs(fs_engine::FsError::Cancelled))
+    ));
+
+    let staging = librarian.games_root.join(".yago_staging");
+    assert_eq!(fs::read_dir(staging).unwrap().count(), 0);
+}
+
+#[tokio::test]
+async fn test_import_respects_extraction_limits() {
+    let dir = tempdir().unwrap();
+    let librarian = setup(dir.path());
+
+    let archive = dir.path().join("mod.zip");
+    write_zip(&archive, &[("a.ini", b"[A]"), ("b.ini", b"[B]")]);
+
+    let mut options = ImportOptions::default();
+    options.extraction.limits.max_entries = 1;
+
+    let result =
+        Importer::import_mod_with_options(&librarian, archive, "test".into(), options).await;
+    assert!(matches!(
+        result,
+        Err(LibrarianError::Fs(fs_engine::FsError::ArchiveLimit(_)))
+    ));
+}
+
+#[tokio::test]
+async fn test_import_extracts_off_the_runtime_thread() {
+    let dir = tempdir().unwrap();
+    let librarian = setup(dir.path());
+
+    let archive = dir.path().join("mod.zip");
+    write_zip(&archive, &[("mod.ini", b"[TextureOverride]")]);
+
+    let threads = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let seen = threads.clone();
+    let options = ImportOptions {
+        on_stage: Some(std::sync::Arc::new(move |stage| {
+            seen.lock()
+                .unwrap()
+                .push((stage, std::thread::current().id()));
+        })),
+        ..Default::default()
+    };
+    Importer::import_mod_with_options(&librarian, archive, "test".into(), options)
+        .await
+        .unwrap();
+
+    // The test runtime has a single thread; extraction must not run on it
+    let runtime_thread = std::thread::current().id();
+    let threads = threads.lock().unwrap();
+    let (_, extracting) = threads
+        .iter()
+        .find(|(stage, _)| *stage == ImportStage::Extracting)
+        .unwrap();
+    assert_ne!(*extracting, runtime_thread);
+}
+
+#[tokio::test]
+async fn test_import_remembers_archive_password() {
+    let dir = tempdir().unwrap();
+    let librarian = setup(dir.path());
+
+    let archive = dir.path().join("locked.zip");
+    {
+        let mut zip = zip::ZipWriter::new(File::create(&archive).unwrap());
+        let options = zip::write::SimpleFileOptions::default()
+            .with_aes_encryption(zip::AesMode::Aes256, "hunter2");
+        zip.start_file("mod.ini", options).unwrap();
+        zip.write_all(b"[TextureOverride]").unwrap();
+        zip.finish().unwrap();
+    }
+
+    // 1. No password yet
+    let result = Importer::import_mod(&librarian, archive.clone(), "test".into()).await;
+    assert!(matches!(
+        result,
+        Err(LibrarianError::Fs(fs_engine::FsError::PasswordRequired(_)))
+    ));
+
+    // 2. Retry with the password from the mod page
+    let mut options = ImportOptions::default();
+    options.extraction.password = Some("hunter2".to_string());
+    let record =
+        Importer::import_mod_with_options(&librarian, archive.clone(), "test".into(), options)
+            .await
+            .unwrap();
+    assert!(record.path.join("mod.ini").exists());
+
+    // 3. Re-import of the same file picks the password up by hash
+    let record = Importer::import_mod(&librarian, archive, "test".into())
+        .await
+        .unwrap();
+    assert!(record.path.join("mod.ini").exists());
+}
+
+#[tokio::test]
+async fn test_import_fixes_swapped_normal_maps() {
+    let dir = tempdir().unwrap();
+    let librarian = setup(dir.path());
+
+    // Flat normal map with X moved into alpha
+    let broken = DdsTexture {
+        header: DdsHeader {
+            width: 8,
+            height: 8,
+            mip_count: 1,
+            format: DdsFormat::Rgba8,
+            srgb: false,
+            layers: 1,
+            cubemap: false,
+            dx10: false,
+        },
+        surfaces: vec![image::RgbaImage::from_pixel(
+            8,
+            8,
+            image::Rgba([255, 128, 255, 128]),
+        )],
+    };
+    let dds = broken.to_bytes().unwrap();
+
+    let archive = dir.path().join("mod.zip");
+    write_zip(
+        &archive,
+        &[
+            ("mod.ini", b"[TextureOverride]"),
+            ("BodyNormalMap.dds", &dds),
+            ("BodyDiffuse.dds", &dds),
+        ],
+    );
+
+    let options = ImportOptions {
+        fix_normal_maps: true,
+        ..Default::default()
+    };
+    let record = Importer::import_mod_with_options(&librarian, archive, "zzz".into(), options)
+        .await
+        .unwrap();
+
+    let fixed = DdsTexture::load(&record.path.join("BodyNormalMap.dds")).unwrap();
+    assert_eq!(fixed.image().get_pixel(0, 0).0, [128, 128, 255, 255]);
+    // Only files named like normal maps are touched
+    assert_eq!(fs::read(record.path.join("BodyDiffuse.dds")).unwrap(), dds);
+
+    // Import warms the thumbnail cache from the diffuse texture
+    let thumbnails = librarian.ensure_mod_thumbnails(&record).unwrap().unwrap();
+    assert_eq!(thumbnails.source, std::path::Path::new("BodyDiffuse.dds"));
+    assert!(librarian
+        .mod_thumbnail_dir(&record.id)
+        .join("thumbnails.json")
+        .exists());
+}
+
+#[tokio::test]
+async fn test_import_normalizes_filename_case() {
+    let dir = tempdir().unwrap();
+    let librarian = setup(dir.path());
+
+    let archive = dir.path().join("mod.zip");
+    write_zip(
+        &archive,
+        &[
+            (
+                "Hair.ini",
+                b"[TextureOverrideHair]\nhash = 1234abcd\nps-t0 = ResourceHair\n[ResourceHair]\nfilename = Textures\\HairDiffuse.DDS\n",
+            ),
+            ("Textures/HairDiffuse.dds", b"dds"),
+        ],
+    );
+
+    let options = ImportOptions {
+        normalize_filenames: true,
+        ..Default::default()
+    };
+    let record = Importer::import_mod_with_options(&librarian, archive, "gi".into(), options)
+        .await
+        .unwrap();
+
+    assert!(record.path.join("textures/hairdiffuse.dds").is_file());
+    let ini = fs::read_to_string(record.path.join("hair.ini")).unwrap();
+    assert!(ini.contains("filename = textures\\hairdiffuse.dds"));
+    assert!(ini.contains("ps-t0 = ResourceHair"));
+}
+
+#[tokio::test]
+async fn test_import_deduplicates_shared_textures() {
+    let dir = tempdir().unwrap();
+    let librarian = setup(dir.path());
+
+    let texture = vec![7u8; fs_engine::MIN_BLOB_SIZE as usize * 2];
+    let mut records = Vec::new();
+    for (name, ini) in [("v1.zip", "; v1"), ("v2.zip", "; v2")] {
+        let archive = dir.path().join(name);
+        write_zip(
+            &archive,
+            &[("mod.ini", ini.as_bytes()), ("body.dds", &texture)],
+        );
+        let options = ImportOptions {
+            deduplicate: true,
+            ..Default::default()
+        };
+        let record = Importer::import_mod_with_options(&librarian, archive, "gi".into(), options)
+            .await
+            .unwrap();
+        records.push(record);
+    }
+
+    let stats = librarian.blob_store().stats().unwrap();
+    assert_eq!(stats.blobs, 1);
+    assert_eq!(stats.saved_bytes, texture.len() as u64);
+    assert_eq!(fs::read(records[1].path.join("body.dds")).unwrap(), texture);
+
+    // Dropping one version keeps the blob the other still uses
+    let mut db = librarian.load_game_db("gi").await.unwrap();
+    db.mods.remove(&records[1].id);
+    librarian.save_game_db("gi", &db).await.unwrap();
+    let report = librarian.collect_blob_garbage().await.unwrap();
+    assert_eq!(report.removed_manifests, 1);
+    assert_eq!(report.removed_blobs, 0);
+
+    db.mods.clear();
+    librarian.save_game_db("gi", &db).await.unwrap();
+    let report = librarian.collect_blob_garbage().await.unwrap();
+    assert_eq!(report.removed_blobs, 1);
+}
+
+#[tokio::test]
+async fn test_import_pack_splits_mod_roots() {
+    let dir = tempdir().unwrap();
+    let librarian = setup(dir.path());
+
+    let archive = dir.path().join("Raiden Outfit Pack.zip");
+    write_zip(
+        &archive,
+        &[
+            ("Pack/preview.png", b"png"),
+            (
+                "Pack/Kimono/kimono.ini",
+                b"[TextureOverrideA]\nhash = aaaa0001\n",
+            ),
+            ("Pack/Kimono/body.dds", b"kimono"),
+            (
+                "Pack/Swimsuit/swimsuit.ini",
+                b"[TextureOverrideB]\nhash = bbbb0002\n",
+            ),
+            (
+                "Pack/Swimsuit/mod.json",
+                br#"{"name": "Beach Raiden", "version": "2.0", "author": "x"}"#,
+            ),
+            (
+                "Pack/Hair/Short/short.ini",
+                b"[TextureOverrideC]\nhash = cccc0003\n",
+            ),
+        ],
+    );
+    let pack = Importer::import_pack(
+        &librarian,
+        archive.clone(),
+        "gi".into(),
+        ImportOptions::default(),
+    )
+    .await
+    .unwrap();
+    assert!(pack.failures.is_empty());
+    let records = pack.records;
+
+    let names: Vec<&str> = records.iter().map(|r| r.meta.name.as_str()).collect();
+    assert_eq!(names, vec!["Hair", "Kimono", "Beach Raiden"]);
+    let sources: Vec<&ModSource> = records.iter().map(|r| r.source.as_ref().unwrap()).collect();
+    assert_eq!(sources[0].root, "Hair");
+    assert_eq!(sources[2].root, "Swimsuit");
+    assert!(sources.iter().all(|s| s.pack_id == sources[0].pack_id
+        && s.archive == "Raiden Outfit Pack.zip"
+        && s.archive_hash.len() == 32));
+    assert!(records[0].path.join("Short/short.ini").exists());
+    assert!(records[1].path.join("body.dds").exists());
+    assert_eq!(records[1].compatibility.hashes, vec!["aaaa0001"]);
+
+    let db = librarian.load_game_db("gi").await.unwrap();
+    assert_eq!(
+        librarian::queries::Queries::pack_siblings(&db, records[1].id),
+        vec![records[2].id, records[0].id]
+    );
+    let staging = librarian.games_root.join(".yago_staging");
+    assert_eq!(fs::read_dir(staging).unwrap().count(), 0);
+
+    // Importing the pack again only adds the mods that are not in the library yet
+    write_zip(
+        &archive,
+        &[
+            (
+                "Kimono/kimono.ini",
+                b"[TextureOverrideA]\nhash = aaaa0001\n",
+            ),
+            ("Kimono/body.dds", b"kimono"),
+            ("Maid/maid.ini", b"[TextureOverrideD]\nhash = dddd0004\n"),
+        ],
+    );
+    let ask = || ImportOptions {
+        duplicates: DuplicatePolicy::Ask,
+        ..Default::default()
+    };
+    let again = Importer::import_pack(&librarian, archive.clone(), "gi".into(), ask())
+        .await
+        .unwrap();
+    assert_eq!(again.records.len(), 1);
+    assert_eq!(again.records[0].meta.name, "Maid");
+    // The skipped outfit is reported, not just logged
+    assert_eq!(again.failures.len(), 1);
+    assert_eq!(again.failures[0].root, "Kimono");
+    let err = Importer::import_pack(&librarian, archive, "gi".into(), ask())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, LibrarianError::PossibleDuplicate(_)));
+}
+
+#[tokio::test]
+async fn test_import_pack_keeps_single_mod_whole() {
+    let dir = tempdir().unwrap();
+    let librarian = setup(dir.path());
+
+    // A top-level INI drives the variant folders, so this is one mod
+    let archive = dir.path().join("Merged.zip");
+    write_zip(
+        &archive,
+        &[
+            ("Merged/merged.ini", b"[KeySwap]\nkey = down\n"),
+            ("Merged/A/a.ini", b"[TextureOverrideA]\nhash = aaaa0001\n"),
+            ("Merged/B/b.ini", b"[TextureOverrideB]\nhash = bbbb0002\n"),
+        ],
+    );
+    let options = ImportOptions {
+        name: Some("Merged Outfit".to_string()),
+        ..Default::default()
+    };
+    let records = Importer::import_pack(&librarian, archive, "gi".into(), options)
+        .await
+        .unwrap()
+        .records;
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].meta.name, "Merged Outfit");
+    assert!(records[0].path.join("A/a.ini").exists());
+    assert_eq!(records[0].source.as_ref().unwrap().root, "");
+}
@@ -0,0 +1,237 @@
+mod common;
+
+use common::{setup, write_zip, zip_bytes};
+use librarian::*;
+use std::fs::{self};
+use std::sync::{Arc, Mutex};
+use tempfile::tempdir;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn test_import_folder_copies_filtered_files() {
+    let dir = tempdir().unwrap();
+    let librarian = setup(dir.path());
+
+    let folder = dir.path().join("Downloads/raiden_kimono");
+    fs::create_dir_all(folder.join("Textures")).unwrap();
+    fs::write(folder.join("mod.ini"), "[TextureOverrideBody]\nhash = aa\n").unwrap();
+    fs::write(folder.join("Textures/body.dds"), "dds").unwrap();
+    fs::write(folder.join("readme.exe"), "MZ").unwrap();
+
+    let record = Importer::import_folder(
+        &librarian,
+        folder.clone(),
+        "gi".into(),
+        ImportOptions::default(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(record.meta.name, "Raiden Kimono");
+    assert!(record.path.join("mod.ini").exists());
+    assert!(record.path.join("Textures/body.dds").exists());
+    assert!(!record.path.join("readme.exe").exists());
+    // The user's folder is left alone
+    assert!(folder.join("readme.exe").exists());
+
+    let err = Importer::import_folder(
+        &librarian,
+        dir.path().join("missing"),
+        "gi".into(),
+        ImportOptions::default(),
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(err, LibrarianError::Validation(_)));
+}
+
+#[tokio::test]
+async fn test_import_from_url() {
+    let dir = tempdir().unwrap();
+    let librarian = setup(dir.path());
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/files/Hu%20Tao%20Outfit.zip"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(zip_bytes(&[
+            ("mod.ini", b"[TextureOverrideBody]\nhash = bb\n".as_slice()),
+            ("body.dds", b"dds".as_slice()),
+        ])))
+        .mount(&server)
+        .await;
+
+    let mut received = Vec::new();
+    let record = Importer::import_url(
+        &librarian,
+        &format!("{}/files/Hu%20Tao%20Outfit.zip?token=1", server.uri()),
+        "gi".into(),
+        ImportOptions::default(),
+        |current, total| received.push((current, total)),
+    )
+    .await
+    .unwrap();
+    assert_eq!(record.meta.name, "Hu Tao Outfit");
+    assert!(record.path.join("body.dds").exists());
+    let (current, total) = *received.last().unwrap();
+    assert!(total > 0 && current == total);
+
+    let err = Importer::import_url(
+        &librarian,
+        &format!("{}/files/gone.zip", server.uri()),
+        "gi".into(),
+        ImportOptions::default(),
+        |_, _| {},
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(err, LibrarianError::Download(_)));
+
+    // Nothing is left behind in staging
+    let downloads = librarian.games_root.join(".yago_staging/downloads");
+    assert_eq!(fs::read_dir(downloads).unwrap().count(), 0);
+}
+
+#[tokio::test]
+async fn test_queue_reports_each_item() {
+    let dir = tempdir().unwrap();
+    let librarian = setup(dir.path());
+
+    let mut sources = Vec::new();
+    for name in ["Kimono", "Swimsuit", "Maid"] {
+        let archive = dir.path().join(format!("{name}.zip"));
+        let ini = format!("[TextureOverride{name}]\nhash = {name}\n");
+        write_zip(&archive, &[("mod.ini", ini.as_bytes())]);
+        sources.push(ImportSource::detect(archive.to_str().unwrap()));
+    }
+    let preset = dir.path().join("Sharp.zip");
+    write_zip(&preset, &[("Sharp.ini", b"[Technique]\nSharpen=1\n")]);
+    let broken = dir.path().join("broken.zip");
+    fs::write(&broken, b"not a zip").unwrap();
+
+    let updates: Arc<Mutex<Vec<ImportItem>>> = Arc::default();
+    let seen = updates.clone();
+    let queue = ImportQueue::new(
+        2,
+        Some(Arc::new(move |item: &ImportItem| {
+            seen.lock().unwrap().push(item.clone())
+        })),
+    );
+
+    let mut ids: Vec<_> = sources
+        .into_iter()
+        .map(|source| queue.enqueue(&librarian, "gi", source, ImportOptions::default()))
+        .collect();
+    ids.push(queue.enqueue(
+        &librarian,
+        "gi",
+        ImportSource::Archive { path: preset },
+        ImportOptions::default(),
+    ));
+    ids.push(queue.enqueue(
+        &librarian,
+        "gi",
+        ImportSource::Archive { path: broken },
+        ImportOptions::default(),
+    ));
+    let cancelled = ImportOptions::default();
+    cancelled.extraction.cancel.cancel();
+    ids.push(queue.enqueue(
+        &librarian,
+        "gi",
+        ImportSource::detect(dir.path().join("Kimono.zip").to_str().unwrap()),
+        cancelled,
+    ));
+
+    let items = queue.wait().await;
+    assert_eq!(items.iter().map(|i| i.id).collect::<Vec<_>>(), ids);
+    let db = librarian.load_game_db("gi").await.unwrap();
+    for item in &items[..3] {
+        let ImportStatus::Done { mod_ids } = &item.status else {
+            panic!("expected done, got {:?}", item.status);
+        };
+        assert_eq!(mod_ids.len(), 1);
+        assert!(db.mods.contains_key(&mod_ids[0]));
+    }
+    // Imports running side by side must not lose each other's records
+    assert_eq!(db.mods.len(), 3);
+    assert_eq!(
+        items[3].status,
+        ImportStatus::ImportedAsPreset {
+            preset: "Sharp.ini".into()
+        }
+    );
+    assert!(matches!(items[4].status, ImportStatus::Failed { .. }));
+    assert_eq!(items[5].status, ImportStatus::Cancelled);
+    assert!(!queue.cancel(ids[0]));
+
+    let updates = updates.lock().unwrap();
+    let first: Vec<&ImportStatus> = updates
+        .iter()
+        .filter(|u| u.id == ids[0])
+        .map(|u| &u.status)
+        .collect();
+    assert_eq!(first[0], &ImportStatus::Queued);
+    assert_eq!(first[1], &ImportStatus::Extracting);
+    assert_eq!(first[2], &ImportStatus::Identifying);
+    assert!(matches!(first[3], ImportStatus::Done { .. }));
+
+    queue.clear_finished();
+    assert!(queue.items().is_empty());
+}
+
+#[tokio::test]
+async fn test_queue_splits_packs_from_folders_and_urls() {
+    let dir = tempdir().unwrap();
+    let librarian = setup(dir.path());
+    let pack: &[(&str, &[u8])] = &[
+        (
+            "Kimono/kimono.ini",
+            b"[TextureOverrideA]\nhash = aaaa0001\n",
+        ),
+        ("Kimono/body.dds", b"kimono"),
+        (
+            "Swimsuit/swimsuit.ini",
+            b"[TextureOverrideB]\nhash = bbbb0002\n",
+        ),
+    ];
+
+    let folder = dir.path().join("Outfit Pack");
+    for (name, bytes) in pack {
+        let file = folder.join(name);
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        fs::write(file, bytes).unwrap();
+    }
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/Outfit%20Pack.zip"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(zip_bytes(pack)))
+        .mount(&server)
+        .await;
+
+    let queue = ImportQueue::new(1, None);
+    queue.enqueue(
+        &librarian,
+        "gi",
+        ImportSource::Folder { path: folder },
+        ImportOptions::default(),
+    );
+    queue.enqueue(
+        &librarian,
+        "gi",
+        ImportSource::Url {
+            url: format!("{}/Outfit%20Pack.zip", server.uri()),
+        },
+        ImportOptions::default(),
+    );
+
+    let items = queue.wait().await;
+    let db = librarian.load_game_db("gi").await.unwrap();
+    for item in &items {
+        let ImportStatus::Done { mod_ids } = &item.status else {
+            panic!("expected done, got {:?}", item.status);
+        };
+        assert_eq!(mod_ids.len(), 2);
+        assert!(mod_ids.iter().all(|id| db.mods.contains_key(id)));
+    }
+    let downloads = librarian.games_root.join(".yago_staging/downloads");
+    assert_eq!(fs::read_dir(downloads).unwrap().count(), 0);
+}
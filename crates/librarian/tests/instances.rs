@@ -0,0 +1,188 @@
+mod common;
+
+use common::setup;
+use librarian::queries::{ModQuery, Queries};
+use librarian::*;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::tempdir;
+
+fn templates() -> HashMap<String, GameTemplate> {
+    let template = GameTemplate {
+        id: "genshin".to_string(),
+        name: "Genshin Impact".to_string(),
+        executables: vec!["GenshinImpact.exe".to_string()],
+        sophon_game_biz: "hk4e_global".to_string(),
+        sophon_branch: "main".to_string(),
+        regions: vec![TemplateRegion {
+            id: "cn".to_string(),
+            name: "China".to_string(),
+            executables: vec!["YuanShen.exe".to_string()],
+            sophon_game_biz: "hk4e_cn".to_string(),
+            ..Default::default()
+        }],
+        ..Default::default()
+    };
+    let mut templates = HashMap::new();
+    for (exe, _) in template.all_executables() {
+        templates.insert(exe.to_lowercase(), template.clone());
+    }
+    templates.insert(template.id.clone(), template);
+    templates
+}
+
+fn install(dir: &Path, folder: &str, exe: &str) -> PathBuf {
+    let game_dir = dir.join(folder);
+    fs::create_dir_all(&game_dir).unwrap();
+    let exe_path = game_dir.join(exe);
+    fs::write(&exe_path, [0x4D, 0x5A, 0x90, 0x00]).unwrap();
+    exe_path
+}
+
+#[tokio::test]
+async fn test_installs_of_one_game_get_their_own_ids() {
+    let dir = tempdir().unwrap();
+    let librarian = setup(dir.path());
+    let templates = templates();
+
+    let live = install(dir.path(), "Live", "GenshinImpact.exe");
+    let beta = install(dir.path(), "Beta", "GenshinImpact.exe");
+    let cn = install(dir.path(), "CN", "YuanShen.exe");
+
+    let live_id = Discovery::add_game_by_path(&librarian, live.clone(), &templates)
+        .await
+        .unwrap();
+    assert_eq!(live_id, "genshinimpact.exe");
+    // Adding the same install again finds it
+    let again = Discovery::add_game_by_path(&librarian, live, &templates)
+        .await
+        .unwrap();
+    assert_eq!(again, live_id);
+
+    let beta_id = Discovery::add_instance(&librarian, beta, &templates, Some("Beta".into()))
+        .await
+        .unwrap();
+    assert_eq!(beta_id, "genshinimpact.exe@beta");
+    let cn_id = Discovery::add_game_by_path(&librarian, cn, &templates)
+        .await
+        .unwrap();
+    assert_eq!(cn_id, "yuanshen.exe@cn");
+
+    let other = install(dir.path(), "Other", "GenshinImpact.exe");
+    let other_id = Discovery::add_game_by_path(&librarian, other, &templates)
+        .await
+        .unwrap();
+    assert_eq!(other_id, "genshinimpact.exe-2");
+
+    let db = librarian.load_game_db(&cn_id).await.unwrap();
+    let config = &db.games[&cn_id];
+    assert_eq!(config.template_id.as_deref(), Some("genshin"));
+    assert_eq!(config.region.as_deref(), Some("cn"));
+    let template = GameInstances::template_for(&templates, config).unwrap();
+    assert_eq!(template.sophon_game_biz, "hk4e_cn");
+    assert_eq!(template.sophon_branch, "main");
+    assert_eq!(template.executables, vec!["YuanShen.exe".to_string()]);
+
+    let live_db = librarian.load_game_db(&live_id).await.unwrap();
+    let live_template = GameInstances::template_for(&templates, &live_db.games[&live_id]).unwrap();
+    assert_eq!(live_template.sophon_game_biz, "hk4e_global");
+    assert_ne!(
+        librarian.game_paths(&live_id).mods,
+        librarian.game_paths(&other_id).mods
+    );
+}
+
+#[tokio::test]
+async fn test_shared_mod_pool() {
+    let dir = tempdir().unwrap();
+    let librarian = setup(dir.path());
+    let templates = templates();
+    let live = install(dir.path(), "Live", "GenshinImpact.exe");
+    let beta = install(dir.path(), "Beta", "GenshinImpact.exe");
+    let live_id = Discovery::add_game_by_path(&librarian, live, &templates)
+        .await
+        .unwrap();
+    let beta_id = Discovery::add_instance(&librarian, beta, &templates, Some("beta".into()))
+        .await
+        .unwrap();
+
+    let folder = dir.path().join("Downloads/Raiden Kimono");
+    fs::create_dir_all(&folder).unwrap();
+    fs::write(folder.join("mod.ini"), "[TextureOverrideBody]\nhash = aa\n").unwrap();
+    let own = Importer::import_folder(
+        &librarian,
+        folder,
+        beta_id.clone(),
+        ImportOptions::default(),
+    )
+    .await
+    .unwrap();
+
+    let err = GameInstances::share_mods(&librarian, &beta_id, Some(&beta_id))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, LibrarianError::Validation(_)));
+    GameInstances::share_mods(&librarian, &beta_id, Some(&live_id))
+        .await
+        .unwrap();
+    // Pools are one level deep
+    let err = GameInstances::share_mods(&librarian, &live_id, Some(&beta_id))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, LibrarianError::Validation(_)));
+    assert_eq!(
+        GameInstances::pool_members(&librarian, &live_id)
+            .await
+            .unwrap(),
+        vec![beta_id.clone()]
+    );
+
+    // The beta's own mod moved into the pool
+    let live_db = librarian.load_game_db(&live_id).await.unwrap();
+    assert_eq!(live_db.mods[&own.id].owner_game_id, live_id);
+
+    // Imports into the beta land in the pool
+    let folder = dir.path().join("Downloads/Long Hair");
+    fs::create_dir_all(&folder).unwrap();
+    fs::write(folder.join("mod.ini"), "[TextureOverrideHair]\nhash = bb\n").unwrap();
+    let shared = Importer::import_folder(
+        &librarian,
+        folder,
+        beta_id.clone(),
+        ImportOptions::default(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(shared.owner_game_id, live_id);
+    assert!(shared.path.starts_with(librarian.game_paths(&live_id).mods));
+
+    let live_db = librarian.load_game_db(&live_id).await.unwrap();
+    assert!(live_db.mods.contains_key(&shared.id));
+    let beta_db = librarian.load_game_db(&beta_id).await.unwrap();
+    assert_eq!(beta_db.mods.len(), 2);
+    let hits = Queries::search_mods(&beta_db, &beta_id, &ModQuery::default());
+    assert_eq!(hits.total, 2);
+    let stored = fs::read_to_string(librarian.game_paths(&beta_id).db).unwrap();
+    assert!(!stored.contains(&shared.id.to_string()));
+
+    // Each instance keeps its own profiles
+    let beta_profile = beta_db.games[&beta_id].active_profile_id.clone();
+    assert_ne!(beta_profile, live_db.games[&live_id].active_profile_id);
+    assert!(beta_db.profiles[&beta_profile.parse().unwrap()]
+        .enabled_mod_ids
+        .contains(&shared.id));
+
+    // Leaving the pool leaves the mods with it
+    GameInstances::share_mods(&librarian, &beta_id, None)
+        .await
+        .unwrap();
+    let beta_db = librarian.load_game_db(&beta_id).await.unwrap();
+    assert!(beta_db.mods.is_empty());
+    assert!(beta_db
+        .profiles
+        .values()
+        .all(|p| p.enabled_mod_ids.is_empty()));
+    let live_db = librarian.load_game_db(&live_id).await.unwrap();
+    assert_eq!(live_db.mods.len(), 2);
+}
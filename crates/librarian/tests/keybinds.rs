@@ -0,0 +1,178 @@
+mod common;
+
+use chrono::Utc;
+use common::{setup, write_zip};
+use librarian::models::{
+    LibraryDatabase, ModCompatibility, ModConfig, ModMetadata, ModRecord, Profile,
+};
+use librarian::queries::Queries;
+use librarian::*;
+use std::path::PathBuf;
+use tempfile::tempdir;
+use uuid::Uuid;
+
+const OUTFIT_INI: &[u8] = b"[Constants]\n\
+global persist $hat = 0\n\
+global persist $glasses = 1\n\
+\n\
+; ----------------------\n\
+; Hat on/off\n\
+[KeyHat]\n\
+key = VK_DOWN\n\
+type = cycle\n\
+$hat = 0, 1, 2\n\
+\n\
+[KeyGlasses]\n\
+; Toggle glasses\n\
+key = ctrl no_modifiers VK_G\n\
+back = shift G\n\
+type = toggle\n\
+$glasses = 0,1\n\
+\n\
+[KeyCape]\n\
+key = C\n\
+type = hold\n\
+\n\
+[KeyBroken]\n\
+type = toggle\n\
+\n\
+[TextureOverrideBody]\n\
+hash = aaaa1111\n";
+
+#[tokio::test]
+async fn test_import_reads_keybinds() {
+    let dir = tempdir().unwrap();
+    let librarian = setup(dir.path());
+
+    // A windows-1252 comment must not hide the keys of the INI
+    let mut variant = b"; Variant \xe9t\xe9\n[KeyHat]\nkey = VK_DOWN\n$hat = 0,1\n".to_vec();
+    variant.extend_from_slice(b"[Key Weird]\nResource\\odd = x\n[KeySwap]\nkey = 5\n");
+    let archive = dir.path().join("Outfit.zip");
+    write_zip(
+        &archive,
+        &[
+            ("outfit.ini", OUTFIT_INI),
+            ("Variant/variant.ini", &variant),
+        ],
+    );
+    let record = Importer::import_mod(&librarian, archive, "gi".into())
+        .await
+        .unwrap();
+    let keybinds = &record.config.keybinds;
+    assert_eq!(keybinds.len(), 5, "{:?}", keybinds.keys());
+
+    let hat = &keybinds["KeyHat"];
+    assert_eq!(hat.label, "Hat on/off");
+    assert_eq!(hat.kind, KeybindKind::Cycle);
+    assert_eq!(hat.keys, vec!["VK_DOWN"]);
+    assert_eq!(hat.variable, "$hat");
+    assert_eq!(hat.variables[0].values, vec!["0", "1", "2"]);
+    assert_eq!(hat.ini, "outfit.ini");
+
+    let glasses = &keybinds["KeyGlasses"];
+    assert_eq!(glasses.label, "Toggle glasses");
+    assert_eq!(glasses.kind, KeybindKind::Toggle);
+    assert_eq!(glasses.back, vec!["shift G"]);
+
+    let cape = &keybinds["KeyCape"];
+    assert_eq!(cape.label, "Cape");
+    assert_eq!(cape.kind, KeybindKind::Hold);
+    assert!(cape.variables.is_empty());
+
+    let variant_hat = &keybinds["Variant/variant.ini:KeyHat"];
+    assert_eq!(variant_hat.label, "Variant \u{fffd}t\u{fffd}");
+    assert_eq!(variant_hat.kind, KeybindKind::Activate);
+    assert_eq!(keybinds["KeySwap"].keys, vec!["5"]);
+
+    assert_eq!(
+        KeybindExtractor::normalize_key("ctrl no_modifiers VK_G"),
+        KeybindExtractor::normalize_key("g CTRL")
+    );
+}
+
+fn keyed_record(character: &str, ini: &str) -> ModRecord {
+    ModRecord {
+        id: Uuid::new_v4(),
+        owner_game_id: "gi".to_string(),
+        path: PathBuf::new(),
+        size: "1.0 MB".into(),
+        size_bytes: 1 << 20,
+        meta: ModMetadata {
+            name: format!("{} mod", character),
+            version: "1.0".into(),
+            author: "dev".into(),
+            url: None,
+            preview_image: None,
+            description: None,
+            updated_at: None,
+        },
+        compatibility: ModCompatibility {
+            game: "".into(),
+            character: character.into(),
+            hashes: Vec::new(),
+            fingerprint: "".into(),
+            texture_hashes: Vec::new(),
+            characters: Vec::new(),
+            confidence: 0.0,
+        },
+        config: ModConfig {
+            tags: Vec::new(),
+            keybinds: KeybindExtractor::from_ini(ini, "mod.ini")
+                .into_iter()
+                .collect(),
+            auto_tags: Vec::new(),
+        },
+        enabled: true,
+        added_at: Utc::now(),
+        version_id: Uuid::nil(),
+        versions: Vec::new(),
+        source: None,
+    }
+}
+
+#[test]
+fn test_key_collisions_between_enabled_mods() {
+    let raiden = keyed_record(
+        "Raiden",
+        "[KeyHat]\nkey = VK_DOWN\n[KeyCape]\nkey = ctrl C\n",
+    );
+    let raiden_alt = keyed_record("Raiden", "[KeyHair]\nkey = down\n");
+    let ui = keyed_record(
+        "Unknown",
+        "[KeyToggleUI]\nkey = VK_DOWN\n[KeyMap]\nkey = M\n",
+    );
+    let shader = keyed_record("Global/Other", "[KeyGlow]\nkey = C Ctrl\nback = M\n");
+    let disabled = keyed_record("Unknown", "[KeyAll]\nkey = M\n");
+
+    let mut db = LibraryDatabase::default();
+    for record in [&raiden, &raiden_alt, &ui, &shader, &disabled] {
+        db.mods.insert(record.id, record.clone());
+    }
+    let profile = Profile {
+        load_order: vec![shader.id, ui.id, raiden.id, raiden_alt.id, disabled.id],
+        enabled_mod_ids: vec![raiden.id, raiden_alt.id, ui.id, shader.id],
+        ..Default::default()
+    };
+
+    let collisions = Queries::key_collisions(&db, &profile);
+    let summary: Vec<(&str, Vec<Uuid>)> = collisions
+        .iter()
+        .map(|c| (c.key.as_str(), c.mods.clone()))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("c ctrl", vec![shader.id, raiden.id]),
+            ("down", vec![ui.id, raiden.id, raiden_alt.id]),
+            ("m", vec![shader.id, ui.id]),
+        ]
+    );
+
+    // Skins of one character are cycled, so their shared key is fine on its own
+    let profile = Profile {
+        load_order: vec![raiden.id, raiden_alt.id],
+        enabled_mod_ids: vec![raiden.id, raiden_alt.id],
+        ..Default::default()
+    };
+    assert!(Queries::key_collisions(&db, &profile).is_empty());
+}
@@ -0,0 +1,98 @@
+use librarian::storage::LibrarianConfig;
+use librarian::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::tempdir;
+
+fn setup(root: &PortableRoot) -> Librarian {
+    let config = LibrarianConfig {
+        base_path: root.root().to_path_buf(),
+        mods_path: None,
+        runners_path: None,
+        prefixes_path: None,
+        cache_path: None,
+        games_install_path: None,
+        portable: Some(root.clone()),
+    };
+    let librarian = Librarian::new(config);
+    librarian.ensure_core_dirs().unwrap();
+    librarian
+}
+
+#[test]
+fn test_detect_and_tokenize() {
+    let dir = tempdir().unwrap();
+    assert!(PortableRoot::detect_in(dir.path()).is_none());
+    fs::write(dir.path().join(PORTABLE_MARKER), "").unwrap();
+    let root = PortableRoot::detect_in(dir.path()).unwrap();
+    assert_eq!(root.root(), dir.path().join("data"));
+
+    let inside = root.root().join("mods").join("gi").join("Raiden");
+    let stored = root.tokenize(&inside);
+    assert_eq!(stored, Path::new("$YAGO_ROOT/mods/gi/Raiden"));
+    assert_eq!(root.resolve(&stored), inside);
+    assert_eq!(root.resolve(Path::new("$YAGO_ROOT")), root.root());
+    // Windows separators written on another machine resolve too
+    assert_eq!(
+        root.resolve(Path::new("$YAGO_ROOT\\mods\\gi\\Raiden")),
+        inside
+    );
+
+    let outside = PathBuf::from("/games/gi");
+    assert_eq!(root.tokenize(&outside), outside);
+    assert_eq!(root.resolve(&outside), outside);
+    let lookalike = PathBuf::from("$YAGO_ROOTS/mods");
+    assert_eq!(root.resolve(&lookalike), lookalike);
+}
+
+#[tokio::test]
+async fn test_database_survives_moving_the_drive() {
+    let dir = tempdir().unwrap();
+    let first = PortableRoot::new(dir.path().join("E/yago/data"));
+    let librarian = setup(&first);
+
+    let folder = dir.path().join("Downloads/Raiden Kimono");
+    fs::create_dir_all(&folder).unwrap();
+    fs::write(folder.join("mod.ini"), "[TextureOverrideBody]\nhash = aa\n").unwrap();
+    let record = Importer::import_folder(&librarian, folder, "gi".into(), ImportOptions::default())
+        .await
+        .unwrap();
+    assert!(record.path.starts_with(first.root()));
+
+    let stored = fs::read_to_string(librarian.game_paths("gi").db).unwrap();
+    assert!(stored.contains("$YAGO_ROOT/mods"));
+    assert!(!stored.contains(&*first.root().to_string_lossy()));
+
+    // The same data, mounted somewhere else
+    let second = PortableRoot::new(dir.path().join("F/yago/data"));
+    fs::create_dir_all(second.root().parent().unwrap()).unwrap();
+    fs::rename(first.root(), second.root()).unwrap();
+    let librarian = setup(&second);
+    let db = librarian.load_game_db("gi").await.unwrap();
+    let moved = &db.mods[&record.id];
+    assert!(moved.path.starts_with(second.root()));
+    assert!(moved.path.join("mod.ini").exists());
+}
+
+#[tokio::test]
+async fn test_settings_store_relative_paths() {
+    let dir = tempdir().unwrap();
+    let root = PortableRoot::new(dir.path().join("data"));
+    fs::create_dir_all(root.root()).unwrap();
+    let manager = SettingsManager::portable(root.clone());
+    assert_eq!(manager.config_path, root.root().join("settings.json"));
+
+    let settings = GlobalSettings {
+        mods_path: root.root().join("mods"),
+        default_games_path: PathBuf::from("/games"),
+        ..Default::default()
+    };
+    manager.save(&settings).await.unwrap();
+    let stored = fs::read_to_string(&manager.config_path).unwrap();
+    assert!(stored.contains("$YAGO_ROOT/mods"));
+
+    let loaded = manager.load().await.unwrap();
+    assert_eq!(loaded.mods_path, root.root().join("mods"));
+    assert_eq!(loaded.default_games_path, Path::new("/games"));
+    assert!(loaded.runners_path.as_os_str().is_empty());
+}
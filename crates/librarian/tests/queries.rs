@@ -66,6 +66,9 @@ async fn test_queries_filtering() {
             supported_injection_methods: vec![],
             modloader_enabled: true,
             remote_info: None,
+            template_id: None,
+            region: None,
+            mod_pool: None,
         },
     );
 
@@ -80,6 +83,9 @@ async fn test_queries_filtering() {
                 character: "CharA".into(),
                 hashes: vec![],
                 fingerprint: "".into(),
+                texture_hashes: Vec::new(),
+                characters: Vec::new(),
+                confidence: 0.0,
             },
             meta: ModMetadata {
                 name: "Mod A".into(),
@@ -88,15 +94,21 @@ async fn test_queries_filtering() {
                 url: None,
                 preview_image: None,
                 description: None,
+                updated_at: None,
             },
             config: ModConfig {
                 tags: vec![],
                 keybinds: HashMap::new(),
+                auto_tags: Vec::new(),
             },
             path: PathBuf::new(),
             size: "0".into(),
+            size_bytes: 0,
             enabled: true,
             added_at: Utc::now(),
+            version_id: Uuid::nil(),
+            versions: Vec::new(),
+            source: None,
         },
     );
 
@@ -112,6 +124,9 @@ async fn test_queries_filtering() {
                 character: "Unknown".into(),
                 hashes: vec![],
                 fingerprint: "".into(),
+                texture_hashes: Vec::new(),
+                characters: Vec::new(),
+                confidence: 0.0,
             },
             meta: ModMetadata {
                 name: "Mod B".into(),
@@ -120,15 +135,21 @@ async fn test_queries_filtering() {
                 url: None,
                 preview_image: None,
                 description: None,
+                updated_at: None,
             },
             config: ModConfig {
                 tags: vec![],
                 keybinds: HashMap::new(),
+                auto_tags: Vec::new(),
             },
             path: PathBuf::new(),
             size: "0".into(),
+            size_bytes: 0,
             enabled: true,
             added_at: Utc::now(),
+            version_id: Uuid::nil(),
+            versions: Vec::new(),
+            source: None,
         },
     );
 
@@ -161,6 +182,7 @@ async fn test_character_identification() {
         prefixes_path: None,
         cache_path: None,
         games_install_path: None,
+        portable: None,
     };
     let librarian = Librarian::new(config);
     librarian.ensure_core_dirs().unwrap();
@@ -177,3 +199,247 @@ async fn test_character_identification() {
         .unwrap();
     assert_eq!(record.compatibility.character, "Hero");
 }
+
+fn search_record(
+    name: &str,
+    character: &str,
+    hashes: &[&str],
+    tags: &[&str],
+    size_bytes: u64,
+) -> ModRecord {
+    ModRecord {
+        id: Uuid::new_v4(),
+        owner_game_id: "test".to_string(),
+        compatibility: ModCompatibility {
+            game: "".into(),
+            character: character.into(),
+            hashes: hashes.iter().map(|h| h.to_string()).collect(),
+            fingerprint: "".into(),
+            texture_hashes: Vec::new(),
+            characters: Vec::new(),
+            confidence: 0.0,
+        },
+        meta: ModMetadata {
+            name: name.into(),
+            version: "1.0".into(),
+            author: "dev".into(),
+            url: None,
+            preview_image: None,
+            description: Some(format!("{} for {}", name, character)),
+            updated_at: None,
+        },
+        config: ModConfig {
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            keybinds: HashMap::new(),
+            auto_tags: Vec::new(),
+        },
+        path: PathBuf::new(),
+        size: Importer::format_size(size_bytes),
+        size_bytes,
+        enabled: true,
+        added_at: Utc::now(),
+        version_id: Uuid::nil(),
+        versions: Vec::new(),
+        source: None,
+    }
+}
+
+#[test]
+fn test_search_mods() {
+    use librarian::queries::{ModQuery, ModSort};
+
+    let mut db = LibraryDatabase::default();
+    let kimono = search_record(
+        "Kimono Raiden",
+        "Raiden",
+        &["aa"],
+        &["Outfit"],
+        (12 << 20) + 40_000,
+    );
+    let swimsuit = search_record(
+        "Swimsuit Raiden",
+        "Raiden",
+        &["aa"],
+        &["Outfit", "NSFW"],
+        3 << 20,
+    );
+    let ui = search_record("Clean UI", "Unknown", &["aa", "bb"], &["UI"], 200 << 10);
+    let reshade = search_record("Sharp Shader", "Unknown", &[], &[], 1 << 30);
+    let profile = Profile {
+        load_order: vec![ui.id, kimono.id, swimsuit.id],
+        enabled_mod_ids: vec![kimono.id, swimsuit.id, ui.id],
+        ..Default::default()
+    };
+    for record in [&kimono, &swimsuit, &ui, &reshade] {
+        db.mods.insert(record.id, record.clone());
+    }
+    db.profiles.insert(profile.id, profile.clone());
+
+    let search = |query: ModQuery| {
+        let query = ModQuery {
+            profile_id: Some(profile.id),
+            ..query
+        };
+        Queries::search_mods(&db, "test", &query)
+    };
+    let names = |query: ModQuery| -> Vec<String> {
+        search(query).hits.into_iter().map(|h| h.name).collect()
+    };
+
+    assert_eq!(
+        names(ModQuery::default()),
+        [
+            "Clean UI",
+            "Kimono Raiden",
+            "Sharp Shader",
+            "Swimsuit Raiden"
+        ]
+    );
+    assert_eq!(
+        names(ModQuery {
+            text: "raiden for".into(),
+            sort: ModSort::Relevance,
+            ..Default::default()
+        }),
+        ["Kimono Raiden", "Swimsuit Raiden"]
+    );
+    // Exact sizes, not the rounded "12.0 MB" shown for the kimono
+    assert_eq!(
+        names(ModQuery {
+            max_size: Some(12 << 20),
+            min_size: Some(1 << 20),
+            ..Default::default()
+        }),
+        ["Swimsuit Raiden"]
+    );
+    assert_eq!(
+        names(ModQuery {
+            tags: vec!["outfit".into()],
+            nsfw: Some(false),
+            ..Default::default()
+        }),
+        ["Kimono Raiden"]
+    );
+    assert_eq!(
+        names(ModQuery {
+            enabled: Some(false),
+            ..Default::default()
+        }),
+        ["Sharp Shader"]
+    );
+    assert_eq!(
+        names(ModQuery {
+            min_size: Some(1024 * 1024),
+            max_size: Some(100 * 1024 * 1024),
+            sort: ModSort::Size,
+            descending: true,
+            ..Default::default()
+        }),
+        ["Kimono Raiden", "Swimsuit Raiden"]
+    );
+
+    // Raiden skins cycle with each other but both collide with the UI mod
+    let conflicting = search(ModQuery {
+        conflicting: Some(true),
+        sort: ModSort::LoadOrder,
+        ..Default::default()
+    });
+    assert_eq!(conflicting.total, 3);
+    assert_eq!(conflicting.hits[0].id, ui.id);
+    assert_eq!(conflicting.hits[0].conflicts_with.len(), 2);
+    assert_eq!(conflicting.hits[1].conflicts_with, vec![ui.id]);
+
+    let page = search(ModQuery {
+        offset: 1,
+        limit: Some(2),
+        ..Default::default()
+    });
+    assert_eq!(page.total, 4);
+    assert_eq!(page.hits.len(), 2);
+    assert_eq!(page.hits[0].name, "Kimono Raiden");
+    assert_eq!(page.hits[0].load_order, Some(1));
+}
+
+#[tokio::test]
+async fn test_character_identification_votes() {
+    let dir = tempdir().unwrap();
+    let assets = dir.path().join("assets");
+    fs::create_dir_all(assets.join("hashes")).unwrap();
+    fs::write(
+        assets.join("hashes").join("test.json"),
+        r#"{
+            "characters": {"EEEE0005": "Ei"},
+            "hashes": {
+                "aaaa0001": {"character": "Raiden Shogun", "component": "body", "buffer": "ib"},
+                "aaaa0002": {"character": "Raiden Shogun", "component": "hair", "buffer": "texture"},
+                "aaaa0003": {"character": "Raiden Shogun", "component": "face", "versions": ["4.0"]},
+                "bbbb0004": {"character": "Yae Miko", "component": "weapon", "buffer": "blend"}
+            },
+            "roster": {
+                "Raiden Shogun": {"aliases": ["Ei"], "element": "Electro", "rarity": 5},
+                "Yae Miko": {"element": "Electro", "rarity": 5}
+            }
+        }"#,
+    )
+    .unwrap();
+
+    let index =
+        librarian::gamedata::hash_db::HashIndex::load(&assets.join("hashes").join("test.json"))
+            .unwrap();
+    let found: Vec<String> = [
+        "AAAA0001", "aaaa0002", "aaaa0003", "bbbb0004", "eeee0005", "ffff",
+    ]
+    .iter()
+    .map(|h| h.to_string())
+    .collect();
+    let identification = index.identify_mod(&found, Some("4.0.1"));
+    assert_eq!(identification.matched, 5);
+    assert_eq!(identification.primary(), Some("Raiden Shogun"));
+    assert_eq!(identification.confidence(), 0.8);
+    assert_eq!(
+        identification.characters[0].components,
+        vec!["body", "face", "hair"]
+    );
+    // The face hash is listed for 4.0 only; the weapon hash still makes the cut
+    let identification = index.identify_mod(&found, Some("4.1"));
+    assert_eq!(identification.matched, 4);
+    assert_eq!(identification.characters.len(), 2);
+    assert_eq!(identification.characters[1].name, "Yae Miko");
+    assert_eq!(index.character_info("Ei").unwrap().rarity, Some(5));
+
+    let config = LibrarianConfig {
+        base_path: dir.path().to_path_buf(),
+        mods_path: None,
+        runners_path: None,
+        prefixes_path: None,
+        cache_path: None,
+        games_install_path: None,
+        portable: None,
+    };
+    let librarian = Librarian::new(config);
+    librarian.ensure_core_dirs().unwrap();
+
+    let archive = dir.path().join("duo.zip");
+    let mut zip = zip::ZipWriter::new(File::create(&archive).unwrap());
+    zip.start_file("mod.ini", zip::write::SimpleFileOptions::default())
+        .unwrap();
+    zip.write_all(
+        b"[TextureOverrideBody]\nhash = aaaa0001\n[TextureOverrideWeapon]\nhash = bbbb0004\n",
+    )
+    .unwrap();
+    zip.finish().unwrap();
+
+    let record = import::Importer::import_mod(&librarian, archive, "test".into())
+        .await
+        .unwrap();
+    assert_eq!(record.compatibility.character, "Raiden Shogun");
+    assert_eq!(
+        record.compatibility.characters,
+        vec!["Raiden Shogun", "Yae Miko"]
+    );
+    assert_eq!(record.compatibility.confidence, 0.5);
+
+    let db = librarian.load_game_db("test").await.unwrap();
+    let roster = Queries::get_character_roster(&db, "test");
+    assert_eq!(roster["Yae Miko"].skins[0].id, record.id.to_string());
+}
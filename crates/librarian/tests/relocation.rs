@@ -0,0 +1,218 @@
+mod common;
+
+use common::setup;
+use librarian::models::{GameConfig, Profile};
+use librarian::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::tempdir;
+use uuid::Uuid;
+
+async fn add_game(librarian: &Librarian, game_id: &str, prefix: PathBuf) {
+    let profile = Profile {
+        id: Uuid::new_v4(),
+        ..Default::default()
+    };
+    let mut db = librarian.load_game_db(game_id).await.unwrap();
+    db.games.insert(
+        game_id.to_string(),
+        GameConfig {
+            install_path: PathBuf::from("/games/gi"),
+            exe_path: PathBuf::from("/games/gi/GenshinImpact.exe"),
+            active_runner_id: Some("GE-Proton9-1".into()),
+            prefix_path: Some(prefix),
+            ..common::game_config(game_id, &profile.id.to_string())
+        },
+    );
+    db.profiles.insert(profile.id, profile);
+    librarian.save_game_db(game_id, &db).await.unwrap();
+}
+
+async fn import_sample(librarian: &Librarian, dir: &Path) -> ModRecord {
+    let folder = dir.join("Downloads/Raiden Kimono");
+    fs::create_dir_all(&folder).unwrap();
+    fs::write(folder.join("mod.ini"), "[TextureOverrideBody]\nhash = aa\n").unwrap();
+    fs::write(folder.join("body.dds"), vec![1u8; 2048]).unwrap();
+    Importer::import_folder(librarian, folder, "gi".into(), ImportOptions::default())
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_relocate_mods_rewrites_records() {
+    let dir = tempdir().unwrap();
+    let mut librarian = setup(dir.path());
+    let prefix = librarian.prefixes_root.join("gi");
+    add_game(&librarian, "gi", prefix.clone()).await;
+    let record = import_sample(&librarian, dir.path()).await;
+    let old_root = librarian.mods_root.clone();
+    assert!(record.path.starts_with(&old_root));
+
+    let destination = dir.path().join("Big Disk/mods");
+    let stages = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let seen = stages.clone();
+    let options = RelocationOptions {
+        on_progress: Some(std::sync::Arc::new(move |p: &RelocationProgress| {
+            seen.lock().unwrap().push(p.stage)
+        })),
+        ..Default::default()
+    };
+    let report = StorageRelocator::relocate(
+        &mut librarian,
+        StorageRoot::Mods,
+        destination.clone(),
+        options,
+    )
+    .await
+    .unwrap();
+    assert!(report.source_removed);
+    assert_eq!(report.paths_rewritten, 1);
+    assert!(report.copy.files_copied >= 2);
+    assert!(!old_root.exists());
+    assert_eq!(librarian.mods_root, destination);
+    assert!(!destination.join(".yago_relocation").exists());
+
+    let db = librarian.load_game_db("gi").await.unwrap();
+    let moved = &db.mods[&record.id];
+    assert!(moved.path.starts_with(&destination));
+    assert!(moved.path.join("body.dds").exists());
+    // Paths under other roots are left alone
+    let config = &db.games["gi"];
+    assert_eq!(config.prefix_path.as_ref(), Some(&prefix));
+    assert_eq!(config.active_runner_id.as_deref(), Some("GE-Proton9-1"));
+
+    let stages = stages.lock().unwrap();
+    assert_eq!(stages.first(), Some(&RelocationStage::Copying));
+    assert_eq!(stages.last(), Some(&RelocationStage::Removing));
+}
+
+#[tokio::test]
+async fn test_relocate_prefixes_resumes_after_cancel() {
+    let dir = tempdir().unwrap();
+    let mut librarian = setup(dir.path());
+    let prefix = librarian.prefixes_root.join("gi");
+    fs::create_dir_all(prefix.join("pfx/drive_c/windows")).unwrap();
+    fs::write(prefix.join("pfx/system.reg"), "WINE REGISTRY").unwrap();
+    #[cfg(unix)]
+    std::os::unix::fs::symlink("../drive_c", prefix.join("pfx/c:")).unwrap();
+    add_game(&librarian, "gi", prefix.clone()).await;
+
+    // Somebody else's files are never mixed in
+    let occupied = dir.path().join("occupied");
+    fs::create_dir_all(&occupied).unwrap();
+    fs::write(occupied.join("keep.txt"), "mine").unwrap();
+    let err = StorageRelocator::relocate(
+        &mut librarian,
+        StorageRoot::Prefixes,
+        occupied,
+        RelocationOptions::default(),
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(err, LibrarianError::Validation(_)));
+    let err = StorageRelocator::relocate(
+        &mut librarian,
+        StorageRoot::Prefixes,
+        prefix.join("inside"),
+        RelocationOptions::default(),
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(err, LibrarianError::Validation(_)));
+
+    let destination = dir.path().join("Big Disk/prefixes");
+    let cancelled = RelocationOptions::default();
+    cancelled.cancel.cancel();
+    let err = StorageRelocator::relocate(
+        &mut librarian,
+        StorageRoot::Prefixes,
+        destination.clone(),
+        cancelled,
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        LibrarianError::Fs(fs_engine::FsError::Cancelled)
+    ));
+    assert!(prefix.join("pfx/system.reg").exists());
+    assert_eq!(librarian.prefixes_root, dir.path().join("prefixes"));
+
+    // The half-done destination is recognised and finished
+    let report = StorageRelocator::relocate(
+        &mut librarian,
+        StorageRoot::Prefixes,
+        destination.clone(),
+        RelocationOptions::default(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(report.paths_rewritten, 1);
+    let db = librarian.load_game_db("gi").await.unwrap();
+    let moved = db.games["gi"].prefix_path.clone().unwrap();
+    assert_eq!(moved, destination.join("gi"));
+    assert_eq!(
+        fs::read_to_string(moved.join("pfx/system.reg")).unwrap(),
+        "WINE REGISTRY"
+    );
+    #[cfg(unix)]
+    assert_eq!(
+        fs::read_link(moved.join("pfx/c:")).unwrap(),
+        Path::new("../drive_c")
+    );
+    assert!(!prefix.exists());
+}
+
+#[tokio::test]
+async fn test_relocate_drains_queue_and_can_keep_source() {
+    let dir = tempdir().unwrap();
+    let mut librarian = setup(dir.path());
+    add_game(&librarian, "gi", librarian.prefixes_root.join("gi")).await;
+    import_sample(&librarian, dir.path()).await;
+    let old_root = librarian.mods_root.clone();
+
+    // Queued before the move, these would otherwise commit under the old folder
+    let folder = dir.path().join("Downloads/Late Mod");
+    fs::create_dir_all(&folder).unwrap();
+    fs::write(folder.join("mod.ini"), "[TextureOverrideHair]\n").unwrap();
+    let queue = ImportQueue::new(1, None);
+    for _ in 0..2 {
+        queue.enqueue(
+            &librarian,
+            "gi",
+            ImportSource::Folder {
+                path: folder.clone(),
+            },
+            ImportOptions::default(),
+        );
+    }
+
+    let destination = dir.path().join("Big Disk/mods");
+    let options = RelocationOptions {
+        imports: Some(queue.clone()),
+        keep_source: true,
+        ..Default::default()
+    };
+    let mut report = StorageRelocator::relocate(
+        &mut librarian,
+        StorageRoot::Mods,
+        destination.clone(),
+        options,
+    )
+    .await
+    .unwrap();
+    assert!(queue
+        .items()
+        .iter()
+        .all(|item| item.status == ImportStatus::Cancelled));
+    let db = librarian.load_game_db("gi").await.unwrap();
+    assert_eq!(db.mods.len(), 1);
+
+    // The original stays until the caller has saved the new location
+    assert!(!report.source_removed);
+    assert!(old_root.exists());
+    assert_eq!(librarian.mods_root, destination);
+    assert!(StorageRelocator::remove_source(&mut report));
+    assert!(report.source_removed);
+    assert!(!old_root.exists());
+}
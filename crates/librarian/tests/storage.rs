@@ -13,6 +13,7 @@ async fn test_librarian_flow() {
         prefixes_path: None,
         cache_path: None,
         games_install_path: None,
+        portable: None,
     };
     let librarian = Librarian::new(config);
     librarian.ensure_core_dirs().unwrap();
@@ -36,6 +37,23 @@ async fn test_settings_persistence() {
     assert_eq!(loaded.language, "ja-JP");
 }
 
+#[tokio::test]
+async fn test_settings_recover_from_backup() {
+    let dir = tempdir().unwrap();
+    let manager = SettingsManager::new(dir.path().to_path_buf());
+    let settings = GlobalSettings {
+        language: "de-DE".into(),
+        ..Default::default()
+    };
+    manager.save(&settings).await.unwrap();
+    std::fs::write(&manager.config_path, "{\"language\":").unwrap();
+
+    let (loaded, restored) = manager.read_or_recover().unwrap();
+    assert_eq!(loaded.language, "de-DE");
+    assert!(restored.is_some());
+    assert_eq!(manager.load().await.unwrap().language, "de-DE");
+}
+
 #[tokio::test]
 async fn test_profile_creation() {
     let dir = tempdir().unwrap();
@@ -46,6 +64,7 @@ async fn test_profile_creation() {
         prefixes_path: None,
         cache_path: None,
         games_install_path: None,
+        portable: None,
     };
     let librarian = Librarian::new(config);
     librarian.ensure_core_dirs().unwrap();
@@ -68,6 +87,7 @@ async fn test_load_order_persistence() {
         prefixes_path: None,
         cache_path: None,
         games_install_path: None,
+        portable: None,
     };
     let librarian = Librarian::new(config);
     librarian.ensure_core_dirs().unwrap();
@@ -97,6 +117,7 @@ async fn test_duplicate_profile() {
         prefixes_path: None,
         cache_path: None,
         games_install_path: None,
+        portable: None,
     };
     let librarian = Librarian::new(config);
     librarian.ensure_core_dirs().unwrap();
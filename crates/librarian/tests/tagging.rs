@@ -0,0 +1,142 @@
+mod common;
+
+use common::{setup, write_zip};
+use librarian::*;
+use std::fs::{self};
+use tempfile::tempdir;
+
+const MERGED_INI: &[u8] = b"; Merged Mod: Kimono, Swimsuit\n\
+[Constants]\n\
+global persist $swapvar = 0\n\
+\n\
+[KeySwap]\n\
+key = VK_DOWN\n\
+type = cycle\n\
+$swapvar = 0,1\n\
+\n\
+[KeyHat]\n\
+key = H\n\
+type = toggle\n\
+\n\
+[TextureOverrideRaidenWeapon]\n\
+hash = 1234abcd\n";
+
+#[tokio::test]
+async fn test_import_applies_tag_rules() {
+    let dir = tempdir().unwrap();
+    let librarian = setup(dir.path());
+
+    let archive = dir.path().join("Raiden Merged.zip");
+    write_zip(
+        &archive,
+        &[
+            ("merged.ini", MERGED_INI),
+            ("Kimono/body.ib", b"ib"),
+            ("Kimono/diffuse.dds", b"dds"),
+            ("glow.ini", b"[CustomShaderGlow]\nps = Shaders/glow.hlsl\n"),
+        ],
+    );
+    let record = Importer::import_mod(&librarian, archive, "gi".into())
+        .await
+        .unwrap();
+    assert_eq!(
+        record.config.auto_tags,
+        vec![
+            "Weapon",
+            "Effect/Shader",
+            "Toggles",
+            "Cycle Keys",
+            "Merged",
+            "Model"
+        ]
+    );
+    assert_eq!(record.config.tags[0], "Unknown/Global");
+    assert_eq!(&record.config.tags[1..], record.config.auto_tags.as_slice());
+
+    let archive = dir.path().join("Preset.zip");
+    write_zip(
+        &archive,
+        &[
+            ("preset.ini", b"Techniques=Sharpen@Sharpen.fx\n"),
+            ("notes.txt", b"Install ReShade first"),
+        ],
+    );
+    let preset = Importer::import_mod(&librarian, archive, "gi".into())
+        .await
+        .unwrap();
+    assert_eq!(preset.config.auto_tags, vec!["Requires ReShade"]);
+
+    let archive = dir.path().join("Buffers.zip");
+    write_zip(
+        &archive,
+        &[
+            (
+                "body.ini",
+                b"[ResourceBodyPosition]\nfilename = BodyPosition.buf\n",
+            ),
+            ("body.dds", b"dds"),
+        ],
+    );
+    let buffers = Importer::import_mod(&librarian, archive, "gi".into())
+        .await
+        .unwrap();
+    assert_eq!(buffers.config.auto_tags, vec!["Model"]);
+}
+
+#[tokio::test]
+async fn test_retag_after_rules_change() {
+    let dir = tempdir().unwrap();
+    let librarian = setup(dir.path());
+
+    let archive = dir.path().join("Glow.zip");
+    write_zip(
+        &archive,
+        &[
+            ("glow.ini", b"[ShaderOverrideGlow]\nhash = 00ff00ff\n"),
+            ("glow.dds", b"dds"),
+        ],
+    );
+    let record = Importer::import_mod(&librarian, archive, "gi".into())
+        .await
+        .unwrap();
+    assert_eq!(
+        record.config.auto_tags,
+        vec!["Effect/Shader", "Texture Swap"]
+    );
+
+    // The user drops one rule tag and adds their own
+    let mut db = librarian.load_game_db("gi").await.unwrap();
+    let tags = &mut db.mods.get_mut(&record.id).unwrap().config.tags;
+    tags.retain(|t| t != "Texture Swap");
+    tags.push("Favourite".to_string());
+    librarian.save_game_db("gi", &db).await.unwrap();
+
+    let store = TagRuleStore::new(&librarian.base_path);
+    let mut rules = store.load().unwrap();
+    rules.rules.retain(|r| r.tag != "Effect/Shader");
+    rules.rules.push(TagRule {
+        tag: "Glow".to_string(),
+        enabled: true,
+        when: TagCondition::Entry {
+            section: Some("shaderoverride*".to_string()),
+            key: Some("hash".to_string()),
+            value: None,
+        },
+    });
+    store.save(&rules).unwrap();
+    assert!(fs::read_to_string(&store.path)
+        .unwrap()
+        .contains("\"Glow\""));
+
+    let changed = AutoTagger::retag(&librarian, "gi").await.unwrap();
+    assert_eq!(changed, vec![record.id]);
+    let db = librarian.load_game_db("gi").await.unwrap();
+    let config = &db.mods[&record.id].config;
+    assert_eq!(config.tags, vec!["Unknown/Global", "Favourite", "Glow"]);
+    assert_eq!(config.auto_tags, vec!["Texture Swap", "Glow"]);
+
+    assert!(AutoTagger::retag(&librarian, "gi")
+        .await
+        .unwrap()
+        .is_empty());
+}
@@ -0,0 +1,177 @@
+mod common;
+
+use chrono::{TimeZone, Utc};
+use common::{setup, zip_bytes};
+use librarian::models::Profile;
+use librarian::updates::{compare_versions, is_newer};
+use librarian::*;
+use std::cmp::Ordering;
+use std::fs::{self, File};
+use std::io::Write;
+use tempfile::tempdir;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn remote(version: Option<&str>, uploaded: Option<i64>) -> RemoteModVersion {
+    RemoteModVersion {
+        item_id: "1".to_string(),
+        version: version.map(String::from),
+        updated_at: uploaded.map(|ts| Utc.timestamp_opt(ts, 0).unwrap()),
+        download_url: String::new(),
+        file_name: "mod.zip".to_string(),
+    }
+}
+
+#[test]
+fn test_version_comparison() {
+    assert_eq!(compare_versions("v1.10", "1.9"), Some(Ordering::Greater));
+    assert_eq!(compare_versions("1.0", "1"), Some(Ordering::Equal));
+    assert_eq!(compare_versions("2.0-beta", "2.0"), Some(Ordering::Equal));
+    assert_eq!(compare_versions("Final", "1.0"), None);
+
+    let installed_at = Utc.timestamp_opt(1_000, 0).unwrap();
+    assert!(is_newer(
+        "1.0",
+        installed_at,
+        &remote(Some("1.1"), Some(10))
+    ));
+    assert!(!is_newer(
+        "1.2",
+        installed_at,
+        &remote(Some("1.1"), Some(9_999))
+    ));
+    // Same version re-uploaded later, or no usable version at all: the date decides
+    assert!(is_newer(
+        "1.0",
+        installed_at,
+        &remote(Some("1.0"), Some(2_000))
+    ));
+    assert!(!is_newer("1.0", installed_at, &remote(None, Some(500))));
+    assert!(is_newer("1.0", installed_at, &remote(Some("Final"), None)));
+    assert!(!is_newer("1.0", installed_at, &remote(Some("v1.0"), None)));
+}
+
+#[test]
+fn test_gamebanana_urls() {
+    let provider = GameBananaProvider::new();
+    assert_eq!(
+        provider
+            .item_id("https://gamebanana.com/mods/12345")
+            .as_deref(),
+        Some("12345")
+    );
+    assert_eq!(
+        provider
+            .item_id("https://www.gamebanana.com/mods/download/678#FileInfo_1")
+            .as_deref(),
+        Some("678")
+    );
+    assert_eq!(provider.item_id("https://gamebanana.com/tools/12345"), None);
+    assert_eq!(provider.item_id("https://example.com/mods/12345"), None);
+    assert_eq!(provider.item_id("not a url"), None);
+}
+
+#[tokio::test]
+async fn test_update_reimports_into_existing_record() {
+    let dir = tempdir().unwrap();
+    let librarian = setup(dir.path());
+
+    let mut ids = Vec::new();
+    for name in ["Other.zip", "Raiden.zip"] {
+        let archive = dir.path().join(name);
+        File::create(&archive)
+            .unwrap()
+            .write_all(&zip_bytes(&[("mod.ini", b"; v1")]))
+            .unwrap();
+        let record = Importer::import_mod(&librarian, archive, "gi".into())
+            .await
+            .unwrap();
+        ids.push(record.id);
+    }
+    let (other_id, mod_id) = (ids[0], ids[1]);
+
+    let mut db = librarian.load_game_db("gi").await.unwrap();
+    let tracked = db.mods.get_mut(&mod_id).unwrap();
+    tracked.meta.url = Some("https://gamebanana.com/mods/4242".to_string());
+    tracked.meta.name = "Raiden (renamed)".to_string();
+    tracked.config.tags.push("Favourite".to_string());
+    tracked.added_at = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+    let profile = Profile {
+        load_order: vec![mod_id, other_id],
+        enabled_mod_ids: vec![mod_id],
+        ..Default::default()
+    };
+    db.profiles = [(profile.id, profile.clone())].into();
+    librarian.save_game_db("gi", &db).await.unwrap();
+    let original_path = db.mods[&mod_id].path.clone();
+
+    let server = MockServer::start().await;
+    let page = serde_json::json!({
+        "_sVersion": "1.0",
+        "_aFiles": [
+            {
+                "_sFile": "raiden_v1.zip",
+                "_tsDateAdded": 1_600_000_000,
+                "_sDownloadUrl": format!("{}/dl/1", server.uri()),
+            },
+            {
+                "_sFile": "raiden_v2.zip",
+                "_tsDateAdded": 1_800_000_000,
+                "_sDownloadUrl": format!("{}/dl/2", server.uri()),
+                "_sVersion": "v2.0",
+            }
+        ]
+    });
+    Mock::given(method("GET"))
+        .and(path("/Mod/4242/ProfilePage"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(page))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/dl/2"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(zip_bytes(&[
+            ("mod.ini", b"; v2".as_slice()),
+            ("body.dds", b"dds".as_slice()),
+        ])))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let updater = ModUpdater::with_providers(vec![Box::new(GameBananaProvider::with_base_url(
+        server.uri(),
+    ))]);
+    let updates = updater.check_game(&librarian, "gi").await.unwrap();
+    assert_eq!(updates.len(), 1);
+    let update = &updates[0];
+    assert_eq!(update.mod_id, mod_id);
+    assert!(update.available);
+    assert_eq!(update.remote.version.as_deref(), Some("v2.0"));
+    assert_eq!(update.remote.file_name, "raiden_v2.zip");
+
+    let record = updater
+        .apply(&librarian, "gi", update, ImportOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(record.id, mod_id);
+    assert_eq!(record.path, original_path);
+    assert_eq!(
+        fs::read_to_string(original_path.join("mod.ini")).unwrap(),
+        "; v2"
+    );
+    assert!(original_path.join("body.dds").is_file());
+    assert_eq!(record.meta.version, "v2.0");
+    assert_eq!(record.meta.name, "Raiden (renamed)");
+    assert!(record.config.tags.contains(&"Favourite".to_string()));
+    assert_eq!(record.meta.updated_at, update.remote.updated_at);
+
+    let db = librarian.load_game_db("gi").await.unwrap();
+    assert_eq!(db.mods.len(), 2);
+    assert_eq!(db.profiles[&profile.id].load_order, vec![mod_id, other_id]);
+    assert_eq!(db.profiles[&profile.id].enabled_mod_ids, vec![mod_id]);
+    let staging = librarian.games_root.join(".yago_staging");
+    assert_eq!(fs::read_dir(staging).unwrap().count(), 0);
+
+    // Once installed, the same release is no longer offered
+    let updates = updater.check_game(&librarian, "gi").await.unwrap();
+    assert!(!updates[0].available);
+}
@@ -0,0 +1,266 @@
+mod common;
+
+use common::{setup, write_zip};
+use librarian::models::{GameConfig, Profile};
+use librarian::*;
+use std::path::PathBuf;
+use tempfile::tempdir;
+
+async fn import_release(
+    librarian: &Librarian,
+    dir: &std::path::Path,
+    name: &str,
+    files: &[(&str, &[u8])],
+    replace: Option<uuid::Uuid>,
+) -> ModRecord {
+    let archive = dir.join(name);
+    write_zip(&archive, files);
+    let options = ImportOptions {
+        replace,
+        ..Default::default()
+    };
+    Importer::import_mod_with_options(librarian, archive, "gi".into(), options)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_reimport_archives_previous_version() {
+    let dir = tempdir().unwrap();
+    let librarian = setup(dir.path());
+
+    let v1 = import_release(
+        &librarian,
+        dir.path(),
+        "Raiden.zip",
+        &[
+            (
+                "mod.ini",
+                b"[TextureOverrideBody]\nhash = 1\nrun = CommandListA\n",
+            ),
+            ("old.txt", b"gone in v2"),
+        ],
+        None,
+    )
+    .await;
+    let v2 = import_release(
+        &librarian,
+        dir.path(),
+        "Raiden.zip",
+        &[
+            (
+                "mod.ini",
+                b"[TextureOverrideBody]\nhash = 2\nrun = CommandListA\n",
+            ),
+            ("new.dds", b"dds"),
+        ],
+        Some(v1.id),
+    )
+    .await;
+
+    assert_eq!(v2.id, v1.id);
+    assert_eq!(v2.path, v1.path);
+    assert_ne!(v2.version_id, v1.version_id);
+    assert_eq!(v2.versions.len(), 1);
+    let archived = &v2.versions[0];
+    assert_eq!(archived.id, v1.version_id);
+    assert_eq!(archived.added_at, v1.added_at);
+    assert!(archived.path.join("old.txt").is_file());
+    assert!(v2.path.join("new.dds").is_file());
+    assert!(!v2.path.join("old.txt").exists());
+    assert_eq!(librarian.load_game_db("gi").await.unwrap().mods.len(), 1);
+
+    let diff = VersionHistory::diff(&v2, v1.version_id, v2.version_id).unwrap();
+    assert_eq!(diff.added, vec![PathBuf::from("new.dds")]);
+    assert_eq!(diff.removed, vec![PathBuf::from("old.txt")]);
+    assert_eq!(diff.changed, vec![PathBuf::from("mod.ini")]);
+    assert_eq!(
+        diff.inis[0].lines,
+        vec![
+            LineChange::Removed {
+                line: 2,
+                text: "hash = 1".to_string()
+            },
+            LineChange::Added {
+                line: 2,
+                text: "hash = 2".to_string()
+            },
+        ]
+    );
+
+    // Rolling back swaps the files; the mod keeps its folder
+    let rolled_back = VersionHistory::switch(&librarian, "gi", v1.id, v1.version_id)
+        .await
+        .unwrap();
+    assert_eq!(rolled_back.path, v1.path);
+    assert_eq!(rolled_back.version_id, v1.version_id);
+    assert!(rolled_back.path.join("old.txt").is_file());
+    assert_eq!(rolled_back.versions.len(), 1);
+    assert_eq!(rolled_back.versions[0].id, v2.version_id);
+    assert!(rolled_back.versions[0].path.join("new.dds").is_file());
+    assert_eq!(rolled_back.compatibility.hashes, v1.compatibility.hashes);
+}
+
+#[tokio::test]
+async fn test_switch_recomputes_what_import_derived_from_the_files() {
+    let dir = tempdir().unwrap();
+    let librarian = setup(dir.path());
+
+    let v1 = import_release(
+        &librarian,
+        dir.path(),
+        "Sword.zip",
+        &[("mod.ini", b"[TextureOverrideSwordWeapon]\nhash = 1\n")],
+        None,
+    )
+    .await;
+    let v2 = import_release(
+        &librarian,
+        dir.path(),
+        "Sword.zip",
+        &[
+            (
+                "mod.ini",
+                b"[KeySwap]\ntype = toggle\n[TextureOverrideBody]\nhash = 2\n",
+            ),
+            ("body.dds", b"dds"),
+        ],
+        Some(v1.id),
+    )
+    .await;
+    assert_ne!(v2.compatibility.fingerprint, v1.compatibility.fingerprint);
+    assert!(v2.config.auto_tags.contains(&"Toggles".to_string()));
+
+    let switched = VersionHistory::switch(&librarian, "gi", v1.id, v1.version_id)
+        .await
+        .unwrap();
+    assert_eq!(
+        switched.compatibility.fingerprint,
+        v1.compatibility.fingerprint
+    );
+    assert_eq!(
+        switched.compatibility.texture_hashes,
+        v1.compatibility.texture_hashes
+    );
+    assert_eq!(switched.config.auto_tags, v1.config.auto_tags);
+    assert!(switched.config.tags.contains(&"Weapon".to_string()));
+    assert!(!switched.config.tags.contains(&"Toggles".to_string()));
+
+    // The saved record matches what was returned
+    let db = librarian.load_game_db("gi").await.unwrap();
+    assert_eq!(
+        db.mods[&v1.id].compatibility.fingerprint,
+        v1.compatibility.fingerprint
+    );
+}
+
+#[tokio::test]
+async fn test_pins_survive_pruning() {
+    let dir = tempdir().unwrap();
+    let librarian = setup(dir.path());
+
+    let mut record = import_release(
+        &librarian,
+        dir.path(),
+        "Mod.zip",
+        &[("mod.ini", b"; 1")],
+        None,
+    )
+    .await;
+    let first_version = record.version_id;
+    for release in ["; 2", "; 3", "; 4"] {
+        record = import_release(
+            &librarian,
+            dir.path(),
+            "Mod.zip",
+            &[("mod.ini", release.as_bytes())],
+            Some(record.id),
+        )
+        .await;
+    }
+    assert_eq!(record.versions.len(), 3);
+    assert_eq!(record.versions[2].id, first_version);
+
+    let mut db = librarian.load_game_db("gi").await.unwrap();
+    let profile = Profile::default();
+    db.profiles.insert(profile.id, profile.clone());
+    librarian.save_game_db("gi", &db).await.unwrap();
+
+    let pinned = VersionHistory::pin(&librarian, "gi", profile.id, record.id, Some(first_version))
+        .await
+        .unwrap();
+    let first_path = record.versions[2].path.clone();
+    assert_eq!(record.path_for_profile(&pinned), first_path.as_path());
+    assert!(VersionHistory::pin(
+        &librarian,
+        "gi",
+        profile.id,
+        record.id,
+        Some(uuid::Uuid::new_v4())
+    )
+    .await
+    .is_err());
+
+    // Keeps the newest archived release plus the pinned one
+    let removed = VersionHistory::prune(&librarian, "gi", record.id, 1)
+        .await
+        .unwrap();
+    assert_eq!(removed, vec![record.versions[1].id]);
+    assert!(!record.versions[1].path.exists());
+    assert!(first_path.join("mod.ini").is_file());
+
+    let db = librarian.load_game_db("gi").await.unwrap();
+    let versions: Vec<_> = db.mods[&record.id].versions.iter().map(|v| v.id).collect();
+    assert_eq!(versions, vec![record.versions[0].id, first_version]);
+
+    let unpinned = VersionHistory::pin(&librarian, "gi", profile.id, record.id, None)
+        .await
+        .unwrap();
+    assert_eq!(db.mods[&record.id].path_for_profile(&unpinned), record.path);
+}
+
+#[tokio::test]
+async fn test_prune_keeps_releases_pinned_by_pool_members() {
+    let dir = tempdir().unwrap();
+    let librarian = setup(dir.path());
+    let v1 = import_release(
+        &librarian,
+        dir.path(),
+        "Mod.zip",
+        &[("mod.ini", b"[TextureOverrideBody]\nhash = 1\n")],
+        None,
+    )
+    .await;
+    let record = import_release(
+        &librarian,
+        dir.path(),
+        "Mod.zip",
+        &[("mod.ini", b"[TextureOverrideBody]\nhash = 2\n")],
+        Some(v1.id),
+    )
+    .await;
+
+    // A beta install sharing the mods of "gi" pins the old release
+    let beta = "gi@beta";
+    let profile = Profile::default();
+    let mut db = librarian.load_game_db(beta).await.unwrap();
+    db.mods = librarian.load_game_db("gi").await.unwrap().mods;
+    db.profiles.insert(profile.id, profile.clone());
+    db.games.insert(
+        beta.to_string(),
+        GameConfig {
+            mod_pool: Some("gi".to_string()),
+            ..common::game_config(beta, &profile.id.to_string())
+        },
+    );
+    librarian.save_game_db(beta, &db).await.unwrap();
+    VersionHistory::pin(&librarian, beta, profile.id, record.id, Some(v1.version_id))
+        .await
+        .unwrap();
+
+    let removed = VersionHistory::prune(&librarian, "gi", record.id, 0)
+        .await
+        .unwrap();
+    assert!(removed.is_empty());
+    assert!(record.versions[0].path.join("mod.ini").is_file());
+}
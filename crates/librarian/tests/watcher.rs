@@ -0,0 +1,163 @@
+mod common;
+
+use common::setup;
+use librarian::*;
+use std::fs;
+use std::sync::mpsc;
+use std::time::Duration;
+use tempfile::tempdir;
+
+#[tokio::test]
+async fn test_sync_adopts_updates_and_flags_missing() {
+    let dir = tempdir().unwrap();
+    let librarian = setup(dir.path());
+    let mods_dir = librarian.game_paths("gi").mods;
+
+    // A folder dropped in by hand, with its own metadata
+    let dropped = mods_dir.join("Raiden Outfit");
+    fs::create_dir_all(&dropped).unwrap();
+    fs::write(
+        dropped.join("mod.json"),
+        r#"{"name":"Raiden Outfit","version":"2.0","author":"Someone","url":null,"preview_image":null,"description":null}"#,
+    )
+    .unwrap();
+    fs::write(
+        dropped.join("raiden.ini"),
+        "[TextureOverrideBody]\nhash = 1234abcd\n",
+    )
+    .unwrap();
+    fs::create_dir_all(mods_dir.join(".yago_staging")).unwrap();
+
+    let mut db = librarian.load_game_db("gi").await.unwrap();
+    let events = ModSync::scan(&librarian, "gi", &mut db);
+    assert_eq!(events.len(), 1);
+    let ModSyncEvent::Added { mod_id, name, .. } = &events[0] else {
+        panic!("expected an adopted mod, got {:?}", events);
+    };
+    assert_eq!(name, "Raiden Outfit");
+    let record = &db.mods[mod_id];
+    assert_eq!(record.path, dropped);
+    assert_eq!(record.meta.version, "2.0");
+    assert_eq!(record.compatibility.hashes, vec!["1234abcd".to_string()]);
+    let mod_id = *mod_id;
+
+    // Nothing changed, nothing to report
+    assert!(ModSync::scan(&librarian, "gi", &mut db).is_empty());
+
+    // An in-place edit adds a hash and grows the folder
+    fs::write(
+        dropped.join("raiden.ini"),
+        "[TextureOverrideBody]\nhash = 1234abcd\n[TextureOverrideFace]\nhash = 5678ef01\n",
+    )
+    .unwrap();
+    fs::write(dropped.join("body.dds"), vec![0u8; 4096]).unwrap();
+    let events = ModSync::sync_folders(&librarian, "gi", &mut db, std::slice::from_ref(&dropped));
+    assert_eq!(
+        events,
+        vec![ModSyncEvent::Updated {
+            game_id: "gi".into(),
+            mod_id
+        }]
+    );
+    assert_eq!(db.mods[&mod_id].compatibility.hashes.len(), 2);
+
+    fs::remove_dir_all(&dropped).unwrap();
+    let events = ModSync::scan(&librarian, "gi", &mut db);
+    assert_eq!(
+        events,
+        vec![ModSyncEvent::Missing {
+            game_id: "gi".into(),
+            mod_id
+        }]
+    );
+    assert!(db.mods.contains_key(&mod_id));
+}
+
+#[test]
+fn test_mod_watcher_reports_changed_folders_per_game() {
+    let dir = tempdir().unwrap();
+    let librarian = setup(&dir.path().canonicalize().unwrap());
+    let mods_dir = librarian.game_paths("zzz").mods;
+    fs::create_dir_all(&mods_dir).unwrap();
+
+    let (tx, rx) = mpsc::channel();
+    let _watcher = ModWatcher::start(
+        &librarian,
+        Duration::from_millis(200),
+        move |game, folders| {
+            let _ = tx.send((game, folders));
+        },
+    )
+    .unwrap();
+
+    fs::create_dir_all(mods_dir.join("Ellen/Textures")).unwrap();
+    fs::write(mods_dir.join("Ellen/Textures/body.dds"), "x").unwrap();
+
+    let (game, folders) = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(game, "zzz");
+    assert_eq!(folders, vec![mods_dir.join("Ellen")]);
+}
+
+#[tokio::test]
+async fn test_sync_keeps_hand_set_character() {
+    let dir = tempdir().unwrap();
+    let librarian = setup(dir.path());
+    let folder = librarian.game_paths("gi").mods.join("Outfit");
+    fs::create_dir_all(&folder).unwrap();
+    fs::write(
+        folder.join("outfit.ini"),
+        "[TextureOverrideBody]\nhash = 1234abcd\n",
+    )
+    .unwrap();
+
+    let mut db = librarian.load_game_db("gi").await.unwrap();
+    let events = ModSync::scan(&librarian, "gi", &mut db);
+    let ModSyncEvent::Added { mod_id, .. } = events[0].clone() else {
+        panic!("expected an adopted mod, got {:?}", events);
+    };
+    assert_eq!(db.mods[&mod_id].compatibility.character, "Unknown");
+    db.mods.get_mut(&mod_id).unwrap().compatibility.character = "Raiden Shogun".into();
+
+    fs::write(
+        folder.join("extra.ini"),
+        "[TextureOverrideHair]\nhash = 5678ef01\n",
+    )
+    .unwrap();
+    let events = ModSync::sync_folders(&librarian, "gi", &mut db, std::slice::from_ref(&folder));
+    assert_eq!(events.len(), 1);
+    assert_eq!(db.mods[&mod_id].compatibility.character, "Raiden Shogun");
+}
+
+#[tokio::test]
+async fn test_sync_stored_sees_committed_imports() {
+    let dir = tempdir().unwrap();
+    let librarian = setup(dir.path());
+    // A copy cached before the import
+    let stale = librarian.load_game_db("gi").await.unwrap();
+
+    let source = dir.path().join("Downloads/Raiden Kimono");
+    fs::create_dir_all(&source).unwrap();
+    fs::write(source.join("mod.ini"), "[TextureOverrideBody]\nhash = aa\n").unwrap();
+    let record = Importer::import_folder(&librarian, source, "gi".into(), ImportOptions::default())
+        .await
+        .unwrap();
+    assert!(stale.mods.is_empty());
+
+    let (db, events) = ModSync::sync_stored(&librarian, "gi", std::slice::from_ref(&record.path))
+        .await
+        .unwrap();
+    assert!(events.is_empty());
+    assert_eq!(db.mods.len(), 1);
+    assert_eq!(db.mods[&record.id].source, record.source);
+
+    // A folder named after a record that points elsewhere is not adopted a second time
+    let mut db = db;
+    let folder = record.path.clone();
+    db.mods.get_mut(&record.id).unwrap().path = dir.path().join("Elsewhere");
+    librarian.save_game_db("gi", &db).await.unwrap();
+    let (db, events) = ModSync::sync_stored(&librarian, "gi", &[folder])
+        .await
+        .unwrap();
+    assert!(events.is_empty());
+    assert_eq!(db.mods.len(), 1);
+}
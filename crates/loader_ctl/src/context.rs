@@ -1,9 +1,34 @@
 use crate::error::{LoaderError, Result};
+use fs_engine::PeInfo;
 use ini_forge::{IniDocument, IniPatcher};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 pub struct LoaderContext;
 
+/// Appended to a system or DXVK DLL moved aside for ours, and restored on uninstall.
+pub const PROXY_BACKUP_SUFFIX: &str = ".yago-original";
+
+/// What a graphics proxy DLL (d3d11.dll, dxgi.dll, ...) in the game root actually is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyDllKind {
+    ThreeDMigoto,
+    ReShade,
+    /// DXVK or another D3D-on-Vulkan translation layer.
+    Dxvk,
+    /// Microsoft's own DLL or a Wine builtin.
+    System,
+    /// Not a PE we can read, or nothing identifying in it.
+    Unknown,
+}
+
+impl ProxyDllKind {
+    /// Whether cleanup may delete the file. Unreadable files are assumed to be ours,
+    /// since only DLLs we ship end up in the game root under these names.
+    pub fn is_removable(self) -> bool {
+        !matches!(self, Self::Dxvk | Self::System)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct InstallOptions {
     pub game_id: String,
@@ -42,16 +67,13 @@ impl LoaderContext {
                     "Loader: Copying ReShade to Game Root as dxgi.dll: {:?}",
                     target_dxgi
                 );
-                std::fs::copy(&reshade_source, &target_dxgi).map_err(LoaderError::Io)?;
+                Self::replace_proxy_dll(&reshade_source, &target_dxgi)?;
             } else {
                 println!("Loader: ReShade requested but not found in common loaders.");
             }
 
             // Clean up d3d11.dll (GIMI) if present
-            let d3d11 = game_dir.join("d3d11.dll");
-            if d3d11.exists() {
-                let _ = std::fs::remove_file(d3d11);
-            }
+            Self::remove_proxy_dll(&game_dir.join("d3d11.dll"));
 
             // We are done for ReShadeOnly.
             return Ok(());
@@ -93,7 +115,7 @@ impl LoaderContext {
                     target_reshade
                 );
                 // Always overwrite to ensure version match
-                std::fs::copy(&reshade_source, &target_reshade).map_err(LoaderError::Io)?;
+                Self::replace_proxy_dll(&reshade_source, &target_reshade)?;
 
                 // No d3dx.ini patching required for Dual Proxy method.
             } else {
@@ -103,7 +125,7 @@ impl LoaderContext {
             // Cleanup Logic
             if target_reshade.exists() {
                 println!("Loader: Removing ReShade (dxgi.dll) from game root.");
-                let _ = std::fs::remove_file(&target_reshade);
+                Self::remove_proxy_dll(&target_reshade);
             }
 
             // Legacy Cleanup: Check for iphlpapi.dll and ReShade.dll just in case
@@ -157,7 +179,7 @@ impl LoaderContext {
 
         let target_dll = game_dir.join("d3d11.dll");
         println!("Proxy: Copying loader to {:?}", target_dll);
-        std::fs::copy(&source_dll, &target_dll).map_err(LoaderError::Io)?;
+        Self::replace_proxy_dll(&source_dll, &target_dll)?;
 
         // Copy d3dx.ini
         let source_ini = loader_source.join("d3dx.ini");
@@ -232,7 +254,11 @@ impl LoaderContext {
             "3dmloader.exe",
         ];
 
-        for file in files {
+        // DLLs are identified before d3dx.ini (a 3DMigoto hint) is gone
+        for file in files.iter().filter(|f| f.ends_with(".dll")) {
+            Self::remove_proxy_dll(&game_dir.join(file));
+        }
+        for file in files.iter().filter(|f| !f.ends_with(".dll")) {
             let path = game_dir.join(file);
             if path.exists() {
                 let _ = std::fs::remove_file(&path);
@@ -241,10 +267,7 @@ impl LoaderContext {
 
         // Cleanup Stealth DLL in Prefix (Legacy)
         if let Some(pfx) = prefix_path {
-            let stealth_dll = pfx.join("pfx/drive_c/windows/system32/d3d11.dll");
-            if stealth_dll.exists() {
-                let _ = std::fs::remove_file(stealth_dll);
-            }
+            Self::remove_proxy_dll(&pfx.join("pfx/drive_c/windows/system32/d3d11.dll"));
         }
 
         // Cleanup support directories
@@ -267,4 +290,95 @@ impl LoaderContext {
     pub async fn remove_loader(game_path: &Path) -> Result<()> {
         Self::uninstall_loader(game_path, None).await
     }
+
+    /// Identifies the DLL at `path` from its version resource, imports and neighbours.
+    /// Returns `None` if the file does not exist.
+    pub fn identify_proxy_dll(path: &Path) -> Option<ProxyDllKind> {
+        if !path.is_file() {
+            return None;
+        }
+        let has_d3dx_ini = path
+            .parent()
+            .is_some_and(|dir| dir.join("d3dx.ini").exists());
+
+        let Ok(info) = PeInfo::load(path) else {
+            return Some(if has_d3dx_ini {
+                ProxyDllKind::ThreeDMigoto
+            } else {
+                ProxyDllKind::Unknown
+            });
+        };
+
+        let text = info.version_text();
+        let company = info
+            .version
+            .as_ref()
+            .and_then(|v| v.company_name())
+            .unwrap_or_default()
+            .to_lowercase();
+
+        let kind = if text.contains("reshade") || company.contains("crosire") {
+            ProxyDllKind::ReShade
+        } else if text.contains("3dmigoto") || text.contains("3dmloader") {
+            ProxyDllKind::ThreeDMigoto
+        } else if text.contains("dxvk")
+            || info.imports_dll("vulkan-1.dll")
+            || info.imports_dll("winevulkan.dll")
+        {
+            ProxyDllKind::Dxvk
+        } else if info.wine_builtin || company.contains("microsoft") {
+            ProxyDllKind::System
+        } else if has_d3dx_ini
+            || info.imports_dll("d3dcompiler_47.dll")
+            || info.imports_dll("d3dcompiler_46.dll")
+        {
+            // 3DMigoto builds carry no version strings but compile shaders at runtime
+            ProxyDllKind::ThreeDMigoto
+        } else {
+            ProxyDllKind::Unknown
+        };
+        Some(kind)
+    }
+
+    /// Where a system or DXVK DLL at `path` is kept while ours is installed.
+    pub fn proxy_backup_path(path: &Path) -> PathBuf {
+        let mut name = path.file_name().unwrap_or_default().to_os_string();
+        name.push(PROXY_BACKUP_SUFFIX);
+        path.with_file_name(name)
+    }
+
+    /// Copies our DLL over `target`, first moving a system or DXVK DLL there aside.
+    fn replace_proxy_dll(source: &Path, target: &Path) -> Result<()> {
+        if let Some(kind) = Self::identify_proxy_dll(target) {
+            // Ours is never System or DXVK, so this is the file to keep even if an
+            // earlier install already moved one aside
+            if !kind.is_removable() {
+                let backup = Self::proxy_backup_path(target);
+                println!(
+                    "Loader: Moving {:?} DLL {:?} aside to {:?}",
+                    kind, target, backup
+                );
+                std::fs::rename(target, &backup).map_err(LoaderError::Io)?;
+            }
+        }
+        std::fs::copy(source, target).map_err(LoaderError::Io)?;
+        Ok(())
+    }
+
+    /// Removes a proxy DLL unless it belongs to the system or a translation layer, and
+    /// puts back whatever DLL ours replaced.
+    fn remove_proxy_dll(path: &Path) {
+        match Self::identify_proxy_dll(path) {
+            Some(kind) if kind.is_removable() => {
+                let _ = std::fs::remove_file(path);
+            }
+            Some(kind) => println!("Loader: Keeping {:?} DLL {:?}", kind, path),
+            None => {}
+        }
+        let backup = Self::proxy_backup_path(path);
+        if backup.exists() && !path.exists() {
+            println!("Loader: Restoring {:?}", path);
+            let _ = std::fs::rename(&backup, path);
+        }
+    }
 }
@@ -1,5 +1,5 @@
 pub mod context;
 pub mod error;
 
-pub use context::{InstallOptions, LoaderContext};
+pub use context::{InstallOptions, LoaderContext, ProxyDllKind, PROXY_BACKUP_SUFFIX};
 pub use error::{LoaderError, Result};
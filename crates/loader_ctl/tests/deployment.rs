@@ -1,3 +1,4 @@
+use fs_engine::test_support::fake_dll;
 use loader_ctl::*;
 use std::fs::File;
 use std::path::Path;
@@ -161,3 +162,116 @@ async fn test_uninstall_loader() {
     assert!(!game_dir.join("ShaderFixes").exists());
     assert!(game_dir.join("random_file.txt").exists());
 }
+
+#[test]
+fn test_identify_proxy_dll() {
+    let dir = tempdir().unwrap();
+    let dll = dir.path().join("dxgi.dll");
+    assert_eq!(LoaderContext::identify_proxy_dll(&dll), None);
+
+    std::fs::write(
+        &dll,
+        fake_dll(&[("CompanyName", "crosire"), ("ProductName", "ReShade")]),
+    )
+    .unwrap();
+    assert_eq!(
+        LoaderContext::identify_proxy_dll(&dll),
+        Some(ProxyDllKind::ReShade)
+    );
+
+    std::fs::write(
+        &dll,
+        fake_dll(&[
+            ("CompanyName", "Microsoft Corporation"),
+            ("OriginalFilename", "dxgi.dll"),
+        ]),
+    )
+    .unwrap();
+    assert_eq!(
+        LoaderContext::identify_proxy_dll(&dll),
+        Some(ProxyDllKind::System)
+    );
+
+    std::fs::write(&dll, fake_dll(&[("FileDescription", "DXVK")])).unwrap();
+    assert_eq!(
+        LoaderContext::identify_proxy_dll(&dll),
+        Some(ProxyDllKind::Dxvk)
+    );
+
+    std::fs::write(&dll, fake_dll(&[])).unwrap();
+    assert_eq!(
+        LoaderContext::identify_proxy_dll(&dll),
+        Some(ProxyDllKind::Unknown)
+    );
+    File::create(dir.path().join("d3dx.ini")).unwrap();
+    assert_eq!(
+        LoaderContext::identify_proxy_dll(&dll),
+        Some(ProxyDllKind::ThreeDMigoto)
+    );
+}
+
+#[tokio::test]
+async fn test_uninstall_keeps_system_dlls() {
+    let dir = tempdir().unwrap();
+    let game_dir = dir.path();
+
+    std::fs::write(
+        game_dir.join("d3d11.dll"),
+        fake_dll(&[("FileDescription", "3Dmigoto d3d11 proxy")]),
+    )
+    .unwrap();
+    std::fs::write(
+        game_dir.join("dxgi.dll"),
+        fake_dll(&[("CompanyName", "Microsoft Corporation")]),
+    )
+    .unwrap();
+    File::create(game_dir.join("d3dx.ini")).unwrap();
+
+    LoaderContext::uninstall_loader(game_dir, None)
+        .await
+        .unwrap();
+
+    assert!(!game_dir.join("d3d11.dll").exists());
+    assert!(!game_dir.join("d3dx.ini").exists());
+    assert!(game_dir.join("dxgi.dll").exists());
+}
+
+#[tokio::test]
+async fn test_install_keeps_replaced_system_dll() {
+    let dir = tempdir().unwrap();
+    let game_dir = dir.path().join("game");
+    let loader_source_dir = dir.path().join("loader_src");
+    std::fs::create_dir(&game_dir).unwrap();
+    std::fs::create_dir(&loader_source_dir).unwrap();
+    std::fs::write(
+        loader_source_dir.join("d3d11.dll"),
+        fake_dll(&[("FileDescription", "3Dmigoto d3d11 proxy")]),
+    )
+    .unwrap();
+
+    let dxvk = fake_dll(&[("FileDescription", "DXVK")]);
+    let target = game_dir.join("d3d11.dll");
+    std::fs::write(&target, &dxvk).unwrap();
+
+    LoaderContext::install_proxy(&game_dir, &loader_source_dir, "game.exe")
+        .await
+        .unwrap();
+    assert_eq!(
+        LoaderContext::identify_proxy_dll(&target),
+        Some(ProxyDllKind::ThreeDMigoto)
+    );
+    let backup = LoaderContext::proxy_backup_path(&target);
+    assert_eq!(std::fs::read(&backup).unwrap(), dxvk);
+
+    // Installing again keeps the original, not our own copy
+    LoaderContext::install_proxy(&game_dir, &loader_source_dir, "game.exe")
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&backup).unwrap(), dxvk);
+
+    LoaderContext::uninstall_loader(&game_dir, None)
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&target).unwrap(), dxvk);
+    assert!(!backup.exists());
+}
@@ -30,6 +30,9 @@ pub struct ModProfile {
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct ConflictReport {
     pub overwritten_hashes: HashMap<String, Vec<String>>, // Hash -> List of Mod UUIDs that provided it (last one won)
+    /// Key -> Mod UUIDs binding it. Filled by the caller, which knows the mods' keybinds.
+    #[serde(default)]
+    pub key_collisions: HashMap<String, Vec<String>>,
 }
 
 /// Generates a deployment plan from a list of mod profiles.
@@ -1,6 +1,13 @@
 use crate::config::AppConfig;
 use crate::AppState;
+use fs_engine::CancelToken;
+use librarian::relocation::{
+    RelocationOptions, RelocationProgress, RelocationReport, RelocationStage, StorageRelocator,
+    StorageRoot,
+};
 use librarian::settings::GlobalSettings;
+use std::path::PathBuf;
+use std::sync::Arc;
 use tauri::{Emitter, State};
 
 #[tauri::command]
@@ -34,6 +41,17 @@ pub async fn get_settings(state: State<'_, AppState>) -> Result<GlobalSettings,
     Ok(settings.clone())
 }
 
+/// Files restored from a backup since the last call. Also sent as "data-recovered"
+/// at startup, which can fire before the frontend listens.
+#[tauri::command]
+pub async fn get_data_recoveries(
+    state: State<'_, AppState>,
+) -> Result<Vec<fs_engine::RestoredBackup>, String> {
+    let mut recoveries = state.recoveries.lock().await;
+    recoveries.extend(state.librarian.lock().await.take_recoveries());
+    Ok(std::mem::take(&mut *recoveries))
+}
+
 #[tauri::command]
 pub async fn update_settings(
     app: tauri::AppHandle,
@@ -42,12 +60,6 @@ pub async fn update_settings(
 ) -> Result<(), String> {
     let mut current_settings = state.global_settings.lock().await;
 
-    state
-        .settings_manager
-        .save(&settings)
-        .await
-        .map_err(|e| e.to_string())?;
-
     // If storage path changed, update Librarian
     let base = if settings.yago_storage_path.as_os_str().is_empty() {
         state.app_data_dir.clone()
@@ -84,10 +96,28 @@ pub async fn update_settings(
         } else {
             Some(settings.cache_path.clone())
         },
+        portable: state.librarian.lock().await.portable().cloned(),
     };
 
     let mut librarian = state.librarian.lock().await;
     librarian.update_roots(lib_config);
+    let previous_backend = librarian.backend_kind();
+    if let Some(backend) = settings
+        .library_backend
+        .filter(|kind| *kind != previous_backend)
+    {
+        // A refused switch, such as back to stale game.json files, is not saved
+        if let Err(e) = librarian.open_backend(backend) {
+            let _ = librarian.open_backend(previous_backend);
+            return Err(e.to_string());
+        }
+    }
+
+    state
+        .settings_manager
+        .save(&settings)
+        .await
+        .map_err(|e| e.to_string())?;
     librarian.ensure_core_dirs().map_err(|e| e.to_string())?;
 
     // Re-extract templates to the new path ONLY IF IT CHANGED
@@ -113,3 +143,113 @@ pub async fn update_settings(
     let _ = app.emit("settings-updated", settings);
     Ok(())
 }
+
+/// Moves the mods, runners or prefixes folder to `destination`, rewriting the paths the
+/// library stores, and saves the new location in the settings. Progress comes as
+/// "relocation-progress" events; the library is locked until the move is done.
+#[tauri::command]
+pub async fn relocate_storage(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    root: StorageRoot,
+    destination: String,
+) -> Result<RelocationReport, String> {
+    let cancel = CancelToken::new();
+    {
+        let mut control = state.relocation_control.lock().await;
+        if control.is_some() {
+            return Err("A relocation is already running".into());
+        }
+        *control = Some(cancel.clone());
+    }
+    // Moving the mods would otherwise reach the watcher as thousands of edits
+    if root == StorageRoot::Mods {
+        *state.mod_watcher.lock().await = None;
+    }
+
+    let progress_app = app.clone();
+    let last_sent = std::sync::Mutex::new(None);
+    let options = RelocationOptions {
+        cancel,
+        on_progress: Some(Arc::new(move |progress: &RelocationProgress| {
+            // One event per percent is plenty for a progress bar
+            let percent = (progress.bytes_done * 100).checked_div(progress.bytes_total);
+            let mut last = last_sent.lock().unwrap();
+            if *last != Some((progress.stage, percent)) {
+                *last = Some((progress.stage, percent));
+                let _ = progress_app.emit("relocation-progress", progress);
+            }
+        })),
+        // Queued imports carry the old paths
+        imports: Some(state.import_queue.clone()),
+        // The original goes only once the settings point at the copy
+        keep_source: true,
+    };
+
+    // Commands saving a cached database must not write the old paths back meanwhile
+    let mut dbs = state.game_dbs.lock().await;
+    let result = {
+        let mut librarian = state.librarian.lock().await;
+        StorageRelocator::relocate(&mut librarian, root, PathBuf::from(&destination), options).await
+    };
+    *state.relocation_control.lock().await = None;
+    let librarian = state.librarian.lock().await.clone();
+    if root == StorageRoot::Mods {
+        *state.mod_watcher.lock().await =
+            crate::start_mod_watcher(app.clone(), state.game_dbs.clone(), &librarian);
+    }
+    let mut report = result.map_err(|e| e.to_string())?;
+
+    for (game_id, db) in dbs.iter_mut() {
+        match librarian.load_game_db(game_id).await {
+            Ok(moved) => *db = moved,
+            Err(e) => eprintln!("Failed to reload {} after relocation: {}", game_id, e),
+        }
+    }
+    let _ = app.emit("library-updated", dbs.clone());
+    drop(dbs);
+
+    let mut settings = state.global_settings.lock().await;
+    match root {
+        StorageRoot::Mods => settings.mods_path = report.to.clone(),
+        StorageRoot::Runners => settings.runners_path = report.to.clone(),
+        StorageRoot::Prefixes => settings.prefixes_path = report.to.clone(),
+    }
+    state
+        .settings_manager
+        .save(&settings)
+        .await
+        .map_err(|e| e.to_string())?;
+    let _ = app.emit("settings-updated", settings.clone());
+    drop(settings);
+
+    let _ = app.emit(
+        "relocation-progress",
+        RelocationProgress {
+            root,
+            stage: RelocationStage::Removing,
+            files_done: 0,
+            files_total: 0,
+            bytes_done: 0,
+            bytes_total: 0,
+        },
+    );
+    let mut removing = report.clone();
+    report.source_removed = tauri::async_runtime::spawn_blocking(move || {
+        StorageRelocator::remove_source(&mut removing)
+    })
+    .await
+    .unwrap_or(false);
+    Ok(report)
+}
+
+#[tauri::command]
+pub async fn cancel_relocation(state: State<'_, AppState>) -> Result<(), String> {
+    match state.relocation_control.lock().await.as_ref() {
+        Some(cancel) => {
+            cancel.cancel();
+            Ok(())
+        }
+        None => Err("No relocation running".into()),
+    }
+}
@@ -1,5 +1,6 @@
 use crate::AppState;
 use librarian::models::InstallStatus;
+use librarian::GameInstances;
 use sophon_engine::orchestrator::ProgressDetailed;
 use sophon_engine::{ChunkOrchestrator, OrchestratorEvent, SophonClient};
 use tauri::{Emitter, Manager, State};
@@ -34,7 +35,8 @@ pub async fn start_game_download(
             return Err("Installation path not set".to_string());
         }
 
-        let template = templates.get(&game_id).ok_or("Template not found")?.clone();
+        let template =
+            GameInstances::template_for(&templates, config).ok_or("Template not found")?;
         (install_path, template)
     };
 
@@ -216,14 +218,13 @@ pub async fn repair_game(
         let install_path = config.install_path.clone();
         let selected_categories = config.installed_components.clone();
 
-        let template = templates
-            .get(&game_id)
+        let template = GameInstances::template_for(&templates, config)
             .or_else(|| {
-                // Try stripping .exe
+                // Try finding a template where the short_name or name matches partially
                 let base = game_id.trim_end_matches(".exe");
-                templates.get(base).or_else(|| {
-                    // Try finding a template where the short_name or name matches partially
-                    templates.values().find(|t| {
+                templates
+                    .values()
+                    .find(|t| {
                         let name_low = t.name.to_lowercase();
                         let short_low = t.short_name.to_lowercase();
                         base.contains(&short_low)
@@ -231,10 +232,9 @@ pub async fn repair_game(
                             || short_low.contains(base)
                             || name_low.contains(base)
                     })
-                })
+                    .cloned()
             })
-            .ok_or_else(|| format!("Template not found for game ID: {}", game_id))?
-            .clone();
+            .ok_or_else(|| format!("Template not found for game ID: {}", game_id))?;
 
         (install_path, selected_categories, template)
     };
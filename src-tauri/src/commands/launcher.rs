@@ -15,12 +15,6 @@ pub async fn resolve_runner_path(
 ) -> (PathBuf, RunnerType) {
     println!("Marshal: Resolving runner for ID: {:?}", rid_opt);
 
-    let base_storage = if settings.yago_storage_path.as_os_str().is_empty() {
-        app_data_dir.to_path_buf()
-    } else {
-        settings.yago_storage_path.clone()
-    };
-
     let find_proton = |dir: PathBuf| -> Option<PathBuf> {
         let possible = ["proton", "proton.sh", "files/bin/proton"];
         for p in possible {
@@ -33,7 +27,7 @@ pub async fn resolve_runner_path(
     };
 
     if let Some(rid) = rid_opt {
-        let local_dir = base_storage.join("runners").join(&rid);
+        let local_dir = settings.runners_dir(app_data_dir).join(&rid);
         let settings_dir = settings.steam_compat_tools_path.join(&rid);
 
         if let Some(p) = find_proton(local_dir.clone()) {
@@ -101,9 +95,11 @@ pub async fn deploy_mods(
         .file_name()
         .and_then(|s| s.to_str())
         .unwrap_or_default();
-    let game_id = exe_name.to_lowercase();
     let game_root = exe_path.parent().ok_or("Invalid path")?.to_path_buf();
     let dbs = state.game_dbs.lock().await;
+    // Installs of the same game share the exe name, so look for this very install
+    let game_id = librarian::GameInstances::find_loaded(&dbs, &exe_path)
+        .unwrap_or_else(|| exe_name.to_lowercase());
     let db = dbs.get(&game_id).ok_or("Game not found")?;
     let config = db.games.get(&game_id).ok_or("Config missing")?;
     let p_uuid = Uuid::parse_str(&config.active_profile_id).map_err(|e| e.to_string())?;
@@ -120,8 +116,10 @@ pub async fn deploy_mods(
     let mut profiles_for_weaver = Vec::new();
     let mut add_to_list = |mod_id: &Uuid| {
         if let Some(record) = db.mods.get(mod_id) {
-            let mut ini_path = record.path.join("disabled.ini");
-            for entry in walkdir::WalkDir::new(&record.path)
+            // A release pinned by the profile is deployed straight from the history
+            let mod_root = record.path_for_profile(profile);
+            let mut ini_path = mod_root.join("disabled.ini");
+            for entry in walkdir::WalkDir::new(mod_root)
                 .max_depth(2)
                 .into_iter()
                 .filter_map(|e| e.ok())
@@ -133,7 +131,7 @@ pub async fn deploy_mods(
             }
             profiles_for_weaver.push(logic_weaver::ModProfile {
                 uuid: record.id.to_string(),
-                mod_root: record.path.clone(),
+                mod_root: mod_root.to_path_buf(),
                 ini_path,
                 deploy_path: format!("YAGO/{}/", record.id),
                 character: record.compatibility.character.clone(),
@@ -155,8 +153,12 @@ pub async fn deploy_mods(
             add_to_list(mid);
         }
     }
-    let (plan, report) =
+    let (plan, mut report) =
         logic_weaver::generate_deployment_plan(profiles_for_weaver).map_err(|e| e.to_string())?;
+    report.key_collisions = librarian::queries::Queries::key_collisions(db, profile)
+        .into_iter()
+        .map(|c| (c.key, c.mods.iter().map(Uuid::to_string).collect()))
+        .collect();
 
     let settings = state.global_settings.lock().await;
     let base_storage = if settings.yago_storage_path.as_os_str().is_empty() {
@@ -170,6 +172,18 @@ pub async fn deploy_mods(
     fs_engine::execute_deployment(&loaders_root, &plan, Some("Mods")).map_err(|e| e.to_string())?;
     let game_mods_dir = game_root.join("Mods");
     let virtual_mods_dir = loaders_root.join("Mods");
+    // A real folder there is left from a manual setup; linking over it would lose those mods
+    if librarian::ModAdoption::is_unmanaged(&game_mods_dir) {
+        return Err(
+            "The game's Mods folder holds mods YAGO does not manage. Adopt them into the library first."
+                .into(),
+        );
+    }
+    if std::fs::symlink_metadata(&game_mods_dir).is_ok_and(|m| m.is_dir()) {
+        // Screenshots, notes and the like are kept in the adoption backup folder
+        librarian::ModAdoption::set_aside_leftovers(&game_mods_dir).map_err(|e| e.to_string())?;
+        let _ = std::fs::remove_dir(&game_mods_dir);
+    }
     let is_proxy = config.injection_method == librarian::InjectionMethod::Proxy
         || (cfg!(target_os = "linux")
             && config.injection_method == librarian::InjectionMethod::Loader);
@@ -384,7 +398,7 @@ pub async fn launch_game(
             if !settings.wine_prefix_path.as_os_str().is_empty() {
                 settings.wine_prefix_path.clone()
             } else {
-                base_storage.join("prefixes").join(&game_id)
+                settings.prefixes_dir(&state.app_data_dir).join(&game_id)
             }
         });
 
@@ -1,13 +1,14 @@
 use crate::AppState;
 use fs_engine::{ExeInspector, Safety};
 use librarian::catalog::RemoteCatalogEntry;
+use librarian::queries::{ModQuery, ModQueryResult, Queries, SavedSearch};
 use librarian::scanner::DiscoveredGame;
-use librarian::{CatalogManager, Discovery, FpsConfig, LibraryDatabase};
+use librarian::{CatalogManager, Discovery, FpsConfig, GameInstances, LibraryDatabase};
 use proc_marshal::InjectionMethod;
 use sophon_engine::SophonClient;
 use std::collections::HashMap;
 use std::path::PathBuf;
-use tauri::{Emitter, Manager, State};
+use tauri::{Emitter, State};
 use uuid::Uuid;
 
 #[tauri::command]
@@ -30,16 +31,21 @@ pub async fn initialize_remote_game(
     app: tauri::AppHandle,
     state: State<'_, AppState>,
     template_id: String,
+    region: Option<String>,
 ) -> Result<String, String> {
-    let game_id = template_id.clone();
+    // Each region of a game is an instance of its own
+    let game_id = GameInstances::instance_id(&template_id, region.as_deref());
 
     // 1. Scoped check/load
     let (exists, template) = {
         let templates = state.game_templates.lock().await;
         let t = templates
             .get(&template_id)
-            .ok_or_else(|| format!("Template {} not found", template_id))?
-            .clone();
+            .ok_or_else(|| format!("Template {} not found", template_id))?;
+        if let Some(region) = region.as_deref().filter(|r| t.region(r).is_none()) {
+            return Err(format!("{} has no region {}", t.name, region));
+        }
+        let t = t.for_region(region.as_deref());
 
         let dbs = state.game_dbs.lock().await;
         (dbs.contains_key(&game_id), t)
@@ -101,6 +107,9 @@ pub async fn initialize_remote_game(
             .clone()
             .unwrap_or_default(),
         remote_info: None,
+        template_id: Some(template.id.clone()),
+        region,
+        mod_pool: None,
     };
 
     let mut db = LibraryDatabase::default();
@@ -132,7 +141,7 @@ pub async fn get_install_options(
     let db = dbs
         .get(&game_id)
         .ok_or_else(|| format!("Game {} not found", game_id))?;
-    let _config = db
+    let config = db
         .games
         .get(&game_id)
         .ok_or_else(|| format!("Config for {} missing", game_id))?;
@@ -140,12 +149,12 @@ pub async fn get_install_options(
     // We need RemoteInfo to get the manifest URL
     // If it's missing, we try to fetch it from template
     let templates = state.game_templates.lock().await;
-    let template = templates
-        .get(&game_id)
+    let template = GameInstances::template_for(&templates, config)
         .or_else(|| {
             let base = game_id.trim_end_matches(".exe");
-            templates.get(base).or_else(|| {
-                templates.values().find(|t| {
+            templates
+                .values()
+                .find(|t| {
                     let name_low = t.name.to_lowercase();
                     let short_low = t.short_name.to_lowercase();
                     base.contains(&short_low)
@@ -153,7 +162,7 @@ pub async fn get_install_options(
                         || short_low.contains(base)
                         || name_low.contains(base)
                 })
-            })
+                .cloned()
         })
         .ok_or_else(|| format!("Template not found for game ID: {}", game_id))?;
 
@@ -326,6 +335,72 @@ pub async fn get_skin_inventory(
     ))
 }
 
+/// Mods of a game matching a query, so the mod manager does not have to filter the
+/// whole library itself.
+#[tauri::command]
+pub async fn search_mods(
+    state: State<'_, AppState>,
+    game_id: String,
+    query: ModQuery,
+) -> Result<ModQueryResult, String> {
+    let dbs = state.game_dbs.lock().await;
+    let db = dbs
+        .get(&game_id)
+        .ok_or_else(|| format!("Game {} not found", game_id))?;
+    Ok(Queries::search_mods(db, &game_id, &query))
+}
+
+#[tauri::command]
+pub async fn get_saved_searches(
+    state: State<'_, AppState>,
+    game_id: Option<String>,
+) -> Result<Vec<SavedSearch>, String> {
+    let settings = state.global_settings.lock().await;
+    Ok(settings
+        .saved_searches
+        .iter()
+        .filter(|s| game_id.is_none() || s.game_id.is_none() || s.game_id == game_id)
+        .cloned()
+        .collect())
+}
+
+/// Adds a saved search, or replaces the one with the same id.
+#[tauri::command]
+pub async fn save_search(state: State<'_, AppState>, search: SavedSearch) -> Result<(), String> {
+    let mut settings = state.global_settings.lock().await;
+    let mut updated = settings.clone();
+    match updated
+        .saved_searches
+        .iter_mut()
+        .find(|s| s.id == search.id)
+    {
+        Some(existing) => *existing = search,
+        None => updated.saved_searches.push(search),
+    }
+    state
+        .settings_manager
+        .save(&updated)
+        .await
+        .map_err(|e| e.to_string())?;
+    *settings = updated;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn delete_saved_search(state: State<'_, AppState>, id: String) -> Result<(), String> {
+    let id = Uuid::parse_str(&id).map_err(|e| e.to_string())?;
+    let mut settings = state.global_settings.lock().await;
+    let mut updated = settings.clone();
+    updated.saved_searches.retain(|s| s.id != id);
+    state
+        .settings_manager
+        .save(&updated)
+        .await
+        .map_err(|e| e.to_string())?;
+    *settings = updated;
+    Ok(())
+}
+
 #[tauri::command]
 pub async fn open_path(path: String) -> Result<(), String> {
     #[cfg(target_os = "linux")]
@@ -515,7 +590,11 @@ pub async fn sync_game_assets(
         let mut dbs = state.game_dbs.lock().await;
         let templates = state.game_templates.lock().await;
 
-        if let (Some(db), Some(template)) = (dbs.get_mut(&game_id), templates.get(&game_id)) {
+        let template = dbs
+            .get(&game_id)
+            .and_then(|db| db.games.get(&game_id))
+            .and_then(|config| GameInstances::template_for(&templates, config));
+        if let (Some(db), Some(template)) = (dbs.get_mut(&game_id), template) {
             if let Some(config) = db.games.get_mut(&game_id) {
                 println!("Syncing assets for {}: using local templates", game_id);
 
@@ -566,6 +645,7 @@ pub async fn add_game(
     app: tauri::AppHandle,
     state: State<'_, AppState>,
     path: String,
+    region: Option<String>,
 ) -> Result<String, String> {
     let path_buf = PathBuf::from(&path);
     let templates_guard: tokio::sync::MutexGuard<'_, HashMap<String, librarian::GameTemplate>> =
@@ -573,24 +653,19 @@ pub async fn add_game(
 
     let game_id = {
         let librarian = state.librarian.lock().await;
-        Discovery::add_game_by_path(&librarian, path_buf, &templates_guard)
+        Discovery::add_instance(&librarian, path_buf, &templates_guard, region)
             .await
             .map_err(|e| e.to_string())?
     };
     drop(templates_guard);
     #[cfg(target_os = "linux")]
     {
-        let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
-
-        let settings = state.global_settings.lock().await;
-        let base_storage = if settings.yago_storage_path.as_os_str().is_empty() {
-            app_data_dir.clone()
-        } else {
-            settings.yago_storage_path.clone()
-        };
-        drop(settings);
-
-        let prefix_path = base_storage.join("prefixes").join(&game_id);
+        let prefix_path = state
+            .global_settings
+            .lock()
+            .await
+            .prefixes_dir(&state.app_data_dir)
+            .join(&game_id);
         if !prefix_path.exists() {
             std::fs::create_dir_all(prefix_path.join("pfx")).map_err(|e| e.to_string())?;
         }
@@ -619,7 +694,7 @@ pub async fn add_game(
         // Fetch remote version if it's a Sophon game
         if let Some(config) = db.games.get_mut(&game_id) {
             let templates = state.game_templates.lock().await;
-            if let Some(template) = templates.get(&game_id) {
+            if let Some(template) = GameInstances::template_for(&templates, config) {
                 if !template.sophon_package_id.is_empty() {
                     let client = SophonClient::new();
                     let build_res = client
@@ -680,10 +755,24 @@ pub async fn remove_game(
 
     let mut dbs: tokio::sync::MutexGuard<'_, HashMap<String, LibraryDatabase>> =
         state.game_dbs.lock().await;
+    // Its mods folder and records are also another instance's
+    if let Some(member) = dbs
+        .iter()
+        .find(|(id, db)| db.games.get(*id).and_then(|g| g.mod_pool.as_ref()) == Some(&game_id))
+        .map(|(id, _)| id.clone())
+    {
+        return Err(format!(
+            "{} uses the mods of this game; stop sharing first",
+            member
+        ));
+    }
     if let Some(db) = dbs.remove(&game_id) {
         let librarian = state.librarian.lock().await;
         let game_paths = librarian.game_paths(&game_id);
 
+        librarian
+            .delete_game_db(&game_id)
+            .map_err(|e| e.to_string())?;
         if game_paths.root.exists() {
             std::fs::remove_dir_all(game_paths.root).map_err(|e| e.to_string())?;
         }
@@ -775,7 +864,10 @@ pub async fn sync_templates(
             let dbs = state.game_dbs.lock().await;
 
             let db = dbs.get(&id).cloned();
-            let t = templates.get(&id).cloned();
+            let t = db
+                .as_ref()
+                .and_then(|db| db.games.get(&id))
+                .and_then(|config| GameInstances::template_for(&templates, config));
             (t, db)
         };
 
@@ -918,6 +1010,13 @@ pub async fn wipe_game_mods(
 ) -> Result<(), String> {
     let mut dbs = state.game_dbs.lock().await;
     if let Some(db) = dbs.get_mut(&game_id) {
+        if let Some(pool) = db.games.get(&game_id).and_then(|g| g.mod_pool.as_ref()) {
+            return Err(format!(
+                "This game uses the mods of {}; stop sharing before wiping",
+                pool
+            ));
+        }
+
         // 1. Delete mod files from disk
         let librarian = state.librarian.lock().await;
         let mods_root = librarian.game_paths(&game_id).mods;
@@ -939,12 +1038,53 @@ pub async fn wipe_game_mods(
             .save_game_db(&game_id, db)
             .await
             .map_err(|e| e.to_string())?;
+        GameInstances::sync_loaded(&mut dbs, &game_id);
         let _ = app.emit("library-updated", dbs.clone());
         return Ok(());
     }
     Err("Game not found".to_string())
 }
 
+/// Makes the game use the mods of another instance of it (`pool`), or its own again.
+#[tauri::command]
+pub async fn share_game_mods(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    game_id: String,
+    pool: Option<String>,
+) -> Result<(), String> {
+    let mut dbs = state.game_dbs.lock().await;
+    let librarian = state.librarian.lock().await.clone();
+    let previous = dbs
+        .get(&game_id)
+        .and_then(|db| db.games.get(&game_id))
+        .and_then(|g| g.mod_pool.clone());
+    GameInstances::share_mods(&librarian, &game_id, pool.as_deref())
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // Every instance of the pool now sees a different set of mods
+    let pool_id = pool.clone().or(previous).unwrap_or_else(|| game_id.clone());
+    let affected: Vec<String> = dbs
+        .iter()
+        .filter(|(id, db)| {
+            **id == game_id
+                || **id == pool_id
+                || db.games.get(*id).and_then(|g| g.mod_pool.as_ref()) == Some(&pool_id)
+        })
+        .map(|(id, _)| id.clone())
+        .collect();
+    for id in affected {
+        let db = librarian
+            .load_game_db(&id)
+            .await
+            .map_err(|e| e.to_string())?;
+        dbs.insert(id, db);
+    }
+    let _ = app.emit("library-updated", dbs.clone());
+    Ok(())
+}
+
 #[tauri::command]
 pub async fn reset_game_profiles(
     app: tauri::AppHandle,
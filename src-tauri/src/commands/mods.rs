@@ -1,23 +1,448 @@
 use crate::AppState;
+use fs_engine::{CancelToken, ExtractOptions, ExtractionProgress};
+use librarian::adoption::{AdoptMode, AdoptionPreview, AdoptionReport, ModAdoption};
+use librarian::duplicates::{
+    DuplicateFinder, DuplicateMatch, DuplicatePair, DuplicatePolicy, DUPLICATE_THRESHOLD,
+};
+use librarian::import::{ImportOptions, PackFailure};
+use librarian::import_queue::{ImportItem, ImportSource};
 use librarian::models::ModRecord;
+use librarian::queries::{KeyCollision, Queries};
+use librarian::tagging::{AutoTagger, TagRuleStore, TagRules};
+use librarian::updates::{ModUpdate, ModUpdater};
+use librarian::versions::{VersionDiff, VersionHistory};
+use librarian::{GameInstances, LibrarianError};
+use serde::Serialize;
 use std::path::PathBuf;
+use std::sync::Arc;
 use tauri::{Emitter, State};
 use uuid::Uuid;
 
+#[derive(Clone, Serialize)]
+struct ImportProgressEvent {
+    path: String,
+    progress: ExtractionProgress,
+}
+
+#[derive(Clone, Serialize)]
+struct ImportDuplicateEvent {
+    path: String,
+    existing: DuplicateMatch,
+}
+
+#[derive(Clone, Serialize)]
+struct ImportPackIncompleteEvent {
+    path: String,
+    imported: Vec<Uuid>,
+    failures: Vec<PackFailure>,
+}
+
 #[tauri::command]
 pub async fn import_mod(
     app: tauri::AppHandle,
     state: State<'_, AppState>,
     game_id: String,
     path: String,
+    password: Option<String>,
+    replace_mod_id: Option<String>,
+    keep_duplicates: Option<bool>,
 ) -> Result<ModRecord, String> {
-    let path_buf = PathBuf::from(path);
-    let record = {
+    let path_buf = PathBuf::from(&path);
+    let replace = replace_mod_id
+        .map(|id| Uuid::parse_str(&id).map_err(|e| e.to_string()))
+        .transpose()?;
+
+    let cancel = CancelToken::new();
+    state
+        .import_controls
+        .lock()
+        .await
+        .insert(path.clone(), cancel.clone());
+
+    let app_clone = app.clone();
+    let event_path = path.clone();
+    let options = ImportOptions {
+        extraction: ExtractOptions {
+            cancel,
+            on_progress: Some(Arc::new(move |progress| {
+                let _ = app_clone.emit(
+                    "import-progress",
+                    ImportProgressEvent {
+                        path: event_path.clone(),
+                        progress,
+                    },
+                );
+            })),
+            password,
+            ..Default::default()
+        },
+        replace,
+        // The user is asked once; answering "keep both" re-runs the import with this set
+        duplicates: if keep_duplicates.unwrap_or(false) {
+            DuplicatePolicy::KeepBoth
+        } else {
+            DuplicatePolicy::Ask
+        },
+        ..default_import_options(&state, &game_id).await
+    };
+
+    let result = {
         let librarian = state.librarian.lock().await;
-        librarian::import::Importer::import_mod(&librarian, path_buf, game_id.clone())
+        librarian::import::Importer::import_pack(&librarian, path_buf, game_id.clone(), options)
             .await
-            .map_err(|e| e.to_string())?
     };
+    state.import_controls.lock().await.remove(&path);
+    if let Err(LibrarianError::PossibleDuplicate(existing)) = &result {
+        let _ = app.emit(
+            "import-duplicate",
+            ImportDuplicateEvent {
+                path: path.clone(),
+                existing: existing.clone(),
+            },
+        );
+    }
+    let pack = result.map_err(|e| e.to_string())?;
+    if !pack.failures.is_empty() {
+        let _ = app.emit(
+            "import-pack-incomplete",
+            ImportPackIncompleteEvent {
+                path: path.clone(),
+                imported: pack.records.iter().map(|r| r.id).collect(),
+                failures: pack.failures,
+            },
+        );
+    }
+    let records = pack.records;
+
+    let mut dbs = state.game_dbs.lock().await;
+    if let Some(db) = dbs.get_mut(&game_id) {
+        for record in &records {
+            db.mods.insert(record.id, record.clone());
+        }
+        let _ = app.emit("library-updated", dbs.clone());
+    }
+
+    // A pack split into several mods answers with the first; the rest come with the library
+    records
+        .into_iter()
+        .next()
+        .ok_or_else(|| "Nothing was imported".to_string())
+}
+
+/// Import settings that follow from the game and the user's settings.
+async fn default_import_options(state: &State<'_, AppState>, game_id: &str) -> ImportOptions {
+    ImportOptions {
+        fix_normal_maps: game_id == "zzz",
+        // Windows resolves mixed-case INI references on its own
+        normalize_filenames: cfg!(not(windows)),
+        deduplicate: state.global_settings.lock().await.deduplicate_mods,
+        ..Default::default()
+    }
+}
+
+/// The game's own Mods folder, next to its executable.
+async fn game_mods_folder(state: &State<'_, AppState>, game_id: &str) -> Result<PathBuf, String> {
+    let dbs = state.game_dbs.lock().await;
+    let config = dbs
+        .get(game_id)
+        .and_then(|db| db.games.get(game_id))
+        .ok_or("Game not found")?;
+    config
+        .exe_path
+        .parent()
+        .map(|root| root.join("Mods"))
+        .ok_or_else(|| "Invalid path".to_string())
+}
+
+/// Mods left in the game's Mods folder by a manual 3DMigoto setup, identified and
+/// checked against the library.
+#[tauri::command]
+pub async fn preview_mod_adoption(
+    state: State<'_, AppState>,
+    game_id: String,
+) -> Result<AdoptionPreview, String> {
+    let mods_dir = game_mods_folder(&state, &game_id).await?;
+    let librarian = state.librarian.lock().await.clone();
+    ModAdoption::preview(&librarian, &game_id, &mods_dir)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Brings folders from the adoption preview into the library, moving the originals to
+/// a backup folder next to the game's Mods folder unless `copy` is set.
+#[tauri::command]
+pub async fn adopt_mods(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    game_id: String,
+    folders: Vec<String>,
+    copy: Option<bool>,
+) -> Result<AdoptionReport, String> {
+    let mods_dir = game_mods_folder(&state, &game_id).await?;
+    let librarian = state.librarian.lock().await.clone();
+    let mode = if copy.unwrap_or(false) {
+        AdoptMode::Copy
+    } else {
+        AdoptMode::Move
+    };
+    let folders: Vec<PathBuf> = folders.into_iter().map(PathBuf::from).collect();
+    let options = default_import_options(&state, &game_id).await;
+    let report = ModAdoption::adopt(&librarian, &game_id, &mods_dir, &folders, mode, options)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // Adoption also changed the active profile, so the whole database is reloaded
+    let db = librarian
+        .load_game_db(&game_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut dbs = state.game_dbs.lock().await;
+    dbs.insert(game_id.clone(), db);
+    GameInstances::sync_loaded(&mut dbs, &game_id);
+    let _ = app.emit("library-updated", dbs.clone());
+    Ok(report)
+}
+
+/// Queues archives, folders and download links for import. Progress is reported per
+/// item through "import-queue-updated" events.
+#[tauri::command]
+pub async fn queue_imports(
+    state: State<'_, AppState>,
+    game_id: String,
+    sources: Vec<String>,
+) -> Result<Vec<ImportItem>, String> {
+    let librarian = state.librarian.lock().await.clone();
+    let mut ids = Vec::new();
+    for source in sources {
+        let options = ImportOptions {
+            // A batch stops at likely duplicates; they can be imported one by one
+            duplicates: DuplicatePolicy::Ask,
+            ..default_import_options(&state, &game_id).await
+        };
+        ids.push(state.import_queue.enqueue(
+            &librarian,
+            &game_id,
+            ImportSource::detect(&source),
+            options,
+        ));
+    }
+    Ok(state
+        .import_queue
+        .items()
+        .into_iter()
+        .filter(|item| ids.contains(&item.id))
+        .collect())
+}
+
+#[tauri::command]
+pub async fn get_import_queue(state: State<'_, AppState>) -> Result<Vec<ImportItem>, String> {
+    Ok(state.import_queue.items())
+}
+
+/// Cancels one queued import, or all of them without an id.
+#[tauri::command]
+pub async fn cancel_queued_import(
+    state: State<'_, AppState>,
+    item_id: Option<String>,
+) -> Result<(), String> {
+    match item_id {
+        Some(id) => {
+            let id = Uuid::parse_str(&id).map_err(|e| e.to_string())?;
+            if !state.import_queue.cancel(id) {
+                return Err("No unfinished import with this id".to_string());
+            }
+        }
+        None => state.import_queue.cancel_all(),
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn clear_import_queue(state: State<'_, AppState>) -> Result<(), String> {
+    state.import_queue.clear_finished();
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn cancel_import(state: State<'_, AppState>, path: String) -> Result<(), String> {
+    let controls = state.import_controls.lock().await;
+    if let Some(token) = controls.get(&path) {
+        token.cancel();
+        Ok(())
+    } else {
+        Err("No active import found for this file".to_string())
+    }
+}
+
+#[tauri::command]
+pub async fn inspect_archive(path: String) -> Result<fs_engine::ArchivePreview, String> {
+    let path_buf = PathBuf::from(path);
+    tauri::async_runtime::spawn_blocking(move || {
+        fs_engine::ArchiveInspector::open(&path_buf).and_then(|inspector| inspector.inspect())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+/// Cached thumbnails for a mod, regenerated if its files changed. Paths are served
+/// through `yago-asset://` since they live under the cache folder.
+#[tauri::command]
+pub async fn get_mod_thumbnails(
+    state: State<'_, AppState>,
+    game_id: String,
+    mod_id: String,
+) -> Result<Option<fs_engine::ThumbnailSet>, String> {
+    let mod_uuid = Uuid::parse_str(&mod_id).map_err(|e| e.to_string())?;
+    let mod_path = mod_path(&state, &game_id, &mod_id).await?;
+    let cache_dir = state.librarian.lock().await.mod_thumbnail_dir(&mod_uuid);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        fs_engine::Thumbnailer::ensure(
+            &mod_path,
+            &cache_dir,
+            &fs_engine::ThumbnailOptions::default(),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+/// Shows which files in a mod would be lowercased, which names collide and which INI
+/// references would be rewritten or are already broken, without changing anything.
+#[tauri::command]
+pub async fn preview_filename_normalization(
+    state: State<'_, AppState>,
+    game_id: String,
+    mod_id: String,
+) -> Result<fs_engine::NormalizationPlan, String> {
+    let mod_path = mod_path(&state, &game_id, &mod_id).await?;
+    tauri::async_runtime::spawn_blocking(move || fs_engine::CaseNormalizer::plan(&mod_path))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn normalize_mod_filenames(
+    state: State<'_, AppState>,
+    game_id: String,
+    mod_id: String,
+) -> Result<fs_engine::NormalizationReport, String> {
+    let mod_path = mod_path(&state, &game_id, &mod_id).await?;
+    tauri::async_runtime::spawn_blocking(move || fs_engine::CaseNormalizer::normalize(&mod_path))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// How much space the blob store saves, for the storage settings page.
+#[tauri::command]
+pub async fn get_storage_stats(
+    state: State<'_, AppState>,
+) -> Result<fs_engine::StoreStats, String> {
+    let store = state.librarian.lock().await.blob_store();
+    tauri::async_runtime::spawn_blocking(move || store.stats())
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// Links the large files of every mod already in the library into the blob store.
+#[tauri::command]
+pub async fn deduplicate_library(
+    state: State<'_, AppState>,
+) -> Result<fs_engine::IngestReport, String> {
+    let librarian = state.librarian.lock().await.clone();
+    let records: Vec<ModRecord> = state
+        .game_dbs
+        .lock()
+        .await
+        .values()
+        .flat_map(|db| db.mods.values().cloned())
+        .collect();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut total = fs_engine::IngestReport::default();
+        for record in records.iter().filter(|r| r.path.is_dir()) {
+            match librarian.deduplicate_mod(record) {
+                Ok(report) => {
+                    total.linked += report.linked;
+                    total.deduplicated += report.deduplicated;
+                    total.skipped += report.skipped;
+                    total.bytes_saved += report.bytes_saved;
+                }
+                Err(e) => eprintln!("Failed to deduplicate {}: {}", record.id, e),
+            }
+        }
+        total
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn collect_storage_garbage(
+    state: State<'_, AppState>,
+) -> Result<fs_engine::GcReport, String> {
+    let librarian = state.librarian.lock().await.clone();
+    librarian
+        .collect_blob_garbage()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Polls the mod sites of every tracked mod of a game.
+#[tauri::command]
+pub async fn check_mod_updates(
+    state: State<'_, AppState>,
+    game_id: String,
+) -> Result<Vec<ModUpdate>, String> {
+    let librarian = state.librarian.lock().await.clone();
+    ModUpdater::new()
+        .check_game(&librarian, &game_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Downloads the newest release of a mod and installs it in place, keeping its id,
+/// profiles and load order.
+#[tauri::command]
+pub async fn update_mod(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    game_id: String,
+    mod_id: String,
+) -> Result<ModRecord, String> {
+    let mod_uuid = Uuid::parse_str(&mod_id).map_err(|e| e.to_string())?;
+    let librarian = state.librarian.lock().await.clone();
+    let db = librarian
+        .load_game_db(&game_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    let current = db
+        .mods
+        .get(&mod_uuid)
+        .ok_or_else(|| "Mod not found".to_string())?;
+
+    let updater = ModUpdater::new();
+    let update = updater
+        .check(current)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Mod is not linked to a supported mod site".to_string())?;
+
+    let options = ImportOptions {
+        fix_normal_maps: game_id == "zzz",
+        normalize_filenames: cfg!(not(windows)),
+        deduplicate: state.global_settings.lock().await.deduplicate_mods,
+        ..Default::default()
+    };
+    let record = updater
+        .apply(&librarian, &game_id, &update, options)
+        .await
+        .map_err(|e| e.to_string())?;
 
     let mut dbs = state.game_dbs.lock().await;
     if let Some(db) = dbs.get_mut(&game_id) {
@@ -28,6 +453,97 @@ pub async fn import_mod(
     Ok(record)
 }
 
+/// Makes an archived release of a mod the active one.
+#[tauri::command]
+pub async fn switch_mod_version(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    game_id: String,
+    mod_id: String,
+    version_id: String,
+) -> Result<ModRecord, String> {
+    let mod_uuid = Uuid::parse_str(&mod_id).map_err(|e| e.to_string())?;
+    let version_uuid = Uuid::parse_str(&version_id).map_err(|e| e.to_string())?;
+    let librarian = state.librarian.lock().await.clone();
+    let record = VersionHistory::switch(&librarian, &game_id, mod_uuid, version_uuid)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut dbs = state.game_dbs.lock().await;
+    if let Some(db) = dbs.get_mut(&game_id) {
+        db.mods.insert(record.id, record.clone());
+        let _ = app.emit("library-updated", dbs.clone());
+    }
+    Ok(record)
+}
+
+/// File and INI changes between two releases of a mod, `from` being the older one.
+#[tauri::command]
+pub async fn diff_mod_versions(
+    state: State<'_, AppState>,
+    game_id: String,
+    mod_id: String,
+    from: String,
+    to: String,
+) -> Result<VersionDiff, String> {
+    let mod_uuid = Uuid::parse_str(&mod_id).map_err(|e| e.to_string())?;
+    let from = Uuid::parse_str(&from).map_err(|e| e.to_string())?;
+    let to = Uuid::parse_str(&to).map_err(|e| e.to_string())?;
+    let record = state
+        .game_dbs
+        .lock()
+        .await
+        .get(&game_id)
+        .and_then(|db| db.mods.get(&mod_uuid).cloned())
+        .ok_or_else(|| "Mod not found".to_string())?;
+
+    tauri::async_runtime::spawn_blocking(move || VersionHistory::diff(&record, from, to))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// Deletes all but the newest `keep` archived releases of a mod.
+#[tauri::command]
+pub async fn prune_mod_versions(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    game_id: String,
+    mod_id: String,
+    keep: usize,
+) -> Result<Vec<Uuid>, String> {
+    let mod_uuid = Uuid::parse_str(&mod_id).map_err(|e| e.to_string())?;
+    let librarian = state.librarian.lock().await.clone();
+    let removed = VersionHistory::prune(&librarian, &game_id, mod_uuid, keep)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if !removed.is_empty() {
+        let db = librarian
+            .load_game_db(&game_id)
+            .await
+            .map_err(|e| e.to_string())?;
+        let mut dbs = state.game_dbs.lock().await;
+        dbs.insert(game_id.clone(), db);
+        GameInstances::sync_loaded(&mut dbs, &game_id);
+        let _ = app.emit("library-updated", dbs.clone());
+    }
+    Ok(removed)
+}
+
+async fn mod_path(
+    state: &State<'_, AppState>,
+    game_id: &str,
+    mod_id: &str,
+) -> Result<PathBuf, String> {
+    let mod_uuid = Uuid::parse_str(mod_id).map_err(|e| e.to_string())?;
+    let dbs = state.game_dbs.lock().await;
+    dbs.get(game_id)
+        .and_then(|db| db.mods.get(&mod_uuid))
+        .map(|record| record.path.clone())
+        .ok_or_else(|| "Mod not found".to_string())
+}
+
 #[tauri::command]
 pub async fn add_mod(
     app: tauri::AppHandle,
@@ -35,7 +551,50 @@ pub async fn add_mod(
     game_id: String,
     path: String,
 ) -> Result<ModRecord, String> {
-    import_mod(app, state, game_id, path).await
+    import_mod(app, state, game_id, path, None, None, Some(true)).await
+}
+
+/// Pairs of the game's mods that look like the same mod imported twice.
+#[tauri::command]
+pub async fn get_duplicate_report(
+    state: State<'_, AppState>,
+    game_id: String,
+) -> Result<Vec<DuplicatePair>, String> {
+    let librarian = state.librarian.lock().await.clone();
+    let pairs = DuplicateFinder::report(&librarian, &game_id, DUPLICATE_THRESHOLD)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // The report fingerprints older mods; keep the cache in step so they are not lost
+    let db = librarian
+        .load_game_db(&game_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    state.game_dbs.lock().await.insert(game_id, db);
+    Ok(pairs)
+}
+
+/// Keys bound by more than one mod enabled in the profile (the active one if unset).
+#[tauri::command]
+pub async fn get_key_collisions(
+    state: State<'_, AppState>,
+    game_id: String,
+    profile_id: Option<String>,
+) -> Result<Vec<KeyCollision>, String> {
+    let dbs = state.game_dbs.lock().await;
+    let db = dbs.get(&game_id).ok_or("Game not found")?;
+    let profile_id = match profile_id {
+        Some(id) => id,
+        None => db
+            .games
+            .get(&game_id)
+            .ok_or("Config missing")?
+            .active_profile_id
+            .clone(),
+    };
+    let p_uuid = Uuid::parse_str(&profile_id).map_err(|e| e.to_string())?;
+    let profile = db.profiles.get(&p_uuid).ok_or("Profile missing")?;
+    Ok(Queries::key_collisions(db, profile))
 }
 
 #[tauri::command]
@@ -58,6 +617,13 @@ pub async fn delete_mod(
             if record.path.exists() {
                 let _ = std::fs::remove_dir_all(&record.path);
             }
+            let librarian = state.librarian.lock().await.clone();
+            let _ = std::fs::remove_dir_all(librarian.mod_thumbnail_dir(&mod_uuid));
+            let _ = std::fs::remove_dir_all(VersionHistory::versions_dir(
+                &librarian, game_id, mod_uuid,
+            ));
+            // Its blobs are left for the next garbage collection
+            let _ = librarian.blob_store().release(&mod_id);
 
             // Remove from profiles
             for profile in db.profiles.values_mut() {
@@ -72,6 +638,8 @@ pub async fn delete_mod(
                 .save_game_db(game_id, db)
                 .await
                 .map_err(|e: librarian::LibrarianError| e.to_string())?;
+            let game_id = game_id.clone();
+            GameInstances::sync_loaded(&mut dbs, &game_id);
             let _ = app.emit("library-updated", dbs.clone());
             return Ok(());
         }
@@ -141,6 +709,7 @@ pub async fn update_mod_tags(
                 .save_game_db(&game_id, db)
                 .await
                 .map_err(|e: librarian::LibrarianError| e.to_string())?;
+            GameInstances::sync_loaded(&mut dbs, &game_id);
             let _ = app.emit("library-updated", dbs.clone());
             return Ok(());
         }
@@ -149,6 +718,47 @@ pub async fn update_mod_tags(
     Err("Mod not found".to_string())
 }
 
+#[tauri::command]
+pub async fn get_tag_rules(state: State<'_, AppState>) -> Result<TagRules, String> {
+    let librarian = state.librarian.lock().await.clone();
+    TagRuleStore::new(&librarian.base_path)
+        .load()
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn save_tag_rules(state: State<'_, AppState>, rules: TagRules) -> Result<(), String> {
+    let librarian = state.librarian.lock().await.clone();
+    TagRuleStore::new(&librarian.base_path)
+        .save(&rules)
+        .map_err(|e| e.to_string())
+}
+
+/// Applies the current tag rules to every mod of a game. Returns the retagged mods.
+#[tauri::command]
+pub async fn retag_mods(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    game_id: String,
+) -> Result<Vec<Uuid>, String> {
+    let librarian = state.librarian.lock().await.clone();
+    let changed = AutoTagger::retag(&librarian, &game_id)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if !changed.is_empty() {
+        let db = librarian
+            .load_game_db(&game_id)
+            .await
+            .map_err(|e| e.to_string())?;
+        let mut dbs = state.game_dbs.lock().await;
+        dbs.insert(game_id.clone(), db);
+        GameInstances::sync_loaded(&mut dbs, &game_id);
+        let _ = app.emit("library-updated", dbs.clone());
+    }
+    Ok(changed)
+}
+
 #[tauri::command]
 pub async fn get_mod_files(
     _state: State<'_, AppState>,
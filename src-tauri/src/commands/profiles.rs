@@ -222,3 +222,101 @@ pub async fn set_load_order(
     }
     Err("Profile or Game not found".to_string())
 }
+
+/// Holds a mod at one of its releases in a profile; `version_id: None` follows the
+/// active release again.
+#[tauri::command]
+pub async fn pin_mod_version(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    game_id: String,
+    profile_id: String,
+    mod_id: String,
+    version_id: Option<String>,
+) -> Result<Profile, String> {
+    let p_uuid = Uuid::parse_str(&profile_id).map_err(|e| e.to_string())?;
+    let mod_uuid = Uuid::parse_str(&mod_id).map_err(|e| e.to_string())?;
+    let version_uuid = version_id
+        .map(|id| Uuid::parse_str(&id))
+        .transpose()
+        .map_err(|e| e.to_string())?;
+
+    let librarian = state.librarian.lock().await.clone();
+    let profile =
+        librarian::VersionHistory::pin(&librarian, &game_id, p_uuid, mod_uuid, version_uuid)
+            .await
+            .map_err(|e| e.to_string())?;
+
+    let mut dbs = state.game_dbs.lock().await;
+    if let Some(db) = dbs.get_mut(&game_id) {
+        db.profiles.insert(profile.id, profile.clone());
+        let _ = app.emit("library-updated", dbs.clone());
+    }
+    Ok(profile)
+}
+
+/// Writes a profile to a shareable bundle, optionally with every mod's files.
+#[tauri::command]
+pub async fn export_profile_bundle(
+    state: State<'_, AppState>,
+    game_id: String,
+    profile_id: String,
+    path: String,
+    embed_archives: bool,
+) -> Result<librarian::ProfileBundle, String> {
+    let p_uuid = Uuid::parse_str(&profile_id).map_err(|e| e.to_string())?;
+    let librarian = state.librarian.lock().await.clone();
+    librarian::ProfileBundles::export(
+        &librarian,
+        &game_id,
+        p_uuid,
+        std::path::Path::new(&path),
+        embed_archives,
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn inspect_profile_bundle(path: String) -> Result<librarian::ProfileBundle, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        librarian::ProfileBundles::read_manifest(std::path::Path::new(&path))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+/// Recreates a bundled profile, importing embedded mods that are not in the library.
+#[tauri::command]
+pub async fn import_profile_bundle(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    game_id: String,
+    path: String,
+) -> Result<librarian::BundleImportReport, String> {
+    let options = librarian::ImportOptions {
+        fix_normal_maps: game_id == "zzz",
+        normalize_filenames: cfg!(not(windows)),
+        deduplicate: state.global_settings.lock().await.deduplicate_mods,
+        ..Default::default()
+    };
+    let librarian = state.librarian.lock().await.clone();
+    let report = librarian::ProfileBundles::import(
+        &librarian,
+        &game_id,
+        std::path::Path::new(&path),
+        options,
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let db = librarian
+        .load_game_db(&game_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut dbs = state.game_dbs.lock().await;
+    dbs.insert(game_id, db);
+    let _ = app.emit("library-updated", dbs.clone());
+    Ok(report)
+}
@@ -40,9 +40,9 @@ pub async fn get_setup_status(
     } else {
         settings.yago_storage_path.clone()
     };
+    let runners_dir = settings.runners_dir(&state.app_data_dir);
     drop(settings);
 
-    let runners_dir = base_storage.join("runners");
     let has_runners = runners_dir.exists()
         && std::fs::read_dir(runners_dir)
             .map(|e| e.count() > 0)
@@ -325,13 +325,11 @@ pub async fn download_proton(
     app: tauri::AppHandle,
     state: State<'_, AppState>,
 ) -> Result<(), String> {
-    let settings = state.global_settings.lock().await;
-    let base_storage = if settings.yago_storage_path.as_os_str().is_empty() {
-        state.app_data_dir.clone()
-    } else {
-        settings.yago_storage_path.clone()
-    };
-    drop(settings);
+    let runners_dir = state
+        .global_settings
+        .lock()
+        .await
+        .runners_dir(&state.app_data_dir);
 
     let repo_full = {
         let config = state.app_config.lock().await;
@@ -345,7 +343,6 @@ pub async fn download_proton(
     let owner = parts[0];
     let repo = parts[1];
 
-    let runners_dir = base_storage.join("runners");
     if !runners_dir.exists() {
         std::fs::create_dir_all(&runners_dir).map_err(|e| e.to_string())?;
     }
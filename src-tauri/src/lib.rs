@@ -6,7 +6,8 @@ use tokio::sync::Mutex;
 
 // Backend Imports
 use librarian::{
-    GameTemplate, GlobalSettings, Librarian, LibraryDatabase, SettingsManager, TemplateRegistry,
+    GameTemplate, GlobalSettings, ImportItem, ImportQueue, ImportStatus, Librarian,
+    LibraryDatabase, ModSync, ModWatcher, PortableRoot, SettingsManager, TemplateRegistry,
 };
 
 use include_dir::{include_dir, Dir};
@@ -35,6 +36,93 @@ pub struct AppState {
     pub global_settings: Arc<Mutex<GlobalSettings>>,
     pub app_config: Arc<Mutex<AppConfig>>,
     pub download_controls: Arc<Mutex<HashMap<String, tokio::sync::watch::Sender<bool>>>>,
+    pub import_controls: Arc<Mutex<HashMap<String, fs_engine::CancelToken>>>,
+    pub mod_watcher: Arc<Mutex<Option<ModWatcher>>>,
+    pub import_queue: ImportQueue,
+    /// Cancels the storage relocation under way, if any.
+    pub relocation_control: Arc<Mutex<Option<fs_engine::CancelToken>>>,
+    /// Damaged settings or library files restored from backups, until the UI asks.
+    pub recoveries: Arc<Mutex<Vec<fs_engine::RestoredBackup>>>,
+}
+
+/// Quiet period before on-disk mod edits are folded into the library.
+const MOD_WATCH_DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(1500);
+
+/// Re-syncs the changed mod folders of one game, saves them, refreshes the cached
+/// database and tells the frontend.
+fn sync_mod_folders(
+    app: tauri::AppHandle,
+    dbs: Arc<Mutex<HashMap<String, LibraryDatabase>>>,
+    librarian: Librarian,
+    game_id: String,
+    folders: Vec<PathBuf>,
+) {
+    tauri::async_runtime::spawn(async move {
+        let mut dbs_guard = dbs.lock().await;
+        if !dbs_guard.contains_key(&game_id) {
+            return;
+        }
+        // The cached copy may predate an import that just committed, so sync the stored one
+        let (db, events) = match ModSync::sync_stored(&librarian, &game_id, &folders).await {
+            Ok(synced) => synced,
+            Err(e) => {
+                eprintln!("ModWatcher: failed to sync {}: {}", game_id, e);
+                return;
+            }
+        };
+        if events.is_empty() {
+            return;
+        }
+        dbs_guard.insert(game_id.clone(), db);
+        librarian::GameInstances::sync_loaded(&mut dbs_guard, &game_id);
+        let _ = app.emit("mods-synced", &events);
+        let _ = app.emit("library-updated", dbs_guard.clone());
+    });
+}
+
+/// Watches the library's mods folder, folding edits into the cached databases.
+fn start_mod_watcher(
+    app: tauri::AppHandle,
+    dbs: Arc<Mutex<HashMap<String, LibraryDatabase>>>,
+    librarian: &Librarian,
+) -> Option<ModWatcher> {
+    let watch_librarian = librarian.clone();
+    match ModWatcher::start(librarian, MOD_WATCH_DEBOUNCE, move |game_id, folders| {
+        sync_mod_folders(
+            app.clone(),
+            dbs.clone(),
+            watch_librarian.clone(),
+            game_id,
+            folders,
+        )
+    }) {
+        Ok(watcher) => Some(watcher),
+        Err(e) => {
+            eprintln!("ModWatcher: failed to start: {}", e);
+            None
+        }
+    }
+}
+
+/// Reloads one game's database into the cache after a background import committed to it.
+fn reload_game_db(
+    app: tauri::AppHandle,
+    dbs: Arc<Mutex<HashMap<String, LibraryDatabase>>>,
+    librarian: Arc<Mutex<Librarian>>,
+    game_id: String,
+) {
+    tauri::async_runtime::spawn(async move {
+        let librarian = librarian.lock().await.clone();
+        match librarian.load_game_db(&game_id).await {
+            Ok(db) => {
+                let mut dbs_guard = dbs.lock().await;
+                dbs_guard.insert(game_id.clone(), db);
+                librarian::GameInstances::sync_loaded(&mut dbs_guard, &game_id);
+                let _ = app.emit("library-updated", dbs_guard.clone());
+            }
+            Err(e) => eprintln!("Importer: failed to reload {}: {}", game_id, e),
+        }
+    });
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -128,22 +216,36 @@ pub fn run() {
 
             // Paths
             let app_handle = app.handle();
-            let app_data_dir = app_handle
-                .path()
-                .app_data_dir()
-                .expect("failed to get app data dir");
+            // A portable install keeps everything next to the executable instead
+            let portable = PortableRoot::detect();
+            let app_data_dir = match &portable {
+                Some(portable) => {
+                    println!("Running portable from {}", portable.root().display());
+                    std::fs::create_dir_all(portable.root())
+                        .expect("failed to create portable data dir");
+                    portable.root().to_path_buf()
+                }
+                None => app_handle
+                    .path()
+                    .app_data_dir()
+                    .expect("failed to get app data dir"),
+            };
 
             // Initialize Settings (Sync/Immediate load for AppState)
-            let settings_manager = Arc::new(SettingsManager::new(app_data_dir.clone()));
+            let settings_manager = Arc::new(match &portable {
+                Some(portable) => SettingsManager::portable(portable.clone()),
+                None => SettingsManager::new(app_data_dir.clone()),
+            });
 
             // We use standard fs for the initial settings load to avoid block_on runtime panics
-            let settings_path = app_data_dir.join("settings.json");
-            let settings = if settings_path.exists() {
-                let content = std::fs::read_to_string(&settings_path).unwrap_or_default();
-                serde_json::from_str::<GlobalSettings>(&content).unwrap_or_default()
-            } else {
-                GlobalSettings::default()
-            };
+            let (mut settings, settings_recovery) =
+                settings_manager.read_or_recover().unwrap_or_else(|e| {
+                    eprintln!("Failed to read settings: {}", e);
+                    (GlobalSettings::default(), None)
+                });
+            let recoveries_mutex = Arc::new(Mutex::new(
+                settings_recovery.into_iter().collect::<Vec<_>>(),
+            ));
 
             // Determine Roots
             let base_storage = if settings.yago_storage_path.as_os_str().is_empty() {
@@ -180,14 +282,32 @@ pub fn run() {
                 } else {
                     Some(settings.cache_path.clone())
                 },
+                portable,
             };
 
             // Initialize Librarian
-            let librarian = Librarian::new(lib_config);
+            let mut librarian = Librarian::new(lib_config);
             librarian
                 .ensure_core_dirs()
                 .expect("failed to ensure core directories");
 
+            // A library that never chose a backend keeps the one it already uses
+            let backend_chosen = settings.library_backend.is_some();
+            let backend = *settings
+                .library_backend
+                .get_or_insert_with(|| librarian.detect_backend());
+            // On failure the library reports the error instead of reading stale files
+            match librarian.open_backend(backend) {
+                Ok(imported) if !imported.is_empty() => {
+                    println!(
+                        "Imported {} game(s) into the library database",
+                        imported.len()
+                    )
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("Library database unavailable: {}", e),
+            }
+
             // EXTRACT BUNDLED ASSETS
             // 1. App Config (to root)
             let config_path = app_data_dir.join("app_config.json");
@@ -229,8 +349,19 @@ pub fn run() {
             let librarian_clone = librarian.clone();
             let templates_arc = templates_mutex.clone();
             let dbs_arc = dbs_mutex.clone();
+            let watcher_mutex = Arc::new(Mutex::new(None));
+            let watcher_arc = watcher_mutex.clone();
+            let recoveries_arc = recoveries_mutex.clone();
+            let settings_manager_clone = settings_manager.clone();
+            let detected_settings = (!backend_chosen).then(|| settings.clone());
 
             tauri::async_runtime::spawn(async move {
+                if let Some(settings) = detected_settings {
+                    if let Err(e) = settings_manager_clone.save(&settings).await {
+                        eprintln!("Failed to save the library backend setting: {}", e);
+                    }
+                }
+
                 // Load Templates
                 let registry = TemplateRegistry::new(librarian_clone.templates_root.clone());
                 if let Ok(templates) = registry.load_all().await {
@@ -238,20 +369,56 @@ pub fn run() {
                     *guard = templates;
                 }
 
-                // Load all game DBs
+                // Load all game DBs, catching up on mod folders edited while closed
                 if let Ok(ids) = librarian_clone.discover_game_ids().await {
                     let mut dbs_guard = dbs_arc.lock().await;
                     for id in ids {
-                        if let Ok(db) = librarian_clone.load_game_db(&id).await {
+                        if let Ok(mut db) = librarian_clone.load_game_db(&id).await {
+                            if !ModSync::scan(&librarian_clone, &id, &mut db).is_empty() {
+                                let _ = librarian_clone.save_game_db(&id, &db).await;
+                            }
                             dbs_guard.insert(id, db);
                         }
                     }
                 }
 
+                // Report files that had to be restored from a backup
+                let mut recoveries = recoveries_arc.lock().await;
+                recoveries.extend(librarian_clone.take_recoveries());
+                if !recoveries.is_empty() {
+                    let _ = app_handle_clone.emit("data-recovered", recoveries.clone());
+                }
+                drop(recoveries);
+
+                // Watch for edits from here on
+                *watcher_arc.lock().await =
+                    start_mod_watcher(app_handle_clone.clone(), dbs_arc.clone(), &librarian_clone);
+
                 println!("Background Initialization Complete.");
                 let _ = app_handle_clone.emit("library-updated", dbs_arc.lock().await.clone());
             });
 
+            let queue_app = app_handle.clone();
+            let queue_dbs = dbs_mutex.clone();
+            let queue_librarian = librarian_mutex.clone();
+            let import_queue = ImportQueue::new(
+                librarian::DEFAULT_IMPORT_CONCURRENCY,
+                Some(Arc::new(move |item: &ImportItem| {
+                    let _ = queue_app.emit("import-queue-updated", item);
+                    if matches!(
+                        item.status,
+                        ImportStatus::Done { .. } | ImportStatus::PartlyDone { .. }
+                    ) {
+                        reload_game_db(
+                            queue_app.clone(),
+                            queue_dbs.clone(),
+                            queue_librarian.clone(),
+                            item.game_id.clone(),
+                        );
+                    }
+                })),
+            );
+
             app.manage(AppState {
                 app_data_dir: app_data_dir.clone(),
                 librarian: librarian_mutex,
@@ -263,6 +430,11 @@ pub fn run() {
                 global_settings: Arc::new(Mutex::new(settings)),
                 app_config,
                 download_controls: Arc::new(Mutex::new(HashMap::new())),
+                import_controls: Arc::new(Mutex::new(HashMap::new())),
+                mod_watcher: watcher_mutex,
+                import_queue,
+                relocation_control: Arc::new(Mutex::new(None)),
+                recoveries: recoveries_mutex,
             });
 
             Ok(())
@@ -279,6 +451,28 @@ pub fn run() {
             commands::library::remove_game,
             commands::mods::import_mod,
             commands::mods::add_mod,
+            commands::mods::queue_imports,
+            commands::mods::preview_mod_adoption,
+            commands::mods::adopt_mods,
+            commands::mods::get_import_queue,
+            commands::mods::cancel_queued_import,
+            commands::mods::clear_import_queue,
+            commands::mods::inspect_archive,
+            commands::mods::cancel_import,
+            commands::mods::get_mod_thumbnails,
+            commands::mods::preview_filename_normalization,
+            commands::mods::normalize_mod_filenames,
+            commands::mods::get_storage_stats,
+            commands::config::get_data_recoveries,
+            commands::mods::deduplicate_library,
+            commands::mods::collect_storage_garbage,
+            commands::mods::check_mod_updates,
+            commands::mods::update_mod,
+            commands::mods::switch_mod_version,
+            commands::mods::diff_mod_versions,
+            commands::mods::prune_mod_versions,
+            commands::mods::get_duplicate_report,
+            commands::mods::get_key_collisions,
             commands::mods::validate_mod,
             commands::mods::delete_mod,
             commands::mods::toggle_mod,
@@ -287,17 +481,30 @@ pub fn run() {
             commands::launcher::kill_game,
             commands::config::get_settings,
             commands::config::update_settings,
+            commands::config::relocate_storage,
+            commands::config::cancel_relocation,
             commands::config::get_app_config,
             commands::config::update_app_config,
             commands::profiles::set_load_order,
             commands::mods::update_mod_tags,
+            commands::mods::get_tag_rules,
+            commands::mods::save_tag_rules,
+            commands::mods::retag_mods,
             commands::library::get_skin_inventory,
+            commands::library::search_mods,
+            commands::library::get_saved_searches,
+            commands::library::save_search,
+            commands::library::delete_saved_search,
             commands::profiles::switch_profile,
             commands::profiles::create_profile,
             commands::profiles::duplicate_profile,
             commands::profiles::update_profile,
             commands::profiles::delete_profile,
             commands::profiles::rename_profile,
+            commands::profiles::pin_mod_version,
+            commands::profiles::export_profile_bundle,
+            commands::profiles::inspect_profile_bundle,
+            commands::profiles::import_profile_bundle,
             commands::launcher::update_game_config,
             commands::library::list_runners,
             commands::library::open_path,
@@ -324,6 +531,7 @@ pub fn run() {
             commands::download::resume_game_download,
             commands::download::repair_game,
             commands::library::wipe_game_mods,
+            commands::library::share_game_mods,
             commands::library::reset_game_profiles,
             commands::library::remove_game_prefix,
             commands::library::uninstall_game_files,